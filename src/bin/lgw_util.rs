@@ -0,0 +1,483 @@
+/*
+    Concentrator bring-up utilities, equivalent to the util_* and test_loragw_hal_* programs of
+    the reference HAL.
+
+    Usage: lgw_util [-c global_conf.json] <command> [options]
+
+        chip_id                         print the EUI of the SX1302
+        rx [-n nb_pkt] [-t seconds] [-r region]
+                                        print received packets, then the RX statistics
+        tx [-f MHz] [-s SF] [-p dBm] [-b kHz] [-k rf_chain] [-n repeat] [-z size] [-t delay_ms] [-i]
+                                        send LoRa packets, -f -s and -p take a start[:stop[:step]] sweep
+        net_downlink [-P port] [-f MHz] [-s SF] [-b kHz] [-p dBm] [-z size] [-n nb_pkt] [-t delay_ms] [-i]
+                                        act as a network server, send PULL_RESP to the packet forwarder
+        reg r <register>                read a SX1302 register, by id or SX1302_REG_* name
+        reg w <register> <value>        write a SX1302 register
+        reg dump [pattern] [-s] [-o file]
+                                        read all registers, or a block such as SX1302_REG_RX_TOP_*
+        reg diff <dump_a> <dump_b>      compare two dumps
+        mcu ping|status                 query the MCU of the USB concentrator
+
+    All commands but net_downlink and reg diff load the SX130x_conf section of the configuration
+    file. The region of rx replaces the channel plan of the file, keeping its board settings.
+    Connecting resets the SX1302, reg dump -s starts the concentrator first so that the dump
+    shows the configured registers.
+*/
+
+use std::net::{SocketAddr, UdpSocket};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::json;
+use tracing::{info, warn};
+
+use loragw_hal::hal::loragw_com::LoragwComTrait;
+use loragw_hal::hal::loragw_conf::LgwGlobalConf;
+use loragw_hal::hal::loragw_reg::{LoragwRegTrait, LGW_TOTALREGS, LOREGS, LOREGS_NAMES};
+use loragw_hal::hal::loragw_regdump::{lgw_reg_find, LgwRegDump};
+use loragw_hal::hal::loragw_region::{LgwChannelPlan, LgwRegion};
+use loragw_hal::hal::{Hal, LgwHal, LgwPktRx, LgwPktTx, Modulation, TxMode, BW_125KHZ, BW_250KHZ, BW_500KHZ, CR_LORA_4_5,
+                      STAT_CRC_BAD, STAT_CRC_OK, STAT_NO_CRC, TX_FREE, TX_STATUS};
+use loragw_hal::pkt_fwd::gwmp::{encode_header, GwmpHeader, HEADER_EUI_SIZE, PKT_PULL_ACK, PKT_PULL_DATA, PKT_PULL_RESP,
+                                PKT_PUSH_ACK, PKT_PUSH_DATA, PKT_TX_ACK};
+
+const DEFAULT_CONF_PATH: &str = "global_conf.json";
+const DEFAULT_NET_PORT: u16 = 1730;
+const TX_STATUS_POLL: Duration = Duration::from_millis(5);
+const TX_ACK_WAIT: Duration = Duration::from_secs(1);     /* after the last PULL_RESP */
+
+/* Command line, options are taken out as they are read and the rest is positional */
+struct Args {
+    items: Vec<String>,
+}
+
+impl Args {
+    fn flag(&mut self, name: &str) -> bool {
+        match self.items.iter().position(|a| a == name) {
+            Some(i) => {
+                self.items.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn opt(&mut self, name: &str) -> Result<Option<String>> {
+        let Some(i) = self.items.iter().position(|a| a == name) else {
+            return Ok(None);
+        };
+        if i + 1 >= self.items.len() {
+            return Err(anyhow!("ERROR: option {} requires a value", name));
+        }
+        let value = self.items.remove(i + 1);
+        self.items.remove(i);
+        Ok(Some(value))
+    }
+
+    fn opt_or<T: FromStr>(&mut self, name: &str, default: T) -> Result<T> {
+        match self.opt(name)? {
+            Some(v) => v.parse().map_err(|_| anyhow!("ERROR: invalid value {} for option {}", v, name)),
+            None => Ok(default),
+        }
+    }
+
+    fn next(&mut self) -> Option<String> {
+        if self.items.is_empty() { None } else { Some(self.items.remove(0)) }
+    }
+
+    /* Options left over are not supported by the command */
+    fn done(&self) -> Result<()> {
+        match self.items.first() {
+            Some(a) => Err(anyhow!("ERROR: unexpected argument {}", a)),
+            None => Ok(()),
+        }
+    }
+}
+
+/* "start[:stop[:step]]" scaled to integers (e.g. MHz to Hz), step 1 by default */
+fn sweep(arg: &str, scale: f64) -> Result<Vec<i64>> {
+    let mut values = Vec::with_capacity(3);
+    for v in arg.split(':') {
+        let v: f64 = v.parse().map_err(|_| anyhow!("ERROR: invalid sweep {}", arg))?;
+        values.push((v * scale).round() as i64);
+    }
+    let (start, stop, step) = match values[..] {
+        [start] => (start, start, 1),
+        [start, stop] => (start, stop, scale.round() as i64),
+        [start, stop, step] => (start, stop, step),
+        _ => return Err(anyhow!("ERROR: invalid sweep {}, expected start[:stop[:step]]", arg)),
+    };
+    if step <= 0 || stop < start {
+        return Err(anyhow!("ERROR: invalid sweep {}, stop must not be below start and step must be positive", arg));
+    }
+    Ok((start ..= stop).step_by(step as usize).collect())
+}
+
+fn parse_bandwidth(khz: u32) -> Result<u8> {
+    match khz {
+        125 => Ok(BW_125KHZ),
+        250 => Ok(BW_250KHZ),
+        500 => Ok(BW_500KHZ),
+        _ => Err(anyhow!("ERROR: invalid bandwidth {} kHz, expected 125, 250 or 500", khz)),
+    }
+}
+
+/* Register id, or SX1302_REG_* name */
+fn parse_register(arg: Option<String>) -> Result<u16> {
+    let arg = arg.ok_or(anyhow!("ERROR: a register is required"))?;
+    match arg.parse::<u16>() {
+        Ok(id) if id < LGW_TOTALREGS => Ok(id),
+        Ok(_) => Err(anyhow!("ERROR: invalid register id {}, expected 0 to {}", arg, LGW_TOTALREGS - 1)),
+        Err(_) => lgw_reg_find(&arg).ok_or(anyhow!("ERROR: unknown register {}", arg)),
+    }
+}
+
+/* Decimal, or hexadecimal with 0x */
+fn parse_value(arg: Option<String>) -> Result<i32> {
+    let arg = arg.ok_or(anyhow!("ERROR: a value is required"))?;
+    let value = match arg.strip_prefix("0x").or(arg.strip_prefix("0X")) {
+        Some(hex) => i32::from_str_radix(hex, 16).ok(),
+        None => arg.parse().ok(),
+    };
+    value.ok_or(anyhow!("ERROR: invalid register value {}", arg))
+}
+
+fn load_conf(hal: &mut Hal, conf_path: &str, region: Option<LgwRegion>) -> Result<()> {
+    let mut conf = LgwGlobalConf::from_file(conf_path)?;
+    if let Some(region) = region {
+        LgwChannelPlan::new(region)?.apply(&mut conf);
+        info!("INFO: using the {} channel plan", region);
+    }
+    conf.apply(hal)
+}
+
+fn chip_id(hal: &mut Hal, conf_path: &str, args: Args) -> Result<()> {
+    args.done()?;
+    load_conf(hal, conf_path, None)?;
+
+    hal.lgw_start()?;
+    let eui = hal.lgw_get_eui();
+    hal.lgw_stop()?;
+
+    println!("INFO: concentrator EUI: 0x{:016x}", eui?);
+    Ok(())
+}
+
+fn print_pkt(pkt: &LgwPktRx) {
+    let status = match pkt.status {
+        STAT_CRC_OK => "CRC OK",
+        STAT_CRC_BAD => "CRC BAD",
+        STAT_NO_CRC => "NO CRC",
+        _ => "UNKNOWN",
+    };
+    let dr = match pkt.modulation {
+        Modulation::FSK => format!("{}bps", pkt.datarate),
+        _ => format!("SF{} BW{}", pkt.datarate, Hal::lgw_bw_getval(pkt.bandwidth) / 1000),
+    };
+    println!("----- {} packet -----", pkt.modulation);
+    println!("  count_us: {}, freq_hz: {} ({:+} Hz), rf_chain: {}, if_chain: {}, modem_id: {}",
+        pkt.count_us, pkt.freq_hz, pkt.freq_offset, pkt.rf_chain, pkt.if_chain, pkt.modem_id);
+    println!("  {}, CR{}, {}, size: {}, RSSI chan: {:.1} dBm, RSSI sig: {:.1} dBm, SNR: {:.1} dB",
+        dr, pkt.coderate, status, pkt.size, pkt.rssic, pkt.rssis, pkt.snr);
+    println!("  payload: {:02X?}", &pkt.payload[..pkt.size as usize]);
+}
+
+fn rx(hal: &mut Hal, conf_path: &str, mut args: Args) -> Result<()> {
+    let nb_pkt: u64 = args.opt_or("-n", 0)?;           /* 0: no limit */
+    let duration: u64 = args.opt_or("-t", 0)?;         /* 0: no limit */
+    let region = args.opt("-r")?.map(|r| r.parse::<LgwRegion>()).transpose()?;
+    args.done()?;
+    load_conf(hal, conf_path, region)?;
+
+    hal.lgw_start()?;
+    info!("INFO: concentrator started, waiting for packets");
+
+    let start = Instant::now();
+    let mut nb_rx: u64 = 0;
+    let mut res = Ok(());
+    while (nb_pkt == 0 || nb_rx < nb_pkt) && (duration == 0 || start.elapsed() < Duration::from_secs(duration)) {
+        match hal.lgw_receive() {
+            Ok(pkts) if pkts.is_empty() => thread::sleep(Duration::from_millis(10)),
+            Ok(pkts) => {
+                for pkt in pkts.iter() {
+                    print_pkt(pkt);
+                }
+                nb_rx += pkts.len() as u64;
+            }
+            Err(e) => {
+                res = Err(e);
+                break;
+            }
+        }
+    }
+
+    println!("{}", hal.lgw_stats());
+    hal.lgw_stop()?;
+    res
+}
+
+fn tx(hal: &mut Hal, conf_path: &str, mut args: Args) -> Result<()> {
+    let freqs = sweep(&args.opt_or("-f", "868.1".to_string())?, 1e6)?;
+    let sfs = sweep(&args.opt_or("-s", "7".to_string())?, 1.0)?;
+    let powers = sweep(&args.opt_or("-p", "14".to_string())?, 1.0)?;
+    let bandwidth = parse_bandwidth(args.opt_or("-b", 125)?)?;
+    let rf_chain: u8 = args.opt_or("-k", 0)?;
+    let repeat: u32 = args.opt_or("-n", 1)?;
+    let size: u16 = args.opt_or("-z", 16)?;
+    let delay_ms: u64 = args.opt_or("-t", 1000)?;
+    let invert_pol = args.flag("-i");
+    args.done()?;
+    if !(4 ..= 255).contains(&size) {
+        return Err(anyhow!("ERROR: invalid payload size {}, expected 4 to 255", size));
+    }
+    load_conf(hal, conf_path, None)?;
+
+    hal.lgw_start()?;
+    let res = tx_sweep(hal, &freqs, &sfs, &powers, repeat, delay_ms, |pkt| {
+        pkt.rf_chain = rf_chain;
+        pkt.bandwidth = bandwidth;
+        pkt.invert_pol = invert_pol;
+        pkt.size = size;
+    });
+    println!("{}", hal.lgw_stats());
+    hal.lgw_stop()?;
+    res
+}
+
+/* Send repeat packets for each (frequency, SF, power) and wait for each TX to complete */
+fn tx_sweep<F: Fn(&mut LgwPktTx)>(hal: &mut Hal, freqs: &[i64], sfs: &[i64], powers: &[i64], repeat: u32, delay_ms: u64, setup: F) -> Result<()> {
+    let mut cnt: u32 = 0;
+
+    for &freq_hz in freqs {
+        for &sf in sfs {
+            for &power in powers {
+                for _ in 0 .. repeat {
+                    let mut pkt = LgwPktTx {
+                        freq_hz: freq_hz as u32,
+                        tx_mode: TxMode::IMMEDIATE,
+                        rf_power: power as i8,
+                        modulation: Modulation::LORA,
+                        datarate: sf as u32,
+                        coderate: CR_LORA_4_5,
+                        preamble: 8,
+                        ..Default::default()
+                    };
+                    setup(&mut pkt);
+                    for i in 0 .. pkt.size as usize {
+                        pkt.payload[i] = i as u8;
+                    }
+                    pkt.payload[..4].copy_from_slice(&cnt.to_be_bytes());    /* packet counter */
+
+                    hal.lgw_send(&pkt)?;
+                    while hal.lgw_status(pkt.rf_chain, TX_STATUS)? != TX_FREE {
+                        thread::sleep(TX_STATUS_POLL);
+                    }
+                    info!("INFO: packet {} sent, {} Hz, SF{}, {} dBm", cnt, freq_hz, sf, power);
+                    cnt += 1;
+
+                    thread::sleep(Duration::from_millis(delay_ms));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn net_downlink(mut args: Args) -> Result<()> {
+    let port: u16 = args.opt_or("-P", DEFAULT_NET_PORT)?;
+    let freq_mhz: f64 = args.opt_or("-f", 868.1)?;
+    let sf: u32 = args.opt_or("-s", 9)?;
+    let bandwidth: u32 = args.opt_or("-b", 125)?;
+    let power: i8 = args.opt_or("-p", 14)?;
+    let size: usize = args.opt_or("-z", 16)?;
+    let nb_pkt: u32 = args.opt_or("-n", 1)?;
+    let delay_ms: u64 = args.opt_or("-t", 1000)?;
+    let invert_pol = args.flag("-i");
+    args.done()?;
+    parse_bandwidth(bandwidth)?;
+    if size > 255 {
+        return Err(anyhow!("ERROR: invalid payload size {}, expected 0 to 255", size));
+    }
+
+    let socket = UdpSocket::bind(("0.0.0.0", port))
+        .map_err(|e| anyhow!("ERROR: failed to bind UDP port {}: {}", port, e))?;
+    socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+    info!("INFO: waiting for PULL_DATA on port {}", port);
+
+    let payload: Vec<u8> = (0 .. size).map(|i| i as u8).collect();
+    let mut gateway: Option<SocketAddr> = None;
+    let mut nb_sent: u32 = 0;
+    let mut next_tx = Instant::now();
+    let mut buf = [0u8; 65535];
+
+    loop {
+        if nb_sent == nb_pkt && next_tx.elapsed() >= TX_ACK_WAIT {
+            break;
+        }
+
+        if let (Some(addr), true) = (gateway, nb_sent < nb_pkt && Instant::now() >= next_tx) {
+            let txpk = json!({"txpk": {
+                "imme": true,
+                "freq": freq_mhz,
+                "rfch": 0,
+                "powe": power,
+                "modu": "LORA",
+                "datr": format!("SF{}BW{}", sf, bandwidth),
+                "codr": "4/5",
+                "ipol": invert_pol,
+                "size": size,
+                "data": BASE64.encode(&payload),
+            }});
+            let mut dgram = encode_header(rand::random(), PKT_PULL_RESP, None);
+            dgram.extend_from_slice(txpk.to_string().as_bytes());
+            socket.send_to(&dgram, addr)?;
+            nb_sent += 1;
+            info!("INFO: PULL_RESP {}/{} sent to {}", nb_sent, nb_pkt, addr);
+            next_tx = Instant::now() + Duration::from_millis(delay_ms);
+            if nb_sent == nb_pkt {
+                next_tx = Instant::now();
+            }
+        }
+
+        let (len, addr) = match socket.recv_from(&mut buf) {
+            Ok(r) => r,
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => continue,
+            Err(e) => return Err(anyhow!("ERROR: UDP receive failed: {}", e)),
+        };
+        let Ok(hdr) = GwmpHeader::parse(&buf[..len]) else {
+            continue;
+        };
+        match hdr.ident {
+            PKT_PULL_DATA => {
+                if gateway != Some(addr) {
+                    info!("INFO: PULL_DATA from {}, downlink route open", addr);
+                }
+                gateway = Some(addr);
+                socket.send_to(&encode_header(hdr.token, PKT_PULL_ACK, None), addr)?;
+            }
+            PKT_PUSH_DATA => {
+                socket.send_to(&encode_header(hdr.token, PKT_PUSH_ACK, None), addr)?;
+            }
+            PKT_TX_ACK if len > HEADER_EUI_SIZE => {
+                info!("INFO: TX_ACK: {}", String::from_utf8_lossy(&buf[HEADER_EUI_SIZE..len]));
+            }
+            PKT_TX_ACK => info!("INFO: TX_ACK, no error"),
+            _ => warn!("WARNING: unexpected datagram type {} from {}", hdr.ident, addr),
+        }
+    }
+
+    Ok(())
+}
+
+fn reg(hal: &mut Hal, conf_path: &str, mut args: Args) -> Result<()> {
+    match args.next().as_deref() {
+        Some("r") => {
+            let register_id = parse_register(args.next())?;
+            args.done()?;
+            with_connection(hal, conf_path, |hal| {
+                let v = hal.lgw_reg_r(register_id)?;
+                let r = &LOREGS[register_id as usize];
+                println!("{} ({}): {} (0x{:X}), page {}, addr 0x{:04X}, offs {}, leng {}, default {}",
+                    LOREGS_NAMES[register_id as usize], register_id, v, v, r.page, r.addr, r.offs, r.leng, r.dflt);
+                Ok(())
+            })
+        }
+        Some("w") => {
+            let register_id = parse_register(args.next())?;
+            let value = parse_value(args.next())?;
+            args.done()?;
+            with_connection(hal, conf_path, |hal| hal.lgw_reg_w(register_id, value))
+        }
+        Some("dump") => {
+            let start = args.flag("-s");
+            let output = args.opt("-o")?;
+            let pattern = args.next().unwrap_or("*".to_string());
+            args.done()?;
+            let dump = if start {
+                load_conf(hal, conf_path, None)?;
+                hal.lgw_start()?;
+                let dump = hal.lgw_reg_dump(&pattern);
+                hal.lgw_stop()?;
+                dump?
+            } else {
+                with_connection(hal, conf_path, |hal| hal.lgw_reg_dump(&pattern))?
+            };
+            match output {
+                Some(path) => std::fs::write(&path, dump.to_string())
+                    .map_err(|e| anyhow!("ERROR: failed to write {}: {}", path, e)),
+                None => {
+                    print!("{}", dump);
+                    Ok(())
+                }
+            }
+        }
+        Some("diff") => {
+            let (Some(a), Some(b)) = (args.next(), args.next()) else {
+                return Err(anyhow!("ERROR: expected reg diff <dump_a> <dump_b>"));
+            };
+            args.done()?;
+            let diffs = LgwRegDump::from_file(&a)?.diff(&LgwRegDump::from_file(&b)?);
+            for d in diffs.iter() {
+                println!("{}", d);
+            }
+            println!("{} register(s) differ", diffs.len());
+            Ok(())
+        }
+        _ => Err(anyhow!("ERROR: expected reg r, reg w, reg dump or reg diff")),
+    }
+}
+
+/* Connect to the SX1302 without starting the concentrator, then disconnect */
+fn with_connection<T, F: FnOnce(&mut Hal) -> Result<T>>(hal: &mut Hal, conf_path: &str, f: F) -> Result<T> {
+    load_conf(hal, conf_path, None)?;
+    hal.lgw_connect()?;
+    let res = f(hal);
+    hal.mcu.close();
+    res
+}
+
+fn mcu(hal: &mut Hal, conf_path: &str, mut args: Args) -> Result<()> {
+    let op = args.next();
+    args.done()?;
+    load_conf(hal, conf_path, None)?;
+
+    hal.lgw_com_open()?;
+    let res = match op.as_deref() {
+        Some("ping") => hal.mcu.mcu_ping().map(|ping| {
+            println!("MCU firmware: {}, unique id: {:08X}{:08X}{:08X}",
+                ping.version.trim_end_matches('\0'), ping.unique_id_high, ping.unique_id_mid, ping.unique_id_low);
+        }),
+        Some("status") => hal.mcu.get_mcu_status().map(|status| {
+            println!("MCU system time: {} ms, temperature: {:.1} C", status.system_time_ms, status.temperature);
+        }),
+        _ => Err(anyhow!("ERROR: expected mcu ping or mcu status")),
+    };
+    hal.mcu.close();
+    res
+}
+
+fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let mut args = Args { items: std::env::args().skip(1).collect() };
+    let conf_path = args.opt_or("-c", DEFAULT_CONF_PATH.to_string())?;
+    let Some(command) = args.next() else {
+        return Err(anyhow!("ERROR: no command, expected chip_id, rx, tx, net_downlink, reg or mcu"));
+    };
+
+    let mut hal = Hal::new();
+    match command.as_str() {
+        "chip_id" => chip_id(&mut hal, &conf_path, args),
+        "rx" => rx(&mut hal, &conf_path, args),
+        "tx" => tx(&mut hal, &conf_path, args),
+        "net_downlink" => net_downlink(args),
+        "reg" => reg(&mut hal, &conf_path, args),
+        "mcu" => mcu(&mut hal, &conf_path, args),
+        _ => Err(anyhow!("ERROR: unknown command {}", command)),
+    }
+}
@@ -0,0 +1,47 @@
+/*
+    Semtech UDP packet forwarder.
+
+    Usage: lora_pkt_fwd [global_conf.json]
+
+    The SX130x_conf section configures the concentrator, gateway_conf the network server.
+*/
+
+use std::thread;
+
+use anyhow::Result;
+use tracing::info;
+
+use loragw_hal::hal::loragw_runner::LgwRunner;
+use loragw_hal::hal::Hal;
+use loragw_hal::pkt_fwd::{PacketForwarder, PktFwdConf};
+
+const DEFAULT_CONF_PATH: &str = "global_conf.json";
+
+fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let conf_path = std::env::args().nth(1).unwrap_or(DEFAULT_CONF_PATH.to_string());
+    info!("INFO: using configuration file {}", conf_path);
+
+    let mut hal = Hal::new();
+    let conf = hal.lgw_load_conf(&conf_path)?;
+    let fwd_conf = PktFwdConf::from_global_conf(&conf)?;
+    let stat_interval = fwd_conf.stat_interval;
+
+    let runner = LgwRunner::spawn(hal, std::time::Duration::from_millis(10))?;
+    info!("INFO: concentrator started, packet can now be received");
+
+    let fwd = PacketForwarder::spawn(fwd_conf, runner)?;
+    while fwd.is_running() {
+        thread::sleep(stat_interval);
+        let stats = fwd.stats();
+        info!("### [UPSTREAM] ### RF packets received: {} ({} CRC OK, {} CRC bad, {} no CRC), forwarded: {}, PUSH_DATA sent: {}, acknowledged: {}",
+            stats.rx_rcv, stats.rx_ok, stats.rx_bad, stats.rx_nocrc, stats.up_pkt_fwd, stats.up_dgram_sent, stats.up_ack_rcv);
+        info!("### [DOWNSTREAM] ### PULL_DATA sent: {} ({} acknowledged), PULL_RESP received: {}, TX ok: {}, rejected: {}, failed: {}",
+            stats.dw_pull_sent, stats.dw_ack_rcv, stats.dw_dgram_rcv, stats.tx_ok, stats.tx_rejected, stats.tx_fail);
+    }
+
+    info!("INFO: forwarder stopped, exiting");
+    fwd.stop()?;
+    Ok(())
+}
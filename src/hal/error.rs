@@ -1,15 +1,159 @@
+use std::fmt;
+
+use super::loragw_jit::JitError;
+use super::loragw_regulatory::LgwRegError;
+
+/* Firmware running on the SX1302 embedded MCUs, and the SX1261 PRAM patch */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LgwFirmware {
+    AGC,
+    ARB,
+    CAL,
+    Sx1261Pram,
+}
+
+impl fmt::Display for LgwFirmware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LgwFirmware::AGC => write!(f, "AGC"),
+            LgwFirmware::ARB => write!(f, "ARB"),
+            LgwFirmware::CAL => write!(f, "CAL"),
+            LgwFirmware::Sx1261Pram => write!(f, "SX1261 PRAM"),
+        }
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
-    #[error("LGW_HAL_ERROR")]
-    LGW_HAL_ERROR,
+    /* Configuration */
+    #[error("concentrator is running, stop it before touching configuration")]
+    ConcentratorRunning,
+
+    #[error("concentrator is not started")]
+    ConcentratorNotStarted,
+
+    #[error("invalid configuration {param}={value}: {reason}")]
+    InvalidConfig { param: &'static str, value: String, reason: &'static str },
+
+    /* Transport and MCU protocol */
+    #[error("transport failure while {op}")]
+    Transport { op: &'static str, #[source] source: std::io::Error },
+
+    #[error("request too large ({size} bytes, maximum {max})")]
+    RequestTooLarge { size: usize, max: usize },
+
+    #[error("ACK too large ({size} bytes, maximum {max})")]
+    AckTooLarge { size: usize, max: usize },
+
+    #[error("wrong ACK type 0x{received:02X} (expected 0x{expected:02X})")]
+    WrongAck { expected: u8, received: u8 },
+
+    #[error("invalid ACK: {reason}")]
+    InvalidAck { reason: &'static str },
+
+    #[error("invalid SPI request: {reason}")]
+    InvalidSpiRequest { reason: &'static str },
+
+    #[error("SPI request {req_id} failed with status {status}")]
+    SpiRequest { req_id: u8, status: u8 },
+
+    #[error("SPI request {req_id} has an unknown type 0x{req_type:02X}")]
+    SpiRequestType { req_id: u8, req_type: u8 },
+
+    #[error("GPIO write failed (port:{port} pin:{pin} value:{value})")]
+    Gpio { port: u8, pin: u8, value: u8 },
+
+    /* Register layer */
+    #[error("register {register_id}: {reason}")]
+    Register { register_id: u16, reason: &'static str },
+
+    #[error("register at 0x{address:04X}: {reason}")]
+    RegisterLayout { address: u16, reason: &'static str },
+
+    #[error("register {register_id}: communication failure")]
+    RegisterCom { register_id: u16, #[source] source: anyhow::Error },
+
+    #[error("SPI burst at 0x{address:04X}: {reason}")]
+    Burst { address: u16, reason: &'static str },
+
+    /* Firmwares */
+    #[error("{fw} firmware load failed: {reason}")]
+    FirmwareLoad { fw: LgwFirmware, reason: &'static str },
+
+    #[error("{fw} firmware version mismatch (expected {expected}, got {got})")]
+    FirmwareVersion { fw: LgwFirmware, expected: u8, got: u8 },
+
+    #[error("{fw} firmware: {reason}")]
+    Firmware { fw: LgwFirmware, reason: &'static str },
+
+    #[error("SX1261 PRAM patch version mismatch (expected {expected}, got {got:?})")]
+    PramVersion { expected: String, got: String },
+
+    /* RX */
+    #[error("invalid packet in RX buffer: {reason}")]
+    RxPacket { reason: &'static str },
+
+    #[error("fine timestamp not available: {reason}")]
+    FineTimestamp { reason: &'static str },
+
+    /* Radios */
+    #[error("radio {rf_chain} setup failed: {reason}")]
+    RadioSetup { rf_chain: u8, reason: &'static str },
+
+    #[error("radio {rf_chain} register 0x{address:02X}: communication failure")]
+    RadioRegisterCom { rf_chain: u8, address: u8, #[source] source: anyhow::Error },
+
+    #[error("radio calibration failed: {reason}")]
+    Calibration { reason: &'static str },
+
+    /* TX */
+    #[error("TX rejected, {param}={value}: {reason}")]
+    TxRejected { param: &'static str, value: String, reason: &'static str },
+
+    #[error("TX on RF chain {rf_chain} not allowed by LBT, channel {freq_hz}Hz is busy")]
+    LbtNotAllowed { rf_chain: u8, freq_hz: u32 },
+
+    #[error("TX abort on RF chain {rf_chain} timed out")]
+    TxAbortTimeout { rf_chain: u8 },
+
+    #[error("TX failed on RF chain {rf_chain}")]
+    Tx { rf_chain: u8, #[source] source: anyhow::Error },
+
+    #[error("JIT queue rejected the packet: {0}")]
+    Jit(#[from] JitError),
+
+    #[error("TX not allowed by regional regulations: {0}")]
+    Regulatory(#[from] LgwRegError),
+
+    /* Spectral scan */
+    #[error("spectral scan at {freq_hz}Hz did not complete: {reason}")]
+    SpectralScan { freq_hz: u32, reason: &'static str },
+
+    /* Start sequence */
+    #[error("lgw_start failed during {step}")]
+    Start { step: &'static str, #[source] source: anyhow::Error },
+
+    /* Runner */
+    #[error("concentrator runner has stopped")]
+    RunnerStopped,
+}
 
-    #[error("LGW_REG_ERROR")]
-    LGW_REG_ERROR,
+impl Error {
+    pub(crate) fn config(param: &'static str, value: impl fmt::Display, reason: &'static str) -> Self {
+        Error::InvalidConfig { param, value: value.to_string(), reason }
+    }
 
-    #[error("LGW_COM_ERROR")]
-    LGW_COM_ERROR,
+    pub(crate) fn tx_rejected(param: &'static str, value: impl fmt::Display, reason: &'static str) -> Self {
+        Error::TxRejected { param, value: value.to_string(), reason }
+    }
 
+    /* Structured errors found in an error chain, outermost first */
+    pub fn chain(err: &anyhow::Error) -> impl Iterator<Item = &Error> {
+        err.chain().filter_map(|e| e.downcast_ref::<Error>())
+    }
 
-    #[error("LGW_USB_ERROR")]
-    LGW_USB_ERROR
-}
\ No newline at end of file
+    /* Innermost structured error of an error chain, i.e. the actual cause */
+    pub fn cause(err: &anyhow::Error) -> Option<&Error> {
+        Self::chain(err).last()
+    }
+}
@@ -0,0 +1,333 @@
+/*
+    Async front-end of the HAL (cargo feature "tokio").
+
+    The Hal and its blocking serial I/O are confined to the runner thread (see loragw_runner),
+    the async side only exchanges messages with it and never blocks the executor:
+
+        let mut gw = AsyncHal::new(hal, Duration::from_millis(10));
+        let mut rx = gw.receive().unwrap();
+        gw.start().await?;
+        while let Some(pkt) = rx.next().await { ... }
+*/
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use futures_core::Stream;
+use tokio::sync::{mpsc, oneshot};
+use tracing::debug;
+
+use super::error::Error;
+use super::loragw_runner::{LgwRunner, LgwRunnerCmd, LgwRunnerHandle, LgwRunnerStartError, LgwRunnerStatus};
+use super::{Hal, LgwPktRx, LgwPktTx};
+
+/**
+@struct LgwRxStream
+@brief Stream of the packets received by an AsyncHal, across stop/start cycles
+*/
+#[derive(Debug)]
+pub struct LgwRxStream {
+    rx: mpsc::UnboundedReceiver<LgwPktRx>,
+}
+
+impl Stream for LgwRxStream {
+    type Item = LgwPktRx;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<LgwPktRx>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/**
+@struct AsyncLgwHandle
+@brief Clonable handle sending packets through a started AsyncHal
+*/
+#[derive(Debug, Clone)]
+pub struct AsyncLgwHandle {
+    runner: LgwRunnerHandle,
+}
+
+impl AsyncLgwHandle {
+    /* Resolves once the SX1302 accepted the packet (sent immediately or scheduled) */
+    pub async fn send(&self, pkt: LgwPktTx) -> Result<()> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.runner.post(LgwRunnerCmd::Send(Box::new(pkt), Box::new(move |res| { let _ = reply_tx.send(res); })))?;
+        reply_rx.await.map_err(|_| Error::RunnerStopped)?
+    }
+
+    pub async fn abort_tx(&self, rf_chain: u8) -> Result<()> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.runner.post(LgwRunnerCmd::AbortTx(rf_chain, Box::new(move |res| { let _ = reply_tx.send(res); })))?;
+        reply_rx.await.map_err(|_| Error::RunnerStopped)?
+    }
+
+    pub async fn status(&self) -> Result<LgwRunnerStatus> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.runner.post(LgwRunnerCmd::Status(Box::new(move |res| { let _ = reply_tx.send(res); })))?;
+        reply_rx.await.map_err(|_| Error::RunnerStopped)?
+    }
+}
+
+/* start/stop running on a blocking task, kept until completed so that the Hal is not lost
+   when the start/stop future is dropped before completion */
+#[derive(Debug)]
+enum Transition {
+    Start(tokio::task::JoinHandle<Result<(LgwRunnerHandle, JoinHandle<Hal>), LgwRunnerStartError>>),
+    Stop(tokio::task::JoinHandle<thread::Result<Hal>>),
+}
+
+/**
+@struct AsyncHal
+@brief Async concentrator: start/stop, RX as a Stream and TX as futures
+*/
+#[derive(Debug)]
+pub struct AsyncHal {
+    hal: Option<Hal>,
+    running: Option<(AsyncLgwHandle, JoinHandle<Hal>)>,
+    transition: Option<Transition>,
+    poll_interval: Duration,
+    pkt_tx: mpsc::UnboundedSender<LgwPktRx>,
+    pkt_rx: Option<mpsc::UnboundedReceiver<LgwPktRx>>,
+}
+
+impl AsyncHal {
+    /* The Hal must already be configured, poll_interval is the RX buffer polling period */
+    pub fn new(hal: Hal, poll_interval: Duration) -> Self {
+        let (pkt_tx, pkt_rx) = mpsc::unbounded_channel();
+        Self {
+            hal: Some(hal),
+            running: None,
+            transition: None,
+            poll_interval,
+            pkt_tx,
+            pkt_rx: Some(pkt_rx),
+        }
+    }
+
+    /* Stream of received packets, can only be taken once */
+    pub fn receive(&mut self) -> Option<LgwRxStream> {
+        self.pkt_rx.take().map(|rx| LgwRxStream { rx })
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /* Complete a start or stop whose future was dropped before completion */
+    async fn settle(&mut self) -> Result<()> {
+        match &mut self.transition {
+            None => Ok(()),
+            Some(Transition::Start(task)) => {
+                let started = task.await;
+                self.transition = None;
+                match started? {
+                    Ok((runner, thread)) => {
+                        self.running = Some((AsyncLgwHandle { runner }, thread));
+                        Ok(())
+                    }
+                    Err(e) => {
+                        self.hal = e.hal.map(|hal| *hal);
+                        Err(e.error)
+                    }
+                }
+            }
+            Some(Transition::Stop(task)) => {
+                let stopped = task.await;
+                self.transition = None;
+                self.hal = Some(stopped?.map_err(|_| anyhow!("ERROR: runner thread panicked"))?);
+                Ok(())
+            }
+        }
+    }
+
+    /* Start the concentrator on the runner thread. If lgw_start fails the Hal is kept, so that
+       it can be reconfigured (see into_inner) or started again. Cancel safe: if the future is
+       dropped, the start completes in the background and the next start/stop call picks up its
+       result. */
+    pub async fn start(&mut self) -> Result<()> {
+        self.settle().await?;
+        if self.running.is_some() {
+            return Ok(());
+        }
+        let hal = self.hal.take().ok_or_else(|| anyhow!("ERROR: no Hal left to start, the runner thread panicked"))?;
+        let poll_interval = self.poll_interval;
+        let pkt_tx = self.pkt_tx.clone();
+
+        self.transition = Some(Transition::Start(tokio::task::spawn_blocking(move || {
+            LgwRunner::spawn_with(hal, poll_interval, move |pkt| {
+                let _ = pkt_tx.send(pkt);
+            })
+        })));
+        self.settle().await
+    }
+
+    /* Stop the concentrator, the Hal is kept for a later start. Cancel safe, as start. */
+    pub async fn stop(&mut self) -> Result<()> {
+        self.settle().await?;
+        let Some((handle, thread)) = self.running.take() else {
+            return Ok(());
+        };
+        handle.runner.stop();
+        self.transition = Some(Transition::Stop(tokio::task::spawn_blocking(move || thread.join())));
+        self.settle().await
+    }
+
+    /* Handle to send packets from other tasks, only valid until the next stop */
+    pub fn handle(&self) -> Result<AsyncLgwHandle> {
+        match &self.running {
+            Some((handle, _)) => Ok(handle.clone()),
+            None => Err(Error::ConcentratorNotStarted.into()),
+        }
+    }
+
+    pub async fn send(&self, pkt: LgwPktTx) -> Result<()> {
+        self.handle()?.send(pkt).await
+    }
+
+    pub async fn status(&self) -> Result<LgwRunnerStatus> {
+        self.handle()?.status().await
+    }
+
+    /* Give the Hal back, e.g. to reconfigure it. None if still started or if a start/stop did
+       not complete: call stop() first. */
+    pub fn into_inner(mut self) -> Option<Hal> {
+        self.hal.take()
+    }
+}
+
+impl Drop for AsyncHal {
+    fn drop(&mut self) {
+        if let Some((handle, thread)) = self.running.take() {
+            /* can not await here: ask the runner to stop and let it finish on its own */
+            debug!("AsyncHal dropped while started, stopping concentrator");
+            handle.runner.stop();
+            drop(thread);
+        }
+        /* a runner started in the background stops on its own once its handle is dropped */
+        drop(self.transition.take());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::{poll_fn, Future};
+    use crate::hal::loragw_conf::LgwGlobalConf;
+    use crate::hal::mcu::sim::{SimConcentrator, SimRxPacket};
+    use crate::hal::{Modulation, TxMode, BW_125KHZ, CR_LORA_4_5, STAT_CRC_OK};
+
+    const POLL_INTERVAL: Duration = Duration::from_millis(5);
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    const GLOBAL_CONF: &str = r#"{"SX130x_conf": {
+        "com_path": "/dev/ttyACM0", "clksrc": 0, "lorawan_public": true,
+        "radio_0": {
+            "enable": true, "type": "SX1250", "freq": 867500000, "rssi_offset": -215.4, "tx_enable": true,
+            "tx_gain_lut": [{"rf_power": 14, "pa_gain": 0, "pwr_idx": 17}]
+        },
+        "radio_1": {"enable": true, "type": "SX1250", "freq": 868500000, "rssi_offset": -215.4, "tx_enable": false},
+        "chan_multiSF_0": {"enable": true, "radio": 1, "if": -400000},
+        "chan_multiSF_3": {"enable": true, "radio": 0, "if": -400000}
+    }}"#;
+
+    fn sim_gw() -> (SimConcentrator, AsyncHal) {
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        LgwGlobalConf::parse(GLOBAL_CONF).unwrap().apply(&mut hal).unwrap();
+        (sim, AsyncHal::new(hal, POLL_INTERVAL))
+    }
+
+    fn pkt() -> LgwPktTx {
+        LgwPktTx {
+            tx_mode: TxMode::IMMEDIATE,
+            freq_hz: 869_525_000,
+            rf_power: 14,
+            modulation: Modulation::LORA,
+            bandwidth: BW_125KHZ,
+            datarate: 7,
+            coderate: CR_LORA_4_5,
+            invert_pol: true,
+            preamble: 8,
+            size: 4,
+            ..Default::default()
+        }
+    }
+
+    /* poll a future once, then drop it as a select!/timeout would */
+    async fn cancel_after_first_poll<F: Future>(fut: F) {
+        let mut fut = std::pin::pin!(fut);
+        let _ = poll_fn(|cx| Poll::Ready(fut.as_mut().poll(cx).is_ready())).await;
+    }
+
+    #[tokio::test]
+    async fn start_receive_send_stop() {
+        let (sim, mut gw) = sim_gw();
+        let mut rx = gw.receive().unwrap();
+        assert!(gw.receive().is_none());
+        assert!(gw.handle().is_err());
+
+        tokio::time::timeout(TIMEOUT, gw.start()).await.unwrap().unwrap();
+        assert!(gw.is_started());
+
+        sim.inject_rx(&SimRxPacket { if_chain: 3, datarate: 9, payload: vec![1, 2, 3], ..Default::default() }).unwrap();
+        let p = tokio::time::timeout(TIMEOUT, poll_fn(|cx| Pin::new(&mut rx).poll_next(cx))).await.unwrap().unwrap();
+        assert_eq!((p.if_chain, p.datarate, p.status), (3, 9, STAT_CRC_OK));
+        assert_eq!(&p.payload[..3], &[1, 2, 3]);
+
+        gw.handle().unwrap().send(pkt()).await.unwrap();
+        let mut sent = Vec::new();
+        for _ in 0..200 {
+            sent.extend(sim.take_tx());
+            if !sent.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].freq_hz.abs_diff(869_525_000) < 100);
+
+        let status = gw.status().await.unwrap();
+        assert_eq!(status.nb_rx, 1);
+
+        tokio::time::timeout(TIMEOUT, gw.stop()).await.unwrap().unwrap();
+        assert!(!gw.is_started());
+        assert!(gw.send(pkt()).await.is_err());
+        let hal = gw.into_inner().expect("Hal not given back");
+        assert!(!hal.ctx.is_started);
+    }
+
+    #[tokio::test]
+    async fn start_error_keeps_hal() {
+        let (_sim, mut gw) = sim_gw();
+        /* fine timestamping is not available on the simulated SX1302 */
+        gw.hal.as_mut().unwrap().ctx.ftime_cfg.enable = true;
+
+        let e = gw.start().await.unwrap_err();
+        assert!(matches!(Error::chain(&e).next(), Some(Error::Start { .. })), "{e:#}");
+        assert!(!gw.is_started());
+
+        gw.hal.as_mut().unwrap().ctx.ftime_cfg.enable = false;
+        gw.start().await.unwrap();
+        gw.stop().await.unwrap();
+        assert!(gw.into_inner().is_some());
+    }
+
+    #[tokio::test]
+    async fn cancelled_start_and_stop() {
+        let (_sim, mut gw) = sim_gw();
+
+        cancel_after_first_poll(gw.start()).await;
+        assert!(gw.hal.is_none() && gw.transition.is_some());
+        tokio::time::timeout(TIMEOUT, gw.start()).await.unwrap().unwrap();
+        assert!(gw.is_started());
+
+        cancel_after_first_poll(gw.stop()).await;
+        assert!(!gw.is_started());
+        tokio::time::timeout(TIMEOUT, gw.stop()).await.unwrap().unwrap();
+        let hal = gw.into_inner().expect("Hal lost by the cancelled stop");
+        assert!(!hal.ctx.is_started);
+    }
+}
@@ -0,0 +1,411 @@
+/*
+    SX125x calibration, driven by the CAL firmware running on the SX1302 AGC MCU.
+
+    The firmware owns the radios during a test: it tunes them, enables the TX tone or the TX path
+    and puts them back to standby. The host only exchanges the test parameters through the AGC
+    mailboxes, then searches the correction coefficients with the SX1302 signal analyser, which
+    measures the correlation of the selected radio IQ stream with a tone at a given frequency.
+
+    Mailbox protocol, the firmware acknowledges each step by reporting it in the AGC status:
+    - 0xFF: resume after the version check (status 0x00)
+    - 0x01: latch the test command (mailbox 2), radio type (mailbox 1) and TX gains (mailbox 0)
+    - 0x02: latch the RX PLL value (mailboxes 2..0, MSB first) and start the test
+    - 0x03: stop the test, radios back to standby
+
+    RX image: the other radio transmits a tone CAL_TX_TONE_FREQ_HZ above the LO, the image shows up
+    at -CAL_TX_TONE_FREQ_HZ and is cancelled by the IQ amplitude/phase compensation of RADIO_FE.
+    TX DC offset: the radio loops its TX back into its own RX, tuned CAL_TX_TONE_FREQ_HZ below the
+    TX LO, the LO leakage shows up at +CAL_TX_TONE_FREQ_HZ and is cancelled by the TX I/Q offsets.
+*/
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use libm::log10;
+use tracing::{debug, error, info, warn};
+
+use super::error::{Error, LgwFirmware};
+use super::helper::wait_ms;
+use super::loragw_reg::*;
+use super::loragw_sx1302::LorgwSx1302Trait;
+use super::loragw_sx125x::sx125x_freq_to_reg;
+use super::{Hal, LgwConfRxrf, LgwRadioType, LgwTxGainLut, LoragwRegTrait, LGW_RF_CHAIN_NB, TX_GAIN_LUT_SIZE_MAX};
+
+const CAL_ITER: usize =                     3;          /* number of runs per test, the best one is kept */
+const CAL_TX_TONE_FREQ_HZ: i32 =            250000;
+const CAL_SIG_ANA_FREQ_STEP_HZ: i32 =       15625;      /* 4MHz / 256 */
+const CAL_SIG_ANA_DURATION: i32 =           0;          /* correlation duration: 0:1, 1:2, 2:4, 3:8 ms */
+const CAL_STATUS_TIMEOUT: Duration =        Duration::from_millis(500);
+const CAL_SIG_ANA_TIMEOUT: Duration =       Duration::from_millis(100);
+
+const CAL_CMD_RX_IMAGE: u8 =                0x10;
+const CAL_CMD_TX_DC_OFFSET: u8 =            0x20;
+
+const CAL_RX_IQ_COMP_MAX: i8 =              31;         /* 6 bits signed */
+const CAL_RX_SEARCH_STEP: i8 =              8;
+const CAL_TX_OFFSET_MAX: i8 =               127;
+const CAL_TX_SEARCH_STEP: i8 =              32;
+
+const CAL_RX_IMAGE_REJ_MIN_DB: f64 =        25.0;
+const CAL_TX_DC_REJ_MIN_DB: f64 =           10.0;
+
+/**
+@struct CalResult
+@brief Best coefficients found by one calibration run
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CalResult {
+    x: i8,          //*!> RX: amplitude coefficient, TX: I offset */
+    y: i8,          //*!> RX: phase coefficient, TX: Q offset */
+    rej_db: f64,    //*!> rejection achieved */
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CalTest {
+    RxImage,
+    TxDcOffset,
+}
+
+fn best_of(runs: &[CalResult]) -> CalResult {
+    runs.iter().copied().fold(CalResult { rej_db: f64::MIN, ..Default::default() }, |best, r| if r.rej_db > best.rej_db { r } else { best })
+}
+
+fn rejection_db(reference: u16, residual: u16) -> f64 {
+    20.0 * log10(reference.max(1) as f64 / residual.max(1) as f64)
+}
+
+pub trait LoragwCalTrait {
+    fn sx1302_cal_start(&mut self, fw_version: u8, context_rf_chain: &[LgwConfRxrf], txgain_lut: &mut [LgwTxGainLut]) -> Result<()>;
+    fn sx1302_cal_wait_status(&mut self, status: u8) -> Result<()>;
+    fn sx1302_cal_test_start(&mut self, cmd: u8, radio_type: LgwRadioType, gains: u8, freq_hz: u32) -> Result<()>;
+    fn sx1302_cal_test_stop(&mut self) -> Result<()>;
+    fn sx1302_cal_sig_ana(&mut self, rf_chain: u8, freq_hz: i32) -> Result<u16>;
+    fn sx1302_cal_set_coeffs(&mut self, test: CalTest, rf_chain: u8, x: i8, y: i8) -> Result<()>;
+    fn sx1302_cal_search(&mut self, test: CalTest, rf_chain: u8) -> Result<CalResult>;
+    fn sx1302_cal_rx_image(&mut self, rf_chain: u8, freq_hz: u32, radio_type: LgwRadioType) -> Result<CalResult>;
+    fn sx1302_cal_tx_dc_offset(&mut self, rf_chain: u8, freq_hz: u32, radio_type: LgwRadioType, dac_gain: u8, mix_gain: u8) -> Result<CalResult>;
+}
+
+impl LoragwCalTrait for Hal {
+
+    fn sx1302_cal_start(&mut self, fw_version: u8, context_rf_chain: &[LgwConfRxrf], txgain_lut: &mut [LgwTxGainLut]) -> Result<()> {
+
+        /* Wait for the CAL fw to be started, its version is then available in mailbox 0 */
+        self.sx1302_cal_wait_status(0x01)?;
+
+        let version = self.sx1302_agc_mailbox_read(0)?;
+        if version != fw_version {
+            error!("ERROR: wrong CAL fw version ({})\n", version);
+            return Err(Error::FirmwareVersion { fw: LgwFirmware::CAL, expected: fw_version, got: version }.into());
+        }
+        info!("CAL FW VERSION: {}\n", version);
+
+        /* Notify the CAL fw that it can resume */
+        self.sx1302_agc_mailbox_write(3, 0xFF)?;
+        self.sx1302_cal_wait_status(0x00)?;
+
+        info!("CAL: started\n");
+
+        /* The signal analyser is driven by the host */
+        self.lgw_reg_w(SX1302_REG_RADIO_FE_SIG_ANA_CFG_FORCE_HAL_CTRL, 1)?;
+        self.lgw_reg_w(SX1302_REG_RADIO_FE_SIG_ANA_CFG_DURATION, CAL_SIG_ANA_DURATION)?;
+        self.lgw_reg_w(SX1302_REG_RADIO_FE_SIG_ANA_CFG_EN, 1)?;
+
+        let res = (|| -> Result<()> {
+            /* RX image calibration, using the other radio as a tone generator */
+            for i in 0 .. LGW_RF_CHAIN_NB as usize {
+                if !context_rf_chain[i].enable {
+                    continue;
+                }
+                if context_rf_chain[0]._type != context_rf_chain[1]._type {
+                    warn!("WARNING: RX image calibration of radio {} skipped, it needs two radios of the same type\n", i);
+                    continue;
+                }
+                let mut runs = [CalResult::default(); CAL_ITER];
+                for run in runs.iter_mut() {
+                    *run = self.sx1302_cal_rx_image(i as u8, context_rf_chain[i].freq_hz, context_rf_chain[i]._type)?;
+                }
+                let best = best_of(&runs);
+                self.sx1302_cal_set_coeffs(CalTest::RxImage, i as u8, best.x, best.y)?;
+                info!("INFO: RX image calibration of radio {}: amp:{} phi:{} rejection:{:.1}dB\n", i, best.x, best.y, best.rej_db);
+                if best.rej_db < CAL_RX_IMAGE_REJ_MIN_DB {
+                    warn!("WARNING: poor RX image rejection on radio {} ({:.1}dB)\n", i, best.rej_db);
+                }
+            }
+
+            /* TX DC offset calibration, once per distinct DAC/mixer gain pair of the LUT */
+            for i in 0 .. LGW_RF_CHAIN_NB as usize {
+                if !context_rf_chain[i].tx_enable {
+                    continue;
+                }
+                let lut = &mut txgain_lut[i];
+                let mut done: Vec<(u8, u8, CalResult)> = Vec::with_capacity(TX_GAIN_LUT_SIZE_MAX);
+                for j in 0 .. lut.size {
+                    let (dac_gain, mix_gain) = (lut.lut[j].dac_gain, lut.lut[j].mix_gain);
+                    let best = match done.iter().find(|(d, m, _)| *d == dac_gain && *m == mix_gain) {
+                        Some((_, _, r)) => *r,
+                        None => {
+                            let mut runs = [CalResult::default(); CAL_ITER];
+                            for run in runs.iter_mut() {
+                                *run = self.sx1302_cal_tx_dc_offset(i as u8, context_rf_chain[i].freq_hz, context_rf_chain[i]._type, dac_gain, mix_gain)?;
+                            }
+                            let best = best_of(&runs);
+                            info!("INFO: TX DC offset calibration of radio {} (dac:{} mix:{}): i:{} q:{} rejection:{:.1}dB\n", i, dac_gain, mix_gain, best.x, best.y, best.rej_db);
+                            if best.rej_db < CAL_TX_DC_REJ_MIN_DB {
+                                warn!("WARNING: poor TX DC offset rejection on radio {} (dac:{} mix:{}, {:.1}dB)\n", i, dac_gain, mix_gain, best.rej_db);
+                            }
+                            done.push((dac_gain, mix_gain, best));
+                            best
+                        }
+                    };
+                    lut.lut[j].offset_i = best.x;
+                    lut.lut[j].offset_q = best.y;
+                }
+                /* Offsets are applied per packet by sx1302_send */
+                self.sx1302_cal_set_coeffs(CalTest::TxDcOffset, i as u8, 0, 0)?;
+            }
+            Ok(())
+        })();
+
+        /* Give the signal analyser back, whatever the outcome */
+        self.lgw_reg_w(SX1302_REG_RADIO_FE_SIG_ANA_CFG_EN, 0)?;
+        self.lgw_reg_w(SX1302_REG_RADIO_FE_SIG_ANA_CFG_FORCE_HAL_CTRL, 0)?;
+        res?;
+
+        info!("CAL: done\n");
+        Ok(())
+    }
+
+    fn sx1302_cal_wait_status(&mut self, status: u8) -> Result<()> {
+        let start = Instant::now();
+        loop {
+            if self.sx1302_agc_status()? == status {
+                return Ok(());
+            }
+            if start.elapsed() > CAL_STATUS_TIMEOUT {
+                error!("ERROR: CAL fw did not report status 0x{:02X}\n", status);
+                return Err(Error::Calibration { reason: "timeout waiting for CAL fw status" }.into());
+            }
+            wait_ms(1);
+        }
+    }
+
+    fn sx1302_cal_test_start(&mut self, cmd: u8, radio_type: LgwRadioType, gains: u8, freq_hz: u32) -> Result<()> {
+        let Some((part_int, part_frac)) = sx125x_freq_to_reg(radio_type, freq_hz) else {
+            return Err(Error::config("radio_type", radio_type, "calibration firmware only supports SX1255/SX1257").into());
+        };
+
+        /* Test parameters */
+        self.sx1302_agc_mailbox_write(2, cmd)?;
+        self.sx1302_agc_mailbox_write(1, if radio_type == LgwRadioType::LGW_RADIO_TYPE_SX1255 { 0 } else { 1 })?;
+        self.sx1302_agc_mailbox_write(0, gains)?;
+        self.sx1302_agc_mailbox_write(3, 0x00)?; /* sync */
+        self.sx1302_agc_mailbox_write(3, 0x01)?;
+        self.sx1302_cal_wait_status(0x01)?;
+
+        /* RX PLL, then start */
+        self.sx1302_agc_mailbox_write(2, part_int as u8)?;
+        self.sx1302_agc_mailbox_write(1, (part_frac >> 8) as u8)?;
+        self.sx1302_agc_mailbox_write(0, part_frac as u8)?;
+        self.sx1302_agc_mailbox_write(3, 0x02)?;
+        self.sx1302_cal_wait_status(0x02)
+    }
+
+    fn sx1302_cal_test_stop(&mut self) -> Result<()> {
+        self.sx1302_agc_mailbox_write(3, 0x03)?;
+        self.sx1302_cal_wait_status(0x03)
+    }
+
+    fn sx1302_cal_sig_ana(&mut self, rf_chain: u8, freq_hz: i32) -> Result<u16> {
+        self.lgw_reg_w(SX1302_REG_RADIO_FE_SIG_ANA_CFG_RADIO_SEL, rf_chain as i32)?;
+        self.lgw_reg_w(SX1302_REG_RADIO_FE_SIG_ANA_FREQ_FREQ, (freq_hz / CAL_SIG_ANA_FREQ_STEP_HZ) & 0xFF)?;
+        self.lgw_reg_w(SX1302_REG_RADIO_FE_SIG_ANA_CFG_START, 0)?;
+        self.lgw_reg_w(SX1302_REG_RADIO_FE_SIG_ANA_CFG_START, 1)?;
+
+        let start = Instant::now();
+        while self.lgw_reg_r(SX1302_REG_RADIO_FE_SIG_ANA_CFG_VALID)? == 0 {
+            if start.elapsed() > CAL_SIG_ANA_TIMEOUT {
+                error!("ERROR: signal analyser timeout on radio {}\n", rf_chain);
+                return Err(Error::Calibration { reason: "signal analyser timeout" }.into());
+            }
+            wait_ms(1); /* correlation over CAL_SIG_ANA_DURATION */
+        }
+
+        let msb = self.lgw_reg_r(SX1302_REG_RADIO_FE_SIG_ANA_ABS_MSB_CORR_ABS_OUT)?;
+        let lsb = self.lgw_reg_r(SX1302_REG_RADIO_FE_SIG_ANA_ABS_LSB_CORR_ABS_OUT)?;
+        Ok((((msb & 0xFF) << 8) | (lsb & 0xFF)) as u16)
+    }
+
+    fn sx1302_cal_set_coeffs(&mut self, test: CalTest, rf_chain: u8, x: i8, y: i8) -> Result<()> {
+        match test {
+            CalTest::RxImage => {
+                let (reg_amp, reg_phi) = if rf_chain == 0 {
+                    (SX1302_REG_RADIO_FE_IQ_COMP_AMP_COEFF_RADIO_A_AMP_COEFF, SX1302_REG_RADIO_FE_IQ_COMP_PHI_COEFF_RADIO_A_PHI_COEFF)
+                } else {
+                    (SX1302_REG_RADIO_FE_IQ_COMP_AMP_COEFF_RADIO_B_AMP_COEFF, SX1302_REG_RADIO_FE_IQ_COMP_PHI_COEFF_RADIO_B_PHI_COEFF)
+                };
+                self.lgw_reg_w(reg_amp, x as i32 & 0x3F)?;
+                self.lgw_reg_w(reg_phi, y as i32 & 0x3F)
+            }
+            CalTest::TxDcOffset => {
+                let (reg_i, reg_q) = if rf_chain == 0 {
+                    (SX1302_REG_TX_TOP_A_TX_RFFE_IF_I_OFFSET_I_OFFSET, SX1302_REG_TX_TOP_A_TX_RFFE_IF_Q_OFFSET_Q_OFFSET)
+                } else {
+                    (SX1302_REG_TX_TOP_B_TX_RFFE_IF_I_OFFSET_I_OFFSET, SX1302_REG_TX_TOP_B_TX_RFFE_IF_Q_OFFSET_Q_OFFSET)
+                };
+                self.lgw_reg_w(reg_i, x as i32)?;
+                self.lgw_reg_w(reg_q, y as i32)
+            }
+        }
+    }
+
+    /* Pattern search of the (x, y) coefficients minimizing the residual, halving the step once
+       no neighbour improves it. The residual is a smooth bowl, so this converges in a few dozen
+       signal analyser runs. */
+    fn sx1302_cal_search(&mut self, test: CalTest, rf_chain: u8) -> Result<CalResult> {
+        let (max, mut step, reference, probe) = match test {
+            CalTest::RxImage => {
+                let reference = self.sx1302_cal_sig_ana(rf_chain, CAL_TX_TONE_FREQ_HZ)?;
+                (CAL_RX_IQ_COMP_MAX, CAL_RX_SEARCH_STEP, reference, -CAL_TX_TONE_FREQ_HZ)
+            }
+            CalTest::TxDcOffset => {
+                self.sx1302_cal_set_coeffs(test, rf_chain, 0, 0)?;
+                let reference = self.sx1302_cal_sig_ana(rf_chain, CAL_TX_TONE_FREQ_HZ)?;
+                (CAL_TX_OFFSET_MAX, CAL_TX_SEARCH_STEP, reference, CAL_TX_TONE_FREQ_HZ)
+            }
+        };
+
+        let (mut x, mut y) = (0i8, 0i8);
+        self.sx1302_cal_set_coeffs(test, rf_chain, x, y)?;
+        let mut best = self.sx1302_cal_sig_ana(rf_chain, probe)?;
+
+        while step > 0 {
+            let mut improved = true;
+            while improved {
+                improved = false;
+                for (dx, dy) in [(step, 0), (-step, 0), (0, step), (0, -step)] {
+                    let nx = (x as i16 + dx as i16).clamp(-(max as i16) - 1, max as i16) as i8;
+                    let ny = (y as i16 + dy as i16).clamp(-(max as i16) - 1, max as i16) as i8;
+                    if (nx, ny) == (x, y) {
+                        continue;
+                    }
+                    self.sx1302_cal_set_coeffs(test, rf_chain, nx, ny)?;
+                    let corr = self.sx1302_cal_sig_ana(rf_chain, probe)?;
+                    if corr < best {
+                        (x, y, best) = (nx, ny, corr);
+                        improved = true;
+                    }
+                }
+            }
+            step /= 2;
+        }
+
+        debug!("CAL: {:?} radio {}: x:{} y:{} reference:{} residual:{}", test, rf_chain, x, y, reference, best);
+        Ok(CalResult { x, y, rej_db: rejection_db(reference, best) })
+    }
+
+    fn sx1302_cal_rx_image(&mut self, rf_chain: u8, freq_hz: u32, radio_type: LgwRadioType) -> Result<CalResult> {
+        self.sx1302_cal_test_start(CAL_CMD_RX_IMAGE | rf_chain, radio_type, 0, freq_hz)?;
+        let res = self.sx1302_cal_search(CalTest::RxImage, rf_chain);
+        self.sx1302_cal_test_stop()?;
+        res
+    }
+
+    fn sx1302_cal_tx_dc_offset(&mut self, rf_chain: u8, freq_hz: u32, radio_type: LgwRadioType, dac_gain: u8, mix_gain: u8) -> Result<CalResult> {
+        self.sx1302_cal_test_start(CAL_CMD_TX_DC_OFFSET | rf_chain, radio_type, ((dac_gain & 0x03) << 4) | (mix_gain & 0x0F), freq_hz)?;
+        let res = self.sx1302_cal_search(CalTest::TxDcOffset, rf_chain);
+        self.sx1302_cal_test_stop()?;
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::cal_fw::CAL_FIRMWARE_SX125X;
+    use crate::hal::loragw_conf::LgwGlobalConf;
+    use crate::hal::mcu::sim::{sim_cal_tx_dc_offset, SimConcentrator, SIM_CAL_RX_IQ_COMP};
+    use crate::hal::LgwHal;
+
+    const GLOBAL_CONF: &str = r#"{"SX130x_conf": {
+        "com_path": "/dev/ttyACM0", "clksrc": 1, "lorawan_public": true,
+        "radio_0": {
+            "enable": true, "type": "SX1257", "freq": 867500000, "rssi_offset": -166.0, "tx_enable": true,
+            "tx_gain_lut": [
+                {"rf_power": 12, "pa_gain": 2, "dac_gain": 3, "mix_gain": 10},
+                {"rf_power": 14, "pa_gain": 2, "dac_gain": 3, "mix_gain": 10},
+                {"rf_power": 20, "pa_gain": 3, "dac_gain": 2, "mix_gain": 14}
+            ]
+        },
+        "radio_1": {"enable": true, "type": "SX1257", "freq": 868500000, "rssi_offset": -166.0, "tx_enable": false}
+    }}"#;
+
+    fn sim_hal() -> (SimConcentrator, Hal) {
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        LgwGlobalConf::parse(GLOBAL_CONF).unwrap().apply(&mut hal).unwrap();
+        (sim, hal)
+    }
+
+    #[test]
+    fn calibration_fills_lut() {
+        let (sim, mut hal) = sim_hal();
+        hal.lgw_start().unwrap();
+
+        /* TX DC offsets, once per DAC/mixer gain pair */
+        let lut = &hal.ctx.tx_gain_lut[0];
+        assert_eq!(lut.size, 3);
+        for gain in &lut.lut[.. lut.size] {
+            assert_eq!((gain.offset_i, gain.offset_q), sim_cal_tx_dc_offset(0, gain.dac_gain, gain.mix_gain), "dac:{} mix:{}", gain.dac_gain, gain.mix_gain);
+        }
+        assert_ne!((lut.lut[0].offset_i, lut.lut[0].offset_q), (lut.lut[2].offset_i, lut.lut[2].offset_q));
+        assert_eq!(hal.ctx.tx_gain_lut[1].size, 0);
+
+        /* RX image IQ compensation, left in RADIO_FE */
+        let sign6 = |v: i32| ((v << 2) as i8 >> 2) as i32;
+        let amp_phi = |reg_amp, reg_phi| (sign6(sim.reg_r(reg_amp).unwrap()), sign6(sim.reg_r(reg_phi).unwrap()));
+        assert_eq!(amp_phi(SX1302_REG_RADIO_FE_IQ_COMP_AMP_COEFF_RADIO_A_AMP_COEFF, SX1302_REG_RADIO_FE_IQ_COMP_PHI_COEFF_RADIO_A_PHI_COEFF), SIM_CAL_RX_IQ_COMP[0]);
+        assert_eq!(amp_phi(SX1302_REG_RADIO_FE_IQ_COMP_AMP_COEFF_RADIO_B_AMP_COEFF, SX1302_REG_RADIO_FE_IQ_COMP_PHI_COEFF_RADIO_B_PHI_COEFF), SIM_CAL_RX_IQ_COMP[1]);
+
+        /* signal analyser given back */
+        assert_eq!(sim.reg_r(SX1302_REG_RADIO_FE_SIG_ANA_CFG_EN).unwrap(), 0);
+        hal.lgw_stop().unwrap();
+    }
+
+    #[test]
+    fn mailbox_protocol() {
+        let (sim, mut hal) = sim_hal();
+        hal.lgw_start().unwrap();
+        let agc_status = || sim.reg_r(SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS).unwrap();
+
+        /* the CAL fw reports its version, then waits for 0xFF */
+        hal.sx1302_agc_load_firmware(&CAL_FIRMWARE_SX125X).unwrap();
+        hal.sx1302_cal_wait_status(0x01).unwrap();
+        assert_eq!(hal.sx1302_agc_mailbox_read(0).unwrap(), 1);
+        hal.sx1302_agc_mailbox_write(3, 0xFF).unwrap();
+        hal.sx1302_cal_wait_status(0x00).unwrap();
+
+        hal.sx1302_cal_test_start(CAL_CMD_TX_DC_OFFSET, LgwRadioType::LGW_RADIO_TYPE_SX1257, 0x3A, 867_500_000).unwrap();
+        assert_eq!(agc_status(), 0x02);
+        let err = hal.sx1302_cal_wait_status(0x01).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::Calibration { .. })));
+        hal.sx1302_cal_test_stop().unwrap();
+        assert_eq!(agc_status(), 0x03);
+
+        let err = hal.sx1302_cal_test_start(CAL_CMD_RX_IMAGE, LgwRadioType::LGW_RADIO_TYPE_SX1250, 0, 867_500_000).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::InvalidConfig { param: "radio_type", .. })));
+        hal.lgw_stop().unwrap();
+    }
+
+    #[test]
+    fn wrong_fw_version() {
+        let (_sim, mut hal) = sim_hal();
+        hal.lgw_start().unwrap();
+        hal.sx1302_agc_load_firmware(&CAL_FIRMWARE_SX125X).unwrap();
+
+        let (rf_chain, mut lut) = (hal.ctx.rf_chain_cfg.clone(), hal.ctx.tx_gain_lut);
+        let err = hal.sx1302_cal_start(2, &rf_chain, &mut lut).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::FirmwareVersion { fw: LgwFirmware::CAL, expected: 2, got: 1 })));
+        hal.lgw_stop().unwrap();
+    }
+}
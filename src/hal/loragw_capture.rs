@@ -0,0 +1,588 @@
+/*
+    Packet capture in pcapng files, for Wireshark.
+
+    Every packet returned by lgw_receive and every packet accepted by lgw_send is written as an
+    Enhanced Packet Block with the LoRaTap v1 link-layer header (LINKTYPE_LORATAP) in front of
+    the payload:
+
+        version, padding, length            1 + 1 + 2 bytes (big endian, as all LoRaTap fields)
+        frequency, bandwidth, SF            4 + 1 + 1       bandwidth in 125 kHz steps
+        packet/max/current RSSI, SNR        1 + 1 + 1 + 1   RSSI = -139 + value dBm, SNR = value / 4 dB
+        sync word                           1
+        source gateway, timestamp           8 + 4           timestamp is count_us
+        flags, CR, datarate                 1 + 1 + 2       datarate in bps, FSK only
+        IF chain, RF chain, tag             1 + 1 + 2
+
+    pcapng is used rather than pcap for the fields LoRaTap has no room for: the direction is
+    set in the packet flags (inbound for RX, outbound for TX), the fine timestamp, channel RSSI
+    and TX power go in the packet comment. Block timestamps are the host time the packet was
+    handed over, in µs.
+
+    With rotation enabled, a new file is started once the current one exceeds max_bytes or is
+    older than max_duration: capture.pcapng, then capture_1.pcapng, capture_2.pcapng...
+*/
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use tracing::{error, info};
+
+use super::{Hal, LgwPktRx, LgwPktTx, Modulation, TxMode, STAT_CRC_BAD, STAT_CRC_OK, STAT_NO_CRC};
+
+const LINKTYPE_LORATAP: u16 = 270;
+const LORATAP_VERSION: u8 = 1;
+const LORATAP_HEADER_SIZE: usize = 35;
+
+const LORA_SYNC_WORD_PUBLIC: u8 = 0x34;
+const LORA_SYNC_WORD_PRIVATE: u8 = 0x12;
+
+/* pcapng block types and options */
+const PCAPNG_SHB: u32 = 0x0A0D0D0A;
+const PCAPNG_IDB: u32 = 0x00000001;
+const PCAPNG_EPB: u32 = 0x00000006;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B3C4D;
+const OPT_ENDOFOPT: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const OPT_SHB_USERAPPL: u16 = 4;
+const OPT_IF_NAME: u16 = 2;
+const OPT_EPB_FLAGS: u16 = 2;
+const EPB_FLAGS_INBOUND: u32 = 0x1;
+const EPB_FLAGS_OUTBOUND: u32 = 0x2;
+
+/* LoRaTap flags */
+const LORATAP_FLAG_MOD_FSK: u8 = 0x01;
+const LORATAP_FLAG_IQ_INVERTED: u8 = 0x02;
+const LORATAP_FLAG_IMPLICIT_HDR: u8 = 0x04;
+const LORATAP_FLAG_CRC_OK: u8 = 0x08;
+const LORATAP_FLAG_CRC_BAD: u8 = 0x10;
+const LORATAP_FLAG_NO_CRC: u8 = 0x20;
+
+/**
+@struct LgwCaptureConf
+@brief Capture file and rotation settings
+*/
+#[derive(Debug, Clone, Default)]
+pub struct LgwCaptureConf {
+    pub path: PathBuf,                      //*!> first capture file, rotated files get a _<n> suffix */
+    pub max_bytes: Option<u64>,             //*!> start a new file once the current one exceeds this size */
+    pub max_duration: Option<Duration>,     //*!> start a new file once the current one is older than this */
+    pub gateway_id: u64,                    //*!> written in the source gateway field of LoRaTap */
+}
+
+/**
+@struct LgwCapture
+@brief pcapng writer of LoRaTap records
+*/
+#[derive(Debug)]
+pub struct LgwCapture {
+    conf: LgwCaptureConf,
+    file: BufWriter<File>,
+    file_path: PathBuf,
+    file_index: u32,
+    file_bytes: u64,
+    file_opened: Instant,
+    nb_records: u64,
+}
+
+/* Block options, each padded to 32 bits */
+fn push_option(buf: &mut Vec<u8>, code: u16, value: &[u8]) {
+    buf.extend_from_slice(&code.to_le_bytes());
+    buf.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    buf.extend_from_slice(value);
+    buf.resize(buf.len().next_multiple_of(4), 0);
+}
+
+/* Wrap a block body with its type and total length */
+fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+    let total = (12 + body.len().next_multiple_of(4)) as u32;
+    let mut buf = Vec::with_capacity(total as usize);
+    buf.extend_from_slice(&block_type.to_le_bytes());
+    buf.extend_from_slice(&total.to_le_bytes());
+    buf.extend_from_slice(body);
+    buf.resize(total as usize - 4, 0);
+    buf.extend_from_slice(&total.to_le_bytes());
+    buf
+}
+
+fn file_header() -> Vec<u8> {
+    let mut shb = Vec::new();
+    shb.extend_from_slice(&PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+    shb.extend_from_slice(&1u16.to_le_bytes());          /* major version */
+    shb.extend_from_slice(&0u16.to_le_bytes());          /* minor version */
+    shb.extend_from_slice(&(-1i64).to_le_bytes());       /* section length, unknown */
+    push_option(&mut shb, OPT_SHB_USERAPPL, b"loragw-hal");
+    push_option(&mut shb, OPT_ENDOFOPT, &[]);
+
+    let mut idb = Vec::new();
+    idb.extend_from_slice(&LINKTYPE_LORATAP.to_le_bytes());
+    idb.extend_from_slice(&0u16.to_le_bytes());          /* reserved */
+    idb.extend_from_slice(&0u32.to_le_bytes());          /* no snap length */
+    push_option(&mut idb, OPT_IF_NAME, b"sx1302");
+    push_option(&mut idb, OPT_ENDOFOPT, &[]);
+
+    let mut header = block(PCAPNG_SHB, &shb);
+    header.extend(block(PCAPNG_IDB, &idb));
+    header
+}
+
+/* RSSI in LoRaTap unit, -139 dBm offset and 1 dB steps */
+fn loratap_rssi(rssi: f32) -> u8 {
+    (rssi + 139.0).round().clamp(0.0, 255.0) as u8
+}
+
+fn loratap_bandwidth(bandwidth: u8) -> u8 {
+    (Hal::lgw_bw_getval(bandwidth).max(0) / 125000) as u8
+}
+
+/* Fields of the LoRaTap header that differ between RX and TX packets */
+struct LoraTap {
+    freq_hz: u32,
+    bandwidth: u8,
+    sf: u8,
+    rssi: [u8; 3],
+    snr: u8,
+    count_us: u32,
+    flags: u8,
+    coderate: u8,
+    datarate: u16,
+    if_chain: u8,
+    rf_chain: u8,
+}
+
+impl LoraTap {
+    fn from_modulation(modulation: Modulation, bandwidth: u8, datarate: u32) -> (u8, u8, u8, u16) {
+        match modulation {
+            Modulation::FSK => (0, 0, LORATAP_FLAG_MOD_FSK, datarate.min(u16::MAX as u32) as u16),
+            _ => (loratap_bandwidth(bandwidth), datarate as u8, 0, 0),
+        }
+    }
+
+    fn rx(pkt: &LgwPktRx) -> Self {
+        let (bandwidth, sf, mut flags, datarate) = Self::from_modulation(pkt.modulation, pkt.bandwidth, pkt.datarate);
+        flags |= match pkt.status {
+            STAT_CRC_OK => LORATAP_FLAG_CRC_OK,
+            STAT_CRC_BAD => LORATAP_FLAG_CRC_BAD,
+            STAT_NO_CRC => LORATAP_FLAG_NO_CRC,
+            _ => 0,
+        };
+        Self {
+            freq_hz: pkt.freq_hz,
+            bandwidth,
+            sf,
+            rssi: [loratap_rssi(pkt.rssis), loratap_rssi(pkt.rssis), loratap_rssi(pkt.rssic)],
+            snr: (pkt.snr * 4.0).round().clamp(i8::MIN as f32, i8::MAX as f32) as i8 as u8,
+            count_us: pkt.count_us,
+            flags,
+            coderate: pkt.coderate,
+            datarate,
+            if_chain: pkt.if_chain,
+            rf_chain: pkt.rf_chain,
+        }
+    }
+
+    fn tx(pkt: &LgwPktTx) -> Self {
+        let (bandwidth, sf, mut flags, datarate) = Self::from_modulation(pkt.modulation, pkt.bandwidth, pkt.datarate);
+        if pkt.invert_pol {
+            flags |= LORATAP_FLAG_IQ_INVERTED;
+        }
+        if pkt.no_header {
+            flags |= LORATAP_FLAG_IMPLICIT_HDR;
+        }
+        if pkt.no_crc {
+            flags |= LORATAP_FLAG_NO_CRC;
+        }
+        Self {
+            freq_hz: pkt.freq_hz,
+            bandwidth,
+            sf,
+            rssi: [0; 3],
+            snr: 0,
+            count_us: pkt.count_us,
+            flags,
+            coderate: pkt.coderate,
+            datarate,
+            if_chain: 0,
+            rf_chain: pkt.rf_chain,
+        }
+    }
+
+    fn header(&self, sync_word: u8, gateway_id: u64) -> [u8; LORATAP_HEADER_SIZE] {
+        let mut h = Vec::with_capacity(LORATAP_HEADER_SIZE);
+        h.push(LORATAP_VERSION);
+        h.push(0);
+        h.extend_from_slice(&(LORATAP_HEADER_SIZE as u16).to_be_bytes());
+        h.extend_from_slice(&self.freq_hz.to_be_bytes());
+        h.push(self.bandwidth);
+        h.push(self.sf);
+        h.extend_from_slice(&self.rssi);
+        h.push(self.snr);
+        h.push(sync_word);
+        h.extend_from_slice(&gateway_id.to_be_bytes());
+        h.extend_from_slice(&self.count_us.to_be_bytes());
+        h.push(self.flags);
+        h.push(self.coderate);
+        h.extend_from_slice(&self.datarate.to_be_bytes());
+        h.push(self.if_chain);
+        h.push(self.rf_chain);
+        h.extend_from_slice(&0u16.to_be_bytes());            /* tag */
+
+        let mut header = [0u8; LORATAP_HEADER_SIZE];
+        header.copy_from_slice(&h);
+        header
+    }
+}
+
+fn rotated_path(path: &Path, index: u32) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}_{}.{}", stem, index, ext.to_string_lossy()),
+        None => format!("{}_{}", stem, index),
+    };
+    path.with_file_name(name)
+}
+
+fn create(path: &Path) -> Result<BufWriter<File>> {
+    let mut file = BufWriter::new(File::create(path)
+        .map_err(|e| anyhow!("ERROR: failed to create capture file {}: {}", path.display(), e))?);
+    file.write_all(&file_header())?;
+    Ok(file)
+}
+
+impl LgwCapture {
+    pub fn open(conf: LgwCaptureConf) -> Result<Self> {
+        let file = create(&conf.path)?;
+        info!("INFO: capturing packets to {}", conf.path.display());
+        Ok(Self {
+            file_path: conf.path.clone(),
+            file,
+            file_index: 0,
+            file_bytes: file_header().len() as u64,
+            file_opened: Instant::now(),
+            nb_records: 0,
+            conf,
+        })
+    }
+
+    /* File currently written */
+    pub fn path(&self) -> &Path {
+        &self.file_path
+    }
+
+    /* Number of packets written since the capture was opened, over all files */
+    pub fn nb_records(&self) -> u64 {
+        self.nb_records
+    }
+
+    fn rotate_if_needed(&mut self) -> Result<()> {
+        let too_big = self.conf.max_bytes.is_some_and(|max| self.file_bytes >= max);
+        let too_old = self.conf.max_duration.is_some_and(|max| self.file_opened.elapsed() >= max);
+        if !too_big && !too_old {
+            return Ok(());
+        }
+
+        self.file.flush()?;
+        let path = rotated_path(&self.conf.path, self.file_index + 1);
+        self.file = create(&path)?;
+        info!("INFO: capture rotated to {}", path.display());
+        self.file_index += 1;
+        self.file_path = path;
+        self.file_bytes = file_header().len() as u64;
+        self.file_opened = Instant::now();
+        Ok(())
+    }
+
+    fn write_record(&mut self, tap: &LoraTap, sync_word: u8, payload: &[u8], flags: u32, comment: &str) -> Result<()> {
+        self.rotate_if_needed()?;
+
+        let ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_micros() as u64;
+        let len = (LORATAP_HEADER_SIZE + payload.len()) as u32;
+
+        let mut epb = Vec::with_capacity(64 + len as usize + comment.len());
+        epb.extend_from_slice(&0u32.to_le_bytes());                  /* interface id */
+        epb.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+        epb.extend_from_slice(&(ts as u32).to_le_bytes());
+        epb.extend_from_slice(&len.to_le_bytes());                   /* captured length */
+        epb.extend_from_slice(&len.to_le_bytes());                   /* original length */
+        epb.extend_from_slice(&tap.header(sync_word, self.conf.gateway_id));
+        epb.extend_from_slice(payload);
+        epb.resize(epb.len().next_multiple_of(4), 0);
+        push_option(&mut epb, OPT_EPB_FLAGS, &flags.to_le_bytes());
+        push_option(&mut epb, OPT_COMMENT, comment.as_bytes());
+        push_option(&mut epb, OPT_ENDOFOPT, &[]);
+
+        let record = block(PCAPNG_EPB, &epb);
+        self.file.write_all(&record)
+            .map_err(|e| anyhow!("ERROR: failed to write capture file {}: {}", self.file_path.display(), e))?;
+        self.file_bytes += record.len() as u64;
+        self.nb_records += 1;
+        Ok(())
+    }
+
+    pub fn write_rx(&mut self, pkt: &LgwPktRx, lorawan_public: bool) -> Result<()> {
+        let mut comment = format!("RX rssic {:.1} dBm, rssis {:.1} dBm, snr {:.1} dB [{:.1}, {:.1}]", pkt.rssic, pkt.rssis, pkt.snr, pkt.snr_min, pkt.snr_max);
+        if pkt.ftime_received {
+            comment += &format!(", ftime {} ns", pkt.ftime);
+        }
+        let size = (pkt.size as usize).min(pkt.payload.len());
+        self.write_record(&LoraTap::rx(pkt), sync_word(lorawan_public), &pkt.payload[.. size], EPB_FLAGS_INBOUND, &comment)
+    }
+
+    pub fn write_tx(&mut self, pkt: &LgwPktTx, lorawan_public: bool) -> Result<()> {
+        let mode = match pkt.tx_mode {
+            TxMode::IMMEDIATE => "immediate",
+            TxMode::TIMESTAMPED => "timestamped",
+            TxMode::ON_GPS => "on GPS",
+        };
+        let comment = format!("TX {} dBm, {}", pkt.rf_power, mode);
+        let size = (pkt.size as usize).min(pkt.payload.len());
+        self.write_record(&LoraTap::tx(pkt), sync_word(lorawan_public), &pkt.payload[.. size], EPB_FLAGS_OUTBOUND, &comment)
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+fn sync_word(lorawan_public: bool) -> u8 {
+    if lorawan_public { LORA_SYNC_WORD_PUBLIC } else { LORA_SYNC_WORD_PRIVATE }
+}
+
+impl Hal {
+    /* Start writing every received and sent packet to a pcapng file, replaces a running capture */
+    pub fn lgw_capture_start(&mut self, conf: LgwCaptureConf) -> Result<()> {
+        self.lgw_capture_stop()?;
+        self.capture = Some(LgwCapture::open(conf)?);
+        Ok(())
+    }
+
+    /* Stop the capture and flush its file */
+    pub fn lgw_capture_stop(&mut self) -> Result<()> {
+        if let Some(mut capture) = self.capture.take() {
+            capture.flush()?;
+            info!("INFO: capture stopped, {} packets written", capture.nb_records());
+        }
+        Ok(())
+    }
+
+    /* Capture failures are reported but do not fail lgw_receive/lgw_send */
+    pub(crate) fn lgw_capture_rx(&mut self, pkts: &[LgwPktRx]) {
+        let lorawan_public = self.ctx.board_cfg.lorawan_public;
+        if let Some(capture) = self.capture.as_mut() {
+            for pkt in pkts {
+                if let Err(e) = capture.write_rx(pkt, lorawan_public) {
+                    error!("{}", e);
+                }
+            }
+            if let Err(e) = capture.flush() {
+                error!("ERROR: failed to flush capture file: {}", e);
+            }
+        }
+    }
+
+    pub(crate) fn lgw_capture_tx(&mut self, pkt: &LgwPktTx) {
+        let lorawan_public = self.ctx.board_cfg.lorawan_public;
+        if let Some(capture) = self.capture.as_mut() {
+            if let Err(e) = capture.write_tx(pkt, lorawan_public).and_then(|_| capture.flush()) {
+                error!("{}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::{BW_125KHZ, CR_LORA_4_5, DR_LORA_SF7};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("loragw-capture-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn le16(b: &[u8]) -> u16 {
+        u16::from_le_bytes(b[.. 2].try_into().unwrap())
+    }
+
+    fn le32(b: &[u8]) -> u32 {
+        u32::from_le_bytes(b[.. 4].try_into().unwrap())
+    }
+
+    /* (block type, body) of every block, checking the leading and trailing lengths match */
+    fn blocks(mut data: &[u8]) -> Vec<(u32, Vec<u8>)> {
+        let mut blocks = Vec::new();
+        while !data.is_empty() {
+            let total = le32(&data[4 ..]) as usize;
+            assert_eq!(total % 4, 0);
+            assert_eq!(le32(&data[total - 4 ..]) as usize, total);
+            blocks.push((le32(data), data[8 .. total - 4].to_vec()));
+            data = &data[total ..];
+        }
+        blocks
+    }
+
+    /* (code, value) of the options found at the start of buf */
+    fn options(mut buf: &[u8]) -> Vec<(u16, Vec<u8>)> {
+        let mut options = Vec::new();
+        loop {
+            let (code, len) = (le16(buf), le16(&buf[2 ..]) as usize);
+            if code == OPT_ENDOFOPT {
+                return options;
+            }
+            options.push((code, buf[4 .. 4 + len].to_vec()));
+            buf = &buf[(4 + len).next_multiple_of(4) ..];
+        }
+    }
+
+    /* (LoRaTap header and payload, EPB flags, comment) of an EPB body */
+    fn packet(body: &[u8]) -> (Vec<u8>, u32, String) {
+        let len = le32(&body[12 ..]) as usize;
+        assert_eq!(le32(&body[16 ..]) as usize, len);
+        let opts = options(&body[(20 + len).next_multiple_of(4) ..]);
+        assert_eq!(opts[0].0, OPT_EPB_FLAGS);
+        assert_eq!(opts[1].0, OPT_COMMENT);
+        (body[20 .. 20 + len].to_vec(), le32(&opts[0].1), String::from_utf8(opts[1].1.clone()).unwrap())
+    }
+
+    fn rx_pkt() -> LgwPktRx {
+        let mut pkt = LgwPktRx::new();
+        pkt.freq_hz = 868_100_000;
+        pkt.if_chain = 3;
+        pkt.rf_chain = 1;
+        pkt.status = STAT_CRC_OK;
+        pkt.count_us = 0x01020304;
+        pkt.modulation = Modulation::LORA;
+        pkt.bandwidth = BW_125KHZ;
+        pkt.datarate = DR_LORA_SF7;
+        pkt.coderate = CR_LORA_4_5;
+        pkt.rssic = -48.2;
+        pkt.rssis = -50.6;
+        pkt.snr = 9.75;
+        pkt.size = 5;
+        pkt.payload[.. 5].copy_from_slice(&[0x40, 0x11, 0x22, 0x33, 0x44]);
+        pkt
+    }
+
+    fn tx_pkt() -> LgwPktTx {
+        let mut pkt = LgwPktTx {
+            freq_hz: 869_525_000,
+            tx_mode: TxMode::TIMESTAMPED,
+            count_us: 5_000_000,
+            rf_power: 27,
+            modulation: Modulation::FSK,
+            datarate: 50000,
+            no_crc: true,
+            size: 3,
+            ..Default::default()
+        };
+        pkt.payload[.. 3].copy_from_slice(&[0xA0, 0xB1, 0xC2]);
+        pkt
+    }
+
+    #[test]
+    fn pcapng_records() {
+        let dir = temp_dir("records");
+        let path = dir.join("capture.pcapng");
+        let mut hal = Hal::new();
+        hal.ctx.board_cfg.lorawan_public = true;
+        hal.lgw_capture_start(LgwCaptureConf { path: path.clone(), gateway_id: 0xAA555A0000000101, ..Default::default() }).unwrap();
+        hal.lgw_capture_rx(&[rx_pkt()]);
+        hal.lgw_capture_tx(&tx_pkt());
+        hal.lgw_capture_stop().unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        let blocks = blocks(&data);
+        assert_eq!(blocks.iter().map(|b| b.0).collect::<Vec<_>>(), [PCAPNG_SHB, PCAPNG_IDB, PCAPNG_EPB, PCAPNG_EPB]);
+
+        /* SHB: magic, version 1.0, unknown section length, application name */
+        let shb = &blocks[0].1;
+        assert_eq!(shb.len() + 12, 48);
+        assert_eq!((le32(shb), le16(&shb[4 ..]), le16(&shb[6 ..])), (PCAPNG_BYTE_ORDER_MAGIC, 1, 0));
+        assert_eq!(&shb[8 .. 16], &[0xFF; 8]);
+        assert_eq!(options(&shb[16 ..]), [(OPT_SHB_USERAPPL, b"loragw-hal".to_vec())]);
+
+        /* IDB: LoRaTap link type */
+        let idb = &blocks[1].1;
+        assert_eq!(idb.len() + 12, 36);
+        assert_eq!((le16(idb), le32(&idb[4 ..])), (LINKTYPE_LORATAP, 0));
+        assert_eq!(options(&idb[8 ..]), [(OPT_IF_NAME, b"sx1302".to_vec())]);
+
+        /* RX packet */
+        let (rx, flags, comment) = packet(&blocks[2].1);
+        assert_eq!(flags, EPB_FLAGS_INBOUND);
+        assert_eq!(comment, "RX rssic -48.2 dBm, rssis -50.6 dBm, snr 9.8 dB [0.0, 0.0]");
+        assert_eq!(rx.len(), LORATAP_HEADER_SIZE + 5);
+        assert_eq!(blocks[2].1.len() + 12, 12 + 20 + 40 + 8 + (4 + comment.len().next_multiple_of(4)) + 4);
+        assert_eq!((rx[0], rx[1], &rx[2 .. 4]), (LORATAP_VERSION, 0, &[0, 35][..]));
+        assert_eq!(&rx[4 .. 8], &868_100_000u32.to_be_bytes());
+        assert_eq!((rx[8], rx[9]), (1, 7));                                  /* 125kHz, SF7 */
+        assert_eq!(&rx[10 .. 15], &[88, 88, 91, 39, LORA_SYNC_WORD_PUBLIC]);  /* RSSIs, SNR * 4 */
+        assert_eq!(&rx[15 .. 23], &0xAA555A0000000101u64.to_be_bytes());
+        assert_eq!(&rx[23 .. 27], &[0x01, 0x02, 0x03, 0x04]);
+        assert_eq!((rx[27], rx[28], &rx[29 .. 31]), (LORATAP_FLAG_CRC_OK, CR_LORA_4_5, &[0, 0][..]));
+        assert_eq!((rx[31], rx[32], &rx[33 .. 35]), (3, 1, &[0, 0][..]));
+        assert_eq!(&rx[35 ..], &[0x40, 0x11, 0x22, 0x33, 0x44]);
+
+        /* TX packet */
+        let (tx, flags, comment) = packet(&blocks[3].1);
+        assert_eq!(flags, EPB_FLAGS_OUTBOUND);
+        assert_eq!(comment, "TX 27 dBm, timestamped");
+        assert_eq!(&tx[4 .. 8], &869_525_000u32.to_be_bytes());
+        assert_eq!((tx[8], tx[9]), (0, 0));
+        assert_eq!(&tx[10 .. 15], &[0, 0, 0, 0, LORA_SYNC_WORD_PUBLIC]);
+        assert_eq!(&tx[23 .. 27], &5_000_000u32.to_be_bytes());
+        assert_eq!((tx[27], &tx[29 .. 31]), (LORATAP_FLAG_MOD_FSK | LORATAP_FLAG_NO_CRC, &50000u16.to_be_bytes()[..]));
+        assert_eq!(&tx[35 ..], &[0xA0, 0xB1, 0xC2]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tx_flags_private_network() {
+        let dir = temp_dir("flags");
+        let path = dir.join("capture.pcapng");
+        let mut capture = LgwCapture::open(LgwCaptureConf { path: path.clone(), ..Default::default() }).unwrap();
+        let pkt = LgwPktTx {
+            modulation: Modulation::LORA, bandwidth: BW_125KHZ, datarate: DR_LORA_SF7, invert_pol: true, no_header: true, ..Default::default()
+        };
+        capture.write_tx(&pkt, false).unwrap();
+        capture.flush().unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        let (tx, _, _) = packet(&blocks(&data)[2].1);
+        assert_eq!((tx[8], tx[9], tx[14]), (1, 7, LORA_SYNC_WORD_PRIVATE));
+        assert_eq!(tx[27], LORATAP_FLAG_IQ_INVERTED | LORATAP_FLAG_IMPLICIT_HDR);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotation() {
+        let dir = temp_dir("rotation");
+        let path = dir.join("capture.pcapng");
+        let max_bytes = file_header().len() as u64 + 1;
+        let mut capture = LgwCapture::open(LgwCaptureConf { path: path.clone(), max_bytes: Some(max_bytes), ..Default::default() }).unwrap();
+
+        /* the first record fits, the next ones each start a new file */
+        for _ in 0 .. 3 {
+            capture.write_rx(&rx_pkt(), true).unwrap();
+        }
+        capture.flush().unwrap();
+        assert_eq!(capture.path(), dir.join("capture_2.pcapng"));
+        assert_eq!(capture.nb_records(), 3);
+
+        for name in ["capture.pcapng", "capture_1.pcapng", "capture_2.pcapng"] {
+            let data = std::fs::read(dir.join(name)).unwrap();
+            assert_eq!(blocks(&data).iter().map(|b| b.0).collect::<Vec<_>>(), [PCAPNG_SHB, PCAPNG_IDB, PCAPNG_EPB], "{}", name);
+        }
+        assert!(!dir.join("capture_3.pcapng").exists());
+        assert_eq!(rotated_path(Path::new("/tmp/capture"), 4), Path::new("/tmp/capture_4"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use super::{mcu::McuTrait, Hal};
 use std::fmt;
+use std::io::{Read, Write};
 
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -44,6 +45,77 @@ impl fmt::Display for LgwSpiMuxTarget {
     }
 }
 
+/* Byte stream carrying the MCU protocol (serial port, in-memory pipe, TCP tunnel...) */
+pub trait LgwComTransport: fmt::Debug + Send {
+    fn open(&mut self, port_name: String) -> Result<()>;
+    fn close(&mut self);
+    fn is_open(&self) -> bool;
+    fn write(&mut self, data: &[u8]) -> Result<usize, std::io::Error>;
+    fn read(&mut self, data: &mut [u8]) -> Result<usize, std::io::Error>;
+
+    fn read_exact(&mut self, data: &mut [u8]) -> Result<(), std::io::Error> {
+        let mut n = 0;
+        while n < data.len() {
+            match self.read(&mut data[n..]) {
+                Ok(0) => return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "failed to fill whole buffer")),
+                Ok(k) => n += k,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/* Transport over an already connected byte stream (e.g. TcpStream), open() is a no-op */
+#[derive(Debug)]
+pub struct StreamComPort<S> {
+    stream: Option<S>,
+}
+
+impl<S: Read + Write + fmt::Debug + Send> StreamComPort<S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream: Some(stream)
+        }
+    }
+}
+
+impl<S: Read + Write + fmt::Debug + Send> LgwComTransport for StreamComPort<S> {
+    fn open(&mut self, _port_name: String) -> Result<()> {
+        if self.stream.is_none() {
+            return Err(anyhow::anyhow!("ERROR: stream has been closed"));
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.stream = None;
+    }
+
+    fn is_open(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
+        match self.stream {
+            Some(ref mut stream) => {
+                stream.write_all(data)?;
+                stream.flush()?;
+                Ok(data.len())
+            }
+            None => Err(std::io::Error::other("Port not open"))
+        }
+    }
+
+    fn read(&mut self, data: &mut [u8]) -> Result<usize, std::io::Error> {
+        match self.stream {
+            Some(ref mut stream) => stream.read(data),
+            None => Err(std::io::Error::other("Port not open"))
+        }
+    }
+}
+
 pub trait LoragwComTrait {
     fn lgw_com_open(&mut self)->Result<()>;
     fn lgw_com_w(&mut self, spi_mux_target:LgwSpiMuxTarget, address: u16, value: u8) -> Result<()>;
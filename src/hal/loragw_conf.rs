@@ -0,0 +1,903 @@
+/*
+    Loader for the Semtech packet forwarder configuration file (global_conf.json).
+
+    Only the "SX130x_conf" object is interpreted, with the same keys and defaults as the
+    reference lora_pkt_fwd. Other top level objects (gateway_conf, debug_conf...) are kept as
+    raw JSON for the application. Errors always name the offending key, e.g.
+    "SX130x_conf.radio_0.freq".
+
+    A configuration can be written back with to_json/to_file, the output is accepted by parse
+    and by the reference packet forwarder.
+*/
+
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use tracing::{debug, info};
+
+use super::loragw_com::LgwComType;
+use super::{Hal, LgwConfDemod, LgwConfLbt, LgwConfRxIf, LgwConfRxrf, LgwConfSx1261, LgwConfigBoard, LgwConfigFtime, LgwFtimeMode, LgwHal,
+            LgwLbtScanTime, LgwRadioType, LgwRssiTcomp, LgwTxGain, BW_125KHZ, BW_250KHZ, BW_500KHZ, BW_UNDEFINED, LGW_IF_CHAIN_NB,
+            LGW_LBT_CHANNEL_NB_MAX, LGW_MULTI_NB, LGW_MULTI_SF_EN, LGW_RF_CHAIN_NB, TX_GAIN_LUT_SIZE_MAX};
+
+const SX130X_CONF_KEY: &str = "SX130x_conf";
+const IF_CHAIN_LORA_STD: usize = 8;
+const IF_CHAIN_FSK: usize = 9;
+
+/**
+@struct LgwConfSpectralScan
+@brief Background spectral scan of "sx1261_conf.spectral_scan", run by the application
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LgwConfSpectralScan {
+    pub enable: bool,
+    pub freq_hz_start: u32,     //*!> center frequency of the first channel (in Hz) */
+    pub nb_chan: u8,            //*!> number of channels to scan, 200kHz apart */
+    pub nb_scan: u16,           //*!> number of RSSI reads per channel */
+    pub pace_s: u32,            //*!> delay between two sweeps (in seconds) */
+}
+
+/**
+@struct LgwGlobalConf
+@brief Concentrator configuration read from a global_conf.json file
+*/
+#[derive(Debug, Clone)]
+pub struct LgwGlobalConf {
+    pub board: LgwConfigBoard,
+    pub rf_chains: [LgwConfRxrf; LGW_RF_CHAIN_NB as usize],
+    pub demod: LgwConfDemod,
+    pub if_chains: [LgwConfRxIf; LGW_IF_CHAIN_NB as usize],
+    pub ftime: LgwConfigFtime,
+    pub sx1261: LgwConfSx1261,
+    pub spectral_scan: LgwConfSpectralScan,     //*!> not used by the HAL itself, see lgw_spectral_scan_sweep */
+    pub tx_freq_min: [u32; LGW_RF_CHAIN_NB as usize],   //*!> lowest TX frequency allowed on each RF chain, 0 for no limit */
+    pub tx_freq_max: [u32; LGW_RF_CHAIN_NB as usize],   //*!> highest TX frequency allowed on each RF chain, 0 for no limit */
+    pub gateway_conf: Option<Value>,    //*!> "gateway_conf" object, not interpreted by the HAL */
+    pub debug_conf: Option<Value>,      //*!> "debug_conf" object, not interpreted by the HAL */
+}
+
+impl LgwGlobalConf {
+    /* Empty configuration, with the same defaults as parse for missing keys */
+    pub fn new(board: LgwConfigBoard) -> Self {
+        Self {
+            board,
+            rf_chains: Default::default(),
+            demod: LgwConfDemod { multisf_datarate: LGW_MULTI_SF_EN },
+            if_chains: Default::default(),
+            ftime: Default::default(),
+            sx1261: Default::default(),
+            spectral_scan: Default::default(),
+            tx_freq_min: Default::default(),
+            tx_freq_max: Default::default(),
+            gateway_conf: None,
+            debug_conf: None,
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("ERROR: failed to read {}: {}", path.display(), e))?;
+
+        Self::parse(&s).map_err(|e| anyhow!("{} ({})", e, path.display()))
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        /* reference files may contain C/C++ style comments, as accepted by the packet forwarder */
+        let root: Value = serde_json::from_str(&strip_comments(s))
+            .map_err(|e| anyhow!("ERROR: invalid JSON: {}", e))?;
+        let root = root.as_object().ok_or(anyhow!("ERROR: JSON root must be an object"))?;
+
+        let sx130x = root.get(SX130X_CONF_KEY)
+            .ok_or(anyhow!("ERROR: missing {} object", SX130X_CONF_KEY))?;
+        let sx130x = as_object(sx130x, SX130X_CONF_KEY)?;
+
+        let mut conf = Self {
+            gateway_conf: root.get("gateway_conf").cloned(),
+            debug_conf: root.get("debug_conf").cloned(),
+            ..Self::new(parse_board(sx130x)?)
+        };
+
+        for i in 0 .. LGW_RF_CHAIN_NB as usize {
+            let key = format!("radio_{}", i);
+            if let Some(obj) = sx130x.get(&key) {
+                let key = path(&[SX130X_CONF_KEY, &key]);
+                let obj = as_object(obj, &key)?;
+                conf.rf_chains[i] = parse_radio(obj, &key)?;
+                if conf.rf_chains[i].enable && conf.rf_chains[i].tx_enable {
+                    /* not used by the HAL itself, only by the packet forwarder to validate downlinks */
+                    conf.tx_freq_min[i] = field(obj, &key, "tx_freq_min")?.unwrap_or(0);
+                    conf.tx_freq_max[i] = field(obj, &key, "tx_freq_max")?.unwrap_or(0);
+                }
+            }
+        }
+
+        if let Some(obj) = sx130x.get("chan_multiSF_All") {
+            let key = path(&[SX130X_CONF_KEY, "chan_multiSF_All"]);
+            conf.demod = parse_multisf_all(as_object(obj, &key)?, &key)?;
+        }
+
+        for i in 0 .. LGW_MULTI_NB as usize {
+            let key = format!("chan_multiSF_{}", i);
+            if let Some(obj) = sx130x.get(&key) {
+                let key = path(&[SX130X_CONF_KEY, &key]);
+                conf.if_chains[i] = parse_chan_multisf(as_object(obj, &key)?, &key)?;
+            }
+        }
+
+        if let Some(obj) = sx130x.get("chan_Lora_std") {
+            let key = path(&[SX130X_CONF_KEY, "chan_Lora_std"]);
+            conf.if_chains[IF_CHAIN_LORA_STD] = parse_chan_lora_std(as_object(obj, &key)?, &key)?;
+        }
+
+        if let Some(obj) = sx130x.get("chan_FSK") {
+            let key = path(&[SX130X_CONF_KEY, "chan_FSK"]);
+            conf.if_chains[IF_CHAIN_FSK] = parse_chan_fsk(as_object(obj, &key)?, &key)?;
+        }
+
+        if let Some(obj) = sx130x.get("fine_timestamp") {
+            let key = path(&[SX130X_CONF_KEY, "fine_timestamp"]);
+            conf.ftime = parse_fine_timestamp(as_object(obj, &key)?, &key)?;
+        }
+
+        if let Some(obj) = sx130x.get("sx1261_conf") {
+            let key = path(&[SX130X_CONF_KEY, "sx1261_conf"]);
+            let obj = as_object(obj, &key)?;
+            conf.sx1261 = parse_sx1261(obj, &key)?;
+
+            if let Some(scan) = obj.get("spectral_scan") {
+                let key = path(&[&key, "spectral_scan"]);
+                conf.spectral_scan = parse_spectral_scan(as_object(scan, &key)?, &key)?;
+                /* the SX1261 is needed for the scan as for LBT */
+                conf.sx1261.enable |= conf.spectral_scan.enable;
+            }
+        }
+
+        Ok(conf)
+    }
+
+    /* global_conf.json representation, disabled chains only carry their "enable" key */
+    pub fn to_json(&self) -> Value {
+        let mut sx130x = write_board(&self.board);
+
+        for (i, rf) in self.rf_chains.iter().enumerate() {
+            sx130x.insert(format!("radio_{}", i), write_radio(rf, self.tx_freq_min[i], self.tx_freq_max[i]));
+        }
+
+        let sfs: Vec<u8> = (5 ..= 12).filter(|sf| self.demod.multisf_datarate & (1 << (sf - 5)) != 0).collect();
+        sx130x.insert("chan_multiSF_All".to_string(), json!({ "spreading_factor_enable": sfs }));
+
+        for (i, ifc) in self.if_chains.iter().enumerate() {
+            let (key, obj) = match i {
+                IF_CHAIN_LORA_STD => ("chan_Lora_std".to_string(), write_chan_lora_std(ifc)),
+                IF_CHAIN_FSK => ("chan_FSK".to_string(), write_chan_fsk(ifc)),
+                _ => (format!("chan_multiSF_{}", i), write_chan(ifc)),
+            };
+            sx130x.insert(key, Value::Object(obj));
+        }
+
+        sx130x.insert("fine_timestamp".to_string(), json!({ "enable": self.ftime.enable, "mode": self.ftime.mode }));
+        sx130x.insert("sx1261_conf".to_string(), write_sx1261(&self.sx1261, &self.spectral_scan));
+
+        let mut root = Map::new();
+        root.insert(SX130X_CONF_KEY.to_string(), Value::Object(sx130x));
+        if let Some(v) = &self.gateway_conf {
+            root.insert("gateway_conf".to_string(), v.clone());
+        }
+        if let Some(v) = &self.debug_conf {
+            root.insert("debug_conf".to_string(), v.clone());
+        }
+
+        Value::Object(root)
+    }
+
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let s = serde_json::to_string_pretty(&self.to_json())
+            .map_err(|e| anyhow!("ERROR: failed to serialize configuration: {}", e))?;
+
+        std::fs::write(path, s + "\n")
+            .map_err(|e| anyhow!("ERROR: failed to write {}: {}", path.display(), e))
+    }
+
+    /* Apply the configuration through the lgw_*_setconf functions, the concentrator must be stopped */
+    pub fn apply(&self, hal: &mut Hal) -> Result<()> {
+        hal.lgw_board_setconf(&self.board)
+            .map_err(|e| rejected(&[SX130X_CONF_KEY], e))?;
+
+        for (i, rf) in self.rf_chains.iter().enumerate() {
+            let key = format!("radio_{}", i);
+            hal.lgw_rxrf_setconf(i as u8, rf)
+                .map_err(|e| rejected(&[SX130X_CONF_KEY, &key], e))?;
+
+            if let Some(lut) = rf.tx_gain_lut.as_ref().filter(|_| rf.enable && rf.tx_enable) {
+                hal.lgw_txgain_setconf(i as u8, lut)
+                    .map_err(|e| rejected(&[SX130X_CONF_KEY, &key, "tx_gain_lut"], e))?;
+            }
+        }
+
+        hal.lgw_demod_setconf(&self.demod);
+
+        for (i, ifc) in self.if_chains.iter().enumerate() {
+            let key = match i {
+                IF_CHAIN_LORA_STD => "chan_Lora_std".to_string(),
+                IF_CHAIN_FSK => "chan_FSK".to_string(),
+                _ => format!("chan_multiSF_{}", i),
+            };
+            hal.lgw_rxif_setconf(i as u8, ifc)
+                .map_err(|e| rejected(&[SX130X_CONF_KEY, &key], e))?;
+        }
+
+        hal.lgw_ftime_setconf(&self.ftime)
+            .map_err(|e| rejected(&[SX130X_CONF_KEY, "fine_timestamp"], e))?;
+
+        hal.lgw_sx1261_setconf(&self.sx1261)
+            .map_err(|e| rejected(&[SX130X_CONF_KEY, "sx1261_conf"], e))?;
+
+        info!("INFO: global configuration applied");
+        Ok(())
+    }
+}
+
+impl Hal {
+    /* Read a global_conf.json file and apply its SX130x_conf section */
+    pub fn lgw_load_conf<P: AsRef<Path>>(&mut self, path: P) -> Result<LgwGlobalConf> {
+        let conf = LgwGlobalConf::from_file(path)?;
+        conf.apply(self)?;
+        Ok(conf)
+    }
+}
+
+fn path(keys: &[&str]) -> String {
+    keys.join(".")
+}
+
+fn rejected(keys: &[&str], e: anyhow::Error) -> anyhow::Error {
+    anyhow!("ERROR: {} rejected: {}", path(keys), e)
+}
+
+fn as_object<'a>(value: &'a Value, key: &str) -> Result<&'a Map<String, Value>> {
+    value.as_object().ok_or(anyhow!("ERROR: {} must be an object", key))
+}
+
+/* Optional field, None when missing or null */
+fn field<T: DeserializeOwned>(obj: &Map<String, Value>, parent: &str, key: &str) -> Result<Option<T>> {
+    match obj.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(v) => serde_json::from_value(v.clone())
+            .map(Some)
+            .map_err(|e| anyhow!("ERROR: invalid value for {}.{}: {}", parent, key, e)),
+    }
+}
+
+fn required<T: DeserializeOwned>(obj: &Map<String, Value>, parent: &str, key: &str) -> Result<T> {
+    field(obj, parent, key)?.ok_or(anyhow!("ERROR: missing {}.{}", parent, key))
+}
+
+fn invalid(parent: &str, key: &str, msg: &str) -> anyhow::Error {
+    anyhow!("ERROR: invalid value for {}.{}: {}", parent, key, msg)
+}
+
+fn parse_board(obj: &Map<String, Value>) -> Result<LgwConfigBoard> {
+    let parent = SX130X_CONF_KEY;
+    let mut board = LgwConfigBoard::default();
+
+    if let Some(com_type) = field::<String>(obj, parent, "com_type")? {
+        board.com_type = match com_type.as_str() {
+            "USB" | "usb" => LgwComType::LGW_COM_USB,
+            "SPI" | "spi" => LgwComType::LGW_COM_SPI,
+            _ => return Err(invalid(parent, "com_type", &format!("{} (expected USB or SPI)", com_type))),
+        };
+    }
+    board.com_path = required(obj, parent, "com_path")?;
+    board.lorawan_public = field(obj, parent, "lorawan_public")?.unwrap_or(false);
+    board.clksrc = required(obj, parent, "clksrc")?;
+    board.full_duplex = field(obj, parent, "full_duplex")?.unwrap_or(false);
+
+    debug!("Note: board configuration; com_type:{} com_path:{} lorawan_public:{} clksrc:{} full_duplex:{}", board.com_type, board.com_path, board.lorawan_public, board.clksrc, board.full_duplex);
+    Ok(board)
+}
+
+fn parse_radio(obj: &Map<String, Value>, parent: &str) -> Result<LgwConfRxrf> {
+    let mut rf = LgwConfRxrf {
+        enable: field(obj, parent, "enable")?.unwrap_or(false),
+        ..Default::default()
+    };
+    if !rf.enable {
+        return Ok(rf);
+    }
+
+    rf.freq_hz = required(obj, parent, "freq")?;
+    rf._type = required(obj, parent, "type")?;
+    if rf._type == LgwRadioType::LGW_RADIO_TYPE_NONE {
+        return Err(invalid(parent, "type", "NONE"));
+    }
+    rf.rssi_offset = field(obj, parent, "rssi_offset")?.unwrap_or(0.0);
+    rf.rssi_tcomp = field(obj, parent, "rssi_tcomp")?.unwrap_or(LgwRssiTcomp { coeff_a: 0.0, coeff_b: 0.0, coeff_c: 0.0, coeff_d: 0.0, coeff_e: 0.0 });
+    rf.single_input_mode = field(obj, parent, "single_input_mode")?.unwrap_or(false);
+    rf.tx_enable = field(obj, parent, "tx_enable")?.unwrap_or(false);
+
+    if rf.tx_enable {
+        if let Some(lut) = field::<Vec<LgwTxGain>>(obj, parent, "tx_gain_lut")? {
+            if lut.is_empty() || lut.len() > TX_GAIN_LUT_SIZE_MAX {
+                return Err(invalid(parent, "tx_gain_lut", &format!("{} entries (expected 1 to {})", lut.len(), TX_GAIN_LUT_SIZE_MAX)));
+            }
+            rf.tx_gain_lut = Some(lut);
+        }
+    }
+
+    Ok(rf)
+}
+
+fn parse_multisf_all(obj: &Map<String, Value>, parent: &str) -> Result<LgwConfDemod> {
+    let sfs: Vec<u8> = required(obj, parent, "spreading_factor_enable")?;
+    let mut mask = 0u8;
+
+    for sf in sfs {
+        if !(5 ..= 12).contains(&sf) {
+            return Err(invalid(parent, "spreading_factor_enable", &format!("SF{} (expected 5 to 12)", sf)));
+        }
+        mask |= 1 << (sf - 5);
+    }
+
+    Ok(LgwConfDemod { multisf_datarate: mask })
+}
+
+fn lora_bandwidth(obj: &Map<String, Value>, parent: &str) -> Result<u8> {
+    match field::<u32>(obj, parent, "bandwidth")? {
+        None => Ok(BW_UNDEFINED),
+        Some(500000) => Ok(BW_500KHZ),
+        Some(250000) => Ok(BW_250KHZ),
+        Some(125000) => Ok(BW_125KHZ),
+        Some(bw) => Err(invalid(parent, "bandwidth", &format!("{} (expected 125000, 250000 or 500000)", bw))),
+    }
+}
+
+fn parse_chan_multisf(obj: &Map<String, Value>, parent: &str) -> Result<LgwConfRxIf> {
+    let mut ifc = LgwConfRxIf {
+        enable: field(obj, parent, "enable")?.unwrap_or(false),
+        ..Default::default()
+    };
+    if !ifc.enable {
+        return Ok(ifc);
+    }
+
+    ifc.rf_chain = required(obj, parent, "radio")?;
+    ifc.freq_hz = required(obj, parent, "if")?;
+
+    Ok(ifc)
+}
+
+fn parse_chan_lora_std(obj: &Map<String, Value>, parent: &str) -> Result<LgwConfRxIf> {
+    let mut ifc = LgwConfRxIf {
+        enable: field(obj, parent, "enable")?.unwrap_or(false),
+        ..Default::default()
+    };
+    if !ifc.enable {
+        return Ok(ifc);
+    }
+
+    ifc.rf_chain = required(obj, parent, "radio")?;
+    ifc.freq_hz = required(obj, parent, "if")?;
+    ifc.bandwidth = lora_bandwidth(obj, parent)?;
+    ifc.datarate = field(obj, parent, "spread_factor")?.unwrap_or(0);
+    ifc.implicit_hdr = field(obj, parent, "implicit_hdr")?.unwrap_or(false);
+    if ifc.implicit_hdr {
+        ifc.implicit_payload_length = required(obj, parent, "implicit_payload_length")?;
+        ifc.implicit_crc_en = required(obj, parent, "implicit_crc_en")?;
+        ifc.implicit_coderate = required(obj, parent, "implicit_coderate")?;
+    }
+
+    Ok(ifc)
+}
+
+fn parse_chan_fsk(obj: &Map<String, Value>, parent: &str) -> Result<LgwConfRxIf> {
+    let mut ifc = LgwConfRxIf {
+        enable: field(obj, parent, "enable")?.unwrap_or(false),
+        ..Default::default()
+    };
+    if !ifc.enable {
+        return Ok(ifc);
+    }
+
+    ifc.rf_chain = required(obj, parent, "radio")?;
+    ifc.freq_hz = required(obj, parent, "if")?;
+    ifc.datarate = required(obj, parent, "datarate")?;
+
+    /* bandwidth is either given or deduced from the frequency deviation */
+    let mut bw: u32 = field(obj, parent, "bandwidth")?.unwrap_or(0);
+    let fdev: u32 = field(obj, parent, "freq_deviation")?.unwrap_or(0);
+    if bw == 0 && fdev != 0 {
+        bw = 2 * fdev + ifc.datarate;
+    }
+    ifc.bandwidth = match bw {
+        0 => BW_UNDEFINED,
+        1 ..= 125000 => BW_125KHZ,
+        125001 ..= 250000 => BW_250KHZ,
+        250001 ..= 500000 => BW_500KHZ,
+        _ => return Err(invalid(parent, "bandwidth", &format!("{} (maximum 500000)", bw))),
+    };
+
+    if let Some(size) = field::<u8>(obj, parent, "sync_word_size")? {
+        ifc.sync_word_size = size;
+        ifc.sync_word = required(obj, parent, "sync_word")?;
+    }
+
+    Ok(ifc)
+}
+
+fn parse_fine_timestamp(obj: &Map<String, Value>, parent: &str) -> Result<LgwConfigFtime> {
+    let mut ftime = LgwConfigFtime {
+        enable: field(obj, parent, "enable")?.unwrap_or(false),
+        ..Default::default()
+    };
+    if ftime.enable {
+        ftime.mode = field::<LgwFtimeMode>(obj, parent, "mode")?.unwrap_or(LgwFtimeMode::LGW_FTIME_MODE_HIGH_CAPACITY);
+    }
+
+    Ok(ftime)
+}
+
+fn parse_sx1261(obj: &Map<String, Value>, parent: &str) -> Result<LgwConfSx1261> {
+    let mut sx1261 = LgwConfSx1261 {
+        spi_path: field(obj, parent, "spi_path")?,
+        rssi_offset: field(obj, parent, "rssi_offset")?.unwrap_or(0.0),
+        ..Default::default()
+    };
+
+    if let Some(lbt) = obj.get("lbt") {
+        let parent = path(&[parent, "lbt"]);
+        sx1261.lbt_conf = parse_lbt(as_object(lbt, &parent)?, &parent)?;
+    }
+
+    /* the SX1261 is only needed for LBT and spectral scan */
+    sx1261.enable = field(obj, parent, "enable")?.unwrap_or(false) || sx1261.lbt_conf.enable;
+
+    Ok(sx1261)
+}
+
+fn parse_spectral_scan(obj: &Map<String, Value>, parent: &str) -> Result<LgwConfSpectralScan> {
+    let mut scan = LgwConfSpectralScan {
+        enable: field(obj, parent, "enable")?.unwrap_or(false),
+        ..Default::default()
+    };
+    if !scan.enable {
+        return Ok(scan);
+    }
+
+    scan.freq_hz_start = required(obj, parent, "freq_start")?;
+    scan.nb_chan = required(obj, parent, "nb_chan")?;
+    if scan.nb_chan == 0 {
+        return Err(invalid(parent, "nb_chan", "0 (at least one channel)"));
+    }
+    scan.nb_scan = required(obj, parent, "nb_scan")?;
+    if scan.nb_scan == 0 {
+        return Err(invalid(parent, "nb_scan", "0 (at least one RSSI read)"));
+    }
+    scan.pace_s = required(obj, parent, "pace_s")?;
+
+    Ok(scan)
+}
+
+fn parse_lbt(obj: &Map<String, Value>, parent: &str) -> Result<LgwConfLbt> {
+    let mut lbt = LgwConfLbt {
+        enable: field(obj, parent, "enable")?.unwrap_or(false),
+        ..Default::default()
+    };
+    if !lbt.enable {
+        return Ok(lbt);
+    }
+
+    lbt.rssi_target = required(obj, parent, "rssi_target")?;
+
+    let channels: Vec<Value> = required(obj, parent, "channels")?;
+    if channels.len() > LGW_LBT_CHANNEL_NB_MAX {
+        return Err(invalid(parent, "channels", &format!("{} channels (maximum {})", channels.len(), LGW_LBT_CHANNEL_NB_MAX)));
+    }
+    lbt.nb_channel = channels.len() as u8;
+
+    for (i, chan) in channels.iter().enumerate() {
+        let parent = format!("{}.channels[{}]", parent, i);
+        let chan = as_object(chan, &parent)?;
+        let conf = &mut lbt.channels[i];
+
+        conf.freq_hz = required(chan, &parent, "freq_hz")?;
+        conf.bandwidth = lora_bandwidth(chan, &parent)?;
+        conf.scan_time_us = match required::<u32>(chan, &parent, "scan_time_us")? {
+            128 => LgwLbtScanTime::LGW_LBT_SCAN_TIME_128_US,
+            5000 => LgwLbtScanTime::LGW_LBT_SCAN_TIME_5000_US,
+            t => return Err(invalid(&parent, "scan_time_us", &format!("{} (expected 128 or 5000)", t))),
+        };
+        conf.transmit_time_ms = required(chan, &parent, "transmit_time_ms")?;
+    }
+
+    Ok(lbt)
+}
+
+fn write_board(board: &LgwConfigBoard) -> Map<String, Value> {
+    let mut obj = Map::new();
+    let com_type = match board.com_type {
+        LgwComType::LGW_COM_SPI => "SPI",
+        _ => "USB",
+    };
+
+    obj.insert("com_type".to_string(), json!(com_type));
+    obj.insert("com_path".to_string(), json!(board.com_path));
+    obj.insert("lorawan_public".to_string(), json!(board.lorawan_public));
+    obj.insert("clksrc".to_string(), json!(board.clksrc));
+    obj.insert("full_duplex".to_string(), json!(board.full_duplex));
+    obj
+}
+
+fn write_radio(rf: &LgwConfRxrf, tx_freq_min: u32, tx_freq_max: u32) -> Value {
+    if !rf.enable {
+        return json!({ "enable": false });
+    }
+
+    let mut obj = Map::new();
+    obj.insert("enable".to_string(), json!(true));
+    obj.insert("type".to_string(), json!(rf._type));
+    obj.insert("freq".to_string(), json!(rf.freq_hz));
+    obj.insert("rssi_offset".to_string(), json!(rf.rssi_offset));
+    obj.insert("rssi_tcomp".to_string(), json!(rf.rssi_tcomp));
+    obj.insert("single_input_mode".to_string(), json!(rf.single_input_mode));
+    obj.insert("tx_enable".to_string(), json!(rf.tx_enable));
+    if rf.tx_enable {
+        obj.insert("tx_freq_min".to_string(), json!(tx_freq_min));
+        obj.insert("tx_freq_max".to_string(), json!(tx_freq_max));
+        if let Some(lut) = &rf.tx_gain_lut {
+            obj.insert("tx_gain_lut".to_string(), json!(lut));
+        }
+    }
+
+    Value::Object(obj)
+}
+
+fn write_chan(ifc: &LgwConfRxIf) -> Map<String, Value> {
+    let mut obj = Map::new();
+    obj.insert("enable".to_string(), json!(ifc.enable));
+    if ifc.enable {
+        obj.insert("radio".to_string(), json!(ifc.rf_chain));
+        obj.insert("if".to_string(), json!(ifc.freq_hz));
+    }
+    obj
+}
+
+/* Bandwidth in Hz, omitted when undefined */
+fn write_bandwidth(obj: &mut Map<String, Value>, bandwidth: u8) {
+    match Hal::lgw_bw_getval(bandwidth) {
+        -1 => {}
+        bw => {
+            obj.insert("bandwidth".to_string(), json!(bw));
+        }
+    }
+}
+
+fn write_chan_lora_std(ifc: &LgwConfRxIf) -> Map<String, Value> {
+    let mut obj = write_chan(ifc);
+    if !ifc.enable {
+        return obj;
+    }
+
+    write_bandwidth(&mut obj, ifc.bandwidth);
+    obj.insert("spread_factor".to_string(), json!(ifc.datarate));
+    obj.insert("implicit_hdr".to_string(), json!(ifc.implicit_hdr));
+    if ifc.implicit_hdr {
+        obj.insert("implicit_payload_length".to_string(), json!(ifc.implicit_payload_length));
+        obj.insert("implicit_crc_en".to_string(), json!(ifc.implicit_crc_en));
+        obj.insert("implicit_coderate".to_string(), json!(ifc.implicit_coderate));
+    }
+    obj
+}
+
+fn write_chan_fsk(ifc: &LgwConfRxIf) -> Map<String, Value> {
+    let mut obj = write_chan(ifc);
+    if !ifc.enable {
+        return obj;
+    }
+
+    write_bandwidth(&mut obj, ifc.bandwidth);
+    obj.insert("datarate".to_string(), json!(ifc.datarate));
+    if ifc.sync_word_size != 0 {
+        obj.insert("sync_word_size".to_string(), json!(ifc.sync_word_size));
+        obj.insert("sync_word".to_string(), json!(ifc.sync_word));
+    }
+    obj
+}
+
+fn write_sx1261(sx1261: &LgwConfSx1261, scan: &LgwConfSpectralScan) -> Value {
+    let lbt = &sx1261.lbt_conf;
+    let channels: Vec<Value> = lbt.channels[.. lbt.nb_channel as usize].iter()
+        .map(|c| {
+            let mut chan = Map::new();
+            chan.insert("freq_hz".to_string(), json!(c.freq_hz));
+            write_bandwidth(&mut chan, c.bandwidth);
+            chan.insert("scan_time_us".to_string(), json!(c.scan_time_us as u32));
+            chan.insert("transmit_time_ms".to_string(), json!(c.transmit_time_ms));
+            Value::Object(chan)
+        })
+        .collect();
+
+    let mut obj = Map::new();
+    obj.insert("enable".to_string(), json!(sx1261.enable));
+    if let Some(spi_path) = &sx1261.spi_path {
+        obj.insert("spi_path".to_string(), json!(spi_path));
+    }
+    obj.insert("rssi_offset".to_string(), json!(sx1261.rssi_offset));
+    obj.insert("spectral_scan".to_string(), if scan.enable {
+        json!({
+            "enable": true,
+            "freq_start": scan.freq_hz_start,
+            "nb_chan": scan.nb_chan,
+            "nb_scan": scan.nb_scan,
+            "pace_s": scan.pace_s,
+        })
+    } else {
+        json!({ "enable": false })
+    });
+    obj.insert("lbt".to_string(), json!({
+        "enable": lbt.enable,
+        "rssi_target": lbt.rssi_target,
+        "channels": channels,
+    }));
+
+    Value::Object(obj)
+}
+
+/* Remove comments outside of strings */
+fn strip_comments(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            if c == '\\' {
+                if let Some(n) = chars.next() {
+                    out.push(n);
+                }
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for n in chars.by_ref() {
+                    if n == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for n in chars.by_ref() {
+                    if prev == '*' && n == '/' {
+                        break;
+                    }
+                    if n == '\n' {
+                        out.push('\n');
+                    }
+                    prev = n;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::LgwRadioType;
+
+    /* Reference CoreCell EU868 file, with LBT and spectral scan, as shipped with lora_pkt_fwd */
+    const GLOBAL_CONF: &str = r#"{
+        /* SX1302 board */
+        "SX130x_conf": {
+            "com_type": "USB",
+            "com_path": "/dev/ttyACM0", // CoreCell
+            "lorawan_public": true,
+            "clksrc": 0,
+            "antenna_gain": 0, /* antenna gain, in dBi */
+            "full_duplex": false,
+            "fine_timestamp": {"enable": true, "mode": "all_sf"},
+            "sx1261_conf": {
+                "spi_path": "/dev/spidev0.1",
+                "rssi_offset": 0,
+                "spectral_scan": {"enable": true, "freq_start": 867100000, "nb_chan": 8, "nb_scan": 2000, "pace_s": 10},
+                "lbt": {
+                    "enable": true,
+                    "rssi_target": -70,
+                    "channels": [
+                        {"freq_hz": 867100000, "bandwidth": 125000, "scan_time_us": 128, "transmit_time_ms": 400},
+                        {"freq_hz": 869525000, "bandwidth": 250000, "scan_time_us": 5000, "transmit_time_ms": 4000}
+                    ]
+                }
+            },
+            "radio_0": {
+                "enable": true, "type": "SX1250", "freq": 867500000, "rssi_offset": -215.4,
+                "rssi_tcomp": {"coeff_a": 0, "coeff_b": 0, "coeff_c": 20.41, "coeff_d": 2162.56, "coeff_e": 0},
+                "tx_enable": true, "tx_freq_min": 863000000, "tx_freq_max": 870000000,
+                "tx_gain_lut": [
+                    {"rf_power": 12, "pa_gain": 0, "pwr_idx": 15},
+                    {"rf_power": 27, "pa_gain": 1, "pwr_idx": 22}
+                ]
+            },
+            "radio_1": {"enable": true, "type": "SX1250", "freq": 868500000, "rssi_offset": -215.4, "tx_enable": false},
+            "chan_multiSF_All": {"spreading_factor_enable": [5, 6, 7, 8, 9, 10, 11, 12]},
+            "chan_multiSF_0": {"enable": true, "radio": 1, "if": -400000},
+            "chan_multiSF_1": {"enable": true, "radio": 1, "if": -200000},
+            "chan_multiSF_2": {"enable": true, "radio": 1, "if": 0},
+            "chan_multiSF_3": {"enable": true, "radio": 0, "if": -400000},
+            "chan_multiSF_4": {"enable": true, "radio": 0, "if": -200000},
+            "chan_multiSF_5": {"enable": true, "radio": 0, "if": 0},
+            "chan_multiSF_6": {"enable": true, "radio": 0, "if": 200000},
+            "chan_multiSF_7": {"enable": false, "radio": 0, "if": 400000},
+            "chan_Lora_std": {"enable": true, "radio": 1, "if": -200000, "bandwidth": 250000, "spread_factor": 7,
+                              "implicit_hdr": false, "implicit_payload_length": 17, "implicit_crc_en": false, "implicit_coderate": 1},
+            "chan_FSK": {"enable": true, "radio": 1, "if": 300000, "freq_deviation": 25000, "datarate": 50000}
+        },
+        "gateway_conf": {"gateway_ID": "AA555A0000000000", "server_address": "http://localhost//", "serv_port_up": 1700}
+    }"#;
+
+    fn edit(f: impl FnOnce(&mut Value)) -> String {
+        let mut root: Value = serde_json::from_str(&strip_comments(GLOBAL_CONF)).unwrap();
+        f(&mut root[SX130X_CONF_KEY]);
+        root.to_string()
+    }
+
+    fn parse_err(s: &str) -> String {
+        LgwGlobalConf::parse(s).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn strip() {
+        assert_eq!(strip_comments("{\"a\": 1, // one\n\"b\": /* two\n */ 2}"), "{\"a\": 1, \n\"b\": \n 2}");
+        assert_eq!(strip_comments(r#"{"url": "http://x/*y*/", "s": "\"//\""}"#), r#"{"url": "http://x/*y*/", "s": "\"//\""}"#);
+    }
+
+    #[test]
+    fn parse() {
+        let conf = LgwGlobalConf::parse(GLOBAL_CONF).unwrap();
+
+        assert_eq!(conf.board.com_path, "/dev/ttyACM0");
+        assert!(conf.board.lorawan_public && !conf.board.full_duplex);
+        assert_eq!(conf.gateway_conf.as_ref().unwrap()["server_address"], "http://localhost//");
+        assert!(conf.debug_conf.is_none());
+
+        let rf0 = &conf.rf_chains[0];
+        assert!(rf0.enable && rf0.tx_enable);
+        assert_eq!((rf0.freq_hz, rf0._type, rf0.rssi_offset), (867_500_000, LgwRadioType::LGW_RADIO_TYPE_SX1250, -215.4));
+        assert_eq!((rf0.rssi_tcomp.coeff_c, rf0.rssi_tcomp.coeff_d), (20.41, 2162.56));
+        let lut = rf0.tx_gain_lut.as_ref().unwrap();
+        assert_eq!(lut.len(), 2);
+        assert_eq!((lut[1].rf_power, lut[1].pa_gain, lut[1].pwr_idx), (27, 1, 22));
+        assert_eq!((conf.tx_freq_min[0], conf.tx_freq_max[0]), (863_000_000, 870_000_000));
+        assert!(!conf.rf_chains[1].tx_enable && conf.rf_chains[1].tx_gain_lut.is_none());
+        assert_eq!((conf.tx_freq_min[1], conf.tx_freq_max[1]), (0, 0));
+
+        assert_eq!(conf.demod.multisf_datarate, 0xFF);
+        assert_eq!((conf.if_chains[0].rf_chain, conf.if_chains[0].freq_hz), (1, -400_000));
+        assert_eq!((conf.if_chains[6].rf_chain, conf.if_chains[6].freq_hz), (0, 200_000));
+        assert!(!conf.if_chains[7].enable);
+        let std = &conf.if_chains[IF_CHAIN_LORA_STD];
+        assert_eq!((std.bandwidth, std.datarate, std.implicit_hdr, std.implicit_payload_length), (BW_250KHZ, 7, false, 0));
+        let fsk = &conf.if_chains[IF_CHAIN_FSK];
+        assert_eq!((fsk.freq_hz, fsk.datarate, fsk.sync_word_size), (300_000, 50_000, 0));
+        assert_eq!(fsk.bandwidth, BW_125KHZ);   /* 2 x 25kHz + 50kbps */
+
+        assert!(conf.ftime.enable);
+        assert_eq!(conf.ftime.mode, LgwFtimeMode::LGW_FTIME_MODE_ALL_SF);
+
+        assert!(conf.sx1261.enable);
+        assert_eq!(conf.sx1261.spi_path.as_deref(), Some("/dev/spidev0.1"));
+        let lbt = &conf.sx1261.lbt_conf;
+        assert!(lbt.enable);
+        assert_eq!((lbt.rssi_target, lbt.nb_channel), (-70, 2));
+        assert_eq!((lbt.channels[0].freq_hz, lbt.channels[0].bandwidth, lbt.channels[0].transmit_time_ms), (867_100_000, BW_125KHZ, 400));
+        assert!(matches!(lbt.channels[0].scan_time_us, LgwLbtScanTime::LGW_LBT_SCAN_TIME_128_US));
+        assert_eq!((lbt.channels[1].freq_hz, lbt.channels[1].bandwidth, lbt.channels[1].transmit_time_ms), (869_525_000, BW_250KHZ, 4000));
+        assert!(matches!(lbt.channels[1].scan_time_us, LgwLbtScanTime::LGW_LBT_SCAN_TIME_5000_US));
+        assert_eq!(conf.spectral_scan, LgwConfSpectralScan { enable: true, freq_hz_start: 867_100_000, nb_chan: 8, nb_scan: 2000, pace_s: 10 });
+    }
+
+    #[test]
+    fn parse_defaults() {
+        let conf = LgwGlobalConf::parse(&edit(|sx130x| {
+            let obj = sx130x.as_object_mut().unwrap();
+            obj.retain(|k, _| ["com_path", "clksrc", "radio_0", "chan_FSK"].contains(&k.as_str()));
+            sx130x["chan_FSK"]["freq_deviation"] = json!(100_000);
+        })).unwrap();
+
+        assert_eq!(conf.board.com_type, LgwComType::LGW_COM_USB);
+        assert!(!conf.board.lorawan_public);
+        assert_eq!(conf.demod.multisf_datarate, LGW_MULTI_SF_EN);
+        assert!(!conf.rf_chains[1].enable && !conf.if_chains[0].enable);
+        assert_eq!(conf.if_chains[IF_CHAIN_FSK].bandwidth, BW_250KHZ);  /* 2 x 100kHz + 50kbps */
+        assert!(!conf.ftime.enable && !conf.sx1261.enable && !conf.spectral_scan.enable);
+
+        /* spectral scan alone enables the SX1261 */
+        let conf = LgwGlobalConf::parse(&edit(|sx130x| sx130x["sx1261_conf"]["lbt"]["enable"] = json!(false))).unwrap();
+        assert!(conf.sx1261.enable && !conf.sx1261.lbt_conf.enable);
+        let conf = LgwGlobalConf::parse(&edit(|sx130x| {
+            sx130x["sx1261_conf"]["lbt"]["enable"] = json!(false);
+            sx130x["sx1261_conf"]["spectral_scan"]["enable"] = json!(false);
+        })).unwrap();
+        assert!(!conf.sx1261.enable && !conf.spectral_scan.enable);
+    }
+
+    #[test]
+    fn round_trip() {
+        let conf = LgwGlobalConf::parse(GLOBAL_CONF).unwrap();
+        let json = conf.to_json();
+        let again = LgwGlobalConf::parse(&json.to_string()).unwrap();
+        assert_eq!(again.to_json(), json);
+
+        assert_eq!(json[SX130X_CONF_KEY]["chan_FSK"]["bandwidth"], 125_000);
+        assert_eq!(json[SX130X_CONF_KEY]["radio_1"]["tx_enable"], false);
+        assert_eq!(json[SX130X_CONF_KEY]["chan_multiSF_7"], json!({ "enable": false }));
+        assert_eq!(json[SX130X_CONF_KEY]["sx1261_conf"]["lbt"]["channels"][1]["scan_time_us"], 5000);
+        assert_eq!(json[SX130X_CONF_KEY]["sx1261_conf"]["spectral_scan"]["nb_scan"], 2000);
+        assert_eq!(json["gateway_conf"], conf.gateway_conf.unwrap());
+        assert_eq!(again.spectral_scan, conf.spectral_scan);
+        assert_eq!(again.sx1261.lbt_conf.nb_channel, 2);
+        assert_eq!(again.rf_chains[0].tx_gain_lut.as_ref().unwrap().len(), 2);
+        assert_eq!((again.tx_freq_min[0], again.tx_freq_max[0]), (863_000_000, 870_000_000));
+
+        /* through a file too */
+        let path = std::env::temp_dir().join(format!("loragw-conf-{}.json", std::process::id()));
+        again.to_file(&path).unwrap();
+        let from_file = LgwGlobalConf::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(from_file.to_json(), json);
+    }
+
+    #[test]
+    fn invalid_keys() {
+        assert!(parse_err("{\"SX130x_conf\": {}").starts_with("ERROR: invalid JSON: "));
+        assert_eq!(parse_err("{\"gateway_conf\": {}}"), "ERROR: missing SX130x_conf object");
+        assert_eq!(parse_err(&edit(|c| c["radio_1"] = json!([]))), "ERROR: SX130x_conf.radio_1 must be an object");
+        assert_eq!(parse_err(&edit(|c| c.as_object_mut().unwrap().remove("com_path").map(drop).unwrap())), "ERROR: missing SX130x_conf.com_path");
+        assert_eq!(parse_err(&edit(|c| c["com_type"] = json!("PCIe"))), "ERROR: invalid value for SX130x_conf.com_type: PCIe (expected USB or SPI)");
+        assert_eq!(parse_err(&edit(|c| c["radio_1"].as_object_mut().unwrap().remove("freq").map(drop).unwrap())), "ERROR: missing SX130x_conf.radio_1.freq");
+        assert_eq!(parse_err(&edit(|c| c["radio_0"]["type"] = json!("NONE"))), "ERROR: invalid value for SX130x_conf.radio_0.type: NONE");
+        assert_eq!(parse_err(&edit(|c| c["radio_0"]["tx_gain_lut"] = json!([]))), "ERROR: invalid value for SX130x_conf.radio_0.tx_gain_lut: 0 entries (expected 1 to 16)");
+        assert_eq!(parse_err(&edit(|c| c["chan_multiSF_All"]["spreading_factor_enable"] = json!([7, 13]))), "ERROR: invalid value for SX130x_conf.chan_multiSF_All.spreading_factor_enable: SF13 (expected 5 to 12)");
+        assert!(parse_err(&edit(|c| c["chan_multiSF_2"]["if"] = json!("0"))).starts_with("ERROR: invalid value for SX130x_conf.chan_multiSF_2.if: "));
+        assert_eq!(parse_err(&edit(|c| c["chan_Lora_std"]["bandwidth"] = json!(62500))), "ERROR: invalid value for SX130x_conf.chan_Lora_std.bandwidth: 62500 (expected 125000, 250000 or 500000)");
+        assert_eq!(parse_err(&edit(|c| c["chan_FSK"]["freq_deviation"] = json!(250_000))), "ERROR: invalid value for SX130x_conf.chan_FSK.bandwidth: 550000 (maximum 500000)");
+        assert_eq!(parse_err(&edit(|c| c["chan_FSK"]["sync_word_size"] = json!(3))), "ERROR: missing SX130x_conf.chan_FSK.sync_word");
+        assert_eq!(parse_err(&edit(|c| c["sx1261_conf"]["lbt"]["channels"][1]["scan_time_us"] = json!(1000))), "ERROR: invalid value for SX130x_conf.sx1261_conf.lbt.channels[1].scan_time_us: 1000 (expected 128 or 5000)");
+        assert_eq!(parse_err(&edit(|c| c["sx1261_conf"]["lbt"]["channels"] = json!(vec![json!({}); 17]))), "ERROR: invalid value for SX130x_conf.sx1261_conf.lbt.channels: 17 channels (maximum 16)");
+        assert_eq!(parse_err(&edit(|c| c["sx1261_conf"]["spectral_scan"]["nb_scan"] = json!(0))), "ERROR: invalid value for SX130x_conf.sx1261_conf.spectral_scan.nb_scan: 0 (at least one RSSI read)");
+        assert_eq!(parse_err(&edit(|c| c["sx1261_conf"]["spectral_scan"].as_object_mut().unwrap().remove("pace_s").map(drop).unwrap())), "ERROR: missing SX130x_conf.sx1261_conf.spectral_scan.pace_s");
+    }
+
+    #[test]
+    fn apply() {
+        let conf = LgwGlobalConf::parse(GLOBAL_CONF).unwrap();
+        let mut hal = Hal::new();
+        conf.apply(&mut hal).unwrap();
+        assert_eq!(hal.ctx.rf_chain_cfg[1].freq_hz, 868_500_000);
+        assert_eq!(hal.ctx.if_chain_cfg[5].freq_hz, 0);
+        assert_eq!(hal.ctx.sx1261_cfg.lbt_conf.nb_channel, 2);
+
+        /* rejected by lgw_rxif_setconf: outside of the radio bandwidth */
+        let conf = LgwGlobalConf::parse(&edit(|c| c["chan_multiSF_2"]["if"] = json!(900_000))).unwrap();
+        let err = conf.apply(&mut Hal::new()).unwrap_err().to_string();
+        assert!(err.starts_with("ERROR: SX130x_conf.chan_multiSF_2 rejected: "), "{}", err);
+    }
+}
@@ -0,0 +1,666 @@
+/*
+    GPS time reference.
+
+    GpsParser extracts time and position from the byte stream of a GPS receiver: NMEA RMC/GGA
+    sentences and u-blox NAV-TIMEGPS frames, interleaved in any order and cut anywhere, so that a
+    serial port and a recorded log are read the same way.
+
+    GpsTref links the concentrator counter latched on the PPS edge (lgw_get_trigcnt) to the UTC and
+    GPS time of that edge. Each new PPS gives a slope between the counter and the GPS clock, i.e.
+    the error of the concentrator crystal, which is filtered and used to convert counter values to
+    absolute time and back:
+
+        on NMEA_RMC / UBX_NAV_TIMEGPS:  tref.sync(hal.lgw_get_trigcnt()?, parser.time()?)
+        RX packet timestamp:            tref.cnt2utc(pkt.count_us)
+        class B downlink:               tref.gps2cnt(beacon_gps_time)
+
+    Counter values are 32-bit µs wrapping every ~71 minutes, conversions are only valid within
+    ±2^31 µs (~35 minutes) of the last sync.
+*/
+
+use std::io::{self, Read, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serialport::{FlowControl, Parity, SerialPort, StopBits};
+use tracing::{debug, info, warn};
+
+pub const GPS_EPOCH_UNIX: u64 =     315_964_800;    /* 1980-01-06T00:00:00Z, in seconds since the UNIX epoch */
+pub const GPS_REF_MAX_AGE: Duration = Duration::from_secs(30); /* time reference considered lost after that */
+
+const TS_CPS: f64 =                 1E6;            /* counter ticks per second */
+const PLUS_10PPM: f64 =             1.00001;
+const MINUS_10PPM: f64 =            0.99999;
+const XERR_INIT_AVG: u32 =          16;             /* number of syncs averaged to initialize xtal_err */
+const XERR_FILT_COEF: f64 =         256.0;          /* coefficient of the low-pass filter applied to xtal_err */
+
+const NMEA_MAX_LEN: usize =         128;            /* 82 characters per the standard, with some slack */
+const UBX_MAX_LEN: usize =          1024;
+const UBX_SYNC: [u8; 2] =           [0xB5, 0x62];
+const UBX_NAV_TIMEGPS: (u8, u8) =   (0x01, 0x20);
+const UBX_CFG_MSG: (u8, u8) =       (0x06, 0x01);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum GpsMsg {
+    UNKNOWN,            /* neither NMEA nor UBX */
+    IGNORED,            /* valid frame, not used */
+    INVALID,            /* bad checksum or content */
+    INCOMPLETE,         /* more bytes needed */
+    NMEA_RMC,
+    NMEA_GGA,
+    UBX_NAV_TIMEGPS,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, thiserror::Error)]
+#[allow(non_camel_case_types)]
+pub enum GpsError {
+    #[error("NO_TIME_REF")]
+    NO_TIME_REF,
+    #[error("NO_GPS_TIME")]
+    NO_GPS_TIME,
+    #[error("ABERRANT_SYNC")]
+    ABERRANT_SYNC,
+    #[error("OUT_OF_RANGE")]
+    OUT_OF_RANGE,
+}
+
+/**
+@struct GpsCoord
+@brief Position of the gateway
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GpsCoord {
+    pub lat: f64,       //*!> latitude [-90,90] (North +, South -) */
+    pub lon: f64,       //*!> longitude [-180,180] (East +, West -) */
+    pub alt: i16,       //*!> altitude in meters (WGS 84 geoid ref.) */
+}
+
+/**
+@struct GpsTime
+@brief Time of the last PPS edge
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsTime {
+    pub utc: SystemTime,
+    pub gps: Option<Duration>,  //*!> since the GPS epoch, only when NAV-TIMEGPS matches the UTC time */
+}
+
+/* Days since 1970-01-01 of a proleptic Gregorian date */
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+pub fn gps_to_utc(gps: Duration, leap_seconds: i8) -> SystemTime {
+    let unix = Duration::from_secs(GPS_EPOCH_UNIX) + gps;
+    if leap_seconds >= 0 {
+        UNIX_EPOCH + unix - Duration::from_secs(leap_seconds as u64)
+    } else {
+        UNIX_EPOCH + unix + Duration::from_secs(leap_seconds.unsigned_abs() as u64)
+    }
+}
+
+/* Signed difference a - b, in seconds */
+fn diff_secs(a: SystemTime, b: SystemTime) -> f64 {
+    match a.duration_since(b) {
+        Ok(d) => d.as_secs_f64(),
+        Err(e) => -e.duration().as_secs_f64(),
+    }
+}
+
+fn add_secs(t: SystemTime, secs: f64) -> SystemTime {
+    if secs >= 0.0 { t + Duration::from_secs_f64(secs) } else { t - Duration::from_secs_f64(-secs) }
+}
+
+fn ubx_checksum(data: &[u8]) -> (u8, u8) {
+    data.iter().fold((0u8, 0u8), |(a, b), &x| {
+        let a = a.wrapping_add(x);
+        (a, b.wrapping_add(a))
+    })
+}
+
+fn ubx_frame(class: u8, id: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 8);
+    frame.extend_from_slice(&UBX_SYNC);
+    frame.extend_from_slice(&[class, id]);
+    frame.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+    frame.extend_from_slice(payload);
+    let (ck_a, ck_b) = ubx_checksum(&frame[2..]);
+    frame.extend_from_slice(&[ck_a, ck_b]);
+    frame
+}
+
+/* NMEA ddmm.mmmm / dddmm.mmmm coordinate, signed by hemisphere */
+fn nmea_coord(value: &str, hemisphere: &str) -> Option<f64> {
+    let v: f64 = value.parse().ok()?;
+    let deg = (v / 100.0).trunc();
+    let c = deg + (v - deg * 100.0) / 60.0;
+    match hemisphere {
+        "N" | "E" => Some(c),
+        "S" | "W" => Some(-c),
+        _ => None,
+    }
+}
+
+/* hhmmss.sss and ddmmyy fields of RMC */
+fn nmea_utc(time: &str, date: &str) -> Option<SystemTime> {
+    if time.len() < 6 || date.len() != 6 || !time.is_ascii() || !date.is_ascii() {
+        return None;
+    }
+    let num = |s: &str| s.parse::<u32>().ok();
+    let (hh, mm, ss) = (num(&time[0..2])?, num(&time[2..4])?, num(&time[4..6])?);
+    let (day, month, year) = (num(&date[0..2])?, num(&date[2..4])?, num(&date[4..6])?);
+    if hh > 23 || mm > 59 || ss > 60 || !(1..=31).contains(&day) || !(1..=12).contains(&month) {
+        return None;
+    }
+    let frac: f64 = if time.len() > 6 { format!("0{}", &time[6..]).parse().ok()? } else { 0.0 };
+    let days = days_from_civil(2000 + year as i64, month, day);
+    let secs = days * 86400 + (hh * 3600 + mm * 60 + ss) as i64;
+    Some(UNIX_EPOCH + Duration::from_secs(secs as u64) + Duration::from_secs_f64(frac))
+}
+
+/**
+@struct GpsParser
+@brief Incremental parser of a GPS receiver byte stream, keeping the last valid fix
+*/
+#[derive(Debug, Clone, Default)]
+pub struct GpsParser {
+    buf: Vec<u8>,
+    utc: Option<SystemTime>,        //*!> from the last valid RMC */
+    gps_time: Option<Duration>,     //*!> from the last valid NAV-TIMEGPS */
+    leap_seconds: Option<i8>,       //*!> GPS - UTC, from NAV-TIMEGPS */
+    coord: Option<GpsCoord>,        //*!> from the last GGA with a fix */
+    nb_sats: u8,
+}
+
+impl GpsParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Append bytes of the stream, frames are then parsed one by one with next_msg */
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /* Parse the next complete frame, None when more bytes are needed. Garbage between frames is
+       skipped. The parser state (time, coord) reflects the frames returned so far. */
+    pub fn next_msg(&mut self) -> Option<GpsMsg> {
+        let mut pos = 0;
+        let mut msg = None;
+
+        while msg.is_none() && pos < self.buf.len() {
+            let rest = &self.buf[pos..];
+            if rest[0] == b'$' {
+                match rest.iter().position(|&b| b == b'\n') {
+                    /* truncated sentence, restart on the next one */
+                    Some(end) if rest[1..end].contains(&b'$') => {
+                        msg = Some(GpsMsg::INVALID);
+                        pos += 1 + rest[1..end].iter().position(|&b| b == b'$').unwrap_or(0);
+                    }
+                    Some(end) => {
+                        let frame = rest[..end + 1].to_vec();
+                        msg = Some(self.parse_nmea(&frame));
+                        pos += end + 1;
+                    }
+                    None if rest.len() > NMEA_MAX_LEN => {
+                        msg = Some(GpsMsg::INVALID);
+                        pos += 1;
+                    }
+                    None => break,
+                }
+            } else if rest[0] == UBX_SYNC[0] {
+                if rest.len() >= 2 && rest[1] != UBX_SYNC[1] {
+                    pos += 1;
+                    continue;
+                }
+                if rest.len() < 6 {
+                    break;
+                }
+                let len = u16::from_le_bytes([rest[4], rest[5]]) as usize;
+                if len > UBX_MAX_LEN {
+                    msg = Some(GpsMsg::INVALID);
+                    pos += 1;
+                    continue;
+                }
+                if rest.len() < len + 8 {
+                    break;
+                }
+                let frame = rest[..len + 8].to_vec();
+                let m = self.parse_ubx(&frame);
+                /* on a bad checksum the sync word may be false, resynchronize on the next byte */
+                pos += if m == GpsMsg::INVALID { 1 } else { len + 8 };
+                msg = Some(m);
+            } else {
+                pos += 1;
+            }
+        }
+
+        self.buf.drain(..pos);
+        msg
+    }
+
+    /* Read what the stream has available into the parser, Ok(false) at the end of the stream.
+       Read timeouts, as returned by serial ports, are not errors. */
+    pub fn read_from<R: Read + ?Sized>(&mut self, reader: &mut R) -> io::Result<bool> {
+        let mut chunk = [0u8; 256];
+        match reader.read(&mut chunk) {
+            Ok(0) => Ok(false),
+            Ok(n) => {
+                self.feed(&chunk[..n]);
+                Ok(true)
+            }
+            Err(e) if e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::Interrupted => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+
+    /* One NMEA sentence, from '$' to the line end */
+    pub fn parse_nmea(&mut self, frame: &[u8]) -> GpsMsg {
+        let Ok(line) = std::str::from_utf8(frame) else {
+            return GpsMsg::INVALID;
+        };
+        let line = line.trim_end_matches(['\r', '\n']);
+        let Some(body) = line.strip_prefix('$') else {
+            return GpsMsg::UNKNOWN;
+        };
+        let Some((body, checksum)) = body.rsplit_once('*') else {
+            return GpsMsg::INVALID;
+        };
+        let expected = body.bytes().fold(0u8, |acc, b| acc ^ b);
+        if u8::from_str_radix(checksum, 16).ok() != Some(expected) {
+            debug!("GPS: NMEA checksum error ({})", line);
+            return GpsMsg::INVALID;
+        }
+
+        let fields: Vec<&str> = body.split(',').collect();
+        if fields[0].len() != 5 {
+            return GpsMsg::IGNORED;
+        }
+        match fields[0].get(2..) {
+            Some("RMC") => {
+                if fields.len() < 10 {
+                    return GpsMsg::INVALID;
+                }
+                /* NMEA 2.3 adds a mode field, N meaning not valid */
+                let valid = fields[2] == "A" && fields.get(12).is_none_or(|m| !m.starts_with('N'));
+                self.utc = if valid { nmea_utc(fields[1], fields[9]) } else { None };
+                if valid && self.utc.is_none() {
+                    return GpsMsg::INVALID;
+                }
+                GpsMsg::NMEA_RMC
+            }
+            Some("GGA") => {
+                if fields.len() < 10 {
+                    return GpsMsg::INVALID;
+                }
+                let quality: u8 = fields[6].parse().unwrap_or(0);
+                self.nb_sats = fields[7].parse().unwrap_or(0);
+                self.coord = if quality > 0 {
+                    match (nmea_coord(fields[2], fields[3]), nmea_coord(fields[4], fields[5])) {
+                        (Some(lat), Some(lon)) => Some(GpsCoord { lat, lon, alt: fields[9].parse::<f64>().map(|a| a.round() as i16).unwrap_or(0) }),
+                        _ => return GpsMsg::INVALID,
+                    }
+                } else {
+                    None
+                };
+                GpsMsg::NMEA_GGA
+            }
+            _ => GpsMsg::IGNORED,
+        }
+    }
+
+    /* One UBX frame, sync word and checksum included */
+    pub fn parse_ubx(&mut self, frame: &[u8]) -> GpsMsg {
+        if frame.len() < 8 || frame[0..2] != UBX_SYNC {
+            return GpsMsg::UNKNOWN;
+        }
+        let len = u16::from_le_bytes([frame[4], frame[5]]) as usize;
+        if frame.len() < len + 8 {
+            return GpsMsg::INCOMPLETE;
+        }
+        if ubx_checksum(&frame[2 .. len + 6]) != (frame[len + 6], frame[len + 7]) {
+            debug!("GPS: UBX checksum error (class 0x{:02X}, id 0x{:02X})", frame[2], frame[3]);
+            return GpsMsg::INVALID;
+        }
+        if (frame[2], frame[3]) != UBX_NAV_TIMEGPS {
+            return GpsMsg::IGNORED;
+        }
+        if len != 16 {
+            return GpsMsg::INVALID;
+        }
+
+        let p = &frame[6 .. 6 + len];
+        let itow = u32::from_le_bytes([p[0], p[1], p[2], p[3]]);         /* ms of the week */
+        let ftow = i32::from_le_bytes([p[4], p[5], p[6], p[7]]);          /* ns, -500000..500000 */
+        let week = i16::from_le_bytes([p[8], p[9]]);
+        let leap = p[10] as i8;
+        let valid = p[11];
+
+        /* towValid and weekValid, leapSValid on its own */
+        self.gps_time = if valid & 0x03 == 0x03 && week >= 0 {
+            let ns = (week as i64 * 604_800_000 + itow as i64) * 1_000_000 + ftow as i64;
+            (ns >= 0).then(|| Duration::from_nanos(ns as u64))
+        } else {
+            None
+        };
+        if valid & 0x04 != 0 {
+            self.leap_seconds = Some(leap);
+        }
+        GpsMsg::UBX_NAV_TIMEGPS
+    }
+
+    /* Time of the last PPS edge. When both RMC and NAV-TIMEGPS are received the most recent one
+       wins, GPS time being only given when both agree on the same second. */
+    pub fn time(&self) -> Option<GpsTime> {
+        let from_ubx = match (self.gps_time, self.leap_seconds) {
+            (Some(g), Some(l)) => Some((gps_to_utc(g, l), g)),
+            _ => None,
+        };
+        match (self.utc, from_ubx) {
+            (Some(utc), Some((u, g))) if diff_secs(u, utc).abs() < 0.5 => Some(GpsTime { utc, gps: Some(g) }),
+            (Some(utc), Some((u, g))) if u > utc => Some(GpsTime { utc: u, gps: Some(g) }),
+            (Some(utc), _) => Some(GpsTime { utc, gps: None }),
+            (None, Some((u, g))) => Some(GpsTime { utc: u, gps: Some(g) }),
+            (None, None) => None,
+        }
+    }
+
+    pub fn coord(&self) -> Option<GpsCoord> {
+        self.coord
+    }
+
+    pub fn nb_sats(&self) -> u8 {
+        self.nb_sats
+    }
+
+    pub fn leap_seconds(&self) -> Option<i8> {
+        self.leap_seconds
+    }
+}
+
+/**
+@struct GpsTref
+@brief Time reference linking the concentrator counter to UTC and GPS time
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct GpsTref {
+    pub systime: SystemTime,    //*!> system time of the last sync */
+    pub count_us: u32,          //*!> concentrator counter latched on the PPS */
+    pub utc: SystemTime,        //*!> UTC time of the PPS */
+    pub gps: Option<Duration>,  //*!> GPS time of the PPS, since the GPS epoch */
+    pub xtal_err: f64,          //*!> counter ticks per GPS µs, filtered */
+    nb_sync: u32,
+    aber_min1: bool,            /* sync N-1 was aberrant */
+    aber_min2: bool,            /* sync N-2 was aberrant */
+}
+
+impl Default for GpsTref {
+    fn default() -> Self {
+        Self { systime: UNIX_EPOCH, count_us: 0, utc: UNIX_EPOCH, gps: None, xtal_err: 1.0, nb_sync: 0, aber_min1: false, aber_min2: false }
+    }
+}
+
+impl GpsTref {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.nb_sync > 0
+    }
+
+    /* Synced less than GPS_REF_MAX_AGE ago */
+    pub fn is_locked(&self, now: SystemTime) -> bool {
+        self.is_synced() && now.duration_since(self.systime).is_ok_and(|age| age <= GPS_REF_MAX_AGE)
+    }
+
+    /* New PPS: count_us as latched by the concentrator, time as given by the GPS for that edge.
+       A sync whose slope is off by more than 10ppm is rejected, unless it is the third in a row,
+       in which case the reference is reset (the GPS or the concentrator was restarted). */
+    pub fn sync(&mut self, count_us: u32, time: GpsTime) -> Result<(), GpsError> {
+        if !self.is_synced() {
+            self.set(count_us, time);
+            self.nb_sync = 1;
+            info!("INFO: GPS time reference initialized\n");
+            return Ok(());
+        }
+
+        let cnt_diff = count_us.wrapping_sub(self.count_us) as f64 / TS_CPS;
+        let utc_diff = diff_secs(time.utc, self.utc);
+        let slope = if utc_diff > 0.0 { cnt_diff / utc_diff } else { 0.0 };
+        let aber_n0 = !(MINUS_10PPM ..= PLUS_10PPM).contains(&slope);
+
+        let res = if !aber_n0 {
+            self.set(count_us, time);
+            /* running average first, then low-pass filter */
+            self.xtal_err = if self.nb_sync <= XERR_INIT_AVG {
+                self.xtal_err + (slope - self.xtal_err) / self.nb_sync as f64
+            } else {
+                self.xtal_err + (slope - self.xtal_err) / XERR_FILT_COEF
+            };
+            self.nb_sync += 1;
+            Ok(())
+        } else if self.aber_min1 && self.aber_min2 {
+            warn!("WARNING: 3 successive aberrant GPS sync attempts, sync reset\n");
+            self.set(count_us, time);
+            if !(MINUS_10PPM ..= PLUS_10PPM).contains(&self.xtal_err) {
+                self.xtal_err = 1.0;
+            }
+            Ok(())
+        } else {
+            debug!("GPS: aberrant sync ignored (slope {:.7})", slope);
+            Err(GpsError::ABERRANT_SYNC)
+        };
+
+        self.aber_min2 = self.aber_min1;
+        self.aber_min1 = aber_n0;
+        res
+    }
+
+    fn set(&mut self, count_us: u32, time: GpsTime) {
+        self.systime = SystemTime::now();
+        self.count_us = count_us;
+        self.utc = time.utc;
+        self.gps = time.gps;
+    }
+
+    /* Seconds from the reference to count_us, corrected by xtal_err */
+    fn cnt_delta(&self, count_us: u32) -> Result<f64, GpsError> {
+        if !self.is_synced() {
+            return Err(GpsError::NO_TIME_REF);
+        }
+        Ok(count_us.wrapping_sub(self.count_us) as i32 as f64 / (TS_CPS * self.xtal_err))
+    }
+
+    /* Counter value delta_secs after the reference */
+    fn cnt_at(&self, delta_secs: f64) -> Result<u32, GpsError> {
+        if !self.is_synced() {
+            return Err(GpsError::NO_TIME_REF);
+        }
+        let delta_us = (delta_secs * TS_CPS * self.xtal_err).round();
+        if delta_us.abs() >= i32::MAX as f64 {
+            return Err(GpsError::OUT_OF_RANGE);
+        }
+        Ok(self.count_us.wrapping_add(delta_us as i64 as u32))
+    }
+
+    pub fn cnt2utc(&self, count_us: u32) -> Result<SystemTime, GpsError> {
+        Ok(add_secs(self.utc, self.cnt_delta(count_us)?))
+    }
+
+    pub fn utc2cnt(&self, utc: SystemTime) -> Result<u32, GpsError> {
+        self.cnt_at(diff_secs(utc, self.utc))
+    }
+
+    pub fn cnt2gps(&self, count_us: u32) -> Result<Duration, GpsError> {
+        let gps = self.gps.ok_or(GpsError::NO_GPS_TIME)?;
+        let secs = gps.as_secs_f64() + self.cnt_delta(count_us)?;
+        if secs < 0.0 {
+            return Err(GpsError::OUT_OF_RANGE);
+        }
+        Ok(Duration::from_secs_f64(secs))
+    }
+
+    pub fn gps2cnt(&self, gps: Duration) -> Result<u32, GpsError> {
+        let reference = self.gps.ok_or(GpsError::NO_GPS_TIME)?;
+        self.cnt_at(gps.as_secs_f64() - reference.as_secs_f64())
+    }
+}
+
+/* Open the GPS serial port and enable NAV-TIMEGPS on u-blox receivers (other receivers ignore it).
+   The port has a short read timeout, suited to GpsParser::read_from. */
+pub fn lgw_gps_enable(tty_path: &str, baudrate: u32) -> Result<Box<dyn SerialPort>> {
+    let mut port = serialport::new(tty_path, baudrate)
+        .flow_control(FlowControl::None)
+        .parity(Parity::None)
+        .stop_bits(StopBits::One)
+        .timeout(Duration::from_millis(100))
+        .open()?;
+
+    /* CFG-MSG: NAV-TIMEGPS once per navigation solution on the current port */
+    let cmd = ubx_frame(UBX_CFG_MSG.0, UBX_CFG_MSG.1, &[UBX_NAV_TIMEGPS.0, UBX_NAV_TIMEGPS.1, 0x01]);
+    port.write_all(&cmd)?;
+    info!("INFO: GPS serial port {} opened ({} bauds), NAV-TIMEGPS requested\n", tty_path, baudrate);
+
+    Ok(port)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* 4 seconds of a u-blox receiver output (RMC, VTG, GGA, GSA, GSV, GLL and NAV-TIMEGPS each
+       second, one ACK-ACK), from 2026-03-14T12:00:00Z, starting in the middle of a sentence */
+    const CAPTURE: &[u8] = include_bytes!("testdata/gps_ublox.bin");
+    const CAPTURE_SECS: u64 = 4;
+    const LEAP_SECONDS: u64 = 18;
+
+    fn capture_utc(sec: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(days_from_civil(2026, 3, 14) as u64 * 86400 + 12 * 3600 + sec)
+    }
+
+    fn capture_gps(sec: u64) -> Duration {
+        capture_utc(sec).duration_since(UNIX_EPOCH).unwrap() - Duration::from_secs(GPS_EPOCH_UNIX - LEAP_SECONDS)
+    }
+
+    fn parse_all(parser: &mut GpsParser, data: &[u8], chunk: usize) -> Vec<GpsMsg> {
+        let mut msgs = Vec::new();
+        for c in data.chunks(chunk) {
+            parser.feed(c);
+            while let Some(m) = parser.next_msg() {
+                msgs.push(m);
+            }
+        }
+        msgs
+    }
+
+    #[test]
+    fn parse_capture() {
+        let mut expected = Vec::new();
+        for sec in 0..CAPTURE_SECS {
+            expected.extend([GpsMsg::NMEA_RMC, GpsMsg::IGNORED, GpsMsg::NMEA_GGA, GpsMsg::IGNORED, GpsMsg::IGNORED, GpsMsg::IGNORED, GpsMsg::UBX_NAV_TIMEGPS]);
+            if sec == 1 {
+                expected.push(GpsMsg::IGNORED);
+            }
+        }
+
+        /* the same messages whatever the frames are cut at */
+        for chunk in [1, 7, 64, CAPTURE.len()] {
+            let mut parser = GpsParser::new();
+            assert_eq!(parse_all(&mut parser, CAPTURE, chunk), expected, "chunks of {}", chunk);
+
+            let time = parser.time().unwrap();
+            assert_eq!(time.utc, capture_utc(CAPTURE_SECS - 1));
+            assert_eq!(time.gps, Some(capture_gps(CAPTURE_SECS - 1)));
+            assert_eq!(parser.leap_seconds(), Some(LEAP_SECONDS as i8));
+            assert_eq!(parser.nb_sats(), 9);
+            let coord = parser.coord().unwrap();
+            assert!((coord.lat - (48.0 + 52.33412 / 60.0)).abs() < 1e-9);
+            assert!((coord.lon - (2.0 + 21.13765 / 60.0)).abs() < 1e-9);
+            assert_eq!(coord.alt, 42);
+        }
+    }
+
+    #[test]
+    fn parse_capture_bad_checksum() {
+        let mut data = CAPTURE.to_vec();
+        let rmc = data.windows(6).position(|w| w == b"$GPRMC").unwrap();
+        data[rmc + 8] ^= 0x01;
+
+        let mut parser = GpsParser::new();
+        let msgs = parse_all(&mut parser, &data, 16);
+        assert_eq!(msgs[0], GpsMsg::INVALID);
+        assert_eq!(msgs.iter().filter(|&&m| m == GpsMsg::NMEA_RMC).count() as u64, CAPTURE_SECS - 1);
+        assert_eq!(parser.time().unwrap().utc, capture_utc(CAPTURE_SECS - 1));
+    }
+
+    /* Sync on each NAV-TIMEGPS of the capture, the counter running 5ppm fast and wrapping */
+    #[test]
+    fn tref_capture_drift() {
+        const TICKS_PER_SEC: u32 = 1_000_005;
+        let first_cnt = u32::MAX - 1_500_000;
+        let mut parser = GpsParser::new();
+        let mut tref = GpsTref::new();
+        let mut cnt = first_cnt;
+
+        parser.feed(CAPTURE);
+        while let Some(m) = parser.next_msg() {
+            if m == GpsMsg::UBX_NAV_TIMEGPS {
+                tref.sync(cnt, parser.time().unwrap()).unwrap();
+                cnt = cnt.wrapping_add(TICKS_PER_SEC);
+            }
+        }
+        let last_cnt = cnt.wrapping_sub(TICKS_PER_SEC);
+        let last = CAPTURE_SECS - 1;
+        assert!(tref.is_synced());
+        assert_eq!(tref.count_us, last_cnt);
+        assert!((tref.xtal_err - 1.000005).abs() < 1e-9, "xtal_err {}", tref.xtal_err);
+
+        /* one second of counter is TICKS_PER_SEC ticks, before and after the reference */
+        let ahead = tref.cnt2utc(last_cnt.wrapping_add(TICKS_PER_SEC)).unwrap();
+        assert!(diff_secs(ahead, capture_utc(last + 1)).abs() < 1e-6);
+        let behind = tref.cnt2utc(last_cnt.wrapping_sub(3 * TICKS_PER_SEC)).unwrap();
+        assert!(diff_secs(behind, capture_utc(last - 3)).abs() < 1e-6);
+        assert_eq!(tref.cnt2utc(first_cnt).unwrap(), behind);
+        assert_eq!(tref.utc2cnt(capture_utc(last + 1)).unwrap(), last_cnt.wrapping_add(TICKS_PER_SEC));
+        assert_eq!(tref.utc2cnt(capture_utc(0)).unwrap(), first_cnt);
+        assert_eq!(tref.utc2cnt(capture_utc(last) + Duration::from_millis(500)).unwrap(), last_cnt.wrapping_add(500_003));
+
+        let gps = tref.cnt2gps(last_cnt.wrapping_add(TICKS_PER_SEC / 4)).unwrap();
+        assert!((gps.as_secs_f64() - (capture_gps(last) + Duration::from_millis(250)).as_secs_f64()).abs() < 1e-6);
+        assert_eq!(tref.gps2cnt(capture_gps(last + 128)).unwrap(), last_cnt.wrapping_add(128 * TICKS_PER_SEC));
+        assert_eq!(tref.gps2cnt(capture_gps(0)).unwrap(), first_cnt);
+        assert_eq!(tref.gps2cnt(capture_gps(last) + Duration::from_secs(3600)), Err(GpsError::OUT_OF_RANGE));
+    }
+
+    #[test]
+    fn tref_errors() {
+        let mut tref = GpsTref::new();
+        assert_eq!(tref.cnt2utc(0), Err(GpsError::NO_TIME_REF));
+        assert_eq!(tref.utc2cnt(capture_utc(0)), Err(GpsError::NO_TIME_REF));
+
+        /* RMC only, no GPS time */
+        tref.sync(1_000, GpsTime { utc: capture_utc(0), gps: None }).unwrap();
+        assert_eq!(tref.cnt2utc(1_000), Ok(capture_utc(0)));
+        assert_eq!(tref.cnt2gps(1_000), Err(GpsError::NO_GPS_TIME));
+        assert_eq!(tref.gps2cnt(capture_gps(0)), Err(GpsError::NO_GPS_TIME));
+
+        /* counter restarted: 2 aberrant syncs rejected, the third one resets the reference */
+        let time = |sec| GpsTime { utc: capture_utc(sec), gps: Some(capture_gps(sec)) };
+        assert_eq!(tref.sync(1_000 + 100, time(1)), Err(GpsError::ABERRANT_SYNC));
+        assert_eq!(tref.sync(1_000 + 200, time(2)), Err(GpsError::ABERRANT_SYNC));
+        assert_eq!(tref.count_us, 1_000);
+        assert_eq!(tref.sync(1_000 + 300, time(3)), Ok(()));
+        assert_eq!(tref.count_us, 1_300);
+        assert_eq!(tref.xtal_err, 1.0);
+        assert_eq!(tref.cnt2gps(1_300), Ok(capture_gps(3)));
+        assert_eq!(tref.sync(1_300 + 1_000_000, time(4)), Ok(()));
+    }
+}
@@ -0,0 +1,370 @@
+/*
+    Just-in-time queue of downlink packets.
+
+    lgw_send programs the SX1302 immediately and the TX modem holds a single packet, so
+    timestamped downlinks are kept here until they are about to be emitted. Packets are
+    checked at enqueue time against the current counter and against each other:
+
+        |----- pre_delay -----|== packet on air (post_delay) ==|
+                              ^ count_us
+
+    pre_delay covers the TX start delay of the concentrator and the JIT anticipation, two
+    packets whose [count_us - pre_delay, count_us + post_delay] intervals overlap collide.
+
+    All times are values of the 32-bit concentrator counter (1 µs resolution, wrapping every
+    ~71 minutes), as returned by lgw_get_instcnt. The SX1302 counter itself is 27 bits wide and
+    expanded to 32 bits by the HAL, the queue only relies on modulo 2^32 differences, which
+    stay unambiguous as long as packets are queued less than 2^31 µs in advance.
+*/
+
+use std::fmt;
+
+use tracing::{debug, warn};
+
+use super::{lgw_time_on_air, LgwPktTx, TxMode};
+
+pub const JIT_QUEUE_MAX: usize = 32;            /* maximum number of packets in the queue */
+const JIT_NUM_BEACON_IN_QUEUE: u32 = 3;         /* number of beacons to be kept in advance */
+
+pub const TX_START_DELAY: u32 = 1500;           /* microseconds, between the TX command and the actual emission */
+pub const TX_MARGIN_DELAY: u32 = 1000;          /* microseconds, packet loading margin */
+pub const TX_JIT_DELAY: u32 = 40000;            /* microseconds, packets are handed to lgw_send this early */
+pub const TX_MAX_ADVANCE_DELAY: u32 = (JIT_NUM_BEACON_IN_QUEUE + 1) * 128 * 1_000_000; /* microseconds */
+
+const BEACON_GUARD: u32 = 3_000_000;            /* microseconds, no downlink before a beacon */
+const BEACON_RESERVED: u32 = 2_120_000;         /* microseconds, reserved for the beacon itself */
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum JitPktType {
+    DOWNLINK_CLASS_A,
+    DOWNLINK_CLASS_B,
+    DOWNLINK_CLASS_C,
+    BEACON,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, thiserror::Error)]
+#[allow(non_camel_case_types)]
+pub enum JitError {
+    #[error("TOO_LATE")]
+    TOO_LATE,
+    #[error("TOO_EARLY")]
+    TOO_EARLY,
+    #[error("FULL")]
+    FULL,
+    #[error("COLLISION_PACKET")]
+    COLLISION_PACKET,
+    #[error("COLLISION_BEACON")]
+    COLLISION_BEACON,
+    #[error("INVALID")]
+    INVALID,
+}
+
+/**
+@struct JitNode
+@brief Packet waiting in the queue, with its reserved time window
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct JitNode {
+    pub pkt: LgwPktTx,
+    pub pkt_type: JitPktType,
+    pub pre_delay: u32,     //*!> reserved time before count_us (in µs) */
+    pub post_delay: u32,    //*!> reserved time after count_us, i.e. time on air (in µs) */
+}
+
+impl JitNode {
+    fn start(&self) -> u32 {
+        self.pkt.count_us.wrapping_sub(self.pre_delay)
+    }
+
+    fn end(&self) -> u32 {
+        self.pkt.count_us.wrapping_add(self.post_delay)
+    }
+
+    fn collides(&self, other: &JitNode) -> bool {
+        /* relative to our own start, so that intervals spanning a counter wrap compare right */
+        let origin = self.start();
+        let (a0, a1) = (0, self.end().wrapping_sub(origin) as i32);
+        let (b0, b1) = (other.start().wrapping_sub(origin) as i32, other.end().wrapping_sub(origin) as i32);
+        a0 < b1 && b0 < a1
+    }
+}
+
+/**
+@struct JitQueue
+@brief Downlinks sorted by emission time, handed to lgw_send just in time
+*/
+#[derive(Debug, Clone)]
+pub struct JitQueue {
+    nodes: Vec<JitNode>,
+    fsk_sync_word_size: u32,
+}
+
+/* Signed distance from now to time, negative when time is in the past */
+fn delta(time: u32, now: u32) -> i32 {
+    time.wrapping_sub(now) as i32
+}
+
+impl JitQueue {
+    /* fsk_sync_word_size (in bytes) is only used to compute the time on air of FSK packets */
+    pub fn new(fsk_sync_word_size: u32) -> Self {
+        Self {
+            nodes: Vec::with_capacity(JIT_QUEUE_MAX),
+            fsk_sync_word_size,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.nodes.len() >= JIT_QUEUE_MAX
+    }
+
+    pub fn nodes(&self) -> &[JitNode] {
+        &self.nodes
+    }
+
+    /* Queue a packet, time_us being the current concentrator counter.
+       IMMEDIATE packets are given the first free slot and converted to TIMESTAMPED.
+       ON_GPS packets must carry in count_us the counter value expected at the PPS. */
+    pub fn enqueue(&mut self, time_us: u32, pkt: &LgwPktTx, pkt_type: JitPktType) -> Result<(), JitError> {
+        if self.is_full() {
+            warn!("WARNING: JIT queue is full");
+            return Err(JitError::FULL);
+        }
+
+        let mut node = JitNode {
+            pkt: *pkt,
+            pkt_type,
+            pre_delay: TX_START_DELAY + TX_JIT_DELAY,
+            post_delay: lgw_time_on_air(pkt, self.fsk_sync_word_size) * 1000,
+        };
+
+        match (pkt.tx_mode, pkt_type) {
+            (TxMode::IMMEDIATE, JitPktType::BEACON) => return Err(JitError::INVALID),
+            (TxMode::IMMEDIATE, _) => {
+                /* as soon as possible: now, or right after one of the queued packets */
+                node.pkt.tx_mode = TxMode::TIMESTAMPED;
+                node.pkt.count_us = time_us.wrapping_add(TX_START_DELAY + TX_MARGIN_DELAY + TX_JIT_DELAY);
+
+                let mut candidates: Vec<u32> = self.nodes.iter()
+                    .map(|n| n.end().wrapping_add(node.pre_delay + 1))
+                    .filter(|&t| delta(t, node.pkt.count_us) > 0)
+                    .collect();
+                candidates.sort_by_key(|&t| delta(t, time_us));
+
+                let mut slot = Some(node.pkt.count_us).into_iter().chain(candidates);
+                loop {
+                    match slot.next() {
+                        Some(t) => {
+                            node.pkt.count_us = t;
+                            if !self.nodes.iter().any(|n| node.collides(n)) {
+                                break;
+                            }
+                        }
+                        None => return Err(JitError::COLLISION_PACKET),
+                    }
+                }
+            }
+            (_, JitPktType::BEACON) => {
+                node.pre_delay = TX_START_DELAY + BEACON_GUARD + TX_JIT_DELAY;
+                node.post_delay = BEACON_RESERVED;
+            }
+            _ => {}
+        }
+
+        /* timing checks */
+        let advance = delta(node.pkt.count_us, time_us);
+        if advance < (TX_START_DELAY + TX_MARGIN_DELAY + TX_JIT_DELAY) as i32 {
+            debug!("JIT: packet too late (count_us:{} now:{})", node.pkt.count_us, time_us);
+            return Err(JitError::TOO_LATE);
+        }
+        if advance as u32 > TX_MAX_ADVANCE_DELAY {
+            debug!("JIT: packet too early (count_us:{} now:{})", node.pkt.count_us, time_us);
+            return Err(JitError::TOO_EARLY);
+        }
+
+        /* collision checks */
+        if let Some(other) = self.nodes.iter().find(|n| node.collides(n)) {
+            debug!("JIT: packet (count_us:{}) collides with queued packet (count_us:{})", node.pkt.count_us, other.pkt.count_us);
+            return Err(if other.pkt_type == JitPktType::BEACON { JitError::COLLISION_BEACON } else { JitError::COLLISION_PACKET });
+        }
+
+        /* keep the queue sorted by emission time */
+        let pos = self.nodes.iter().position(|n| delta(n.pkt.count_us, node.pkt.count_us) > 0).unwrap_or(self.nodes.len());
+        self.nodes.insert(pos, node);
+        debug!("JIT: packet queued (count_us:{} toa:{}us), {} in queue", node.pkt.count_us, node.post_delay, self.nodes.len());
+
+        Ok(())
+    }
+
+    /* Index of the packet to give to lgw_send now, if any. Packets whose time has passed
+       are dropped. */
+    pub fn peek(&mut self, time_us: u32) -> Option<usize> {
+        self.nodes.retain(|n| {
+            let late = delta(n.pkt.count_us, time_us) < TX_START_DELAY as i32;
+            if late {
+                warn!("WARNING: JIT: dropping packet (count_us:{}), too late to be sent (now:{})", n.pkt.count_us, time_us);
+            }
+            !late
+        });
+
+        match self.nodes.first() {
+            Some(n) if delta(n.pkt.count_us, time_us) < (TX_START_DELAY + TX_JIT_DELAY) as i32 => Some(0),
+            _ => None,
+        }
+    }
+
+    pub fn dequeue(&mut self, index: usize) -> Option<JitNode> {
+        if index < self.nodes.len() {
+            Some(self.nodes.remove(index))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for JitQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "JIT queue: {}/{} packets", self.nodes.len(), JIT_QUEUE_MAX)?;
+        for (i, n) in self.nodes.iter().enumerate() {
+            writeln!(f, " - node[{}]: count_us={} type={:?} pre={} post={}", i, n.pkt.count_us, n.pkt_type, n.pre_delay, n.post_delay)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::{Modulation, BW_125KHZ, CR_LORA_4_5};
+
+    const PRE_DELAY: u32 = TX_START_DELAY + TX_JIT_DELAY;
+    const MIN_ADVANCE: u32 = TX_START_DELAY + TX_MARGIN_DELAY + TX_JIT_DELAY;
+
+    fn pkt(tx_mode: TxMode, count_us: u32) -> LgwPktTx {
+        LgwPktTx {
+            tx_mode,
+            count_us,
+            freq_hz: 869_525_000,
+            modulation: Modulation::LORA,
+            bandwidth: BW_125KHZ,
+            datarate: 7,
+            coderate: CR_LORA_4_5,
+            preamble: 8,
+            size: 10,
+            ..Default::default()
+        }
+    }
+
+    fn node(count_us: u32) -> JitNode {
+        let pkt = pkt(TxMode::TIMESTAMPED, count_us);
+        JitNode { pkt, pkt_type: JitPktType::DOWNLINK_CLASS_A, pre_delay: PRE_DELAY, post_delay: lgw_time_on_air(&pkt, 3) * 1000 }
+    }
+
+    fn toa() -> u32 {
+        node(0).post_delay
+    }
+
+    #[test]
+    fn collides_across_counter_wrap() {
+        let a = node(u32::MAX - 10_000);
+        let b = node(20_000);
+        assert!(a.collides(&b) && b.collides(&a));
+
+        let c = node(a.end().wrapping_add(PRE_DELAY));
+        assert!(!a.collides(&c) && !c.collides(&a));
+        assert!(a.collides(&node(c.pkt.count_us - 1)));
+    }
+
+    #[test]
+    fn enqueue_and_peek_across_counter_wrap() {
+        let now = u32::MAX - 100_000;
+        let mut jit = JitQueue::new(3);
+
+        let first = now.wrapping_add(200_000);
+        jit.enqueue(now, &pkt(TxMode::TIMESTAMPED, first), JitPktType::DOWNLINK_CLASS_A).unwrap();
+        assert_eq!(jit.enqueue(now, &pkt(TxMode::TIMESTAMPED, first.wrapping_add(50_000)), JitPktType::DOWNLINK_CLASS_A), Err(JitError::COLLISION_PACKET));
+        jit.enqueue(now, &pkt(TxMode::TIMESTAMPED, now.wrapping_add(400_000)), JitPktType::DOWNLINK_CLASS_A).unwrap();
+        jit.enqueue(now, &pkt(TxMode::TIMESTAMPED, now.wrapping_add(90_000)), JitPktType::DOWNLINK_CLASS_A).unwrap();
+
+        /* sorted by emission time, even though the last ones wrapped */
+        let times: Vec<u32> = jit.nodes().iter().map(|n| n.pkt.count_us).collect();
+        assert_eq!(times, [now.wrapping_add(90_000), first, now.wrapping_add(400_000)]);
+
+        assert_eq!(jit.peek(now), None);
+        jit.dequeue(0).unwrap();
+        assert_eq!(jit.peek(first.wrapping_sub(PRE_DELAY)), None);
+        assert_eq!(jit.peek(first.wrapping_sub(PRE_DELAY - 1)), Some(0));
+        assert_eq!(jit.dequeue(0).unwrap().pkt.count_us, first);
+
+        /* missed: dropped by peek */
+        assert_eq!(jit.peek(now.wrapping_add(400_000 - TX_START_DELAY + 1)), None);
+        assert!(jit.is_empty());
+    }
+
+    #[test]
+    fn enqueue_delays() {
+        let now = 1_000_000;
+        let mut jit = JitQueue::new(3);
+
+        assert_eq!(jit.enqueue(now, &pkt(TxMode::TIMESTAMPED, now + MIN_ADVANCE - 1), JitPktType::DOWNLINK_CLASS_A), Err(JitError::TOO_LATE));
+        assert_eq!(jit.enqueue(now, &pkt(TxMode::TIMESTAMPED, now + TX_MAX_ADVANCE_DELAY + 1), JitPktType::DOWNLINK_CLASS_A), Err(JitError::TOO_EARLY));
+        jit.enqueue(now, &pkt(TxMode::TIMESTAMPED, now + MIN_ADVANCE), JitPktType::DOWNLINK_CLASS_A).unwrap();
+
+        /* the next packet must leave TX_START_DELAY + TX_JIT_DELAY after the end of the first one */
+        let next = now + MIN_ADVANCE + toa() + PRE_DELAY;
+        assert_eq!(jit.enqueue(now, &pkt(TxMode::TIMESTAMPED, next - 1), JitPktType::DOWNLINK_CLASS_A), Err(JitError::COLLISION_PACKET));
+        jit.enqueue(now, &pkt(TxMode::TIMESTAMPED, next), JitPktType::DOWNLINK_CLASS_A).unwrap();
+
+        /* immediate: first free slot, right after the queued packets */
+        jit.enqueue(now, &pkt(TxMode::IMMEDIATE, 0), JitPktType::DOWNLINK_CLASS_C).unwrap();
+        let last = jit.nodes()[2];
+        assert!(matches!(last.pkt.tx_mode, TxMode::TIMESTAMPED));
+        assert_eq!(last.pkt.count_us, next + toa() + PRE_DELAY + 1);
+    }
+
+    #[test]
+    fn enqueue_full() {
+        let now = 0;
+        let mut jit = JitQueue::new(3);
+        for i in 0 .. JIT_QUEUE_MAX as u32 {
+            jit.enqueue(now, &pkt(TxMode::TIMESTAMPED, MIN_ADVANCE + i * 1_000_000), JitPktType::DOWNLINK_CLASS_A).unwrap();
+        }
+        assert!(jit.is_full());
+        assert_eq!(jit.enqueue(now, &pkt(TxMode::IMMEDIATE, 0), JitPktType::DOWNLINK_CLASS_C), Err(JitError::FULL));
+    }
+
+    #[test]
+    fn beacon_priority() {
+        let now = 5_000_000;
+        let beacon_time = now + 10_000_000;
+        let mut jit = JitQueue::new(3);
+
+        assert_eq!(jit.enqueue(now, &pkt(TxMode::IMMEDIATE, 0), JitPktType::BEACON), Err(JitError::INVALID));
+        jit.enqueue(now, &pkt(TxMode::ON_GPS, beacon_time), JitPktType::BEACON).unwrap();
+
+        /* no downlink in the guard time before the beacon, nor while it is on air */
+        for t in [beacon_time - BEACON_GUARD / 2, beacon_time + BEACON_RESERVED / 2] {
+            assert_eq!(jit.enqueue(now, &pkt(TxMode::TIMESTAMPED, t), JitPktType::DOWNLINK_CLASS_A), Err(JitError::COLLISION_BEACON));
+        }
+        jit.enqueue(now, &pkt(TxMode::TIMESTAMPED, beacon_time - BEACON_GUARD - PRE_DELAY - toa()), JitPktType::DOWNLINK_CLASS_A).unwrap();
+        jit.dequeue(0).unwrap();
+
+        /* immediate downlinks are pushed after a beacon whose guard time has started */
+        let now = beacon_time - BEACON_GUARD;
+        jit.enqueue(now, &pkt(TxMode::IMMEDIATE, 0), JitPktType::DOWNLINK_CLASS_C).unwrap();
+        assert_eq!(jit.nodes()[1].pkt.count_us, beacon_time + BEACON_RESERVED + PRE_DELAY + 1);
+
+        /* the beacon is handed out first */
+        assert_eq!(jit.peek(beacon_time - PRE_DELAY + 1), Some(0));
+        assert_eq!(jit.dequeue(0).unwrap().pkt_type, JitPktType::BEACON);
+        assert_eq!(jit.peek(beacon_time), None);
+    }
+}
@@ -0,0 +1,232 @@
+/*
+    Listen-Before-Talk.
+
+    Before a TX, the SX1261 is tuned on the LBT channel of the packet and continuously compares
+    the channel RSSI to the configured target, driving a GPIO read by the AGC firmware. When the
+    TX is triggered, the AGC only starts the radio if the channel was found free, and reports in
+    its status register whether the TX went out:
+        bit 0/1: TX initiated on radio A/B
+        bit 6/7: radio A/B was not allowed to transmit
+*/
+
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use tracing::{debug, error, warn};
+
+use super::error::Error;
+use super::helper::wait_ms;
+use super::loragw_sx1261::LoragwSx1261Trait;
+use super::loragw_sx1302::LorgwSx1302Trait;
+use super::{lgw_time_on_air, Hal, LgwConfSx1261, LgwPktTx, LgwSpectralScanStatus, Modulation};
+
+const LBT_TX_STATUS_TIMEOUT: Duration = Duration::from_millis(500);  /* max wait for the AGC to trigger the TX */
+
+const AGC_STATUS_CLEAR: u8 =        0xFF;   /* mailbox 0 command clearing the AGC TX status */
+const AGC_STATUS_ACK: u8 =          0x00;
+
+pub trait LoragwLbtTrait {
+    fn lgw_lbt_start(&mut self, context: &LgwConfSx1261, pkt: &LgwPktTx) -> Result<()>;
+    fn lgw_lbt_tx_status(&mut self, rf_chain: u8) -> Result<bool>;
+    fn lgw_lbt_stop(&mut self) -> Result<()>;
+}
+
+impl Hal {
+    /* Poll the AGC status until cond is met, None on timeout */
+    fn lbt_wait_agc_status(&mut self, cond: impl Fn(u8) -> bool) -> Result<Option<u8>> {
+        let start = Instant::now();
+        loop {
+            let status = self.sx1302_agc_status()?;
+            if cond(status) {
+                return Ok(Some(status));
+            }
+            if start.elapsed() > LBT_TX_STATUS_TIMEOUT {
+                return Ok(None);
+            }
+            wait_ms(1);
+        }
+    }
+}
+
+impl LoragwLbtTrait for Hal {
+    fn lgw_lbt_start(&mut self, context: &LgwConfSx1261, pkt: &LgwPktTx) -> Result<()> {
+        let lbt = &context.lbt_conf;
+
+        /* Check if we have a LBT channel for this transmit frequency */
+        let channel = lbt.channels[.. lbt.nb_channel as usize].iter().find(|c| {
+            c.freq_hz == pkt.freq_hz && (pkt.modulation != Modulation::LORA || c.bandwidth == pkt.bandwidth)
+        });
+        let Some(channel) = channel else {
+            error!("ERROR: Cannot start LBT - wrong channel\n");
+            return Err(Error::tx_rejected("freq_hz", pkt.freq_hz, "no LBT channel configured for this frequency/bandwidth").into());
+        };
+
+        /* Check if the packet Time On Air exceeds the maximum allowed transmit time on this channel */
+        let toa_ms = lgw_time_on_air(pkt, self.ctx.fsk_cfg.sync_word_size as u32);
+        if toa_ms > channel.transmit_time_ms as u32 {
+            error!("ERROR: Cannot start LBT - packet time on air ({}ms) exceeds allowed transmit time ({}ms)\n", toa_ms, channel.transmit_time_ms);
+            return Err(Error::tx_rejected("size", pkt.size, "time on air exceeds the LBT channel transmit time").into());
+        }
+
+        /* The SX1261 compares its raw RSSI, corrected by rssi_offset, to the target */
+        let threshold = (lbt.rssi_target as f32 - context.rssi_offset).round().clamp(i8::MIN as f32, i8::MAX as f32) as i8;
+
+        /* The SX1261 may be busy with a spectral scan */
+        if self.sx1261_spectral_scan_status()? == LgwSpectralScanStatus::LGW_SPECTRAL_SCAN_STATUS_ON_GOING {
+            warn!("WARNING: aborting on-going spectral scan to start LBT\n");
+            self.sx1261_spectral_scan_abort()?;
+        }
+
+        self.sx1261_set_rx_params(channel.freq_hz, channel.bandwidth)
+            .context("failed to set LBT scan frequency")?;
+        self.sx1261_lbt_start(channel.scan_time_us, threshold)
+            .context("failed to start LBT")?;
+
+        debug!("LBT: started on {}Hz (target:{}dBm threshold:{}dBm)", channel.freq_hz, lbt.rssi_target, threshold);
+        Ok(())
+    }
+
+    /* Wait for the AGC to trigger the TX and tell whether the channel was free */
+    fn lgw_lbt_tx_status(&mut self, rf_chain: u8) -> Result<bool> {
+        let initiated = self.lbt_wait_agc_status(|status| status & (1 << rf_chain) != 0)?;
+
+        let tx_ok = match initiated {
+            Some(status) => status & (1 << (6 + rf_chain)) == 0,
+            None => {
+                /* still clear the AGC status, the error is reported below */
+                error!("ERROR: LBT: timeout on TX start, not started\n");
+                false
+            }
+        };
+
+        /* Clear AGC transmit status, and wait for it to be cleared */
+        self.sx1302_agc_mailbox_write(0, AGC_STATUS_CLEAR)?;
+        let cleared = self.lbt_wait_agc_status(|status| status == 0x00)?;
+        self.sx1302_agc_mailbox_write(0, AGC_STATUS_ACK)?;
+
+        if initiated.is_none() {
+            return Err(anyhow!("LBT: TX not initiated within {:?}", LBT_TX_STATUS_TIMEOUT));
+        }
+        if cleared.is_none() {
+            warn!("WARNING: LBT: timeout on TX status clear\n");
+            return Err(anyhow!("LBT: AGC TX status not cleared within {:?}", LBT_TX_STATUS_TIMEOUT));
+        }
+
+        Ok(tx_ok)
+    }
+
+    fn lgw_lbt_stop(&mut self) -> Result<()> {
+        self.sx1261_lbt_stop().context("failed to stop LBT")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::loragw_conf::LgwGlobalConf;
+    use crate::hal::loragw_reg::{SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS, SX1302_REG_AGC_MCU_MCU_MAIL_BOX_WR_DATA_BYTE0_MCU_MAIL_BOX_WR_DATA};
+    use crate::hal::mcu::sim::{SimConcentrator, SIM_SX1261_PRAM_VERSION};
+    use crate::hal::{LgwHal, LgwSx1261Pram, TxMode, BW_125KHZ, CR_LORA_4_5};
+
+    const LBT_FREQ_HZ: u32 = 869_525_000;
+
+    const GLOBAL_CONF: &str = r#"{"SX130x_conf": {
+        "com_path": "/dev/ttyACM0", "clksrc": 0, "lorawan_public": true,
+        "radio_0": {
+            "enable": true, "type": "SX1250", "freq": 867500000, "rssi_offset": -215.4, "tx_enable": true,
+            "tx_gain_lut": [{"rf_power": 14, "pa_gain": 0, "pwr_idx": 17}]
+        },
+        "radio_1": {"enable": true, "type": "SX1250", "freq": 868500000, "rssi_offset": -215.4, "tx_enable": false},
+        "sx1261_conf": {
+            "enable": true, "rssi_offset": 0,
+            "lbt": {"enable": true, "rssi_target": -80, "channels": [
+                {"freq_hz": 869525000, "bandwidth": 125000, "scan_time_us": 128, "transmit_time_ms": 400}
+            ]}
+        }
+    }}"#;
+
+    fn started_hal() -> (SimConcentrator, Hal) {
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        LgwGlobalConf::parse(GLOBAL_CONF).unwrap().apply(&mut hal).unwrap();
+        hal.lgw_sx1261_set_pram(LgwSx1261Pram { words: (0 .. 386).collect(), version: SIM_SX1261_PRAM_VERSION.to_string() }).unwrap();
+        hal.lgw_start().unwrap();
+        (sim, hal)
+    }
+
+    fn pkt(freq_hz: u32) -> LgwPktTx {
+        LgwPktTx {
+            tx_mode: TxMode::IMMEDIATE,
+            freq_hz,
+            rf_power: 14,
+            modulation: Modulation::LORA,
+            bandwidth: BW_125KHZ,
+            datarate: 7,
+            coderate: CR_LORA_4_5,
+            invert_pol: true,
+            preamble: 8,
+            size: 4,
+            ..Default::default()
+        }
+    }
+
+    /* the AGC status is cleared, and mailbox 0 acknowledged, after each LBT TX */
+    fn assert_agc_status_cleared(sim: &SimConcentrator) {
+        assert_eq!(sim.reg_r(SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS).unwrap(), 0x00);
+        assert_eq!(sim.reg_r(SX1302_REG_AGC_MCU_MCU_MAIL_BOX_WR_DATA_BYTE0_MCU_MAIL_BOX_WR_DATA).unwrap(), AGC_STATUS_ACK as i32);
+    }
+
+    #[test]
+    fn tx_allowed() {
+        let (sim, mut hal) = started_hal();
+        sim.set_channel_rssi(LBT_FREQ_HZ, -95);
+
+        hal.lgw_send(&pkt(LBT_FREQ_HZ)).unwrap();
+        let tx = sim.take_tx();
+        assert_eq!(tx.len(), 1);
+        assert!(tx[0].freq_hz.abs_diff(LBT_FREQ_HZ) < 100, "TX at {} Hz", tx[0].freq_hz);
+        assert_agc_status_cleared(&sim);
+
+        /* a second TX goes through the same handshake */
+        hal.lgw_send(&pkt(LBT_FREQ_HZ)).unwrap();
+        assert_eq!(sim.take_tx().len(), 1);
+        assert_agc_status_cleared(&sim);
+        assert_eq!(hal.lgw_stats().tx.ok, 2);
+        hal.lgw_stop().unwrap();
+    }
+
+    #[test]
+    fn tx_blocked() {
+        let (sim, mut hal) = started_hal();
+        sim.set_channel_rssi(LBT_FREQ_HZ, -60);
+
+        let err = hal.lgw_send(&pkt(LBT_FREQ_HZ)).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::LbtNotAllowed { rf_chain: 0, freq_hz: LBT_FREQ_HZ })), "{err:#}");
+        assert!(sim.take_tx().is_empty());
+        assert_agc_status_cleared(&sim);
+        assert_eq!(hal.lgw_stats().tx.lbt_blocked, 1);
+
+        /* channel free again */
+        sim.set_channel_rssi(LBT_FREQ_HZ, -95);
+        hal.lgw_send(&pkt(LBT_FREQ_HZ)).unwrap();
+        assert_eq!(sim.take_tx().len(), 1);
+        hal.lgw_stop().unwrap();
+    }
+
+    #[test]
+    fn tx_rejected_without_lbt_channel() {
+        let (sim, mut hal) = started_hal();
+
+        let err = hal.lgw_send(&pkt(868_100_000)).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::TxRejected { .. })), "{err:#}");
+
+        /* time on air above transmit_time_ms */
+        let mut long = pkt(LBT_FREQ_HZ);
+        long.datarate = 12;
+        long.size = 255;
+        let err = hal.lgw_send(&long).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::TxRejected { .. })), "{err:#}");
+        assert!(sim.take_tx().is_empty());
+        hal.lgw_stop().unwrap();
+    }
+}
@@ -0,0 +1,437 @@
+/*
+    Prometheus metrics endpoint (cargo feature "prometheus").
+
+    LgwMetrics accumulates counters and histograms fed by the Hal once lgw_metrics_enable has
+    been called, LgwMetricsServer serves them in the Prometheus text format on a local HTTP
+    listener, from its own thread:
+
+        let metrics = hal.lgw_metrics_enable();
+        let _server = LgwMetricsServer::spawn("127.0.0.1:9100", metrics)?;
+
+    Unlike lgw_stats, these counters are never reset, as Prometheus expects. The MCU temperature
+    is refreshed by lgw_receive (at most every TEMPERATURE_REFRESH), the HTTP thread never
+    touches the concentrator.
+*/
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use tracing::{debug, info, warn};
+
+use super::loragw_stats::{LgwRxCounters, LgwRxStatKey, LgwStats, LgwTxCounters};
+use super::loragw_sx1302::SX1302_FSK_MODEM_ID;
+use super::{lgw_time_on_air, Hal, LgwPktRx, LgwPktTx};
+
+const RSSI_BUCKETS: &[f64] = &[-130.0, -120.0, -110.0, -100.0, -90.0, -80.0, -70.0, -60.0, -50.0, -40.0, -30.0];
+const SNR_BUCKETS: &[f64] = &[-20.0, -15.0, -10.0, -7.5, -5.0, -2.5, 0.0, 2.5, 5.0, 7.5, 10.0, 15.0];
+const SPI_RTT_BUCKETS: &[f64] = &[0.0005, 0.001, 0.002, 0.005, 0.01, 0.02, 0.05, 0.1, 0.25, 0.5];
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/* Cumulative histogram, in the Prometheus sense: each bucket counts the observations <= bound */
+#[derive(Debug, Clone)]
+struct Histogram {
+    bounds: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self { bounds, counts: vec![0; bounds.len()], sum: 0.0, count: 0 }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in self.bounds.iter().zip(self.counts.iter_mut()) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn write(&self, out: &mut String, name: &str, labels: &str) {
+        let sep = if labels.is_empty() { "" } else { "," };
+        for (bound, count) in self.bounds.iter().zip(self.counts.iter()) {
+            let _ = writeln!(out, "{}_bucket{{{}{}le=\"{}\"}} {}", name, labels, sep, bound, count);
+        }
+        let _ = writeln!(out, "{}_bucket{{{}{}le=\"+Inf\"}} {}", name, labels, sep, self.count);
+        let labels = if labels.is_empty() { String::new() } else { format!("{{{}}}", labels) };
+        let _ = writeln!(out, "{}_sum{} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, labels, self.count);
+    }
+}
+
+#[derive(Debug)]
+struct MetricsData {
+    stats: LgwStats,                        /* RX/TX counters, never reset */
+    rssi: BTreeMap<u8, Histogram>,          /* signal RSSI per IF chain */
+    snr: BTreeMap<u8, Histogram>,           /* SNR per IF chain, LoRa only */
+    tx_airtime: BTreeMap<u8, f64>,          /* seconds on air per RF chain */
+    spi_rtt: Histogram,                     /* mcu_spi_write round trips */
+    temperature: Option<f32>,
+    started: Option<Instant>,               /* lgw_start time, None while stopped */
+}
+
+/**
+@struct LgwMetrics
+@brief Metrics shared between the Hal and the HTTP endpoint
+*/
+#[derive(Debug)]
+pub struct LgwMetrics {
+    created: Instant,
+    data: Mutex<MetricsData>,
+}
+
+impl Default for LgwMetrics {
+    fn default() -> Self {
+        Self {
+            created: Instant::now(),
+            data: Mutex::new(MetricsData {
+                stats: LgwStats::default(),
+                rssi: BTreeMap::new(),
+                snr: BTreeMap::new(),
+                tx_airtime: BTreeMap::new(),
+                spi_rtt: Histogram::new(SPI_RTT_BUCKETS),
+                temperature: None,
+                started: None,
+            }),
+        }
+    }
+}
+
+fn rx_labels(key: &LgwRxStatKey) -> String {
+    if key.modem_id == SX1302_FSK_MODEM_ID {
+        format!("if_chain=\"{}\",modulation=\"FSK\",datarate=\"{}\"", key.if_chain, key.datarate)
+    } else {
+        format!("if_chain=\"{}\",modulation=\"LORA\",datarate=\"SF{}\"", key.if_chain, key.datarate)
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+impl LgwMetrics {
+    pub(crate) fn record_rx(&self, pkt: &LgwPktRx) {
+        let mut data = self.data.lock().unwrap();
+        data.stats.record_rx(pkt);
+        data.rssi.entry(pkt.if_chain).or_insert_with(|| Histogram::new(RSSI_BUCKETS)).observe(pkt.rssis as f64);
+        if pkt.modem_id != SX1302_FSK_MODEM_ID {
+            data.snr.entry(pkt.if_chain).or_insert_with(|| Histogram::new(SNR_BUCKETS)).observe(pkt.snr as f64);
+        }
+    }
+
+    pub(crate) fn record_tx(&self, pkt: &LgwPktTx, result: &Result<()>, airtime_ms: u32) {
+        let mut data = self.data.lock().unwrap();
+        data.stats.record_tx(result);
+        if result.is_ok() {
+            *data.tx_airtime.entry(pkt.rf_chain).or_default() += airtime_ms as f64 / 1000.0;
+        }
+    }
+
+    pub(crate) fn record_spi_rtt(&self, rtt: Duration) {
+        self.data.lock().unwrap().spi_rtt.observe(rtt.as_secs_f64());
+    }
+
+    pub(crate) fn set_temperature(&self, temperature: f32) {
+        self.data.lock().unwrap().temperature = Some(temperature);
+    }
+
+    pub(crate) fn set_started(&self, started: bool) {
+        self.data.lock().unwrap().started = if started { Some(Instant::now()) } else { None };
+    }
+
+    /* All metrics, in the Prometheus text exposition format */
+    pub fn render(&self) -> String {
+        let data = self.data.lock().unwrap();
+        let mut out = String::new();
+
+        header(&mut out, "lgw_rx_packets_total", "counter", "Packets received, by IF chain, datarate and CRC status");
+        for (key, c) in data.stats.rx.iter() {
+            let labels = rx_labels(key);
+            let LgwRxCounters { crc_ok, crc_bad, no_crc, .. } = *c;
+            for (status, n) in [("crc_ok", crc_ok), ("crc_bad", crc_bad), ("no_crc", no_crc)] {
+                let _ = writeln!(out, "lgw_rx_packets_total{{{},status=\"{}\"}} {}", labels, status, n);
+            }
+        }
+
+        header(&mut out, "lgw_rx_rssi_dbm", "histogram", "Signal RSSI of the received packets, by IF chain");
+        for (if_chain, h) in data.rssi.iter() {
+            h.write(&mut out, "lgw_rx_rssi_dbm", &format!("if_chain=\"{}\"", if_chain));
+        }
+
+        header(&mut out, "lgw_rx_snr_db", "histogram", "SNR of the received LoRa packets, by IF chain");
+        for (if_chain, h) in data.snr.iter() {
+            h.write(&mut out, "lgw_rx_snr_db", &format!("if_chain=\"{}\"", if_chain));
+        }
+
+        header(&mut out, "lgw_tx_packets_total", "counter", "lgw_send calls, by result");
+        let LgwTxCounters { ok, rejected, lbt_blocked, failed } = data.stats.tx;
+        for (result, n) in [("ok", ok), ("rejected", rejected), ("lbt_blocked", lbt_blocked), ("failed", failed)] {
+            let _ = writeln!(out, "lgw_tx_packets_total{{result=\"{}\"}} {}", result, n);
+        }
+
+        header(&mut out, "lgw_tx_airtime_seconds_total", "counter", "Time on air of the packets sent, by RF chain");
+        for (rf_chain, airtime) in data.tx_airtime.iter() {
+            let _ = writeln!(out, "lgw_tx_airtime_seconds_total{{rf_chain=\"{}\"}} {}", rf_chain, airtime);
+        }
+
+        header(&mut out, "lgw_mcu_spi_write_seconds", "histogram", "Round trip of the USB SPI requests to the MCU");
+        data.spi_rtt.write(&mut out, "lgw_mcu_spi_write_seconds", "");
+
+        if let Some(temperature) = data.temperature {
+            header(&mut out, "lgw_mcu_temperature_celsius", "gauge", "Board temperature reported by the MCU");
+            let _ = writeln!(out, "lgw_mcu_temperature_celsius {}", temperature);
+        }
+
+        header(&mut out, "lgw_concentrator_up", "gauge", "1 if the concentrator is started");
+        let _ = writeln!(out, "lgw_concentrator_up {}", data.started.is_some() as u8);
+        header(&mut out, "lgw_concentrator_uptime_seconds", "gauge", "Time since lgw_start, 0 while stopped");
+        let _ = writeln!(out, "lgw_concentrator_uptime_seconds {}", data.started.map(|t| t.elapsed().as_secs_f64()).unwrap_or(0.0));
+        header(&mut out, "lgw_metrics_uptime_seconds", "gauge", "Time since the metrics were enabled");
+        let _ = writeln!(out, "lgw_metrics_uptime_seconds {}", self.created.elapsed().as_secs_f64());
+
+        out
+    }
+}
+
+/**
+@struct LgwMetricsServer
+@brief HTTP listener serving GET /metrics, stopped when dropped
+*/
+#[derive(Debug)]
+pub struct LgwMetricsServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+fn serve(stream: &mut TcpStream, metrics: &LgwMetrics) -> Result<()> {
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+    stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+
+    let mut request = String::new();
+    BufReader::new(&*stream).read_line(&mut request)?;
+    debug!("Note: metrics request {:?}", request.trim_end());
+
+    let mut parts = request.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", "text/plain; version=0.0.4", metrics.render()),
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "not found, metrics are served on /metrics\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain", "only GET is supported\n".to_string()),
+    };
+
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, content_type, body.len(), body)?;
+    stream.flush()?;
+    Ok(())
+}
+
+impl LgwMetricsServer {
+    /* Listen on addr, e.g. "127.0.0.1:9100" ("127.0.0.1:0" picks a free port, see addr) */
+    pub fn spawn<A: ToSocketAddrs>(addr: A, metrics: Arc<LgwMetrics>) -> Result<Self> {
+        let listener = TcpListener::bind(addr).map_err(|e| anyhow!("ERROR: failed to open the metrics listener: {}", e))?;
+        let addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("lgw-metrics".to_string())
+                .spawn(move || {
+                    for stream in listener.incoming() {
+                        if stop.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        match stream {
+                            Ok(mut stream) => {
+                                if let Err(e) = serve(&mut stream, &metrics) {
+                                    warn!("WARNING: metrics request failed: {}", e);
+                                }
+                            }
+                            Err(e) => warn!("WARNING: metrics connection failed: {}", e),
+                        }
+                    }
+                })?
+        };
+
+        info!("INFO: serving metrics on http://{}/metrics", addr);
+        Ok(Self { addr, stop, thread: Some(thread) })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for LgwMetricsServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        /* wake up the listener blocked in accept */
+        let _ = TcpStream::connect_timeout(&self.addr, HTTP_TIMEOUT);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Hal {
+    /* Start feeding metrics, returns the handle to give to LgwMetricsServer. Calling it again
+       returns the same metrics. */
+    pub fn lgw_metrics_enable(&mut self) -> Arc<LgwMetrics> {
+        if let Some(metrics) = &self.metrics {
+            return metrics.clone();
+        }
+
+        let metrics = Arc::new(LgwMetrics::default());
+        metrics.set_started(self.ctx.is_started);
+        self.metrics = Some(metrics.clone());
+        self.mcu.metrics = Some(metrics.clone());
+        metrics
+    }
+
+    pub(crate) fn lgw_metrics_rx(&mut self, pkts: &[LgwPktRx]) {
+        let Some(metrics) = self.metrics.clone() else {
+            return;
+        };
+        for pkt in pkts {
+            metrics.record_rx(pkt);
+        }
+        if let Ok(temperature) = self.lgw_get_temperature_cached() {
+            metrics.set_temperature(temperature);
+        }
+    }
+
+    pub(crate) fn lgw_metrics_tx(&self, pkt: &LgwPktTx, result: &Result<()>) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_tx(pkt, result, lgw_time_on_air(pkt, self.ctx.fsk_cfg.sync_word_size as u32));
+        }
+    }
+
+    pub(crate) fn lgw_metrics_started(&self, started: bool) {
+        if let Some(metrics) = &self.metrics {
+            metrics.set_started(started);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::hal::error::Error;
+    use crate::hal::{Modulation, STAT_CRC_BAD, STAT_CRC_OK};
+
+    fn lora_pkt(if_chain: u8, status: u8, rssis: f32, snr: f32) -> LgwPktRx {
+        let mut pkt = LgwPktRx::new();
+        pkt.if_chain = if_chain;
+        pkt.modem_id = if_chain;
+        pkt.modulation = Modulation::LORA;
+        pkt.datarate = 7;
+        pkt.status = status;
+        pkt.rssis = rssis;
+        pkt.snr = snr;
+        pkt
+    }
+
+    fn lines(out: &str, prefix: &str) -> Vec<String> {
+        out.lines().filter(|l| l.starts_with(prefix)).map(str::to_string).collect()
+    }
+
+    #[test]
+    fn render() {
+        let metrics = LgwMetrics::default();
+        metrics.record_rx(&lora_pkt(2, STAT_CRC_OK, -95.0, 7.0));
+        metrics.record_rx(&lora_pkt(2, STAT_CRC_BAD, -55.0, -12.0));
+        let mut fsk = lora_pkt(9, STAT_CRC_OK, -130.0, 0.0);
+        fsk.modem_id = SX1302_FSK_MODEM_ID;
+        fsk.modulation = Modulation::FSK;
+        fsk.datarate = 50000;
+        metrics.record_rx(&fsk);
+        metrics.record_tx(&LgwPktTx::default(), &Ok(()), 1500);
+        metrics.record_tx(&LgwPktTx::default(), &Err(Error::LbtNotAllowed { rf_chain: 0, freq_hz: 869_525_000 }.into()), 1500);
+        metrics.record_spi_rtt(Duration::from_micros(1500));
+        metrics.set_temperature(32.5);
+        let out = metrics.render();
+
+        assert_eq!(lines(&out, "lgw_rx_packets_total{if_chain=\"2\""), [
+            "lgw_rx_packets_total{if_chain=\"2\",modulation=\"LORA\",datarate=\"SF7\",status=\"crc_ok\"} 1",
+            "lgw_rx_packets_total{if_chain=\"2\",modulation=\"LORA\",datarate=\"SF7\",status=\"crc_bad\"} 1",
+            "lgw_rx_packets_total{if_chain=\"2\",modulation=\"LORA\",datarate=\"SF7\",status=\"no_crc\"} 0",
+        ]);
+        assert!(out.contains("lgw_rx_packets_total{if_chain=\"9\",modulation=\"FSK\",datarate=\"50000\",status=\"crc_ok\"} 1\n"));
+
+        /* buckets are cumulative, +Inf holds every observation */
+        let rssi = lines(&out, "lgw_rx_rssi_dbm_bucket{if_chain=\"2\"");
+        assert_eq!(rssi.len(), RSSI_BUCKETS.len() + 1);
+        assert_eq!(rssi[2], "lgw_rx_rssi_dbm_bucket{if_chain=\"2\",le=\"-110\"} 0");
+        assert_eq!(rssi[4], "lgw_rx_rssi_dbm_bucket{if_chain=\"2\",le=\"-90\"} 1");
+        assert_eq!(rssi[7], "lgw_rx_rssi_dbm_bucket{if_chain=\"2\",le=\"-60\"} 1");
+        assert_eq!(rssi[8], "lgw_rx_rssi_dbm_bucket{if_chain=\"2\",le=\"-50\"} 2");
+        assert_eq!(rssi[11], "lgw_rx_rssi_dbm_bucket{if_chain=\"2\",le=\"+Inf\"} 2");
+        assert!(out.contains("lgw_rx_rssi_dbm_sum{if_chain=\"2\"} -150\nlgw_rx_rssi_dbm_count{if_chain=\"2\"} 2\n"));
+        assert!(out.contains("lgw_rx_rssi_dbm_bucket{if_chain=\"9\",le=\"-130\"} 1\n"));
+
+        let snr = lines(&out, "lgw_rx_snr_db_bucket{if_chain=\"2\"");
+        assert_eq!(snr[1], "lgw_rx_snr_db_bucket{if_chain=\"2\",le=\"-15\"} 0");
+        assert_eq!(snr[2], "lgw_rx_snr_db_bucket{if_chain=\"2\",le=\"-10\"} 1");
+        assert_eq!(snr[3], "lgw_rx_snr_db_bucket{if_chain=\"2\",le=\"-7.5\"} 1");
+        assert_eq!(snr[9], "lgw_rx_snr_db_bucket{if_chain=\"2\",le=\"7.5\"} 2");
+        assert!(lines(&out, "lgw_rx_snr_db_bucket{if_chain=\"9\"").is_empty());
+
+        assert!(out.contains("lgw_tx_packets_total{result=\"ok\"} 1\n"));
+        assert!(out.contains("lgw_tx_packets_total{result=\"lbt_blocked\"} 1\n"));
+        assert!(out.contains("lgw_tx_airtime_seconds_total{rf_chain=\"0\"} 1.5\n"));
+
+        /* histogram without labels */
+        assert!(out.contains("lgw_mcu_spi_write_seconds_bucket{le=\"0.001\"} 0\nlgw_mcu_spi_write_seconds_bucket{le=\"0.002\"} 1\n"));
+        assert!(out.contains("lgw_mcu_spi_write_seconds_bucket{le=\"+Inf\"} 1\nlgw_mcu_spi_write_seconds_sum 0.0015\nlgw_mcu_spi_write_seconds_count 1\n"));
+
+        assert!(out.contains("# TYPE lgw_mcu_temperature_celsius gauge\nlgw_mcu_temperature_celsius 32.5\n"));
+        assert!(out.contains("lgw_concentrator_up 0\n"));
+        metrics.set_started(true);
+        assert!(metrics.render().contains("lgw_concentrator_up 1\n"));
+    }
+
+    fn http(addr: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn server() {
+        let metrics = Arc::new(LgwMetrics::default());
+        metrics.set_temperature(21.0);
+        let server = LgwMetricsServer::spawn("127.0.0.1:0", metrics).unwrap();
+        let addr = server.addr();
+        assert_ne!(addr.port(), 0);
+
+        let response = http(addr, "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+        assert!(head.contains("Content-Type: text/plain; version=0.0.4\r\n"));
+        assert!(head.contains(&format!("Content-Length: {}\r\n", body.len())));
+        assert!(body.contains("lgw_mcu_temperature_celsius 21\n"));
+
+        assert!(http(addr, "GET / HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(http(addr, "POST /metrics HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+
+        /* the listener is closed once the server is dropped */
+        drop(server);
+        assert!(TcpStream::connect(addr).is_err());
+    }
+}
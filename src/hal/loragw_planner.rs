@@ -0,0 +1,340 @@
+/*
+    Radio center and IF assignment.
+
+    Channels are given as absolute frequencies, the planner picks the center frequency of the
+    two radios and binds every channel to a radio with an IF offset that lgw_rxif_setconf
+    accepts:
+
+        |IF| + channel_bw/2 <= LGW_RF_RX_BANDWIDTH/2
+
+    Channels are sorted by frequency and split in two groups, one per radio. Among the splits
+    that fit, the one with the narrowest widest group is kept, which keeps channels away from
+    the radio band edges. Radio centers are the middle of their group, moved to a 100 kHz grid
+    when every channel still fits.
+*/
+
+use std::fmt;
+
+use super::loragw_conf::LgwGlobalConf;
+use super::{Hal, LgwConfRxIf, BW_125KHZ, BW_250KHZ, BW_500KHZ, LGW_IF_CHAIN_NB, LGW_MULTI_NB, LGW_RF_CHAIN_NB,
+            LGW_RF_RX_BANDWIDTH_125KHZ, LGW_RF_RX_BANDWIDTH_250KHZ, LGW_RF_RX_BANDWIDTH_500KHZ};
+
+const IF_CHAIN_LORA_STD: usize = 8;
+const IF_CHAIN_FSK: usize = 9;
+
+const RADIO_FREQ_GRID: u32 = 100_000;          /* Hz, preferred step of radio center frequencies */
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum LgwPlanError {
+    #[error("no channel to place")]
+    NoChannel,
+
+    #[error("{count} multi-SF channels given, the SX1302 has {max}")]
+    TooManyChannels { count: usize, max: usize },
+
+    #[error("{channel} at {freq_hz}Hz: {reason}")]
+    InvalidChannel { channel: String, freq_hz: u32, reason: &'static str },
+
+    #[error("channels from {low_hz}Hz to {high_hz}Hz need {span_hz}Hz on a single radio but its RX bandwidth is {max_hz}Hz, \
+             even with the best split between the two radios; drop or move channels to narrow that group by {excess_hz}Hz")]
+    DoesNotFit { low_hz: u32, high_hz: u32, span_hz: u32, max_hz: u32, excess_hz: u32 },
+}
+
+/**
+@struct LgwPlanChan
+@brief LoRa service or FSK channel to place
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct LgwPlanChan {
+    pub freq_hz: u32,
+    pub bandwidth: u8,      //*!> BW_125KHZ, BW_250KHZ or BW_500KHZ */
+    pub datarate: u32,      //*!> SF for the LoRa service channel, bps for the FSK channel */
+}
+
+/**
+@struct LgwChannelSet
+@brief Channels to receive, as absolute frequencies
+*/
+#[derive(Debug, Clone, Default)]
+pub struct LgwChannelSet {
+    pub multisf: Vec<u32>,                  //*!> multi-SF 125 kHz channels (in Hz), at most LGW_MULTI_NB */
+    pub lora_std: Option<LgwPlanChan>,      //*!> LoRa service channel */
+    pub fsk: Option<LgwPlanChan>,           //*!> FSK channel */
+}
+
+/**
+@struct LgwRadioPlan
+@brief Radio center frequencies and IF chains computed by lgw_plan_channels
+*/
+#[derive(Debug, Clone)]
+pub struct LgwRadioPlan {
+    pub rf_freq_hz: [u32; LGW_RF_CHAIN_NB as usize],           //*!> center frequency of each radio, 0 if unused */
+    pub if_chains: [LgwConfRxIf; LGW_IF_CHAIN_NB as usize],     //*!> IF chains, indexed as in lgw_rxif_setconf */
+}
+
+impl LgwRadioPlan {
+    /* Absolute frequency of an IF chain, None if it is disabled */
+    pub fn channel_freq(&self, if_chain: usize) -> Option<u32> {
+        let ifc = self.if_chains.get(if_chain).filter(|ifc| ifc.enable)?;
+        Some(self.rf_freq_hz[ifc.rf_chain as usize].wrapping_add_signed(ifc.freq_hz))
+    }
+
+    /* Set the radio centers and IF chains of conf, other settings are kept. Radios not used by
+       the plan are disabled. */
+    pub fn apply(&self, conf: &mut LgwGlobalConf) {
+        for (rf, &freq) in conf.rf_chains.iter_mut().zip(self.rf_freq_hz.iter()) {
+            rf.enable = freq != 0;
+            rf.freq_hz = freq;
+        }
+
+        conf.if_chains = self.if_chains;
+    }
+}
+
+impl fmt::Display for LgwRadioPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, freq) in self.rf_freq_hz.iter().enumerate() {
+            writeln!(f, " - radio_{}: {}", i, if *freq == 0 { "disabled".to_string() } else { format!("{} Hz", freq) })?;
+        }
+        for (i, ifc) in self.if_chains.iter().enumerate() {
+            if let Some(freq) = self.channel_freq(i) {
+                writeln!(f, " - if_chain[{}]: {} Hz (radio_{} {:+} Hz, bw {} Hz)", i, freq, ifc.rf_chain, ifc.freq_hz, Hal::lgw_bw_getval(ifc.bandwidth))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/* Radio RX bandwidth needed by a channel, as checked by lgw_rxif_setconf */
+pub(crate) fn rf_rx_bandwidth(bandwidth: u8) -> u32 {
+    match bandwidth {
+        BW_250KHZ => LGW_RF_RX_BANDWIDTH_250KHZ,
+        BW_500KHZ => LGW_RF_RX_BANDWIDTH_500KHZ,
+        _ => LGW_RF_RX_BANDWIDTH_125KHZ,
+    }
+}
+
+/* Channel to place, at an absolute frequency */
+#[derive(Debug, Clone, Copy)]
+struct Chan {
+    if_chain: usize,
+    freq_hz: u32,
+    bandwidth: u8,
+}
+
+impl Chan {
+    fn low(&self) -> u32 {
+        self.freq_hz - Hal::lgw_bw_getval(self.bandwidth) as u32 / 2
+    }
+
+    fn high(&self) -> u32 {
+        self.freq_hz + Hal::lgw_bw_getval(self.bandwidth) as u32 / 2
+    }
+
+    fn fits(&self, center: u32) -> bool {
+        self.freq_hz.abs_diff(center) + Hal::lgw_bw_getval(self.bandwidth) as u32 / 2 <= rf_rx_bandwidth(self.bandwidth) / 2
+    }
+}
+
+/* (low, high) edges of the band occupied by a group of channels */
+fn band(group: &[Chan]) -> (u32, u32) {
+    let low = group.iter().map(Chan::low).min().unwrap_or(0);
+    let high = group.iter().map(Chan::high).max().unwrap_or(0);
+    (low, high)
+}
+
+/* Radio center for a group of channels, None if they do not all fit in the radio bandwidth */
+fn fit_radio(group: &[Chan]) -> Option<u32> {
+    if group.is_empty() {
+        return Some(0);
+    }
+
+    let (low, high) = band(group);
+    let center = low + (high - low) / 2;
+    let rounded = (center + RADIO_FREQ_GRID / 2) / RADIO_FREQ_GRID * RADIO_FREQ_GRID;
+
+    [rounded, center].into_iter().find(|&c| group.iter().all(|chan| chan.fits(c)))
+}
+
+fn chan_name(if_chain: usize) -> String {
+    match if_chain {
+        IF_CHAIN_LORA_STD => "chan_Lora_std".to_string(),
+        IF_CHAIN_FSK => "chan_FSK".to_string(),
+        _ => format!("chan_multiSF_{}", if_chain),
+    }
+}
+
+fn check_chan(chan: &Chan, bandwidths: &[u8]) -> Result<(), LgwPlanError> {
+    let invalid = |reason| LgwPlanError::InvalidChannel { channel: chan_name(chan.if_chain), freq_hz: chan.freq_hz, reason };
+
+    if !bandwidths.contains(&chan.bandwidth) {
+        return Err(invalid("unsupported bandwidth"));
+    }
+    if chan.freq_hz < Hal::lgw_bw_getval(chan.bandwidth) as u32 / 2 {
+        return Err(invalid("invalid frequency"));
+    }
+    Ok(())
+}
+
+/* Pick the radio centers and bind every channel to a radio and IF offset. Multi-SF channels
+   go to IF chains 0 to 7 in the given order, the LoRa service and FSK channels to chains 8
+   and 9. */
+pub fn lgw_plan_channels(set: &LgwChannelSet) -> Result<LgwRadioPlan, LgwPlanError> {
+    if set.multisf.len() > LGW_MULTI_NB as usize {
+        return Err(LgwPlanError::TooManyChannels { count: set.multisf.len(), max: LGW_MULTI_NB as usize });
+    }
+
+    let mut chans: Vec<Chan> = set.multisf.iter().enumerate()
+        .map(|(i, &freq_hz)| Chan { if_chain: i, freq_hz, bandwidth: BW_125KHZ })
+        .collect();
+    if let Some(c) = set.lora_std {
+        chans.push(Chan { if_chain: IF_CHAIN_LORA_STD, freq_hz: c.freq_hz, bandwidth: c.bandwidth });
+    }
+    if let Some(c) = set.fsk {
+        chans.push(Chan { if_chain: IF_CHAIN_FSK, freq_hz: c.freq_hz, bandwidth: c.bandwidth });
+    }
+
+    if chans.is_empty() {
+        return Err(LgwPlanError::NoChannel);
+    }
+    for chan in chans.iter() {
+        match chan.if_chain {
+            IF_CHAIN_LORA_STD | IF_CHAIN_FSK => check_chan(chan, &[BW_125KHZ, BW_250KHZ, BW_500KHZ])?,
+            _ => check_chan(chan, &[BW_125KHZ])?,
+        }
+    }
+
+    chans.sort_by_key(|c| c.freq_hz);
+
+    /* split after the k lowest channels, k = len meaning a single radio */
+    let width = |group: &[Chan]| -> u32 {
+        let (low, high) = band(group);
+        high - low
+    };
+    let widest = |k: usize| width(&chans[.. k]).max(width(&chans[k ..]));
+
+    let best = (1 ..= chans.len())
+        .filter(|&k| fit_radio(&chans[.. k]).is_some() && fit_radio(&chans[k ..]).is_some())
+        .min_by_key(|&k| widest(k));
+
+    let Some(split) = best else {
+        /* explain with the split that comes closest to fitting */
+        let k = (1 ..= chans.len()).min_by_key(|&k| widest(k)).unwrap_or(chans.len());
+        let group = if width(&chans[.. k]) >= width(&chans[k ..]) { &chans[.. k] } else { &chans[k ..] };
+        let (low_hz, high_hz) = band(group);
+        let max_hz = group.iter().map(|c| rf_rx_bandwidth(c.bandwidth)).min().unwrap_or(0);
+        let span_hz = high_hz - low_hz;
+        return Err(LgwPlanError::DoesNotFit { low_hz, high_hz, span_hz, max_hz, excess_hz: span_hz.saturating_sub(max_hz) });
+    };
+
+    let mut plan = LgwRadioPlan {
+        rf_freq_hz: [fit_radio(&chans[.. split]).unwrap_or(0), fit_radio(&chans[split ..]).unwrap_or(0)],
+        if_chains: Default::default(),
+    };
+
+    for (rank, chan) in chans.iter().enumerate() {
+        let rf_chain = if rank < split { 0 } else { 1 };
+        let ifc = &mut plan.if_chains[chan.if_chain];
+        ifc.enable = true;
+        ifc.rf_chain = rf_chain;
+        ifc.freq_hz = chan.freq_hz as i32 - plan.rf_freq_hz[rf_chain as usize] as i32;
+        ifc.bandwidth = chan.bandwidth;
+    }
+    if let Some(c) = set.lora_std {
+        plan.if_chains[IF_CHAIN_LORA_STD].datarate = c.datarate;
+    }
+    if let Some(c) = set.fsk {
+        plan.if_chains[IF_CHAIN_FSK].datarate = c.datarate;
+    }
+
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn multisf(freqs: &[u32]) -> LgwChannelSet {
+        LgwChannelSet { multisf: freqs.to_vec(), ..Default::default() }
+    }
+
+    fn chan(freq_hz: u32) -> Chan {
+        Chan { if_chain: 0, freq_hz, bandwidth: BW_125KHZ }
+    }
+
+    #[test]
+    fn split_search() {
+        /* EU868: 867.1-867.9 and 868.1-868.8MHz keep both groups within 925kHz, 4 + 6 or 6 + 4
+           channels would make one of them wider than 1MHz */
+        let set = LgwChannelSet {
+            multisf: vec![868_100_000, 868_300_000, 868_500_000, 867_100_000, 867_300_000, 867_500_000, 867_700_000, 867_900_000],
+            lora_std: Some(LgwPlanChan { freq_hz: 868_300_000, bandwidth: BW_250KHZ, datarate: 7 }),
+            fsk: Some(LgwPlanChan { freq_hz: 868_800_000, bandwidth: BW_125KHZ, datarate: 50000 }),
+        };
+        let plan = lgw_plan_channels(&set).unwrap();
+        assert_eq!(plan.rf_freq_hz, [867_500_000, 868_500_000]);
+
+        let expected = [(1, -400_000), (1, -200_000), (1, 0), (0, -400_000), (0, -200_000), (0, 0), (0, 200_000), (0, 400_000), (1, -200_000), (1, 300_000)];
+        for (i, (rf_chain, freq_hz)) in expected.iter().enumerate() {
+            let ifc = &plan.if_chains[i];
+            assert!(ifc.enable, "if_chain[{}]", i);
+            assert_eq!((ifc.rf_chain, ifc.freq_hz), (*rf_chain, *freq_hz), "if_chain[{}]", i);
+            assert_eq!(plan.channel_freq(i), Some((plan.rf_freq_hz[*rf_chain as usize] as i32 + freq_hz) as u32));
+        }
+        assert_eq!((plan.if_chains[8].bandwidth, plan.if_chains[8].datarate), (BW_250KHZ, 7));
+        assert_eq!((plan.if_chains[9].bandwidth, plan.if_chains[9].datarate), (BW_125KHZ, 50000));
+
+        /* a single channel, the other radio is left unused */
+        let plan = lgw_plan_channels(&multisf(&[868_100_000])).unwrap();
+        assert_eq!(plan.rf_freq_hz, [868_100_000, 0]);
+        assert_eq!(plan.channel_freq(0), Some(868_100_000));
+        assert!((1 .. LGW_IF_CHAIN_NB as usize).all(|i| plan.channel_freq(i).is_none()));
+    }
+
+    #[test]
+    fn grid_rounding() {
+        /* centers are moved to the 100kHz grid when the channels still fit */
+        assert_eq!(fit_radio(&[chan(868_150_000)]), Some(868_200_000));
+        assert_eq!(fit_radio(&[chan(867_100_000), chan(867_725_000)]), Some(867_400_000));
+
+        /* 1.6MHz wide group: only its exact middle fits */
+        assert_eq!(fit_radio(&[chan(867_112_500), chan(868_587_500)]), Some(867_850_000));
+        assert_eq!(fit_radio(&[chan(867_112_500), chan(868_600_000)]), None);
+        assert_eq!(fit_radio(&[]), Some(0));
+    }
+
+    #[test]
+    fn too_many_channels() {
+        let freqs: Vec<u32> = (0 .. 9).map(|i| 867_100_000 + 200_000 * i).collect();
+        assert_eq!(lgw_plan_channels(&multisf(&freqs)).unwrap_err(), LgwPlanError::TooManyChannels { count: 9, max: 8 });
+        assert_eq!(lgw_plan_channels(&LgwChannelSet::default()).unwrap_err(), LgwPlanError::NoChannel);
+    }
+
+    #[test]
+    fn invalid_channel() {
+        assert_eq!(lgw_plan_channels(&multisf(&[868_100_000, 50_000])).unwrap_err(),
+            LgwPlanError::InvalidChannel { channel: "chan_multiSF_1".to_string(), freq_hz: 50_000, reason: "invalid frequency" });
+
+        let set = LgwChannelSet {
+            multisf: vec![868_100_000],
+            lora_std: Some(LgwPlanChan { freq_hz: 868_300_000, bandwidth: 0x07, datarate: 7 }),
+            fsk: None,
+        };
+        assert_eq!(lgw_plan_channels(&set).unwrap_err(),
+            LgwPlanError::InvalidChannel { channel: "chan_Lora_std".to_string(), freq_hz: 868_300_000, reason: "unsupported bandwidth" });
+
+        /* 250 and 500kHz are only for the LoRa service and FSK channels */
+        let set = LgwChannelSet { fsk: Some(LgwPlanChan { freq_hz: 868_800_000, bandwidth: BW_500KHZ, datarate: 50000 }), ..multisf(&[868_100_000]) };
+        assert!(lgw_plan_channels(&set).is_ok());
+    }
+
+    #[test]
+    fn does_not_fit() {
+        /* three clusters: the best split leaves 863.1 to 866.0MHz on one radio */
+        let err = lgw_plan_channels(&multisf(&[863_100_000, 863_300_000, 866_000_000, 866_200_000, 869_000_000])).unwrap_err();
+        assert_eq!(err, LgwPlanError::DoesNotFit {
+            low_hz: 863_037_500, high_hz: 866_062_500, span_hz: 3_025_000, max_hz: LGW_RF_RX_BANDWIDTH_125KHZ, excess_hz: 1_425_000,
+        });
+        assert!(err.to_string().ends_with("narrow that group by 1425000Hz"), "{}", err);
+    }
+}
@@ -1,4 +1,4 @@
-use anyhow::{anyhow,Result};
+use anyhow::Result;
 use tracing::{debug, error, info, trace};
 
 use super::error::Error;
@@ -2158,6 +2158,1055 @@ pub const LOREGS: [LgwReg; LGW_TOTALREGS as usize + 1] = [
     LgwReg::new(0,0,0,0,0,0,0,0)
 ];
 
+/* Names of the registers, indexed like LOREGS (the SX1302_REG_* constants) */
+pub static LOREGS_NAMES: [&str; LGW_TOTALREGS as usize] = [
+    "SX1302_REG_COMMON_PAGE_PAGE",
+    "SX1302_REG_COMMON_CTRL0_CLK32_RIF_CTRL",
+    "SX1302_REG_COMMON_CTRL0_HOST_RADIO_CTRL",
+    "SX1302_REG_COMMON_CTRL0_RADIO_MISC_EN",
+    "SX1302_REG_COMMON_CTRL0_SX1261_MODE_RADIO_B",
+    "SX1302_REG_COMMON_CTRL0_SX1261_MODE_RADIO_A",
+    "SX1302_REG_COMMON_CTRL1_SWAP_IQ_RADIO_B",
+    "SX1302_REG_COMMON_CTRL1_SAMPLING_EDGE_RADIO_B",
+    "SX1302_REG_COMMON_CTRL1_SWAP_IQ_RADIO_A",
+    "SX1302_REG_COMMON_CTRL1_SAMPLING_EDGE_RADIO_A",
+    "SX1302_REG_COMMON_SPI_DIV_RATIO_SPI_HALF_PERIOD",
+    "SX1302_REG_COMMON_RADIO_SELECT_RADIO_SELECT",
+    "SX1302_REG_COMMON_GEN_GLOBAL_EN",
+    "SX1302_REG_COMMON_GEN_FSK_MODEM_ENABLE",
+    "SX1302_REG_COMMON_GEN_CONCENTRATOR_MODEM_ENABLE",
+    "SX1302_REG_COMMON_GEN_MBWSSF_MODEM_ENABLE",
+    "SX1302_REG_COMMON_VERSION_VERSION",
+    "SX1302_REG_COMMON_DUMMY_DUMMY",
+    "SX1302_REG_AGC_MCU_CTRL_CLK_EN",
+    "SX1302_REG_AGC_MCU_CTRL_FORCE_HOST_FE_CTRL",
+    "SX1302_REG_AGC_MCU_CTRL_MCU_CLEAR",
+    "SX1302_REG_AGC_MCU_CTRL_HOST_PROG",
+    "SX1302_REG_AGC_MCU_CTRL_PARITY_ERROR",
+    "SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS",
+    "SX1302_REG_AGC_MCU_PA_GAIN_PA_B_GAIN",
+    "SX1302_REG_AGC_MCU_PA_GAIN_PA_A_GAIN",
+    "SX1302_REG_AGC_MCU_RF_EN_A_RADIO_RST",
+    "SX1302_REG_AGC_MCU_RF_EN_A_RADIO_EN",
+    "SX1302_REG_AGC_MCU_RF_EN_A_PA_EN",
+    "SX1302_REG_AGC_MCU_RF_EN_A_LNA_EN",
+    "SX1302_REG_AGC_MCU_RF_EN_B_RADIO_RST",
+    "SX1302_REG_AGC_MCU_RF_EN_B_RADIO_EN",
+    "SX1302_REG_AGC_MCU_RF_EN_B_PA_EN",
+    "SX1302_REG_AGC_MCU_RF_EN_B_LNA_EN",
+    "SX1302_REG_AGC_MCU_LUT_TABLE_A_PA_LUT",
+    "SX1302_REG_AGC_MCU_LUT_TABLE_A_LNA_LUT",
+    "SX1302_REG_AGC_MCU_LUT_TABLE_B_PA_LUT",
+    "SX1302_REG_AGC_MCU_LUT_TABLE_B_LNA_LUT",
+    "SX1302_REG_AGC_MCU_UART_CFG_MSBF",
+    "SX1302_REG_AGC_MCU_UART_CFG_PAR_EN",
+    "SX1302_REG_AGC_MCU_UART_CFG_PAR_MODE",
+    "SX1302_REG_AGC_MCU_UART_CFG_START_LEN",
+    "SX1302_REG_AGC_MCU_UART_CFG_STOP_LEN",
+    "SX1302_REG_AGC_MCU_UART_CFG_WORD_LEN",
+    "SX1302_REG_AGC_MCU_UART_CFG2_BIT_RATE",
+    "SX1302_REG_AGC_MCU_MCU_MAIL_BOX_WR_DATA_BYTE3_MCU_MAIL_BOX_WR_DATA",
+    "SX1302_REG_AGC_MCU_MCU_MAIL_BOX_WR_DATA_BYTE2_MCU_MAIL_BOX_WR_DATA",
+    "SX1302_REG_AGC_MCU_MCU_MAIL_BOX_WR_DATA_BYTE1_MCU_MAIL_BOX_WR_DATA",
+    "SX1302_REG_AGC_MCU_MCU_MAIL_BOX_WR_DATA_BYTE0_MCU_MAIL_BOX_WR_DATA",
+    "SX1302_REG_AGC_MCU_MCU_MAIL_BOX_RD_DATA_BYTE3_MCU_MAIL_BOX_RD_DATA",
+    "SX1302_REG_AGC_MCU_MCU_MAIL_BOX_RD_DATA_BYTE2_MCU_MAIL_BOX_RD_DATA",
+    "SX1302_REG_AGC_MCU_MCU_MAIL_BOX_RD_DATA_BYTE1_MCU_MAIL_BOX_RD_DATA",
+    "SX1302_REG_AGC_MCU_MCU_MAIL_BOX_RD_DATA_BYTE0_MCU_MAIL_BOX_RD_DATA",
+    "SX1302_REG_AGC_MCU_DUMMY_DUMMY3",
+    "SX1302_REG_CLK_CTRL_CLK_SEL_CLKDIV_EN",
+    "SX1302_REG_CLK_CTRL_CLK_SEL_CLK_RADIO_B_SEL",
+    "SX1302_REG_CLK_CTRL_CLK_SEL_CLK_RADIO_A_SEL",
+    "SX1302_REG_CLK_CTRL_DUMMY_DUMMY",
+    "SX1302_REG_TX_TOP_A_TX_TRIG_TX_FSM_CLR",
+    "SX1302_REG_TX_TOP_A_TX_TRIG_TX_TRIG_GPS",
+    "SX1302_REG_TX_TOP_A_TX_TRIG_TX_TRIG_DELAYED",
+    "SX1302_REG_TX_TOP_A_TX_TRIG_TX_TRIG_IMMEDIATE",
+    "SX1302_REG_TX_TOP_A_TIMER_TRIG_BYTE3_TIMER_DELAYED_TRIG",
+    "SX1302_REG_TX_TOP_A_TIMER_TRIG_BYTE2_TIMER_DELAYED_TRIG",
+    "SX1302_REG_TX_TOP_A_TIMER_TRIG_BYTE1_TIMER_DELAYED_TRIG",
+    "SX1302_REG_TX_TOP_A_TIMER_TRIG_BYTE0_TIMER_DELAYED_TRIG",
+    "SX1302_REG_TX_TOP_A_TX_START_DELAY_MSB_TX_START_DELAY",
+    "SX1302_REG_TX_TOP_A_TX_START_DELAY_LSB_TX_START_DELAY",
+    "SX1302_REG_TX_TOP_A_TX_CTRL_WRITE_BUFFER",
+    "SX1302_REG_TX_TOP_A_TX_RAMP_DURATION_TX_RAMP_DURATION",
+    "SX1302_REG_TX_TOP_A_GEN_CFG_0_MODULATION_TYPE",
+    "SX1302_REG_TX_TOP_A_TEST_0_TX_ACTIVE_CTRL",
+    "SX1302_REG_TX_TOP_A_TEST_0_TX_ACTIVE_SEL",
+    "SX1302_REG_TX_TOP_A_TX_FLAG_TX_TIMEOUT",
+    "SX1302_REG_TX_TOP_A_TX_FLAG_PKT_DONE",
+    "SX1302_REG_TX_TOP_A_AGC_TX_BW_AGC_TX_BW",
+    "SX1302_REG_TX_TOP_A_AGC_TX_PWR_AGC_TX_PWR",
+    "SX1302_REG_TX_TOP_A_TIMEOUT_CNT_BYTE_2_TIMEOUT_CNT",
+    "SX1302_REG_TX_TOP_A_TIMEOUT_CNT_BYTE_1_TIMEOUT_CNT",
+    "SX1302_REG_TX_TOP_A_TIMEOUT_CNT_BYTE_0_TIMEOUT_CNT",
+    "SX1302_REG_TX_TOP_A_TX_FSM_STATUS_TX_STATUS",
+    "SX1302_REG_TX_TOP_A_DUMMY_CONTROL_DUMMY",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_CTRL_PLL_DIV_CTRL",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_CTRL_TX_CLK_EDGE",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_CTRL_TX_MODE",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_CTRL_TX_IF_DST",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_CTRL_TX_IF_SRC",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_CTRL2_SX125X_IQ_INVERT",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_CTRL2_PLL_DIV_CTRL_AGC",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_IQ_GAIN_IQ_GAIN",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_I_OFFSET_I_OFFSET",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_Q_OFFSET_Q_OFFSET",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_FREQ_RF_H_FREQ_RF",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_FREQ_RF_M_FREQ_RF",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_FREQ_RF_L_FREQ_RF",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_FREQ_DEV_H_FREQ_DEV",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_FREQ_DEV_L_FREQ_DEV",
+    "SX1302_REG_TX_TOP_A_TX_RFFE_IF_TEST_MOD_FREQ",
+    "SX1302_REG_TX_TOP_A_DUMMY_MODULATOR_DUMMY",
+    "SX1302_REG_TX_TOP_A_FSK_PKT_LEN_PKT_LENGTH",
+    "SX1302_REG_TX_TOP_A_FSK_CFG_0_TX_CONT",
+    "SX1302_REG_TX_TOP_A_FSK_CFG_0_CRC_IBM",
+    "SX1302_REG_TX_TOP_A_FSK_CFG_0_DCFREE_ENC",
+    "SX1302_REG_TX_TOP_A_FSK_CFG_0_CRC_EN",
+    "SX1302_REG_TX_TOP_A_FSK_CFG_0_PKT_MODE",
+    "SX1302_REG_TX_TOP_A_FSK_PREAMBLE_SIZE_MSB_PREAMBLE_SIZE",
+    "SX1302_REG_TX_TOP_A_FSK_PREAMBLE_SIZE_LSB_PREAMBLE_SIZE",
+    "SX1302_REG_TX_TOP_A_FSK_BIT_RATE_MSB_BIT_RATE",
+    "SX1302_REG_TX_TOP_A_FSK_BIT_RATE_LSB_BIT_RATE",
+    "SX1302_REG_TX_TOP_A_FSK_MOD_FSK_REF_PATTERN_SIZE",
+    "SX1302_REG_TX_TOP_A_FSK_MOD_FSK_PREAMBLE_SEQ",
+    "SX1302_REG_TX_TOP_A_FSK_MOD_FSK_REF_PATTERN_EN",
+    "SX1302_REG_TX_TOP_A_FSK_MOD_FSK_GAUSSIAN_SELECT_BT",
+    "SX1302_REG_TX_TOP_A_FSK_MOD_FSK_GAUSSIAN_EN",
+    "SX1302_REG_TX_TOP_A_FSK_REF_PATTERN_BYTE7_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_A_FSK_REF_PATTERN_BYTE6_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_A_FSK_REF_PATTERN_BYTE5_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_A_FSK_REF_PATTERN_BYTE4_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_A_FSK_REF_PATTERN_BYTE3_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_A_FSK_REF_PATTERN_BYTE2_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_A_FSK_REF_PATTERN_BYTE1_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_A_FSK_REF_PATTERN_BYTE0_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_A_DUMMY_GSFK_DUMMY",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG0_0_MODEM_BW",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG0_0_MODEM_SF",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG0_1_PPM_OFFSET_HDR_CTRL",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG0_1_PPM_OFFSET",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG0_1_POST_PREAMBLE_GAP_LONG",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG0_1_CODING_RATE",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG0_2_FINE_SYNCH_EN",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG0_2_MODEM_EN",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG0_2_CADRXTX",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG0_2_IMPLICIT_HEADER",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG0_2_CRC_EN",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG0_3_PAYLOAD_LENGTH",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG1_0_INT_STEP_ORIDE_EN",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG1_0_INT_STEP_ORIDE",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG1_1_MODEM_START",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG1_1_HEADER_DIFF_MODE",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG1_1_ZERO_PAD",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG1_2_PREAMBLE_SYMB_NB",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG1_3_PREAMBLE_SYMB_NB",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG1_4_AUTO_ACK_INT_DELAY",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG1_4_AUTO_ACK_RX",
+    "SX1302_REG_TX_TOP_A_TXRX_CFG1_4_AUTO_ACK_TX",
+    "SX1302_REG_TX_TOP_A_TX_CFG0_0_CHIRP_LOWPASS",
+    "SX1302_REG_TX_TOP_A_TX_CFG0_0_PPM_OFFSET_SIG",
+    "SX1302_REG_TX_TOP_A_TX_CFG0_0_CONTCHIRP",
+    "SX1302_REG_TX_TOP_A_TX_CFG0_0_CHIRP_INVERT",
+    "SX1302_REG_TX_TOP_A_TX_CFG0_0_CONTINUOUS",
+    "SX1302_REG_TX_TOP_A_TX_CFG0_1_POWER_RANGING",
+    "SX1302_REG_TX_TOP_A_TX_CFG1_0_FRAME_NB",
+    "SX1302_REG_TX_TOP_A_TX_CFG1_1_HOP_CTRL",
+    "SX1302_REG_TX_TOP_A_TX_CFG1_1_IFS",
+    "SX1302_REG_TX_TOP_A_FRAME_SYNCH_0_AUTO_SCALE",
+    "SX1302_REG_TX_TOP_A_FRAME_SYNCH_0_DROP_ON_SYNCH",
+    "SX1302_REG_TX_TOP_A_FRAME_SYNCH_0_GAIN",
+    "SX1302_REG_TX_TOP_A_FRAME_SYNCH_0_PEAK1_POS",
+    "SX1302_REG_TX_TOP_A_FRAME_SYNCH_1_FINETIME_ON_LAST",
+    "SX1302_REG_TX_TOP_A_FRAME_SYNCH_1_TIMEOUT_OPT",
+    "SX1302_REG_TX_TOP_A_FRAME_SYNCH_1_PEAK2_POS",
+    "SX1302_REG_TX_TOP_A_LORA_TX_STATE_STATUS",
+    "SX1302_REG_TX_TOP_A_LORA_TX_FLAG_FRAME_DONE",
+    "SX1302_REG_TX_TOP_A_LORA_TX_FLAG_CONT_DONE",
+    "SX1302_REG_TX_TOP_A_LORA_TX_FLAG_PLD_DONE",
+    "SX1302_REG_TX_TOP_A_DUMMY_LORA_DUMMY",
+    "SX1302_REG_TX_TOP_B_TX_TRIG_TX_FSM_CLR",
+    "SX1302_REG_TX_TOP_B_TX_TRIG_TX_TRIG_GPS",
+    "SX1302_REG_TX_TOP_B_TX_TRIG_TX_TRIG_DELAYED",
+    "SX1302_REG_TX_TOP_B_TX_TRIG_TX_TRIG_IMMEDIATE",
+    "SX1302_REG_TX_TOP_B_TIMER_TRIG_BYTE3_TIMER_DELAYED_TRIG",
+    "SX1302_REG_TX_TOP_B_TIMER_TRIG_BYTE2_TIMER_DELAYED_TRIG",
+    "SX1302_REG_TX_TOP_B_TIMER_TRIG_BYTE1_TIMER_DELAYED_TRIG",
+    "SX1302_REG_TX_TOP_B_TIMER_TRIG_BYTE0_TIMER_DELAYED_TRIG",
+    "SX1302_REG_TX_TOP_B_TX_START_DELAY_MSB_TX_START_DELAY",
+    "SX1302_REG_TX_TOP_B_TX_START_DELAY_LSB_TX_START_DELAY",
+    "SX1302_REG_TX_TOP_B_TX_CTRL_WRITE_BUFFER",
+    "SX1302_REG_TX_TOP_B_TX_RAMP_DURATION_TX_RAMP_DURATION",
+    "SX1302_REG_TX_TOP_B_GEN_CFG_0_MODULATION_TYPE",
+    "SX1302_REG_TX_TOP_B_TEST_0_TX_ACTIVE_CTRL",
+    "SX1302_REG_TX_TOP_B_TEST_0_TX_ACTIVE_SEL",
+    "SX1302_REG_TX_TOP_B_TX_FLAG_TX_TIMEOUT",
+    "SX1302_REG_TX_TOP_B_TX_FLAG_PKT_DONE",
+    "SX1302_REG_TX_TOP_B_AGC_TX_BW_AGC_TX_BW",
+    "SX1302_REG_TX_TOP_B_AGC_TX_PWR_AGC_TX_PWR",
+    "SX1302_REG_TX_TOP_B_TIMEOUT_CNT_BYTE_2_TIMEOUT_CNT",
+    "SX1302_REG_TX_TOP_B_TIMEOUT_CNT_BYTE_1_TIMEOUT_CNT",
+    "SX1302_REG_TX_TOP_B_TIMEOUT_CNT_BYTE_0_TIMEOUT_CNT",
+    "SX1302_REG_TX_TOP_B_TX_FSM_STATUS_TX_STATUS",
+    "SX1302_REG_TX_TOP_B_DUMMY_CONTROL_DUMMY",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_CTRL_PLL_DIV_CTRL",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_CTRL_TX_CLK_EDGE",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_CTRL_TX_MODE",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_CTRL_TX_IF_DST",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_CTRL_TX_IF_SRC",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_CTRL2_SX125X_IQ_INVERT",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_CTRL2_PLL_DIV_CTRL_AGC",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_IQ_GAIN_IQ_GAIN",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_I_OFFSET_I_OFFSET",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_Q_OFFSET_Q_OFFSET",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_FREQ_RF_H_FREQ_RF",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_FREQ_RF_M_FREQ_RF",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_FREQ_RF_L_FREQ_RF",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_FREQ_DEV_H_FREQ_DEV",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_FREQ_DEV_L_FREQ_DEV",
+    "SX1302_REG_TX_TOP_B_TX_RFFE_IF_TEST_MOD_FREQ",
+    "SX1302_REG_TX_TOP_B_DUMMY_MODULATOR_DUMMY",
+    "SX1302_REG_TX_TOP_B_FSK_PKT_LEN_PKT_LENGTH",
+    "SX1302_REG_TX_TOP_B_FSK_CFG_0_TX_CONT",
+    "SX1302_REG_TX_TOP_B_FSK_CFG_0_CRC_IBM",
+    "SX1302_REG_TX_TOP_B_FSK_CFG_0_DCFREE_ENC",
+    "SX1302_REG_TX_TOP_B_FSK_CFG_0_CRC_EN",
+    "SX1302_REG_TX_TOP_B_FSK_CFG_0_PKT_MODE",
+    "SX1302_REG_TX_TOP_B_FSK_PREAMBLE_SIZE_MSB_PREAMBLE_SIZE",
+    "SX1302_REG_TX_TOP_B_FSK_PREAMBLE_SIZE_LSB_PREAMBLE_SIZE",
+    "SX1302_REG_TX_TOP_B_FSK_BIT_RATE_MSB_BIT_RATE",
+    "SX1302_REG_TX_TOP_B_FSK_BIT_RATE_LSB_BIT_RATE",
+    "SX1302_REG_TX_TOP_B_FSK_MOD_FSK_REF_PATTERN_SIZE",
+    "SX1302_REG_TX_TOP_B_FSK_MOD_FSK_PREAMBLE_SEQ",
+    "SX1302_REG_TX_TOP_B_FSK_MOD_FSK_REF_PATTERN_EN",
+    "SX1302_REG_TX_TOP_B_FSK_MOD_FSK_GAUSSIAN_SELECT_BT",
+    "SX1302_REG_TX_TOP_B_FSK_MOD_FSK_GAUSSIAN_EN",
+    "SX1302_REG_TX_TOP_B_FSK_REF_PATTERN_BYTE7_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_B_FSK_REF_PATTERN_BYTE6_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_B_FSK_REF_PATTERN_BYTE5_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_B_FSK_REF_PATTERN_BYTE4_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_B_FSK_REF_PATTERN_BYTE3_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_B_FSK_REF_PATTERN_BYTE2_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_B_FSK_REF_PATTERN_BYTE1_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_B_FSK_REF_PATTERN_BYTE0_FSK_REF_PATTERN",
+    "SX1302_REG_TX_TOP_B_DUMMY_GSFK_DUMMY",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG0_0_MODEM_BW",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG0_0_MODEM_SF",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG0_1_PPM_OFFSET_HDR_CTRL",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG0_1_PPM_OFFSET",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG0_1_POST_PREAMBLE_GAP_LONG",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG0_1_CODING_RATE",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG0_2_FINE_SYNCH_EN",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG0_2_MODEM_EN",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG0_2_CADRXTX",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG0_2_IMPLICIT_HEADER",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG0_2_CRC_EN",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG0_3_PAYLOAD_LENGTH",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG1_0_INT_STEP_ORIDE_EN",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG1_0_INT_STEP_ORIDE",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG1_1_MODEM_START",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG1_1_HEADER_DIFF_MODE",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG1_1_ZERO_PAD",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG1_2_PREAMBLE_SYMB_NB",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG1_3_PREAMBLE_SYMB_NB",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG1_4_AUTO_ACK_INT_DELAY",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG1_4_AUTO_ACK_RX",
+    "SX1302_REG_TX_TOP_B_TXRX_CFG1_4_AUTO_ACK_TX",
+    "SX1302_REG_TX_TOP_B_TX_CFG0_0_CHIRP_LOWPASS",
+    "SX1302_REG_TX_TOP_B_TX_CFG0_0_PPM_OFFSET_SIG",
+    "SX1302_REG_TX_TOP_B_TX_CFG0_0_CONTCHIRP",
+    "SX1302_REG_TX_TOP_B_TX_CFG0_0_CHIRP_INVERT",
+    "SX1302_REG_TX_TOP_B_TX_CFG0_0_CONTINUOUS",
+    "SX1302_REG_TX_TOP_B_TX_CFG0_1_POWER_RANGING",
+    "SX1302_REG_TX_TOP_B_TX_CFG1_0_FRAME_NB",
+    "SX1302_REG_TX_TOP_B_TX_CFG1_1_HOP_CTRL",
+    "SX1302_REG_TX_TOP_B_TX_CFG1_1_IFS",
+    "SX1302_REG_TX_TOP_B_FRAME_SYNCH_0_AUTO_SCALE",
+    "SX1302_REG_TX_TOP_B_FRAME_SYNCH_0_DROP_ON_SYNCH",
+    "SX1302_REG_TX_TOP_B_FRAME_SYNCH_0_GAIN",
+    "SX1302_REG_TX_TOP_B_FRAME_SYNCH_0_PEAK1_POS",
+    "SX1302_REG_TX_TOP_B_FRAME_SYNCH_1_FINETIME_ON_LAST",
+    "SX1302_REG_TX_TOP_B_FRAME_SYNCH_1_TIMEOUT_OPT",
+    "SX1302_REG_TX_TOP_B_FRAME_SYNCH_1_PEAK2_POS",
+    "SX1302_REG_TX_TOP_B_LORA_TX_STATE_STATUS",
+    "SX1302_REG_TX_TOP_B_LORA_TX_FLAG_FRAME_DONE",
+    "SX1302_REG_TX_TOP_B_LORA_TX_FLAG_CONT_DONE",
+    "SX1302_REG_TX_TOP_B_LORA_TX_FLAG_PLD_DONE",
+    "SX1302_REG_TX_TOP_B_DUMMY_LORA_DUMMY",
+    "SX1302_REG_GPIO_GPIO_DIR_H_DIRECTION",
+    "SX1302_REG_GPIO_GPIO_DIR_L_DIRECTION",
+    "SX1302_REG_GPIO_GPIO_OUT_H_OUT_VALUE",
+    "SX1302_REG_GPIO_GPIO_OUT_L_OUT_VALUE",
+    "SX1302_REG_GPIO_GPIO_IN_H_IN_VALUE",
+    "SX1302_REG_GPIO_GPIO_IN_L_IN_VALUE",
+    "SX1302_REG_GPIO_GPIO_PD_H_PD_VALUE",
+    "SX1302_REG_GPIO_GPIO_PD_L_PD_VALUE",
+    "SX1302_REG_GPIO_GPIO_SEL_0_SELECTION",
+    "SX1302_REG_GPIO_GPIO_SEL_1_SELECTION",
+    "SX1302_REG_GPIO_GPIO_SEL_2_SELECTION",
+    "SX1302_REG_GPIO_GPIO_SEL_3_SELECTION",
+    "SX1302_REG_GPIO_GPIO_SEL_4_SELECTION",
+    "SX1302_REG_GPIO_GPIO_SEL_5_SELECTION",
+    "SX1302_REG_GPIO_GPIO_SEL_6_SELECTION",
+    "SX1302_REG_GPIO_GPIO_SEL_7_SELECTION",
+    "SX1302_REG_GPIO_GPIO_SEL_8_11_GPIO_11_9_SEL",
+    "SX1302_REG_GPIO_GPIO_SEL_8_11_GPIO_8_SEL",
+    "SX1302_REG_GPIO_HOST_IRQ_TX_TIMEOUT_B",
+    "SX1302_REG_GPIO_HOST_IRQ_TX_TIMEOUT_A",
+    "SX1302_REG_GPIO_HOST_IRQ_TX_DONE_B",
+    "SX1302_REG_GPIO_HOST_IRQ_TX_DONE_A",
+    "SX1302_REG_GPIO_HOST_IRQ_TIMESTAMP",
+    "SX1302_REG_GPIO_HOST_IRQ_RX_BUFFER_WATERMARK",
+    "SX1302_REG_GPIO_HOST_IRQ_EN_TX_TIMEOUT_B",
+    "SX1302_REG_GPIO_HOST_IRQ_EN_TX_TIMEOUT_A",
+    "SX1302_REG_GPIO_HOST_IRQ_EN_TX_DONE_B",
+    "SX1302_REG_GPIO_HOST_IRQ_EN_TX_DONE_A",
+    "SX1302_REG_GPIO_HOST_IRQ_EN_TIMESTAMP",
+    "SX1302_REG_GPIO_HOST_IRQ_EN_RX_BUFFER_WATERMARK",
+    "SX1302_REG_GPIO_DUMMY_DUMMY",
+    "SX1302_REG_TIMESTAMP_GPS_CTRL_GPS_POL",
+    "SX1302_REG_TIMESTAMP_GPS_CTRL_GPS_EN",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_PPS_MSB2_TIMESTAMP_PPS",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_PPS_MSB1_TIMESTAMP_PPS",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_PPS_LSB2_TIMESTAMP_PPS",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_PPS_LSB1_TIMESTAMP_PPS",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_MSB2_TIMESTAMP",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_MSB1_TIMESTAMP",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_LSB2_TIMESTAMP",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_LSB1_TIMESTAMP",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_SET3_TIMESTAMP",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_SET2_TIMESTAMP",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_SET1_TIMESTAMP",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_SET0_TIMESTAMP",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_IRQ_3_TIMESTAMP",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_IRQ_2_TIMESTAMP",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_IRQ_1_TIMESTAMP",
+    "SX1302_REG_TIMESTAMP_TIMESTAMP_IRQ_0_TIMESTAMP",
+    "SX1302_REG_TIMESTAMP_DUMMY_DUMMY",
+    "SX1302_REG_RX_TOP_FREQ_0_MSB_IF_FREQ_0",
+    "SX1302_REG_RX_TOP_FREQ_0_LSB_IF_FREQ_0",
+    "SX1302_REG_RX_TOP_FREQ_1_MSB_IF_FREQ_1",
+    "SX1302_REG_RX_TOP_FREQ_1_LSB_IF_FREQ_1",
+    "SX1302_REG_RX_TOP_FREQ_2_MSB_IF_FREQ_2",
+    "SX1302_REG_RX_TOP_FREQ_2_LSB_IF_FREQ_2",
+    "SX1302_REG_RX_TOP_FREQ_3_MSB_IF_FREQ_3",
+    "SX1302_REG_RX_TOP_FREQ_3_LSB_IF_FREQ_3",
+    "SX1302_REG_RX_TOP_FREQ_4_MSB_IF_FREQ_4",
+    "SX1302_REG_RX_TOP_FREQ_4_LSB_IF_FREQ_4",
+    "SX1302_REG_RX_TOP_FREQ_5_MSB_IF_FREQ_5",
+    "SX1302_REG_RX_TOP_FREQ_5_LSB_IF_FREQ_5",
+    "SX1302_REG_RX_TOP_FREQ_6_MSB_IF_FREQ_6",
+    "SX1302_REG_RX_TOP_FREQ_6_LSB_IF_FREQ_6",
+    "SX1302_REG_RX_TOP_FREQ_7_MSB_IF_FREQ_7",
+    "SX1302_REG_RX_TOP_FREQ_7_LSB_IF_FREQ_7",
+    "SX1302_REG_RX_TOP_RADIO_SELECT_RADIO_SELECT",
+    "SX1302_REG_RX_TOP_RSSI_CONTROL_RSSI_FILTER_ALPHA",
+    "SX1302_REG_RX_TOP_RSSI_CONTROL_SELECT_RSSI",
+    "SX1302_REG_RX_TOP_RSSI_DEF_VALUE_CHAN_RSSI_DEF_VALUE",
+    "SX1302_REG_RX_TOP_CHANN_DAGC_CFG1_CHAN_DAGC_THRESHOLD_HIGH",
+    "SX1302_REG_RX_TOP_CHANN_DAGC_CFG2_CHAN_DAGC_THRESHOLD_LOW",
+    "SX1302_REG_RX_TOP_CHANN_DAGC_CFG3_CHAN_DAGC_MAX_ATTEN",
+    "SX1302_REG_RX_TOP_CHANN_DAGC_CFG3_CHAN_DAGC_MIN_ATTEN",
+    "SX1302_REG_RX_TOP_CHANN_DAGC_CFG4_CHAN_DAGC_STEP",
+    "SX1302_REG_RX_TOP_CHANN_DAGC_CFG5_CHAN_DAGC_MODE",
+    "SX1302_REG_RX_TOP_RSSI_VALUE_CHAN_RSSI",
+    "SX1302_REG_RX_TOP_GAIN_CONTROL_CHAN_GAIN_VALID",
+    "SX1302_REG_RX_TOP_GAIN_CONTROL_CHAN_GAIN",
+    "SX1302_REG_RX_TOP_CLK_CONTROL_CHAN_CLK_EN",
+    "SX1302_REG_RX_TOP_DUMMY0_DUMMY0",
+    "SX1302_REG_RX_TOP_CORR_CLOCK_ENABLE_CLK_EN",
+    "SX1302_REG_RX_TOP_CORRELATOR_EN_CORR_EN",
+    "SX1302_REG_RX_TOP_CORRELATOR_SF_EN_CORR_SF_EN",
+    "SX1302_REG_RX_TOP_CORRELATOR_ENABLE_ONLY_FIRST_DET_EDGE_ENABLE_ONLY_FIRST_DET_EDGE",
+    "SX1302_REG_RX_TOP_CORRELATOR_ENABLE_ACC_CLEAR_ENABLE_CORR_ACC_CLEAR",
+    "SX1302_REG_RX_TOP_SF5_CFG1_ACC_WIN_LEN",
+    "SX1302_REG_RX_TOP_SF5_CFG1_ACC_PEAK_SUM_EN",
+    "SX1302_REG_RX_TOP_SF5_CFG1_ACC_PEAK_POS_SEL",
+    "SX1302_REG_RX_TOP_SF5_CFG1_ACC_COEFF",
+    "SX1302_REG_RX_TOP_SF5_CFG1_ACC_AUTO_RESCALE",
+    "SX1302_REG_RX_TOP_SF5_CFG1_ACC_2_SAME_PEAKS",
+    "SX1302_REG_RX_TOP_SF5_CFG2_ACC_MIN2",
+    "SX1302_REG_RX_TOP_SF5_CFG2_ACC_PNR",
+    "SX1302_REG_RX_TOP_SF5_CFG3_MIN_SINGLE_PEAK",
+    "SX1302_REG_RX_TOP_SF5_CFG4_MSP_PNR",
+    "SX1302_REG_RX_TOP_SF5_CFG5_MSP2_PNR",
+    "SX1302_REG_RX_TOP_SF5_CFG6_MSP_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF5_CFG6_MSP_CNT_MODE",
+    "SX1302_REG_RX_TOP_SF5_CFG6_MSP_POS_SEL",
+    "SX1302_REG_RX_TOP_SF5_CFG7_MSP2_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF5_CFG7_NOISE_COEFF",
+    "SX1302_REG_RX_TOP_SF6_CFG1_ACC_WIN_LEN",
+    "SX1302_REG_RX_TOP_SF6_CFG1_ACC_PEAK_SUM_EN",
+    "SX1302_REG_RX_TOP_SF6_CFG1_ACC_PEAK_POS_SEL",
+    "SX1302_REG_RX_TOP_SF6_CFG1_ACC_COEFF",
+    "SX1302_REG_RX_TOP_SF6_CFG1_ACC_AUTO_RESCALE",
+    "SX1302_REG_RX_TOP_SF6_CFG1_ACC_2_SAME_PEAKS",
+    "SX1302_REG_RX_TOP_SF6_CFG2_ACC_MIN2",
+    "SX1302_REG_RX_TOP_SF6_CFG2_ACC_PNR",
+    "SX1302_REG_RX_TOP_SF6_CFG3_MIN_SINGLE_PEAK",
+    "SX1302_REG_RX_TOP_SF6_CFG4_MSP_PNR",
+    "SX1302_REG_RX_TOP_SF6_CFG5_MSP2_PNR",
+    "SX1302_REG_RX_TOP_SF6_CFG6_MSP_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF6_CFG6_MSP_CNT_MODE",
+    "SX1302_REG_RX_TOP_SF6_CFG6_MSP_POS_SEL",
+    "SX1302_REG_RX_TOP_SF6_CFG7_MSP2_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF6_CFG7_NOISE_COEFF",
+    "SX1302_REG_RX_TOP_SF7_CFG1_ACC_WIN_LEN",
+    "SX1302_REG_RX_TOP_SF7_CFG1_ACC_PEAK_SUM_EN",
+    "SX1302_REG_RX_TOP_SF7_CFG1_ACC_PEAK_POS_SEL",
+    "SX1302_REG_RX_TOP_SF7_CFG1_ACC_COEFF",
+    "SX1302_REG_RX_TOP_SF7_CFG1_ACC_AUTO_RESCALE",
+    "SX1302_REG_RX_TOP_SF7_CFG1_ACC_2_SAME_PEAKS",
+    "SX1302_REG_RX_TOP_SF7_CFG2_ACC_MIN2",
+    "SX1302_REG_RX_TOP_SF7_CFG2_ACC_PNR",
+    "SX1302_REG_RX_TOP_SF7_CFG3_MIN_SINGLE_PEAK",
+    "SX1302_REG_RX_TOP_SF7_CFG4_MSP_PNR",
+    "SX1302_REG_RX_TOP_SF7_CFG5_MSP2_PNR",
+    "SX1302_REG_RX_TOP_SF7_CFG6_MSP_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF7_CFG6_MSP_CNT_MODE",
+    "SX1302_REG_RX_TOP_SF7_CFG6_MSP_POS_SEL",
+    "SX1302_REG_RX_TOP_SF7_CFG7_MSP2_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF7_CFG7_NOISE_COEFF",
+    "SX1302_REG_RX_TOP_SF8_CFG1_ACC_WIN_LEN",
+    "SX1302_REG_RX_TOP_SF8_CFG1_ACC_PEAK_SUM_EN",
+    "SX1302_REG_RX_TOP_SF8_CFG1_ACC_PEAK_POS_SEL",
+    "SX1302_REG_RX_TOP_SF8_CFG1_ACC_COEFF",
+    "SX1302_REG_RX_TOP_SF8_CFG1_ACC_AUTO_RESCALE",
+    "SX1302_REG_RX_TOP_SF8_CFG1_ACC_2_SAME_PEAKS",
+    "SX1302_REG_RX_TOP_SF8_CFG2_ACC_MIN2",
+    "SX1302_REG_RX_TOP_SF8_CFG2_ACC_PNR",
+    "SX1302_REG_RX_TOP_SF8_CFG3_MIN_SINGLE_PEAK",
+    "SX1302_REG_RX_TOP_SF8_CFG4_MSP_PNR",
+    "SX1302_REG_RX_TOP_SF8_CFG5_MSP2_PNR",
+    "SX1302_REG_RX_TOP_SF8_CFG6_MSP_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF8_CFG6_MSP_CNT_MODE",
+    "SX1302_REG_RX_TOP_SF8_CFG6_MSP_POS_SEL",
+    "SX1302_REG_RX_TOP_SF8_CFG7_MSP2_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF8_CFG7_NOISE_COEFF",
+    "SX1302_REG_RX_TOP_SF9_CFG1_ACC_WIN_LEN",
+    "SX1302_REG_RX_TOP_SF9_CFG1_ACC_PEAK_SUM_EN",
+    "SX1302_REG_RX_TOP_SF9_CFG1_ACC_PEAK_POS_SEL",
+    "SX1302_REG_RX_TOP_SF9_CFG1_ACC_COEFF",
+    "SX1302_REG_RX_TOP_SF9_CFG1_ACC_AUTO_RESCALE",
+    "SX1302_REG_RX_TOP_SF9_CFG1_ACC_2_SAME_PEAKS",
+    "SX1302_REG_RX_TOP_SF9_CFG2_ACC_MIN2",
+    "SX1302_REG_RX_TOP_SF9_CFG2_ACC_PNR",
+    "SX1302_REG_RX_TOP_SF9_CFG3_MIN_SINGLE_PEAK",
+    "SX1302_REG_RX_TOP_SF9_CFG4_MSP_PNR",
+    "SX1302_REG_RX_TOP_SF9_CFG5_MSP2_PNR",
+    "SX1302_REG_RX_TOP_SF9_CFG6_MSP_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF9_CFG6_MSP_CNT_MODE",
+    "SX1302_REG_RX_TOP_SF9_CFG6_MSP_POS_SEL",
+    "SX1302_REG_RX_TOP_SF9_CFG7_MSP2_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF9_CFG7_NOISE_COEFF",
+    "SX1302_REG_RX_TOP_SF10_CFG1_ACC_WIN_LEN",
+    "SX1302_REG_RX_TOP_SF10_CFG1_ACC_PEAK_SUM_EN",
+    "SX1302_REG_RX_TOP_SF10_CFG1_ACC_PEAK_POS_SEL",
+    "SX1302_REG_RX_TOP_SF10_CFG1_ACC_COEFF",
+    "SX1302_REG_RX_TOP_SF10_CFG1_ACC_AUTO_RESCALE",
+    "SX1302_REG_RX_TOP_SF10_CFG1_ACC_2_SAME_PEAKS",
+    "SX1302_REG_RX_TOP_SF10_CFG2_ACC_MIN2",
+    "SX1302_REG_RX_TOP_SF10_CFG2_ACC_PNR",
+    "SX1302_REG_RX_TOP_SF10_CFG3_MIN_SINGLE_PEAK",
+    "SX1302_REG_RX_TOP_SF10_CFG4_MSP_PNR",
+    "SX1302_REG_RX_TOP_SF10_CFG5_MSP2_PNR",
+    "SX1302_REG_RX_TOP_SF10_CFG6_MSP_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF10_CFG6_MSP_CNT_MODE",
+    "SX1302_REG_RX_TOP_SF10_CFG6_MSP_POS_SEL",
+    "SX1302_REG_RX_TOP_SF10_CFG7_MSP2_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF10_CFG7_NOISE_COEFF",
+    "SX1302_REG_RX_TOP_SF11_CFG1_ACC_WIN_LEN",
+    "SX1302_REG_RX_TOP_SF11_CFG1_ACC_PEAK_SUM_EN",
+    "SX1302_REG_RX_TOP_SF11_CFG1_ACC_PEAK_POS_SEL",
+    "SX1302_REG_RX_TOP_SF11_CFG1_ACC_COEFF",
+    "SX1302_REG_RX_TOP_SF11_CFG1_ACC_AUTO_RESCALE",
+    "SX1302_REG_RX_TOP_SF11_CFG1_ACC_2_SAME_PEAKS",
+    "SX1302_REG_RX_TOP_SF11_CFG2_ACC_MIN2",
+    "SX1302_REG_RX_TOP_SF11_CFG2_ACC_PNR",
+    "SX1302_REG_RX_TOP_SF11_CFG3_MIN_SINGLE_PEAK",
+    "SX1302_REG_RX_TOP_SF11_CFG4_MSP_PNR",
+    "SX1302_REG_RX_TOP_SF11_CFG5_MSP2_PNR",
+    "SX1302_REG_RX_TOP_SF11_CFG6_MSP_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF11_CFG6_MSP_CNT_MODE",
+    "SX1302_REG_RX_TOP_SF11_CFG6_MSP_POS_SEL",
+    "SX1302_REG_RX_TOP_SF11_CFG7_MSP2_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF11_CFG7_NOISE_COEFF",
+    "SX1302_REG_RX_TOP_SF12_CFG1_ACC_WIN_LEN",
+    "SX1302_REG_RX_TOP_SF12_CFG1_ACC_PEAK_SUM_EN",
+    "SX1302_REG_RX_TOP_SF12_CFG1_ACC_PEAK_POS_SEL",
+    "SX1302_REG_RX_TOP_SF12_CFG1_ACC_COEFF",
+    "SX1302_REG_RX_TOP_SF12_CFG1_ACC_AUTO_RESCALE",
+    "SX1302_REG_RX_TOP_SF12_CFG1_ACC_2_SAME_PEAKS",
+    "SX1302_REG_RX_TOP_SF12_CFG2_ACC_MIN2",
+    "SX1302_REG_RX_TOP_SF12_CFG2_ACC_PNR",
+    "SX1302_REG_RX_TOP_SF12_CFG3_MIN_SINGLE_PEAK",
+    "SX1302_REG_RX_TOP_SF12_CFG4_MSP_PNR",
+    "SX1302_REG_RX_TOP_SF12_CFG5_MSP2_PNR",
+    "SX1302_REG_RX_TOP_SF12_CFG6_MSP_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF12_CFG6_MSP_CNT_MODE",
+    "SX1302_REG_RX_TOP_SF12_CFG6_MSP_POS_SEL",
+    "SX1302_REG_RX_TOP_SF12_CFG7_MSP2_PEAK_NB",
+    "SX1302_REG_RX_TOP_SF12_CFG7_NOISE_COEFF",
+    "SX1302_REG_RX_TOP_DUMMY1_DUMMY1",
+    "SX1302_REG_RX_TOP_DC_NOTCH_CFG1_BW_START",
+    "SX1302_REG_RX_TOP_DC_NOTCH_CFG1_AUTO_BW_RED",
+    "SX1302_REG_RX_TOP_DC_NOTCH_CFG1_NO_FAST_START",
+    "SX1302_REG_RX_TOP_DC_NOTCH_CFG1_BYPASS",
+    "SX1302_REG_RX_TOP_DC_NOTCH_CFG1_ENABLE",
+    "SX1302_REG_RX_TOP_DC_NOTCH_CFG2_BW_LOCKED",
+    "SX1302_REG_RX_TOP_DC_NOTCH_CFG2_BW",
+    "SX1302_REG_RX_TOP_DC_NOTCH_CFG3_BW_RED",
+    "SX1302_REG_RX_TOP_DC_NOTCH_CFG4_IIR_DCC_TIME",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR1_0_FIR1_COEFF_0",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR1_1_FIR1_COEFF_1",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR1_2_FIR1_COEFF_2",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR1_3_FIR1_COEFF_3",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR1_4_FIR1_COEFF_4",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR1_5_FIR1_COEFF_5",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR1_6_FIR1_COEFF_6",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR1_7_FIR1_COEFF_7",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR2_0_FIR2_COEFF_0",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR2_1_FIR2_COEFF_1",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR2_2_FIR2_COEFF_2",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR2_3_FIR2_COEFF_3",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR2_4_FIR2_COEFF_4",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR2_5_FIR2_COEFF_5",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR2_6_FIR2_COEFF_6",
+    "SX1302_REG_RX_TOP_RX_DFE_FIR2_7_FIR2_COEFF_7",
+    "SX1302_REG_RX_TOP_RX_DFE_AGC0_RADIO_GAIN_RED_SEL",
+    "SX1302_REG_RX_TOP_RX_DFE_AGC0_RADIO_GAIN_RED_DB",
+    "SX1302_REG_RX_TOP_RX_DFE_AGC1_DC_COMP_EN",
+    "SX1302_REG_RX_TOP_RX_DFE_AGC1_FORCE_DEFAULT_FIR",
+    "SX1302_REG_RX_TOP_RX_DFE_AGC1_RSSI_EARLY_LATCH",
+    "SX1302_REG_RX_TOP_RX_DFE_AGC1_FREEZE_ON_SYNC",
+    "SX1302_REG_RX_TOP_RX_DFE_AGC2_DAGC_IN_COMP",
+    "SX1302_REG_RX_TOP_RX_DFE_AGC2_DAGC_FIR_HYST",
+    "SX1302_REG_RX_TOP_RX_DFE_AGC2_RSSI_MAX_SAMPLE",
+    "SX1302_REG_RX_TOP_RX_DFE_AGC2_RSSI_MIN_SAMPLE",
+    "SX1302_REG_RX_TOP_RX_DFE_GAIN0_DAGC_FIR_FAST",
+    "SX1302_REG_RX_TOP_RX_DFE_GAIN0_FORCE_GAIN_FIR",
+    "SX1302_REG_RX_TOP_RX_DFE_GAIN0_GAIN_FIR1",
+    "SX1302_REG_RX_TOP_RX_DFE_GAIN0_GAIN_FIR2",
+    "SX1302_REG_RX_TOP_DAGC_CFG_TARGET_LVL",
+    "SX1302_REG_RX_TOP_DAGC_CFG_GAIN_INCR_STEP",
+    "SX1302_REG_RX_TOP_DAGC_CFG_GAIN_DROP_COMP",
+    "SX1302_REG_RX_TOP_DAGC_CFG_COMB_FILTER_EN",
+    "SX1302_REG_RX_TOP_DAGC_CFG_NO_FREEZE_START",
+    "SX1302_REG_RX_TOP_DAGC_CFG_FREEZE_ON_SYNC",
+    "SX1302_REG_RX_TOP_DAGC_CNT0_SAMPLE",
+    "SX1302_REG_RX_TOP_DAGC_CNT1_THR_M6",
+    "SX1302_REG_RX_TOP_DAGC_CNT2_THR_M12",
+    "SX1302_REG_RX_TOP_DAGC_CNT3_THR_M18",
+    "SX1302_REG_RX_TOP_DAGC_CNT4_GAIN",
+    "SX1302_REG_RX_TOP_DAGC_CNT4_FORCE_GAIN",
+    "SX1302_REG_RX_TOP_TXRX_CFG1_PPM_OFFSET_HDR_CTRL",
+    "SX1302_REG_RX_TOP_TXRX_CFG1_PPM_OFFSET",
+    "SX1302_REG_RX_TOP_TXRX_CFG1_MODEM_EN",
+    "SX1302_REG_RX_TOP_TXRX_CFG1_CODING_RATE",
+    "SX1302_REG_RX_TOP_TXRX_CFG2_MODEM_START",
+    "SX1302_REG_RX_TOP_TXRX_CFG2_CADRXTX",
+    "SX1302_REG_RX_TOP_TXRX_CFG2_IMPLICIT_HEADER",
+    "SX1302_REG_RX_TOP_TXRX_CFG2_CRC_EN",
+    "SX1302_REG_RX_TOP_TXRX_CFG3_PAYLOAD_LENGTH",
+    "SX1302_REG_RX_TOP_TXRX_CFG4_INT_STEP_ORIDE_EN",
+    "SX1302_REG_RX_TOP_TXRX_CFG4_INT_STEP_ORIDE",
+    "SX1302_REG_RX_TOP_TXRX_CFG5_HEADER_DIFF_MODE",
+    "SX1302_REG_RX_TOP_TXRX_CFG5_ZERO_PAD",
+    "SX1302_REG_RX_TOP_TXRX_CFG6_PREAMBLE_SYMB_NB",
+    "SX1302_REG_RX_TOP_TXRX_CFG7_PREAMBLE_SYMB_NB",
+    "SX1302_REG_RX_TOP_TXRX_CFG8_AUTO_ACK_INT_DELAY",
+    "SX1302_REG_RX_TOP_TXRX_CFG8_AUTO_ACK_RX",
+    "SX1302_REG_RX_TOP_TXRX_CFG8_AUTO_ACK_TX",
+    "SX1302_REG_RX_TOP_TXRX_CFG8_POST_PREAMBLE_GAP_LONG",
+    "SX1302_REG_RX_TOP_TXRX_CFG9_FINE_SYNCH_EN_SF12",
+    "SX1302_REG_RX_TOP_TXRX_CFG9_FINE_SYNCH_EN_SF11",
+    "SX1302_REG_RX_TOP_TXRX_CFG9_FINE_SYNCH_EN_SF10",
+    "SX1302_REG_RX_TOP_TXRX_CFG9_FINE_SYNCH_EN_SF9",
+    "SX1302_REG_RX_TOP_TXRX_CFG9_FINE_SYNCH_EN_SF8",
+    "SX1302_REG_RX_TOP_TXRX_CFG9_FINE_SYNCH_EN_SF7",
+    "SX1302_REG_RX_TOP_TXRX_CFG9_FINE_SYNCH_EN_SF6",
+    "SX1302_REG_RX_TOP_TXRX_CFG9_FINE_SYNCH_EN_SF5",
+    "SX1302_REG_RX_TOP_RX_CFG0_DFT_PEAK_EN",
+    "SX1302_REG_RX_TOP_RX_CFG0_CHIRP_INVERT",
+    "SX1302_REG_RX_TOP_RX_CFG0_SWAP_IQ",
+    "SX1302_REG_RX_TOP_RX_CFG0_CONTINUOUS",
+    "SX1302_REG_RX_TOP_RX_CFG1_DETECT_TIMEOUT",
+    "SX1302_REG_RX_TOP_RX_CFG2_CLK_EN_RESYNC_DIN",
+    "SX1302_REG_RX_TOP_RX_CFG2_LLR_SCALE",
+    "SX1302_REG_RX_TOP_FRAME_SYNCH0_SF5_PEAK1_POS_SF5",
+    "SX1302_REG_RX_TOP_FRAME_SYNCH1_SF5_PEAK2_POS_SF5",
+    "SX1302_REG_RX_TOP_FRAME_SYNCH0_SF6_PEAK1_POS_SF6",
+    "SX1302_REG_RX_TOP_FRAME_SYNCH1_SF6_PEAK2_POS_SF6",
+    "SX1302_REG_RX_TOP_FRAME_SYNCH0_SF7TO12_PEAK1_POS_SF7TO12",
+    "SX1302_REG_RX_TOP_FRAME_SYNCH1_SF7TO12_PEAK2_POS_SF7TO12",
+    "SX1302_REG_RX_TOP_FRAME_SYNCH2_FINETIME_ON_LAST",
+    "SX1302_REG_RX_TOP_FRAME_SYNCH2_AUTO_SCALE",
+    "SX1302_REG_RX_TOP_FRAME_SYNCH2_DROP_ON_SYNCH",
+    "SX1302_REG_RX_TOP_FRAME_SYNCH2_GAIN",
+    "SX1302_REG_RX_TOP_FRAME_SYNCH2_TIMEOUT_OPT",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_0_GAIN_P_HDR_RED",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_0_ROUNDING",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_0_POS_LIMIT",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_0_SUM_SIZE",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_0_MODE",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_1_GAIN_P_AUTO",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_1_GAIN_P_PAYLOAD",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_1_GAIN_P_PREAMB",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_2_GAIN_I_AUTO",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_2_GAIN_I_PAYLOAD",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_2_GAIN_I_PREAMB",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_3_FINESYNCH_SUM",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_3_FINESYNCH_GAIN",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_4_GAIN_I_EN_SF8",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_4_GAIN_I_EN_SF7",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_4_GAIN_I_EN_SF6",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_4_GAIN_I_EN_SF5",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_5_GAIN_I_EN_SF12",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_5_GAIN_I_EN_SF11",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_5_GAIN_I_EN_SF10",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_5_GAIN_I_EN_SF9",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_6_GAIN_P_PREAMB_SF12",
+    "SX1302_REG_RX_TOP_FINE_TIMING_A_6_GAIN_P_PREAMB_SF5_6",
+    "SX1302_REG_RX_TOP_FINE_TIMING_7_GAIN_I_AUTO_MAX",
+    "SX1302_REG_RX_TOP_FINE_TIMING_7_GAIN_P_AUTO_MAX",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_0_GAIN_P_HDR_RED",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_0_ROUNDING",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_0_POS_LIMIT",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_0_SUM_SIZE",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_0_MODE",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_1_GAIN_P_AUTO",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_1_GAIN_P_PAYLOAD",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_1_GAIN_P_PREAMB",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_2_GAIN_I_AUTO",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_2_GAIN_I_PAYLOAD",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_2_GAIN_I_PREAMB",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_3_FINESYNCH_SUM",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_3_FINESYNCH_GAIN",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_4_GAIN_I_EN_SF8",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_4_GAIN_I_EN_SF7",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_4_GAIN_I_EN_SF6",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_4_GAIN_I_EN_SF5",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_5_GAIN_I_EN_SF12",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_5_GAIN_I_EN_SF11",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_5_GAIN_I_EN_SF10",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_5_GAIN_I_EN_SF9",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_6_GAIN_P_PREAMB_SF12",
+    "SX1302_REG_RX_TOP_FINE_TIMING_B_6_GAIN_P_PREAMB_SF5_6",
+    "SX1302_REG_RX_TOP_FREQ_TO_TIME0_FREQ_TO_TIME_DRIFT_MANT",
+    "SX1302_REG_RX_TOP_FREQ_TO_TIME1_FREQ_TO_TIME_DRIFT_MANT",
+    "SX1302_REG_RX_TOP_FREQ_TO_TIME2_FREQ_TO_TIME_DRIFT_EXP",
+    "SX1302_REG_RX_TOP_FREQ_TO_TIME3_FREQ_TO_TIME_INVERT_FREQ_DELTA",
+    "SX1302_REG_RX_TOP_FREQ_TO_TIME3_FREQ_TO_TIME_INVERT_FINE_DELTA",
+    "SX1302_REG_RX_TOP_FREQ_TO_TIME3_FREQ_TO_TIME_INVERT_FREQ_ERROR",
+    "SX1302_REG_RX_TOP_FREQ_TO_TIME3_FREQ_TO_TIME_INVERT_TIME_SYMB",
+    "SX1302_REG_RX_TOP_FREQ_TO_TIME3_FREQ_TO_TIME_INVERT_TIME_OFFSET",
+    "SX1302_REG_RX_TOP_FREQ_TO_TIME3_FREQ_TO_TIME_INVERT_DETECT",
+    "SX1302_REG_RX_TOP_FREQ_TO_TIME4_FREQ_TO_TIME_INVERT_RNG",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_A_0_FREQ_TRACK_EN_SF8",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_A_0_FREQ_TRACK_EN_SF7",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_A_0_FREQ_TRACK_EN_SF6",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_A_0_FREQ_TRACK_EN_SF5",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_A_1_FREQ_TRACK_EN_SF12",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_A_1_FREQ_TRACK_EN_SF11",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_A_1_FREQ_TRACK_EN_SF10",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_A_1_FREQ_TRACK_EN_SF9",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_B_0_FREQ_TRACK_EN_SF8",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_B_0_FREQ_TRACK_EN_SF7",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_B_0_FREQ_TRACK_EN_SF6",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_B_0_FREQ_TRACK_EN_SF5",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_B_1_FREQ_TRACK_EN_SF12",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_B_1_FREQ_TRACK_EN_SF11",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_B_1_FREQ_TRACK_EN_SF10",
+    "SX1302_REG_RX_TOP_FREQ_TRACK_B_1_FREQ_TRACK_EN_SF9",
+    "SX1302_REG_RX_TOP_FREQ_TRACK2_FREQ_TRACK_FINE",
+    "SX1302_REG_RX_TOP_FREQ_TRACK2_FREQ_TRACK_HDR_SKIP",
+    "SX1302_REG_RX_TOP_FREQ_TRACK3_FREQ_SYNCH_GAIN",
+    "SX1302_REG_RX_TOP_FREQ_TRACK3_FREQ_TRACK_AUTO_THR",
+    "SX1302_REG_RX_TOP_FREQ_TRACK4_SNR_MIN_WINDOW",
+    "SX1302_REG_RX_TOP_FREQ_TRACK4_GAIN_AUTO_SNR_MIN",
+    "SX1302_REG_RX_TOP_FREQ_TRACK4_FREQ_SYNCH_THR",
+    "SX1302_REG_RX_TOP_DETECT_MSP0_MSP_PNR",
+    "SX1302_REG_RX_TOP_DETECT_MSP1_MSP2_PNR",
+    "SX1302_REG_RX_TOP_DETECT_MSP2_MSP2_PEAK_NB",
+    "SX1302_REG_RX_TOP_DETECT_MSP2_MSP_PEAK_NB",
+    "SX1302_REG_RX_TOP_DETECT_MSP3_ACC_MIN2",
+    "SX1302_REG_RX_TOP_DETECT_MSP3_ACC_WIN_LEN",
+    "SX1302_REG_RX_TOP_DETECT_MSP3_MSP_POS_SEL",
+    "SX1302_REG_RX_TOP_DETECT_MSP3_MSP_CNT_MODE",
+    "SX1302_REG_RX_TOP_DETECT_ACC1_USE_GAIN_SYMB",
+    "SX1302_REG_RX_TOP_DETECT_ACC1_ACC_PNR",
+    "SX1302_REG_RX_TOP_DETECT_ACC2_NOISE_COEFF",
+    "SX1302_REG_RX_TOP_DETECT_ACC2_ACC_COEFF",
+    "SX1302_REG_RX_TOP_DETECT_ACC2_ACC_2_SAME_PEAKS",
+    "SX1302_REG_RX_TOP_DETECT_ACC2_ACC_AUTO_RESCALE",
+    "SX1302_REG_RX_TOP_DETECT_ACC2_ACC_PEAK_POS_SEL",
+    "SX1302_REG_RX_TOP_DETECT_ACC2_ACC_PEAK_SUM_EN",
+    "SX1302_REG_RX_TOP_DETECT_ACC3_MIN_SINGLE_PEAK",
+    "SX1302_REG_RX_TOP_TIMESTAMP_SEL_SNR_MIN",
+    "SX1302_REG_RX_TOP_TIMESTAMP_ENABLE",
+    "SX1302_REG_RX_TOP_TIMESTAMP_NB_SYMB",
+    "SX1302_REG_RX_TOP_MODEM_BUSY_MSB_RX_MODEM_BUSY",
+    "SX1302_REG_RX_TOP_MODEM_BUSY_LSB_RX_MODEM_BUSY",
+    "SX1302_REG_RX_TOP_MODEM_STATE_RX_MODEM_STS_SPARE",
+    "SX1302_REG_RX_TOP_MODEM_STATE_RX_MODEM_STATE",
+    "SX1302_REG_RX_TOP_MODEM_SYNC_DELTA_MSB_PEAK_POS_FINE_GAIN_H",
+    "SX1302_REG_RX_TOP_MODEM_SYNC_DELTA_MSB_PEAK_POS_FINE_GAIN_L",
+    "SX1302_REG_RX_TOP_MODEM_SYNC_DELTA_MSB_PEAK_POS_FINE_SIGN",
+    "SX1302_REG_RX_TOP_MODEM_SYNC_DELTA_MSB_MODEM_SYNC_DELTA",
+    "SX1302_REG_RX_TOP_MODEM_SYNC_DELTA_LSB_MODEM_SYNC_DELTA",
+    "SX1302_REG_RX_TOP_MODEM_PPM_OFFSET1_PPM_OFFSET_SF8",
+    "SX1302_REG_RX_TOP_MODEM_PPM_OFFSET1_PPM_OFFSET_SF7",
+    "SX1302_REG_RX_TOP_MODEM_PPM_OFFSET1_PPM_OFFSET_SF6",
+    "SX1302_REG_RX_TOP_MODEM_PPM_OFFSET1_PPM_OFFSET_SF5",
+    "SX1302_REG_RX_TOP_MODEM_PPM_OFFSET2_PPM_OFFSET_SF12",
+    "SX1302_REG_RX_TOP_MODEM_PPM_OFFSET2_PPM_OFFSET_SF11",
+    "SX1302_REG_RX_TOP_MODEM_PPM_OFFSET2_PPM_OFFSET_SF10",
+    "SX1302_REG_RX_TOP_MODEM_PPM_OFFSET2_PPM_OFFSET_SF9",
+    "SX1302_REG_RX_TOP_MODEM_CLOCK_GATE_OVERRIDE_3_CLK_OVERRIDE",
+    "SX1302_REG_RX_TOP_MODEM_CLOCK_GATE_OVERRIDE_2_CLK_OVERRIDE",
+    "SX1302_REG_RX_TOP_MODEM_CLOCK_GATE_OVERRIDE_1_CLK_OVERRIDE",
+    "SX1302_REG_RX_TOP_MODEM_CLOCK_GATE_OVERRIDE_0_CLK_OVERRIDE",
+    "SX1302_REG_RX_TOP_DUMMY2_DUMMY2",
+    "SX1302_REG_RX_TOP_RX_BUFFER_DEBUG_MODE",
+    "SX1302_REG_RX_TOP_RX_BUFFER_DIRECT_RAM_IF",
+    "SX1302_REG_RX_TOP_RX_BUFFER_LEGACY_TIMESTAMP",
+    "SX1302_REG_RX_TOP_RX_BUFFER_STORE_HEADER_ERR_META",
+    "SX1302_REG_RX_TOP_RX_BUFFER_STORE_SYNC_FAIL_META",
+    "SX1302_REG_RX_TOP_RXBUFFER_TIMESTAMP_CFG_MAX_TS_METRICS",
+    "SX1302_REG_RX_TOP_RX_BUFFER_IRQ_CTRL_MSB_RX_BUFFER_IRQ_THRESHOLD",
+    "SX1302_REG_RX_TOP_RX_BUFFER_IRQ_CTRL_LSB_RX_BUFFER_IRQ_THRESHOLD",
+    "SX1302_REG_RX_TOP_RX_BUFFER_LAST_ADDR_READ_MSB_LAST_ADDR_READ",
+    "SX1302_REG_RX_TOP_RX_BUFFER_LAST_ADDR_READ_LSB_LAST_ADDR_READ",
+    "SX1302_REG_RX_TOP_RX_BUFFER_LAST_ADDR_WRITE_MSB_LAST_ADDR_WRITE",
+    "SX1302_REG_RX_TOP_RX_BUFFER_LAST_ADDR_WRITE_LSB_LAST_ADDR_WRITE",
+    "SX1302_REG_RX_TOP_RX_BUFFER_NB_BYTES_MSB_RX_BUFFER_NB_BYTES",
+    "SX1302_REG_RX_TOP_RX_BUFFER_NB_BYTES_LSB_RX_BUFFER_NB_BYTES",
+    "SX1302_REG_RX_TOP_MULTI_SF_SYNC_ERR_PKT_CNT_MULTI_SF_SYNC_ERR_PKTS",
+    "SX1302_REG_RX_TOP_MULTI_SF_PLD_ERR_PKT_CNT_MULTI_SF_PLD_ERR_PKTS",
+    "SX1302_REG_RX_TOP_MULTI_SF_GOOD_PKT_CNT_MULTI_SF_GOOD_PKTS",
+    "SX1302_REG_RX_TOP_SERV_MODEM_SYNC_ERR_PKT_CNT_SERV_MODEM_SYNC_ERR_PKTS",
+    "SX1302_REG_RX_TOP_SERV_MODEM_PLD_ERR_PKT_CNT_SERV_MODEM_PLD_ERR_PKTS",
+    "SX1302_REG_RX_TOP_SERV_MODEM_GOOD_PKT_CNT_SERV_MODEM_GOOD_PKTS",
+    "SX1302_REG_RX_TOP_GFSK_MODEM_SYNC_ERR_PKT_CNT_GFSK_MODEM_SYNC_ERR_PKTS",
+    "SX1302_REG_RX_TOP_GFSK_MODEM_PLD_ERR_PKT_CNT_GFSK_MODEM_PLD_ERR_PKTS",
+    "SX1302_REG_RX_TOP_GFSK_MODEM_GOOD_PKT_CNT_GFSK_MODEM_GOOD_PKTS",
+    "SX1302_REG_RX_TOP_BAD_MODEM_ID_WRITE_0_BAD_MODEM_ID_WRITE",
+    "SX1302_REG_RX_TOP_BAD_MODEM_ID_WRITE_1_BAD_MODEM_ID_WRITE",
+    "SX1302_REG_RX_TOP_BAD_MODEM_ID_WRITE_2_BAD_MODEM_ID_WRITE",
+    "SX1302_REG_RX_TOP_BAD_MODEM_ID_READ_0_BAD_MODEM_ID_READ",
+    "SX1302_REG_RX_TOP_BAD_MODEM_ID_READ_1_BAD_MODEM_ID_READ",
+    "SX1302_REG_RX_TOP_BAD_MODEM_ID_READ_2_BAD_MODEM_ID_READ",
+    "SX1302_REG_RX_TOP_CLOCK_GATE_OVERRIDE_0_CLK_OVERRIDE",
+    "SX1302_REG_RX_TOP_SAMPLE_4_MSPS_LATCHED_125K_SAMPLE_4_MSPS_LATCHED_125K",
+    "SX1302_REG_RX_TOP_DUMMY3_DUMMY3",
+    "SX1302_REG_ARB_MCU_CTRL_CLK_EN",
+    "SX1302_REG_ARB_MCU_CTRL_RADIO_RST",
+    "SX1302_REG_ARB_MCU_CTRL_FORCE_HOST_FE_CTRL",
+    "SX1302_REG_ARB_MCU_CTRL_MCU_CLEAR",
+    "SX1302_REG_ARB_MCU_CTRL_HOST_PROG",
+    "SX1302_REG_ARB_MCU_CTRL_PARITY_ERROR",
+    "SX1302_REG_ARB_MCU_MCU_ARB_STATUS_MCU_ARB_STATUS",
+    "SX1302_REG_ARB_MCU_UART_CFG_MSBF",
+    "SX1302_REG_ARB_MCU_UART_CFG_PAR_EN",
+    "SX1302_REG_ARB_MCU_UART_CFG_PAR_MODE",
+    "SX1302_REG_ARB_MCU_UART_CFG_START_LEN",
+    "SX1302_REG_ARB_MCU_UART_CFG_STOP_LEN",
+    "SX1302_REG_ARB_MCU_UART_CFG_WORD_LEN",
+    "SX1302_REG_ARB_MCU_UART_CFG2_BIT_RATE",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_CFG_0_ARB_DEBUG_CFG_0",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_CFG_1_ARB_DEBUG_CFG_1",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_CFG_2_ARB_DEBUG_CFG_2",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_CFG_3_ARB_DEBUG_CFG_3",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_0_ARB_DEBUG_STS_0",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_1_ARB_DEBUG_STS_1",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_2_ARB_DEBUG_STS_2",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_3_ARB_DEBUG_STS_3",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_4_ARB_DEBUG_STS_4",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_5_ARB_DEBUG_STS_5",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_6_ARB_DEBUG_STS_6",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_7_ARB_DEBUG_STS_7",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_8_ARB_DEBUG_STS_8",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_9_ARB_DEBUG_STS_9",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_10_ARB_DEBUG_STS_10",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_11_ARB_DEBUG_STS_11",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_12_ARB_DEBUG_STS_12",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_13_ARB_DEBUG_STS_13",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_14_ARB_DEBUG_STS_14",
+    "SX1302_REG_ARB_MCU_ARB_DEBUG_STS_15_ARB_DEBUG_STS_15",
+    "SX1302_REG_ARB_MCU_CHANNEL_SYNC_OFFSET_01_CHANNEL_1_OFFSET",
+    "SX1302_REG_ARB_MCU_CHANNEL_SYNC_OFFSET_01_CHANNEL_0_OFFSET",
+    "SX1302_REG_ARB_MCU_CHANNEL_SYNC_OFFSET_23_CHANNEL_3_OFFSET",
+    "SX1302_REG_ARB_MCU_CHANNEL_SYNC_OFFSET_23_CHANNEL_2_OFFSET",
+    "SX1302_REG_ARB_MCU_CHANNEL_SYNC_OFFSET_45_CHANNEL_5_OFFSET",
+    "SX1302_REG_ARB_MCU_CHANNEL_SYNC_OFFSET_45_CHANNEL_4_OFFSET",
+    "SX1302_REG_ARB_MCU_CHANNEL_SYNC_OFFSET_67_CHANNEL_7_OFFSET",
+    "SX1302_REG_ARB_MCU_CHANNEL_SYNC_OFFSET_67_CHANNEL_6_OFFSET",
+    "SX1302_REG_ARB_MCU_DUMMY_DUMMY3",
+    "SX1302_REG_RADIO_FE_GLBL_CTRL_DECIM_B_CLR",
+    "SX1302_REG_RADIO_FE_GLBL_CTRL_DECIM_A_CLR",
+    "SX1302_REG_RADIO_FE_CTRL0_RADIO_A_DC_NOTCH_EN",
+    "SX1302_REG_RADIO_FE_CTRL0_RADIO_A_FORCE_HOST_FILTER_GAIN",
+    "SX1302_REG_RADIO_FE_CTRL0_RADIO_A_HOST_FILTER_GAIN",
+    "SX1302_REG_RADIO_FE_RSSI_DB_DEF_RADIO_A_RSSI_DB_DEFAULT_VALUE",
+    "SX1302_REG_RADIO_FE_RSSI_DEC_DEF_RADIO_A_RSSI_DEC_DEFAULT_VALUE",
+    "SX1302_REG_RADIO_FE_RSSI_DEC_RD_RADIO_A_RSSI_DEC_OUT",
+    "SX1302_REG_RADIO_FE_RSSI_BB_RD_RADIO_A_RSSI_BB_OUT",
+    "SX1302_REG_RADIO_FE_DEC_FILTER_RD_RADIO_A_DEC_FILTER_GAIN",
+    "SX1302_REG_RADIO_FE_RSSI_BB_FILTER_ALPHA_RADIO_A_RSSI_BB_FILTER_ALPHA",
+    "SX1302_REG_RADIO_FE_RSSI_DEC_FILTER_ALPHA_RADIO_A_RSSI_DEC_FILTER_ALPHA",
+    "SX1302_REG_RADIO_FE_IQ_COMP_AMP_COEFF_RADIO_A_AMP_COEFF",
+    "SX1302_REG_RADIO_FE_IQ_COMP_PHI_COEFF_RADIO_A_PHI_COEFF",
+    "SX1302_REG_RADIO_FE_RADIO_DIO_TEST_MODE_RADIO_A_DIO_TEST_MODE",
+    "SX1302_REG_RADIO_FE_RADIO_DIO_TEST_DIR_RADIO_A_DIO_TEST_DIR",
+    "SX1302_REG_RADIO_FE_RADIO_DIO_DIR_RADIO_A_DIO_DIR",
+    "SX1302_REG_RADIO_FE_CTRL0_RADIO_B_DC_NOTCH_EN",
+    "SX1302_REG_RADIO_FE_CTRL0_RADIO_B_FORCE_HOST_FILTER_GAIN",
+    "SX1302_REG_RADIO_FE_CTRL0_RADIO_B_HOST_FILTER_GAIN",
+    "SX1302_REG_RADIO_FE_RSSI_DB_DEF_RADIO_B_RSSI_DB_DEFAULT_VALUE",
+    "SX1302_REG_RADIO_FE_RSSI_DEC_DEF_RADIO_B_RSSI_DEC_DEFAULT_VALUE",
+    "SX1302_REG_RADIO_FE_RSSI_DEC_RD_RADIO_B_RSSI_DEC_OUT",
+    "SX1302_REG_RADIO_FE_RSSI_BB_RD_RADIO_B_RSSI_BB_OUT",
+    "SX1302_REG_RADIO_FE_DEC_FILTER_RD_RADIO_B_DEC_FILTER_GAIN",
+    "SX1302_REG_RADIO_FE_RSSI_BB_FILTER_ALPHA_RADIO_B_RSSI_BB_FILTER_ALPHA",
+    "SX1302_REG_RADIO_FE_RSSI_DEC_FILTER_ALPHA_RADIO_B_RSSI_DEC_FILTER_ALPHA",
+    "SX1302_REG_RADIO_FE_IQ_COMP_AMP_COEFF_RADIO_B_AMP_COEFF",
+    "SX1302_REG_RADIO_FE_IQ_COMP_PHI_COEFF_RADIO_B_PHI_COEFF",
+    "SX1302_REG_RADIO_FE_RADIO_DIO_TEST_MODE_RADIO_B_DIO_TEST_MODE",
+    "SX1302_REG_RADIO_FE_RADIO_DIO_TEST_DIR_RADIO_B_DIO_TEST_DIR",
+    "SX1302_REG_RADIO_FE_RADIO_DIO_DIR_RADIO_B_DIO_DIR",
+    "SX1302_REG_RADIO_FE_SIG_ANA_CFG_VALID",
+    "SX1302_REG_RADIO_FE_SIG_ANA_CFG_BUSY",
+    "SX1302_REG_RADIO_FE_SIG_ANA_CFG_DURATION",
+    "SX1302_REG_RADIO_FE_SIG_ANA_CFG_FORCE_HAL_CTRL",
+    "SX1302_REG_RADIO_FE_SIG_ANA_CFG_START",
+    "SX1302_REG_RADIO_FE_SIG_ANA_CFG_RADIO_SEL",
+    "SX1302_REG_RADIO_FE_SIG_ANA_CFG_EN",
+    "SX1302_REG_RADIO_FE_SIG_ANA_FREQ_FREQ",
+    "SX1302_REG_RADIO_FE_SIG_ANA_ABS_MSB_CORR_ABS_OUT",
+    "SX1302_REG_RADIO_FE_SIG_ANA_ABS_LSB_CORR_ABS_OUT",
+    "SX1302_REG_RADIO_FE_DUMMY_DUMMY",
+    "SX1302_REG_OTP_BYTE_ADDR_ADDR",
+    "SX1302_REG_OTP_RD_DATA_RD_DATA",
+    "SX1302_REG_OTP_STATUS_CHECKSUM_STATUS",
+    "SX1302_REG_OTP_STATUS_FSM_READY",
+    "SX1302_REG_OTP_CFG_ACCESS_MODE",
+    "SX1302_REG_OTP_BIT_POS_POS",
+    "SX1302_REG_OTP_PIN_CTRL_0_TM",
+    "SX1302_REG_OTP_PIN_CTRL_0_STROBE",
+    "SX1302_REG_OTP_PIN_CTRL_0_PGENB",
+    "SX1302_REG_OTP_PIN_CTRL_0_LOAD",
+    "SX1302_REG_OTP_PIN_CTRL_0_CSB",
+    "SX1302_REG_OTP_PIN_CTRL_1_FSCK",
+    "SX1302_REG_OTP_PIN_CTRL_1_FSI",
+    "SX1302_REG_OTP_PIN_CTRL_1_FRST",
+    "SX1302_REG_OTP_PIN_STATUS_FSO",
+    "SX1302_REG_OTP_MODEM_EN_0_MODEM_EN",
+    "SX1302_REG_OTP_MODEM_EN_1_MODEM_EN",
+    "SX1302_REG_OTP_MODEM_SF_EN_SF_EN",
+    "SX1302_REG_OTP_TIMESTAMP_EN_TIMESTAMP_EN",
+    "SX1302_REG_OTP_DUMMY_DUMMY",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_LORA_SERVICE_FREQ_MSB_IF_FREQ_0",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_LORA_SERVICE_FREQ_LSB_IF_FREQ_0",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_LORA_SERVICE_RADIO_SEL_RADIO_SELECT",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DC_NOTCH_CFG1_BW_START",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DC_NOTCH_CFG1_AUTO_BW_RED",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DC_NOTCH_CFG1_NO_FAST_START",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DC_NOTCH_CFG1_BYPASS",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DC_NOTCH_CFG1_ENABLE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DC_NOTCH_CFG2_BW_LOCKED",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DC_NOTCH_CFG2_BW",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DC_NOTCH_CFG3_BW_RED",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DC_NOTCH_CFG4_IIR_DCC_TIME",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR1_0_FIR1_COEFF_0",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR1_1_FIR1_COEFF_1",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR1_2_FIR1_COEFF_2",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR1_3_FIR1_COEFF_3",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR1_4_FIR1_COEFF_4",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR1_5_FIR1_COEFF_5",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR1_6_FIR1_COEFF_6",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR1_7_FIR1_COEFF_7",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR2_0_FIR2_COEFF_0",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR2_1_FIR2_COEFF_1",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR2_2_FIR2_COEFF_2",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR2_3_FIR2_COEFF_3",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR2_4_FIR2_COEFF_4",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR2_5_FIR2_COEFF_5",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR2_6_FIR2_COEFF_6",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_FIR2_7_FIR2_COEFF_7",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_AGC0_RADIO_GAIN_RED_SEL",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_AGC0_RADIO_GAIN_RED_DB",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_AGC1_DC_COMP_EN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_AGC1_FORCE_DEFAULT_FIR",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_AGC1_RSSI_EARLY_LATCH",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_AGC1_FREEZE_ON_SYNC",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_AGC2_DAGC_IN_COMP",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_AGC2_DAGC_FIR_HYST",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_AGC2_RSSI_MAX_SAMPLE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_AGC2_RSSI_MIN_SAMPLE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_GAIN0_DAGC_FIR_FAST",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_GAIN0_FORCE_GAIN_FIR",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_GAIN0_GAIN_FIR1",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_DFE_GAIN0_GAIN_FIR2",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DAGC_CFG_TARGET_LVL",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DAGC_CFG_GAIN_INCR_STEP",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DAGC_CFG_GAIN_DROP_COMP",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DAGC_CFG_COMB_FILTER_EN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DAGC_CFG_NO_FREEZE_START",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DAGC_CFG_FREEZE_ON_SYNC",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DAGC_CNT0_SAMPLE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DAGC_CNT1_THR_M6",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DAGC_CNT2_THR_M12",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DAGC_CNT3_THR_M18",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DAGC_CNT4_GAIN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DAGC_CNT4_FORCE_GAIN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG0_MODEM_BW",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG0_MODEM_SF",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG1_PPM_OFFSET_HDR_CTRL",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG1_PPM_OFFSET",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG1_MODEM_EN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG1_CODING_RATE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG2_FINE_SYNCH_EN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG2_MODEM_START",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG2_CADRXTX",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG2_IMPLICIT_HEADER",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG2_CRC_EN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG3_PAYLOAD_LENGTH",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG4_INT_STEP_ORIDE_EN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG4_INT_STEP_ORIDE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG5_HEADER_DIFF_MODE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG5_ZERO_PAD",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG6_PREAMBLE_SYMB_NB",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG7_PREAMBLE_SYMB_NB",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG8_AUTO_ACK_INT_DELAY",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG8_AUTO_ACK_RX",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG8_AUTO_ACK_TX",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG8_POST_PREAMBLE_GAP_LONG",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_CFG0_DFT_PEAK_EN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_CFG0_CHIRP_INVERT",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_CFG0_SWAP_IQ",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_CFG0_CONTINUOUS",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_CFG1_DETECT_TIMEOUT",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_CFG2_AUTO_ACK_RANGE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_CFG2_AUTO_ACK_DELAY",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_CFG3_RESTART_ON_HDR_ERR",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_CFG3_CLK_EN_RESYNC_DIN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_RX_CFG3_LLR_SCALE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FRAME_SYNCH0_PEAK1_POS",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FRAME_SYNCH1_PEAK2_POS",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FRAME_SYNCH2_FINETIME_ON_LAST",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FRAME_SYNCH2_AUTO_SCALE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FRAME_SYNCH2_DROP_ON_SYNCH",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FRAME_SYNCH2_GAIN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FRAME_SYNCH2_TIMEOUT_OPT",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING0_GAIN_P_HDR_RED",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING0_ROUNDING",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING0_POS_LIMIT",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING0_SUM_SIZE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING0_MODE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING1_GAIN_P_AUTO",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING1_GAIN_P_PAYLOAD",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING1_GAIN_P_PREAMB",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING2_GAIN_I_EN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING2_GAIN_I_PAYLOAD",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING2_GAIN_I_PREAMB",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING3_FINESYNCH_SUM",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING3_FINESYNCH_GAIN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING3_GAIN_I_AUTO",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING4_GAIN_I_AUTO_MAX",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FINE_TIMING4_GAIN_P_AUTO_MAX",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TO_TIME0_FREQ_TO_TIME_DRIFT_MANT",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TO_TIME1_FREQ_TO_TIME_DRIFT_MANT",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TO_TIME2_FREQ_TO_TIME_DRIFT_EXP",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TO_TIME3_FREQ_TO_TIME_INVERT_FREQ_DELTA",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TO_TIME3_FREQ_TO_TIME_INVERT_FINE_DELTA",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TO_TIME3_FREQ_TO_TIME_INVERT_FREQ_ERROR",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TO_TIME3_FREQ_TO_TIME_INVERT_TIME_SYMB",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TO_TIME3_FREQ_TO_TIME_INVERT_TIME_OFFSET",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TO_TIME3_FREQ_TO_TIME_INVERT_DETECT",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TO_TIME4_FREQ_TO_TIME_INVERT_RNG",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TRACK0_FREQ_TRACK_FINE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TRACK0_FREQ_TRACK_HDR_SKIP",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TRACK0_FREQ_TRACK_EN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TRACK1_FREQ_SYNCH_GAIN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TRACK1_FREQ_TRACK_AUTO_THR",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TRACK2_SNR_MIN_WINDOW",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TRACK2_GAIN_AUTO_SNR_MIN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TRACK2_FREQ_SYNCH_THR",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_MSP0_MSP_PNR",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_MSP1_MSP2_PNR",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_MSP2_MSP2_PEAK_NB",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_MSP2_MSP_PEAK_NB",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_MSP3_ACC_MIN2",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_MSP3_ACC_WIN_LEN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_MSP3_MSP_POS_SEL",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_MSP3_MSP_CNT_MODE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_ACC1_USE_GAIN_SYMB",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_ACC1_ACC_PNR",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_ACC2_NOISE_COEFF",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_ACC2_ACC_COEFF",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_ACC2_ACC_2_SAME_PEAKS",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_ACC2_ACC_AUTO_RESCALE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_ACC2_ACC_PEAK_POS_SEL",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_ACC2_ACC_PEAK_SUM_EN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DETECT_ACC3_MIN_SINGLE_PEAK",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TIMESTAMP_SEL_SNR_MIN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TIMESTAMP_ENABLE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TIMESTAMP_NB_SYMB",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_CLOCK_GATE_OVERRIDE_FSK_TRANSPOSE_CLK_OVERRIDE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_CLOCK_GATE_OVERRIDE_FSK_MODEM_CLK_OVERRIDE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_CLOCK_GATE_OVERRIDE_TRANSPOSE_CLK_OVERRIDE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_CLOCK_GATE_OVERRIDE_MODEM_CLK_OVERRIDE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DUMMY0_DUMMY0",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_FREQ_MSB_IF_FREQ_0",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_FREQ_LSB_IF_FREQ_0",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_CFG_0_CRC_IBM",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_CFG_0_DCFREE_ENC",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_CFG_0_CRC_EN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_CFG_0_PKT_MODE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_CFG_1_ADRS_COMP",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_CFG_1_PSIZE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_CFG_1_CH_BW_EXPO",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_CFG_3_MODEM_INVERT_IQ",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_CFG_3_AUTO_AFC",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_CFG_3_RADIO_SELECT",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_CFG_3_RX_INVERT",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_CFG_4_RSSI_LENGTH",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_CFG_4_ERROR_OSR_TOL",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_NODE_ADRS_NODE_ADRS",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_BROADCAST_BROADCAST",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_PKT_LENGTH_PKT_LENGTH",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_TIMEOUT_MSB_TIMEOUT",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_TIMEOUT_LSB_TIMEOUT",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_BIT_RATE_MSB_BIT_RATE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_BIT_RATE_LSB_BIT_RATE",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_REF_PATTERN_BYTE7_FSK_REF_PATTERN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_REF_PATTERN_BYTE6_FSK_REF_PATTERN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_REF_PATTERN_BYTE5_FSK_REF_PATTERN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_REF_PATTERN_BYTE4_FSK_REF_PATTERN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_REF_PATTERN_BYTE3_FSK_REF_PATTERN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_REF_PATTERN_BYTE2_FSK_REF_PATTERN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_REF_PATTERN_BYTE1_FSK_REF_PATTERN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_REF_PATTERN_BYTE0_FSK_REF_PATTERN",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FSK_RSSI_FILTER_ALPHA_FSK_RSSI_FILTER_ALPHA",
+    "SX1302_REG_RX_TOP_LORA_SERVICE_FSK_DUMMY1_DUMMY1",
+    "SX1302_REG_CAPTURE_RAM_CAPTURE_CFG_ENABLE",
+    "SX1302_REG_CAPTURE_RAM_CAPTURE_CFG_CAPTUREWRAP",
+    "SX1302_REG_CAPTURE_RAM_CAPTURE_CFG_CAPTUREFORCETRIGGER",
+    "SX1302_REG_CAPTURE_RAM_CAPTURE_CFG_CAPTURESTART",
+    "SX1302_REG_CAPTURE_RAM_CAPTURE_CFG_RAMCONFIG",
+    "SX1302_REG_CAPTURE_RAM_CAPTURE_SOURCE_A_SOURCEMUX",
+    "SX1302_REG_CAPTURE_RAM_CAPTURE_SOURCE_B_SOURCEMUX",
+    "SX1302_REG_CAPTURE_RAM_CAPTURE_PERIOD_0_CAPTUREPERIOD",
+    "SX1302_REG_CAPTURE_RAM_CAPTURE_PERIOD_1_CAPTUREPERIOD",
+    "SX1302_REG_CAPTURE_RAM_STATUS_CAPCOMPLETE",
+    "SX1302_REG_CAPTURE_RAM_LAST_RAM_ADDR_0_LASTRAMADDR",
+    "SX1302_REG_CAPTURE_RAM_LAST_RAM_ADDR_1_LASTRAMADDR",
+    "SX1302_REG_CAPTURE_RAM_CLOCK_GATE_OVERRIDE_CLK_OVERRIDE",
+    "SX1302_REG_CAPTURE_RAM_DUMMY0_DUMMY0",
+];
+
+
 
 
 pub trait LoragwRegTrait {
@@ -2183,11 +3232,11 @@ impl LoragwRegTrait for Hal {
 
         if size == 0 || data.is_empty() {
             error!("ERROR: BURST OF NULL LENGTH\n");
-            return Err(Error::LGW_REG_ERROR.into());
+            return Err(Error::Register { register_id, reason: "burst of null length" }.into());
         }
         if register_id >= LGW_TOTALREGS {
             error!("ERROR: REGISTER NUMBER OUT OF DEFINED RANGE\n");
-            return Err(Error::LGW_REG_ERROR.into());
+            return Err(Error::Register { register_id, reason: "register number out of defined range" }.into());
         }
     
         /* get register struct from the struct array */
@@ -2196,14 +3245,14 @@ impl LoragwRegTrait for Hal {
         /* reject write to read-only registers */
         if r.rdon {
             error!("ERROR: TRYING TO BURST WRITE A READ-ONLY REGISTER\n");
-            return Err(Error::LGW_REG_ERROR.into());
+            return Err(Error::Register { register_id, reason: "trying to write a read-only register" }.into());
         }
     
         /* do the burst write */
-        if let Err(_) = self.lgw_wb(LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_SX1302, r.addr, data, size){
+        if let Err(e) = self.lgw_wb(LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_SX1302, r.addr, data, size){
     
             error!("ERROR: COM ERROR DURING REGISTER BURST WRITE\n");
-            return Err(Error::LGW_REG_ERROR.into());
+            return Err(Error::RegisterCom { register_id, source: e }.into());
         } else {
             Ok(())
         }
@@ -2214,22 +3263,22 @@ impl LoragwRegTrait for Hal {
 
         if size == 0 || data.is_empty() {
             error!("ERROR: BURST OF NULL LENGTH\n");
-            return Err(anyhow!("LGW_REG_ERROR"));
+            return Err(Error::Register { register_id, reason: "burst of null length" }.into());
         }
 
         if register_id >= LGW_TOTALREGS {
             error!("ERROR: REGISTER NUMBER OUT OF DEFINED RANGE\n");
-            return Err(anyhow!("LGW_REG_ERROR"));
+            return Err(Error::Register { register_id, reason: "register number out of defined range" }.into());
         }
 
         /* get register struct from the struct array */
         let r = LOREGS[register_id as usize].clone();
 
         /* do the burst read */
-        if let Err(_) = self.lgw_rb(LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_SX1302, r.addr, data, size)
+        if let Err(e) = self.lgw_rb(LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_SX1302, r.addr, data, size)
         {
             error!("ERROR: COM ERROR DURING REGISTER BURST READ\n");
-            return Err(anyhow!("LGW_REG_ERROR"));
+            return Err(Error::RegisterCom { register_id, source: e }.into());
         } 
         
         Ok(())
@@ -2243,7 +3292,7 @@ impl LoragwRegTrait for Hal {
 
     
         if data.is_empty() {
-            return Err(anyhow!("LGW_REG_ERROR"))
+            return Err(Error::Burst { address, reason: "empty data array" }.into())
         }
     
         /* prepare command */
@@ -2265,7 +3314,7 @@ impl LoragwRegTrait for Hal {
         if self.mcu.lgw_write_mode == EComWriteMode::LGW_COM_WRITE_MODE_BULK {
             /* makes no sense to read in bulk mode, as we can't get the result */
             error!("ERROR: USB READ BURST FAILURE - bulk mode is enabled\n");
-            return Err(anyhow!("LGW_REG_ERROR"))
+            return Err(Error::Burst { address, reason: "can not read in bulk write mode" }.into())
         } else {
             self.mcu.mcu_spi_write( &mut in_out_buf)?;
         }
@@ -2289,7 +3338,7 @@ impl LoragwRegTrait for Hal {
 
 
         if data.is_empty() {
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Burst { address, reason: "empty data array" }.into());
         }
 
         /* prepare command */
@@ -2328,7 +3377,7 @@ impl LoragwRegTrait for Hal {
         
         if data.is_empty() || size == 0 {
             error!("ERROR: BURST OF NULL LENGTH\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Burst { address: mem_addr, reason: "burst of null length" }.into());
         }
 
         /* write memory by chunks */
@@ -2363,7 +3412,7 @@ impl LoragwRegTrait for Hal {
 
         if data.is_empty() || size == 0 {
             error!("ERROR: BURST OF NULL LENGTH\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Burst { address: mem_addr, reason: "burst of null length" }.into());
         }
 
         /* read memory by chunks */
@@ -2423,7 +3472,7 @@ impl LoragwRegTrait for Hal {
         } else {
             /* register spanning multiple memory bytes but with an offset */
             error!("ERROR: REGISTER SIZE AND OFFSET ARE NOT SUPPORTED\n");
-            return Err(anyhow!("REGISTER SIZE AND OFFSET ARE NOT SUPPORTED"));
+            return Err(Error::RegisterLayout { address: r.addr, reason: "register size and offset not supported" }.into());
         }
 
     }
@@ -2453,7 +3502,7 @@ impl LoragwRegTrait for Hal {
         trace!(register_id=%register_id, reg_value=%reg_value, "lgw_reg_w: ");
         /* check input parameters */
         if register_id >= LGW_TOTALREGS as u16 {
-            return Err(anyhow!("ERROR: REGISTER NUMBER OUT OF DEFINED RANGE"))
+            return Err(Error::Register { register_id, reason: "register number out of defined range" }.into())
         }
 
         /* get register struct from the struct array */
@@ -2461,12 +3510,12 @@ impl LoragwRegTrait for Hal {
 
         /* reject write to read-only registers */
         if r.rdon {
-            return Err(anyhow!("ERROR: TRYING TO WRITE A READ-ONLY REGISTER"))
+            return Err(Error::Register { register_id, reason: "trying to write a read-only register" }.into())
         }
 
         if let Err(e) = self.reg_w(LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_SX1302, r, reg_value) {
             error!("lgw_reg_w failed: {:} - {:}",register_id, e);
-            return Err(e);
+            return Err(Error::RegisterCom { register_id, source: e }.into());
         }
 
         Ok(())
@@ -2480,7 +3529,7 @@ impl LoragwRegTrait for Hal {
 
         if register_id >= LGW_TOTALREGS {
             error!("ERROR: REGISTER NUMBER OUT OF DEFINED RANGE\n");
-            return Err(anyhow!("LGW_REG_ERROR"));
+            return Err(Error::Register { register_id, reason: "register number out of defined range" }.into());
         }
 
         /* get register struct from the struct array */
@@ -2488,9 +3537,9 @@ impl LoragwRegTrait for Hal {
 
         match  self.reg_r(LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_SX1302, r){
             Ok(reg_value) => return Ok(reg_value),
-            Err(_) => {
+            Err(e) => {
                 error!("ERROR: COM ERROR DURING REGISTER READ\n");
-                return Err(anyhow!("LGW_REG_ERROR"));
+                return Err(Error::RegisterCom { register_id, source: e }.into());
             }
         }
     }
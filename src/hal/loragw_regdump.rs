@@ -0,0 +1,288 @@
+/*
+    SX1302 register dumps, and register access by name.
+
+    Registers are named after their SX1302_REG_* constants (the "SX1302_REG_" prefix may be
+    omitted, case does not matter). A dump holds the value and default of every register of a
+    block and is saved as text, one register per line, so that the dumps of a working and of a
+    failing gateway can be compared:
+
+        let dump = hal.lgw_reg_dump("SX1302_REG_RX_TOP_*")?;
+        std::fs::write("failing.txt", dump.to_string())?;
+
+        let working = LgwRegDump::from_file("working.txt")?;
+        for d in working.diff(&dump) {
+            println!("{}", d);
+        }
+
+    The concentrator must be connected, and connecting resets the SX1302: dump the registers of
+    a configured gateway from the process that started it.
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+use super::error::Error;
+use super::loragw_reg::{LoragwRegTrait, LGW_TOTALREGS, LOREGS, LOREGS_NAMES};
+use super::Hal;
+
+const REG_NAME_PREFIX: &str = "SX1302_REG_";
+
+/* Register id from its name */
+pub fn lgw_reg_find(name: &str) -> Option<u16> {
+    let name = name.to_ascii_uppercase();
+    let name = if name.starts_with(REG_NAME_PREFIX) { name } else { format!("{}{}", REG_NAME_PREFIX, name) };
+    LOREGS_NAMES.iter().position(|n| *n == name).map(|id| id as u16)
+}
+
+/* Registers selected by a name, or by a block such as "SX1302_REG_RX_TOP_*". "*" selects all. */
+pub fn lgw_reg_select(pattern: &str) -> Result<Vec<u16>> {
+    let Some(block) = pattern.strip_suffix('*') else {
+        let id = lgw_reg_find(pattern).ok_or(Error::config("register", pattern, "unknown register name"))?;
+        return Ok(vec![id]);
+    };
+
+    let block = block.to_ascii_uppercase();
+    let ids: Vec<u16> = (0 .. LGW_TOTALREGS)
+        .filter(|&id| {
+            let name = LOREGS_NAMES[id as usize];
+            name.starts_with(&block) || name[REG_NAME_PREFIX.len() ..].starts_with(&block)
+        })
+        .collect();
+    if ids.is_empty() {
+        return Err(Error::config("register", pattern, "no register matches").into());
+    }
+    Ok(ids)
+}
+
+/**
+@struct LgwRegValue
+@brief Register read in a dump
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct LgwRegValue {
+    pub name: String,       //*!> SX1302_REG_* name */
+    pub value: i32,
+    pub dflt: i32,          //*!> default value, from LOREGS */
+}
+
+/**
+@struct LgwRegDump
+@brief Values of a set of registers, in register order
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LgwRegDump {
+    pub regs: Vec<LgwRegValue>,
+}
+
+/**
+@struct LgwRegDiff
+@brief Register that differs between two dumps, None when missing from a dump
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct LgwRegDiff {
+    pub name: String,
+    pub a: Option<i32>,
+    pub b: Option<i32>,
+}
+
+impl LgwRegDump {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("ERROR: failed to read {}: {}", path.display(), e))?;
+        s.parse().map_err(|e: anyhow::Error| e.context(format!("ERROR: invalid register dump {}", path.display())))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LgwRegValue> {
+        self.regs.iter().find(|r| r.name.eq_ignore_ascii_case(name))
+    }
+
+    /* Registers whose value is not the default */
+    pub fn non_default(&self) -> impl Iterator<Item = &LgwRegValue> {
+        self.regs.iter().filter(|r| r.value != r.dflt)
+    }
+
+    /* Registers of self that differ in other, then registers only found in other */
+    pub fn diff(&self, other: &LgwRegDump) -> Vec<LgwRegDiff> {
+        let others: HashMap<&str, i32> = other.regs.iter().map(|r| (r.name.as_str(), r.value)).collect();
+        let mut diffs: Vec<LgwRegDiff> = self.regs.iter()
+            .filter(|r| others.get(r.name.as_str()) != Some(&r.value))
+            .map(|r| LgwRegDiff { name: r.name.clone(), a: Some(r.value), b: others.get(r.name.as_str()).copied() })
+            .collect();
+
+        let names: HashSet<&str> = self.regs.iter().map(|r| r.name.as_str()).collect();
+        diffs.extend(other.regs.iter()
+            .filter(|r| !names.contains(r.name.as_str()))
+            .map(|r| LgwRegDiff { name: r.name.clone(), a: None, b: Some(r.value) }));
+        diffs
+    }
+}
+
+/* One register per line: name, value and default, '#' starts a comment */
+impl fmt::Display for LgwRegDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# register value default")?;
+        for r in self.regs.iter() {
+            writeln!(f, "{} {} {}{}", r.name, r.value, r.dflt, if r.value != r.dflt { " *" } else { "" })?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for LgwRegDump {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut regs = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let reg = match fields[..] {
+                [name, value, dflt] | [name, value, dflt, "*"] => value.parse().ok().zip(dflt.parse().ok())
+                    .map(|(value, dflt)| LgwRegValue { name: name.to_string(), value, dflt }),
+                _ => None,
+            };
+            regs.push(reg.ok_or(anyhow!("ERROR: line {}: expected \"register value default\"", i + 1))?);
+        }
+        Ok(Self { regs })
+    }
+}
+
+impl fmt::Display for LgwRegDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = |v: Option<i32>| v.map_or("missing".to_string(), |v| v.to_string());
+        write!(f, "{}: {} -> {}", self.name, value(self.a), value(self.b))
+    }
+}
+
+impl Hal {
+    /* Read the registers selected by pattern, see lgw_reg_select */
+    pub fn lgw_reg_dump(&mut self, pattern: &str) -> Result<LgwRegDump> {
+        let mut regs = Vec::new();
+        for id in lgw_reg_select(pattern)? {
+            regs.push(LgwRegValue {
+                name: LOREGS_NAMES[id as usize].to_string(),
+                value: self.lgw_reg_r(id)?,
+                dflt: LOREGS[id as usize].dflt,
+            });
+        }
+        Ok(LgwRegDump { regs })
+    }
+
+    pub fn lgw_reg_read_name(&mut self, name: &str) -> Result<i32> {
+        let id = lgw_reg_find(name).ok_or(Error::config("register", name, "unknown register name"))?;
+        self.lgw_reg_r(id)
+    }
+
+    pub fn lgw_reg_write_name(&mut self, name: &str, value: i32) -> Result<()> {
+        let id = lgw_reg_find(name).ok_or(Error::config("register", name, "unknown register name"))?;
+        self.lgw_reg_w(id, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::loragw_reg::{SX1302_REG_GPIO_GPIO_DIR_L_DIRECTION, SX1302_REG_GPIO_GPIO_OUT_H_OUT_VALUE};
+    use crate::hal::mcu::sim::SimConcentrator;
+
+    fn connected_hal() -> Hal {
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        hal.lgw_connect().unwrap();
+        hal
+    }
+
+    #[test]
+    fn select() {
+        let gpio = lgw_reg_select("SX1302_REG_GPIO_*").unwrap();
+        assert!(gpio.len() > 2);
+        assert!(gpio.iter().all(|&id| LOREGS_NAMES[id as usize].starts_with("SX1302_REG_GPIO_")));
+        assert!(gpio.contains(&SX1302_REG_GPIO_GPIO_DIR_L_DIRECTION));
+        assert_eq!(lgw_reg_select("gpio_*").unwrap(), gpio);
+
+        let dir = lgw_reg_select("GPIO_GPIO_DIR_*").unwrap();
+        assert_eq!(dir.len(), 2);
+        assert_eq!(lgw_reg_select("*").unwrap().len(), LGW_TOTALREGS as usize);
+
+        /* a name without '*' selects that register only */
+        assert_eq!(lgw_reg_select("gpio_gpio_dir_l_direction").unwrap(), [SX1302_REG_GPIO_GPIO_DIR_L_DIRECTION]);
+        assert_eq!(lgw_reg_find("SX1302_REG_GPIO_GPIO_OUT_H_OUT_VALUE"), Some(SX1302_REG_GPIO_GPIO_OUT_H_OUT_VALUE));
+        assert_eq!(lgw_reg_find("GPIO_GPIO_OUT_H"), None);
+
+        for pattern in ["GPIO_GPIO_DIR", "NOPE_*"] {
+            let err = lgw_reg_select(pattern).unwrap_err();
+            assert!(matches!(Error::cause(&err), Some(Error::InvalidConfig { param: "register", .. })), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn dump_round_trip() {
+        let mut hal = connected_hal();
+        hal.lgw_reg_write_name("gpio_gpio_dir_l_direction", 0x5A).unwrap();
+        assert_eq!(hal.lgw_reg_read_name("SX1302_REG_GPIO_GPIO_DIR_L_DIRECTION").unwrap(), 0x5A);
+
+        let dump = hal.lgw_reg_dump("GPIO_*").unwrap();
+        assert_eq!(dump.regs.len(), lgw_reg_select("GPIO_*").unwrap().len());
+        let dir = dump.get("sx1302_reg_gpio_gpio_dir_l_direction").unwrap();
+        assert_eq!((dir.value, dir.dflt), (0x5A, 0));
+        assert_eq!(dump.non_default().map(|r| r.name.as_str()).collect::<Vec<_>>(), ["SX1302_REG_GPIO_GPIO_DIR_L_DIRECTION"]);
+
+        /* text form: header, one line per register, non default ones marked */
+        let text = dump.to_string();
+        assert_eq!(text.lines().count(), dump.regs.len() + 1);
+        assert!(text.contains("\nSX1302_REG_GPIO_GPIO_DIR_L_DIRECTION 90 0 *\n"));
+        assert!(text.contains("\nSX1302_REG_GPIO_GPIO_DIR_H_DIRECTION 0 0\n"));
+        assert_eq!(text.parse::<LgwRegDump>().unwrap(), dump);
+
+        let path = std::env::temp_dir().join(format!("loragw-regdump-{}.txt", std::process::id()));
+        std::fs::write(&path, &text).unwrap();
+        assert_eq!(LgwRegDump::from_file(&path).unwrap(), dump);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn parse_errors() {
+        let dump: LgwRegDump = "# comment\n\nSX1302_REG_A 1 0 *   # trailing\n  SX1302_REG_B -3 -3\n".parse().unwrap();
+        assert_eq!(dump.regs, [
+            LgwRegValue { name: "SX1302_REG_A".to_string(), value: 1, dflt: 0 },
+            LgwRegValue { name: "SX1302_REG_B".to_string(), value: -3, dflt: -3 },
+        ]);
+
+        for (text, line) in [("SX1302_REG_A 1\n", 1), ("# x\nSX1302_REG_A 1 0\nSX1302_REG_B x 0\n", 3), ("SX1302_REG_A 1 0 +\n", 1)] {
+            let err = text.parse::<LgwRegDump>().unwrap_err();
+            assert!(err.to_string().starts_with(&format!("ERROR: line {}:", line)), "{:?}: {}", text, err);
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let reg = |name: &str, value| LgwRegValue { name: name.to_string(), value, dflt: 0 };
+        let a = LgwRegDump { regs: vec![reg("SX1302_REG_A", 1), reg("SX1302_REG_B", 2), reg("SX1302_REG_C", 3)] };
+        let b = LgwRegDump { regs: vec![reg("SX1302_REG_A", 1), reg("SX1302_REG_C", 4), reg("SX1302_REG_D", 5)] };
+
+        let diffs = a.diff(&b);
+        assert_eq!(diffs, [
+            LgwRegDiff { name: "SX1302_REG_B".to_string(), a: Some(2), b: None },
+            LgwRegDiff { name: "SX1302_REG_C".to_string(), a: Some(3), b: Some(4) },
+            LgwRegDiff { name: "SX1302_REG_D".to_string(), a: None, b: Some(5) },
+        ]);
+        assert_eq!(diffs.iter().map(|d| d.to_string()).collect::<Vec<_>>(),
+            ["SX1302_REG_B: 2 -> missing", "SX1302_REG_C: 3 -> 4", "SX1302_REG_D: missing -> 5"]);
+        assert!(a.diff(&a).is_empty());
+
+        /* dumps of the simulator before and after a write */
+        let mut hal = connected_hal();
+        let before = hal.lgw_reg_dump("GPIO_GPIO_OUT_*").unwrap();
+        hal.lgw_reg_write_name("GPIO_GPIO_OUT_H_OUT_VALUE", 0x9).unwrap();
+        let after: LgwRegDump = hal.lgw_reg_dump("GPIO_GPIO_OUT_*").unwrap().to_string().parse().unwrap();
+        assert_eq!(before.diff(&after), [LgwRegDiff { name: "SX1302_REG_GPIO_GPIO_OUT_H_OUT_VALUE".to_string(), a: Some(0), b: Some(9) }]);
+    }
+}
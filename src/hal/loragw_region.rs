@@ -0,0 +1,360 @@
+/*
+    Regional channel plans.
+
+    A preset lists the uplink channels of a LoRaWAN region (8 multi-SF channels, plus the LoRa
+    service and FSK channels when the region defines them) and computes the radio center
+    frequencies and IF offsets with lgw_plan_channels.
+
+    The plan only covers frequencies. Radio types, RSSI offsets and TX gain tables are board
+    specific and are taken from the configuration the plan is applied to.
+*/
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Result;
+use tracing::debug;
+
+use super::error::Error;
+use super::loragw_conf::LgwGlobalConf;
+use super::loragw_planner::{lgw_plan_channels, LgwChannelSet, LgwPlanChan, LgwRadioPlan};
+use super::{LgwConfigBoard, LgwRadioType, BW_125KHZ, BW_250KHZ, BW_500KHZ, DR_LORA_SF7, DR_LORA_SF8};
+
+const FSK_DATARATE: u32 = 50000;                /* bps, LoRaWAN FSK data rate */
+const MULTISF_SF7_SF12: u8 = 0xFC;
+const MULTISF_SF7_SF10: u8 = 0x3C;              /* US915 uplinks stop at SF10 */
+
+/* Uplink channels of a region */
+struct RegionChannels {
+    multisf: Vec<u32>,                      /* multi-SF channels */
+    lora_std: Option<(u32, u8, u32)>,       /* LoRa service channel (freq, bandwidth, SF) */
+    fsk: Option<u32>,                       /* FSK channel */
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum LgwRegion {
+    EU868,
+    US915(u8),      /* sub-band, 1 to 8 */
+    AU915(u8),      /* sub-band, 1 to 8 */
+    AS923_1,
+    AS923_2,
+    AS923_3,
+    AS923_4,
+    KR920,
+    IN865,
+    CN470(u8),      /* sub-band, 1 to 12 */
+}
+
+impl fmt::Display for LgwRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LgwRegion::EU868 => write!(f, "EU868"),
+            LgwRegion::US915(sb) => write!(f, "US915_{}", sb),
+            LgwRegion::AU915(sb) => write!(f, "AU915_{}", sb),
+            LgwRegion::AS923_1 => write!(f, "AS923_1"),
+            LgwRegion::AS923_2 => write!(f, "AS923_2"),
+            LgwRegion::AS923_3 => write!(f, "AS923_3"),
+            LgwRegion::AS923_4 => write!(f, "AS923_4"),
+            LgwRegion::KR920 => write!(f, "KR920"),
+            LgwRegion::IN865 => write!(f, "IN865"),
+            LgwRegion::CN470(sb) => write!(f, "CN470_{}", sb),
+        }
+    }
+}
+
+/* Accepts the Display form, case insensitive, with '-' or '_' as separator ("us915-2", "AS923_1") */
+impl FromStr for LgwRegion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.to_ascii_uppercase().replace('-', "_");
+        let (band, sub) = match name.split_once('_') {
+            Some((band, sub)) => (band, Some(sub.trim_start_matches("SB"))),
+            None => (name.as_str(), None),
+        };
+        let sub_band = |sub: Option<&str>| -> Result<u8> {
+            sub.and_then(|v| v.parse().ok())
+                .ok_or(Error::config("region", s, "a sub-band number is required, e.g. US915_2").into())
+        };
+
+        let region = match (band, sub) {
+            ("EU868", None) => LgwRegion::EU868,
+            ("US915", _) => LgwRegion::US915(sub_band(sub)?),
+            ("AU915", _) => LgwRegion::AU915(sub_band(sub)?),
+            ("AS923", None) | ("AS923", Some("1")) => LgwRegion::AS923_1,
+            ("AS923", Some("2")) => LgwRegion::AS923_2,
+            ("AS923", Some("3")) => LgwRegion::AS923_3,
+            ("AS923", Some("4")) => LgwRegion::AS923_4,
+            ("KR920", None) => LgwRegion::KR920,
+            ("IN865", None) => LgwRegion::IN865,
+            ("CN470", _) => LgwRegion::CN470(sub_band(sub)?),
+            _ => return Err(Error::config("region", s, "unknown region").into()),
+        };
+
+        region.check()?;
+        Ok(region)
+    }
+}
+
+impl LgwRegion {
+    fn check(&self) -> Result<()> {
+        match *self {
+            LgwRegion::US915(sb) | LgwRegion::AU915(sb) if !(1 ..= 8).contains(&sb) => {
+                Err(Error::config("sub_band", sb, "expected 1 to 8").into())
+            }
+            LgwRegion::CN470(sb) if !(1 ..= 12).contains(&sb) => {
+                Err(Error::config("sub_band", sb, "expected 1 to 12").into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /* AS923 variants are the AS923-1 plan shifted, as defined by the LoRaWAN regional parameters */
+    fn as923_offset(&self) -> i32 {
+        match self {
+            LgwRegion::AS923_2 => -1_800_000,
+            LgwRegion::AS923_3 => -6_600_000,
+            LgwRegion::AS923_4 => -5_900_000,
+            _ => 0,
+        }
+    }
+
+    /* Downlink frequency range, written as tx_freq_min/tx_freq_max of TX enabled radios */
+    pub fn tx_freq_range(&self) -> (u32, u32) {
+        match self {
+            LgwRegion::EU868 => (863_000_000, 870_000_000),
+            LgwRegion::US915(_) => (923_000_000, 928_000_000),
+            LgwRegion::AU915(_) => (915_000_000, 928_000_000),
+            LgwRegion::AS923_1 | LgwRegion::AS923_2 | LgwRegion::AS923_3 | LgwRegion::AS923_4 => (915_000_000, 928_000_000),
+            LgwRegion::KR920 => (920_900_000, 923_300_000),
+            LgwRegion::IN865 => (865_000_000, 867_000_000),
+            LgwRegion::CN470(_) => (500_000_000, 510_000_000),
+        }
+    }
+
+    fn channels(&self) -> RegionChannels {
+        match *self {
+            LgwRegion::EU868 => RegionChannels {
+                multisf: vec![868_100_000, 868_300_000, 868_500_000, 867_100_000, 867_300_000, 867_500_000, 867_700_000, 867_900_000],
+                lora_std: Some((868_300_000, BW_250KHZ, DR_LORA_SF7)),
+                fsk: Some(868_800_000),
+            },
+            LgwRegion::US915(sb) => RegionChannels {
+                multisf: (0 .. 8).map(|i| 902_300_000 + 200_000 * (8 * (sb as u32 - 1) + i)).collect(),
+                lora_std: Some((903_000_000 + 1_600_000 * (sb as u32 - 1), BW_500KHZ, DR_LORA_SF8)),
+                fsk: None,
+            },
+            LgwRegion::AU915(sb) => RegionChannels {
+                multisf: (0 .. 8).map(|i| 915_200_000 + 200_000 * (8 * (sb as u32 - 1) + i)).collect(),
+                lora_std: Some((915_900_000 + 1_600_000 * (sb as u32 - 1), BW_500KHZ, DR_LORA_SF8)),
+                fsk: None,
+            },
+            LgwRegion::AS923_1 | LgwRegion::AS923_2 | LgwRegion::AS923_3 | LgwRegion::AS923_4 => {
+                let shift = |f: u32| f.wrapping_add_signed(self.as923_offset());
+                RegionChannels {
+                    multisf: [923_200_000, 923_400_000, 922_200_000, 922_400_000, 922_600_000, 922_800_000, 923_000_000, 922_000_000]
+                        .into_iter().map(shift).collect(),
+                    lora_std: Some((shift(922_100_000), BW_250KHZ, DR_LORA_SF7)),
+                    fsk: Some(shift(921_800_000)),
+                }
+            }
+            LgwRegion::KR920 => RegionChannels {
+                multisf: vec![922_100_000, 922_300_000, 922_500_000, 922_700_000, 922_900_000, 923_100_000, 923_300_000],
+                lora_std: None,
+                fsk: None,
+            },
+            LgwRegion::IN865 => RegionChannels {
+                multisf: vec![865_062_500, 865_402_500, 865_985_000, 866_185_000, 866_385_000, 866_585_000, 866_785_000, 866_985_000],
+                lora_std: None,
+                fsk: None,
+            },
+            LgwRegion::CN470(sb) => RegionChannels {
+                multisf: (0 .. 8).map(|i| 470_300_000 + 200_000 * (8 * (sb as u32 - 1) + i)).collect(),
+                lora_std: None,
+                fsk: None,
+            },
+        }
+    }
+
+    fn multisf_datarate(&self) -> u8 {
+        match self {
+            LgwRegion::US915(_) => MULTISF_SF7_SF10,
+            _ => MULTISF_SF7_SF12,
+        }
+    }
+}
+
+/**
+@struct LgwChannelPlan
+@brief Radio center frequencies and IF chains generated for a region
+*/
+#[derive(Debug, Clone)]
+pub struct LgwChannelPlan {
+    pub region: LgwRegion,
+    pub radios: LgwRadioPlan,       //*!> radio centers and IF chains */
+    pub multisf_datarate: u8,       //*!> SF mask of the multi-SF demodulators */
+    pub tx_freq_min: u32,           //*!> lowest downlink frequency of the region */
+    pub tx_freq_max: u32,           //*!> highest downlink frequency of the region */
+}
+
+impl LgwChannelPlan {
+    pub fn new(region: LgwRegion) -> Result<Self> {
+        region.check()?;
+
+        let RegionChannels { multisf, lora_std, fsk } = region.channels();
+        let set = LgwChannelSet {
+            multisf,
+            lora_std: lora_std.map(|(freq_hz, bandwidth, datarate)| LgwPlanChan { freq_hz, bandwidth, datarate }),
+            fsk: fsk.map(|freq_hz| LgwPlanChan { freq_hz, bandwidth: BW_125KHZ, datarate: FSK_DATARATE }),
+        };
+        let radios = lgw_plan_channels(&set)?;
+
+        let (tx_freq_min, tx_freq_max) = region.tx_freq_range();
+        debug!("Note: {} channel plan; radio_0:{} radio_1:{}", region, radios.rf_freq_hz[0], radios.rf_freq_hz[1]);
+
+        Ok(Self {
+            region,
+            radios,
+            multisf_datarate: region.multisf_datarate(),
+            tx_freq_min,
+            tx_freq_max,
+        })
+    }
+
+    /* Replace the frequency plan of conf, keeping its board specific settings (radio type,
+       RSSI calibration, TX gain tables). Radios not used by the plan are disabled. */
+    pub fn apply(&self, conf: &mut LgwGlobalConf) {
+        self.radios.apply(conf);
+
+        for (i, rf) in conf.rf_chains.iter().enumerate() {
+            if rf.enable && rf.tx_enable {
+                conf.tx_freq_min[i] = self.tx_freq_min;
+                conf.tx_freq_max[i] = self.tx_freq_max;
+            }
+        }
+        conf.demod.multisf_datarate = self.multisf_datarate;
+    }
+
+    /* Complete configuration for a board, radios of the given type with TX disabled */
+    pub fn global_conf(&self, board: LgwConfigBoard, radio_type: LgwRadioType) -> LgwGlobalConf {
+        let mut conf = LgwGlobalConf::new(board);
+        for rf in conf.rf_chains.iter_mut() {
+            rf._type = radio_type;
+        }
+
+        self.apply(&mut conf);
+        conf
+    }
+}
+
+impl fmt::Display for LgwChannelPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} channel plan", self.region)?;
+        write!(f, "{}", self.radios)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::loragw_planner::rf_rx_bandwidth;
+    use crate::hal::{Hal, LgwConfigBoard};
+
+    fn presets() -> Vec<LgwRegion> {
+        let mut regions = vec![
+            LgwRegion::EU868, LgwRegion::AS923_1, LgwRegion::AS923_2, LgwRegion::AS923_3, LgwRegion::AS923_4,
+            LgwRegion::KR920, LgwRegion::IN865,
+        ];
+        regions.extend((1 ..= 8).flat_map(|sb| [LgwRegion::US915(sb), LgwRegion::AU915(sb)]));
+        regions.extend((1 ..= 12).map(LgwRegion::CN470));
+        regions
+    }
+
+    fn config_err(s: &str) -> (&'static str, String) {
+        let err = s.parse::<LgwRegion>().unwrap_err();
+        match Error::cause(&err) {
+            Some(Error::InvalidConfig { param, value, .. }) => (*param, value.clone()),
+            other => panic!("{}: unexpected error {:?}", s, other),
+        }
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!("EU868".parse::<LgwRegion>().unwrap(), LgwRegion::EU868);
+        assert_eq!("eu868".parse::<LgwRegion>().unwrap(), LgwRegion::EU868);
+        assert_eq!("us915-2".parse::<LgwRegion>().unwrap(), LgwRegion::US915(2));
+        assert_eq!("US915_SB8".parse::<LgwRegion>().unwrap(), LgwRegion::US915(8));
+        assert_eq!("au915_1".parse::<LgwRegion>().unwrap(), LgwRegion::AU915(1));
+        assert_eq!("AS923".parse::<LgwRegion>().unwrap(), LgwRegion::AS923_1);
+        assert_eq!("as923-3".parse::<LgwRegion>().unwrap(), LgwRegion::AS923_3);
+        assert_eq!("CN470_12".parse::<LgwRegion>().unwrap(), LgwRegion::CN470(12));
+
+        /* Display gives back the same region */
+        for region in presets() {
+            assert_eq!(region.to_string().parse::<LgwRegion>().unwrap(), region);
+        }
+
+        assert_eq!(config_err("EU433"), ("region", "EU433".to_string()));
+        assert_eq!(config_err("eu868_1"), ("region", "eu868_1".to_string()));
+        assert_eq!(config_err("AS923_5"), ("region", "AS923_5".to_string()));
+        assert_eq!(config_err("US915"), ("region", "US915".to_string()));
+        assert_eq!(config_err("US915_x"), ("region", "US915_x".to_string()));
+    }
+
+    #[test]
+    fn sub_band_range() {
+        assert_eq!(config_err("US915_9"), ("sub_band", "9".to_string()));
+        assert_eq!(config_err("US915_0"), ("sub_band", "0".to_string()));
+        assert_eq!(config_err("AU915_9"), ("sub_band", "9".to_string()));
+        assert_eq!(config_err("CN470_13"), ("sub_band", "13".to_string()));
+
+        /* the enum can be built directly, the plan checks it too */
+        let err = LgwChannelPlan::new(LgwRegion::CN470(0)).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::InvalidConfig { param: "sub_band", .. })));
+    }
+
+    #[test]
+    fn presets_plan_and_apply() {
+        for region in presets() {
+            let plan = LgwChannelPlan::new(region).unwrap_or_else(|e| panic!("{}: {:?}", region, e));
+            let RegionChannels { multisf, lora_std, fsk } = region.channels();
+
+            /* every channel of the region is received, where the region puts it */
+            let mut expected: Vec<(usize, u32)> = multisf.iter().copied().enumerate().collect();
+            expected.extend(lora_std.map(|(freq, _, _)| (8, freq)));
+            expected.extend(fsk.map(|freq| (9, freq)));
+            for (if_chain, freq) in expected.iter() {
+                assert_eq!(plan.radios.channel_freq(*if_chain), Some(*freq), "{} if_chain[{}]", region, if_chain);
+            }
+            let nb_enabled = plan.radios.if_chains.iter().filter(|ifc| ifc.enable).count();
+            assert_eq!(nb_enabled, expected.len(), "{}", region);
+
+            /* within the radio RX bandwidth */
+            for ifc in plan.radios.if_chains.iter().filter(|ifc| ifc.enable) {
+                let bw_hz = Hal::lgw_bw_getval(ifc.bandwidth);
+                let edge = ifc.freq_hz.abs() + bw_hz / 2;
+                assert!(edge <= rf_rx_bandwidth(ifc.bandwidth) as i32 / 2, "{} {:?}", region, ifc);
+            }
+
+            let conf = plan.global_conf(LgwConfigBoard { lorawan_public: true, ..Default::default() }, LgwRadioType::LGW_RADIO_TYPE_SX1250);
+            assert_eq!(conf.demod.multisf_datarate, if matches!(region, LgwRegion::US915(_)) { MULTISF_SF7_SF10 } else { MULTISF_SF7_SF12 });
+            let mut hal = Hal::new();
+            conf.apply(&mut hal).unwrap_or_else(|e| panic!("{}: {:?}", region, e));
+        }
+    }
+
+    #[test]
+    fn apply_keeps_board_settings() {
+        let plan = LgwChannelPlan::new(LgwRegion::US915(2)).unwrap();
+        let mut conf = plan.global_conf(LgwConfigBoard::default(), LgwRadioType::LGW_RADIO_TYPE_SX1257);
+        conf.rf_chains[0].tx_enable = true;
+        conf.rf_chains[0].rssi_offset = -207.0;
+
+        LgwChannelPlan::new(LgwRegion::EU868).unwrap().apply(&mut conf);
+        assert_eq!(conf.rf_chains[0]._type, LgwRadioType::LGW_RADIO_TYPE_SX1257);
+        assert_eq!(conf.rf_chains[0].rssi_offset, -207.0);
+        assert_eq!((conf.tx_freq_min[0], conf.tx_freq_max[0]), (863_000_000, 870_000_000));
+        assert_eq!(conf.demod.multisf_datarate, MULTISF_SF7_SF12);
+    }
+}
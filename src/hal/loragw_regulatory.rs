@@ -0,0 +1,378 @@
+/*
+    Regulatory checks of downlinks, applied before lgw_send.
+
+    Each region is split in TX bands, with for each band:
+     - a maximum EIRP: rf_power (measured at the board connector) plus the antenna gain,
+     - optionally a duty cycle: the time on air of the packets sent in the band over the last
+       hour must not exceed duty_cycle x 1 hour (EU868, ETSI EN 300 220),
+     - optionally a dwell time: the time on air of a single packet must not exceed it (400 ms
+       for AS923 and US915).
+
+    The whole occupied bandwidth of the packet must lie in a band. Time on air is computed with
+    lgw_time_on_air and accounted when the packet is handed to lgw_send, which for timestamped
+    packets is a few tens of ms before the actual emission.
+*/
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use tracing::{debug, warn};
+
+use super::error::Error;
+use super::loragw_region::LgwRegion;
+use super::{lgw_time_on_air, Hal, LgwHal, LgwPktTx, Modulation};
+
+const DUTY_CYCLE_WINDOW: Duration = Duration::from_secs(3600);     /* observation period of the duty cycle */
+const DWELL_TIME_MS: u32 = 400;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum LgwRegError {
+    #[error("{freq_hz}Hz with {bw_hz}Hz bandwidth is not within an allowed {region} TX band")]
+    OutOfBand { region: LgwRegion, freq_hz: u32, bw_hz: u32 },
+
+    #[error("{eirp_dbm}dBm EIRP exceeds the {max_dbm}dBm allowed in band {band}")]
+    PowerTooHigh { band: &'static str, eirp_dbm: i16, max_dbm: i16 },
+
+    #[error("time on air {toa_ms}ms exceeds the {max_ms}ms dwell time of band {band}")]
+    DwellTime { band: &'static str, toa_ms: u32, max_ms: u32 },
+
+    #[error("time on air {toa_ms}ms exceeds the duty-cycle budget left in band {band} ({remaining_ms}ms of {budget_ms}ms per hour)")]
+    DutyCycle { band: &'static str, toa_ms: u32, remaining_ms: u32, budget_ms: u32 },
+}
+
+/**
+@struct LgwRegBand
+@brief TX band and the rules applying to it
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct LgwRegBand {
+    pub name: &'static str,
+    pub freq_min: u32,                  //*!> lower edge of the band (in Hz) */
+    pub freq_max: u32,                  //*!> upper edge of the band (in Hz) */
+    pub max_eirp: i16,                  //*!> maximum EIRP (in dBm) */
+    pub duty_cycle: Option<f32>,        //*!> maximum fraction of time on air over an hour */
+    pub dwell_time_ms: Option<u32>,     //*!> maximum time on air of a packet */
+}
+
+impl LgwRegBand {
+    const fn new(name: &'static str, freq_min: u32, freq_max: u32, max_eirp: i16) -> Self {
+        Self { name, freq_min, freq_max, max_eirp, duty_cycle: None, dwell_time_ms: None }
+    }
+
+    const fn duty_cycle(mut self, duty_cycle: f32) -> Self {
+        self.duty_cycle = Some(duty_cycle);
+        self
+    }
+
+    const fn dwell_time(mut self, dwell_time_ms: u32) -> Self {
+        self.dwell_time_ms = Some(dwell_time_ms);
+        self
+    }
+
+    fn budget_ms(&self) -> Option<u32> {
+        self.duty_cycle.map(|dc| (DUTY_CYCLE_WINDOW.as_millis() as f32 * dc) as u32)
+    }
+}
+
+/* TX bands of a region. EIRP limits assume the LoRaWAN regional parameters defaults, EU868
+   limits are the ERP limits of ETSI EN 300 220 plus 2.15 dB. */
+pub fn lgw_reg_bands(region: LgwRegion) -> Vec<LgwRegBand> {
+    match region {
+        LgwRegion::EU868 => vec![
+            LgwRegBand::new("863-865MHz", 863_000_000, 865_000_000, 16).duty_cycle(0.001),
+            LgwRegBand::new("g", 865_000_000, 868_000_000, 16).duty_cycle(0.01),
+            LgwRegBand::new("g1", 868_000_000, 868_600_000, 16).duty_cycle(0.01),
+            LgwRegBand::new("g2", 868_700_000, 869_200_000, 16).duty_cycle(0.001),
+            LgwRegBand::new("g3", 869_400_000, 869_650_000, 29).duty_cycle(0.1),
+            LgwRegBand::new("g4", 869_700_000, 870_000_000, 16).duty_cycle(0.01),
+        ],
+        LgwRegion::US915(_) => vec![
+            LgwRegBand::new("US915", 902_000_000, 928_000_000, 36).dwell_time(DWELL_TIME_MS),
+        ],
+        LgwRegion::AS923_1 | LgwRegion::AS923_2 | LgwRegion::AS923_3 | LgwRegion::AS923_4 => {
+            let (freq_min, freq_max) = region.tx_freq_range();
+            vec![LgwRegBand::new("AS923", freq_min, freq_max, 16).dwell_time(DWELL_TIME_MS)]
+        }
+        LgwRegion::AU915(_) => vec![LgwRegBand::new("AU915", 915_000_000, 928_000_000, 30)],
+        LgwRegion::KR920 => vec![LgwRegBand::new("KR920", 920_900_000, 923_300_000, 23)],
+        LgwRegion::IN865 => vec![LgwRegBand::new("IN865", 865_000_000, 867_000_000, 30)],
+        LgwRegion::CN470(_) => vec![LgwRegBand::new("CN470", 470_000_000, 510_000_000, 19)],
+    }
+}
+
+/**
+@struct LgwAirtimeBudget
+@brief Duty-cycle budget of a band over the last hour
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct LgwAirtimeBudget {
+    pub band: &'static str,
+    pub freq_min: u32,
+    pub freq_max: u32,
+    pub used_ms: u32,
+    pub budget_ms: u32,
+    pub remaining_ms: u32,
+}
+
+impl fmt::Display for LgwAirtimeBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "band {} [{}-{}Hz]: {}ms used, {}ms left of {}ms per hour", self.band, self.freq_min, self.freq_max, self.used_ms, self.remaining_ms, self.budget_ms)
+    }
+}
+
+/**
+@struct LgwRegulator
+@brief Tracks the airtime of each TX band and rejects non-compliant packets
+*/
+#[derive(Debug, Clone)]
+pub struct LgwRegulator {
+    region: LgwRegion,
+    bands: Vec<LgwRegBand>,
+    history: Vec<VecDeque<(Instant, u32)>>,     /* per band, (send time, time on air in ms) */
+    antenna_gain: i16,
+    fsk_sync_word_size: u32,
+}
+
+/* Bandwidth occupied by a packet, in Hz */
+fn occupied_bandwidth(pkt: &LgwPktTx) -> u32 {
+    match pkt.modulation {
+        Modulation::LORA => Hal::lgw_bw_getval(pkt.bandwidth).max(0) as u32,
+        Modulation::FSK => 2 * pkt.f_dev as u32 * 1000 + pkt.datarate,
+        _ => 0,
+    }
+}
+
+impl LgwRegulator {
+    /* antenna_gain (in dBi) is added to rf_power to get the EIRP, fsk_sync_word_size (in bytes)
+       is only used to compute the time on air of FSK packets */
+    pub fn new(region: LgwRegion, antenna_gain: i8, fsk_sync_word_size: u32) -> Self {
+        let bands = lgw_reg_bands(region);
+        Self {
+            region,
+            history: vec![VecDeque::new(); bands.len()],
+            bands,
+            antenna_gain: antenna_gain as i16,
+            fsk_sync_word_size,
+        }
+    }
+
+    pub fn region(&self) -> LgwRegion {
+        self.region
+    }
+
+    pub fn bands(&self) -> &[LgwRegBand] {
+        &self.bands
+    }
+
+    fn band_index(&self, pkt: &LgwPktTx) -> Result<usize, LgwRegError> {
+        let half_bw = occupied_bandwidth(pkt) / 2;
+        self.bands.iter()
+            .position(|b| pkt.freq_hz.saturating_sub(half_bw) >= b.freq_min && pkt.freq_hz + half_bw <= b.freq_max)
+            .ok_or(LgwRegError::OutOfBand { region: self.region, freq_hz: pkt.freq_hz, bw_hz: 2 * half_bw })
+    }
+
+    fn used_ms(&mut self, band: usize, now: Instant) -> u32 {
+        let history = &mut self.history[band];
+        while history.front().is_some_and(|&(t, _)| now.saturating_duration_since(t) >= DUTY_CYCLE_WINDOW) {
+            history.pop_front();
+        }
+        history.iter().map(|&(_, toa)| toa).sum()
+    }
+
+    /* Check pkt against the rules of its band, now being the time it is handed to lgw_send */
+    pub fn check(&mut self, pkt: &LgwPktTx, now: Instant) -> Result<(), LgwRegError> {
+        let index = self.band_index(pkt)?;
+        let band = self.bands[index];
+
+        let eirp_dbm = pkt.rf_power as i16 + self.antenna_gain;
+        if eirp_dbm > band.max_eirp {
+            return Err(LgwRegError::PowerTooHigh { band: band.name, eirp_dbm, max_dbm: band.max_eirp });
+        }
+
+        let toa_ms = lgw_time_on_air(pkt, self.fsk_sync_word_size);
+        if let Some(max_ms) = band.dwell_time_ms.filter(|&max| toa_ms > max) {
+            return Err(LgwRegError::DwellTime { band: band.name, toa_ms, max_ms });
+        }
+
+        if let Some(budget_ms) = band.budget_ms() {
+            let remaining_ms = budget_ms.saturating_sub(self.used_ms(index, now));
+            if toa_ms > remaining_ms {
+                return Err(LgwRegError::DutyCycle { band: band.name, toa_ms, remaining_ms, budget_ms });
+            }
+        }
+
+        Ok(())
+    }
+
+    /* Account the time on air of a packet that was sent */
+    pub fn record(&mut self, pkt: &LgwPktTx, now: Instant) {
+        if let Ok(index) = self.band_index(pkt) {
+            let toa_ms = lgw_time_on_air(pkt, self.fsk_sync_word_size);
+            self.history[index].push_back((now, toa_ms));
+            let used_ms = self.used_ms(index, now);
+            debug!("Note: {}ms on air in band {}, {}ms used over the last hour", toa_ms, self.bands[index].name, used_ms);
+        }
+    }
+
+    /* lgw_send, for packets that comply with the regional rules only */
+    pub fn send<H: LgwHal>(&mut self, hal: &mut H, pkt: &LgwPktTx) -> Result<()> {
+        let now = Instant::now();
+        if let Err(e) = self.check(pkt, now) {
+            warn!("WARNING: TX rejected: {}", e);
+            return Err(Error::Regulatory(e).into());
+        }
+
+        hal.lgw_send(pkt)?;
+        self.record(pkt, now);
+        Ok(())
+    }
+
+    /* Duty-cycle budget of every band with a duty-cycle limit */
+    pub fn budgets(&mut self, now: Instant) -> Vec<LgwAirtimeBudget> {
+        (0 .. self.bands.len())
+            .filter_map(|i| {
+                let band = self.bands[i];
+                let budget_ms = band.budget_ms()?;
+                let used_ms = self.used_ms(i, now);
+                Some(LgwAirtimeBudget {
+                    band: band.name,
+                    freq_min: band.freq_min,
+                    freq_max: band.freq_max,
+                    used_ms,
+                    budget_ms,
+                    remaining_ms: budget_ms.saturating_sub(used_ms),
+                })
+            })
+            .collect()
+    }
+
+    /* Airtime left at freq_hz over the next hour, None if the band has no duty-cycle limit or
+       if freq_hz is outside of every band */
+    pub fn remaining_ms(&mut self, freq_hz: u32, now: Instant) -> Option<u32> {
+        let index = self.bands.iter().position(|b| (b.freq_min ..= b.freq_max).contains(&freq_hz))?;
+        let budget_ms = self.bands[index].budget_ms()?;
+        Some(budget_ms.saturating_sub(self.used_ms(index, now)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::{BW_125KHZ, CR_LORA_4_5, DR_LORA_SF12, DR_LORA_SF7};
+
+    fn lora(freq_hz: u32, datarate: u32, rf_power: i8) -> LgwPktTx {
+        LgwPktTx {
+            freq_hz,
+            rf_power,
+            modulation: Modulation::LORA,
+            bandwidth: BW_125KHZ,
+            datarate,
+            coderate: CR_LORA_4_5,
+            preamble: 8,
+            size: 51,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn duty_cycle_window() {
+        let mut reg = LgwRegulator::new(LgwRegion::EU868, 0, 3);
+        let t0 = Instant::now();
+        let pkt = lora(868_100_000, DR_LORA_SF12, 14);
+        let toa_ms = lgw_time_on_air(&pkt, 3);
+        let budget_ms = 36_000;     /* 1% of an hour in g1 */
+
+        /* one packet every 10s until the budget of g1 is exhausted */
+        let mut sent = 0;
+        let mut now = t0;
+        while reg.check(&pkt, now).is_ok() {
+            reg.record(&pkt, now);
+            sent += 1;
+            now = t0 + Duration::from_secs(10 * sent as u64);
+        }
+        assert_eq!(sent, budget_ms / toa_ms);
+        let used_ms = sent * toa_ms;
+        assert_eq!(reg.check(&pkt, now), Err(LgwRegError::DutyCycle { band: "g1", toa_ms, remaining_ms: budget_ms - used_ms, budget_ms }));
+        assert_eq!(reg.remaining_ms(868_100_000, now), Some(budget_ms - used_ms));
+
+        let g1 = reg.budgets(now).into_iter().find(|b| b.band == "g1").unwrap();
+        assert_eq!((g1.used_ms, g1.remaining_ms, g1.budget_ms), (used_ms, budget_ms - used_ms, budget_ms));
+        assert!(reg.budgets(now).iter().filter(|b| b.band != "g1").all(|b| b.used_ms == 0));
+
+        /* other bands have their own budget */
+        assert_eq!(reg.check(&lora(869_525_000, DR_LORA_SF12, 14), now), Ok(()));
+
+        /* the first packet leaves the window an hour after it was sent */
+        let now = t0 + DUTY_CYCLE_WINDOW - Duration::from_millis(1);
+        assert!(reg.check(&pkt, now).is_err());
+        let now = t0 + DUTY_CYCLE_WINDOW;
+        assert_eq!(reg.check(&pkt, now), Ok(()));
+        assert_eq!(reg.remaining_ms(868_100_000, now), Some(budget_ms - used_ms + toa_ms));
+
+        /* and every packet an hour after the last one */
+        let now = t0 + DUTY_CYCLE_WINDOW + Duration::from_secs(10 * (sent as u64 - 1));
+        assert_eq!(reg.remaining_ms(868_100_000, now), Some(budget_ms));
+        assert!(reg.budgets(now).iter().all(|b| b.used_ms == 0));
+    }
+
+    #[test]
+    fn dwell_time() {
+        let now = Instant::now();
+        for (region, freq_hz) in [(LgwRegion::AS923_1, 923_200_000), (LgwRegion::US915(2), 923_300_000)] {
+            let mut reg = LgwRegulator::new(region, 0, 3);
+            let long = lora(freq_hz, DR_LORA_SF12, 14);
+            let short = lora(freq_hz, DR_LORA_SF7, 14);
+            let toa_ms = lgw_time_on_air(&long, 3);
+            assert!(toa_ms > DWELL_TIME_MS && lgw_time_on_air(&short, 3) <= DWELL_TIME_MS);
+
+            let band = reg.bands()[0].name;
+            assert_eq!(reg.check(&long, now), Err(LgwRegError::DwellTime { band, toa_ms, max_ms: DWELL_TIME_MS }), "{}", region);
+            assert_eq!(reg.check(&short, now), Ok(()), "{}", region);
+
+            /* no duty cycle: any number of packets within the dwell time */
+            for _ in 0..1000 {
+                reg.record(&short, now);
+            }
+            assert_eq!(reg.check(&short, now), Ok(()), "{}", region);
+            assert!(reg.budgets(now).is_empty());
+        }
+    }
+
+    #[test]
+    fn eirp() {
+        let now = Instant::now();
+        let mut reg = LgwRegulator::new(LgwRegion::EU868, 2, 3);
+        assert_eq!(reg.check(&lora(868_100_000, DR_LORA_SF7, 14), now), Ok(()));
+        assert_eq!(reg.check(&lora(868_100_000, DR_LORA_SF7, 15), now), Err(LgwRegError::PowerTooHigh { band: "g1", eirp_dbm: 17, max_dbm: 16 }));
+        assert_eq!(reg.check(&lora(869_525_000, DR_LORA_SF7, 27), now), Ok(()));
+        assert_eq!(reg.check(&lora(869_525_000, DR_LORA_SF7, 28), now), Err(LgwRegError::PowerTooHigh { band: "g3", eirp_dbm: 30, max_dbm: 29 }));
+
+        let mut reg = LgwRegulator::new(LgwRegion::EU868, -3, 3);
+        assert_eq!(reg.check(&lora(868_100_000, DR_LORA_SF7, 19), now), Ok(()));
+    }
+
+    #[test]
+    fn band_edges() {
+        let now = Instant::now();
+        let mut reg = LgwRegulator::new(LgwRegion::EU868, 0, 3);
+        let out_of_band = |freq_hz| Err(LgwRegError::OutOfBand { region: LgwRegion::EU868, freq_hz, bw_hz: 125_000 });
+
+        /* the whole 125kHz must lie in g1 [868.0, 868.6]MHz */
+        assert_eq!(reg.check(&lora(868_062_500, DR_LORA_SF7, 14), now), Ok(()));
+        assert_eq!(reg.check(&lora(868_062_499, DR_LORA_SF7, 14), now), out_of_band(868_062_499));
+        assert_eq!(reg.check(&lora(868_537_500, DR_LORA_SF7, 14), now), Ok(()));
+        assert_eq!(reg.check(&lora(868_537_501, DR_LORA_SF7, 14), now), out_of_band(868_537_501));
+
+        /* gap between g1 and g2, outside of the region */
+        assert_eq!(reg.check(&lora(868_650_000, DR_LORA_SF7, 14), now), out_of_band(868_650_000));
+        assert_eq!(reg.remaining_ms(868_650_000, now), None);
+        assert_eq!(reg.check(&lora(862_990_000, DR_LORA_SF7, 14), now), out_of_band(862_990_000));
+        assert_eq!(reg.check(&lora(869_950_000, DR_LORA_SF7, 14), now), out_of_band(869_950_000));
+
+        /* a rejected packet is not accounted */
+        reg.record(&lora(868_650_000, DR_LORA_SF12, 14), now);
+        assert!(reg.budgets(now).iter().all(|b| b.used_ms == 0));
+    }
+}
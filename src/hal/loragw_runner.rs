@@ -0,0 +1,475 @@
+/*
+    Concentrator runner: owns the Hal on a dedicated thread.
+
+    The thread polls the SX1302 RX buffer at a fixed interval and forwards every received packet
+    over a channel. TX requests, status queries and shutdown are posted to a command queue, so
+    that RX and TX can be driven from different parts of the application. Downlinks can either be
+    sent right away, or enqueued in a JIT queue (see loragw_jit) from which the thread hands them
+    to lgw_send just before their emission time:
+
+        let runner = LgwRunner::spawn(hal, Duration::from_millis(10))?;     // on error, the Hal is in the LgwRunnerStartError
+        let tx = runner.handle();           // clonable, can be moved to another thread
+        for pkt in runner.packets() { ... }
+*/
+
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use tracing::{debug, error, info, warn};
+
+use super::error::Error;
+use super::loragw_jit::{JitPktType, JitQueue};
+use super::{Hal, LgwHal, LgwPktRx, LgwPktTx, TX_EMITTING, TX_STATUS};
+
+/* Period of the JIT queue checks while it is not empty, must stay well below TX_JIT_DELAY */
+const JIT_CHECK_PERIOD: Duration = Duration::from_millis(10);
+
+/* Completion called by the runner thread with the result of a command */
+pub(crate) type LgwRunnerReply<T> = Box<dyn FnOnce(Result<T>) + Send>;
+
+/* Commands posted to the runner thread, each one carries its reply */
+pub(crate) enum LgwRunnerCmd {
+    Send(Box<LgwPktTx>, LgwRunnerReply<()>),
+    Enqueue(Box<LgwPktTx>, JitPktType, LgwRunnerReply<()>),
+    AbortTx(u8, LgwRunnerReply<()>),
+    TxStatus(u8, LgwRunnerReply<u8>),
+    Instcnt(LgwRunnerReply<u32>),
+    Status(LgwRunnerReply<LgwRunnerStatus>),
+    Stop,
+}
+
+/**
+@struct LgwRunnerStatus
+@brief Snapshot of the concentrator state, as seen by the runner thread
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LgwRunnerStatus {
+    pub count_us: u32,          //*!> internal concentrator counter (in µs) */
+    pub temperature: f32,       //*!> concentrator temperature (in °C) */
+    pub nb_rx: u64,             //*!> number of packets delivered since start */
+    pub nb_tx: u64,             //*!> number of packets successfully sent since start */
+    pub nb_rx_errors: u64,      //*!> number of failed polls of the RX buffer */
+    pub nb_jit_queued: u32,     //*!> number of packets waiting in the JIT queue */
+}
+
+/**
+@struct LgwRunnerStartError
+@brief Failure to start a runner, gives the Hal back so that it can be reconfigured
+*/
+#[derive(Debug)]
+pub struct LgwRunnerStartError {
+    pub hal: Option<Box<Hal>>,      //*!> None if the runner thread could not be spawned or panicked */
+    pub error: anyhow::Error,
+}
+
+impl fmt::Display for LgwRunnerStartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#}", self.error)
+    }
+}
+
+impl From<LgwRunnerStartError> for anyhow::Error {
+    fn from(e: LgwRunnerStartError) -> Self {
+        e.error
+    }
+}
+
+/**
+@struct LgwRunnerHandle
+@brief Clonable handle posting commands to a running concentrator
+*/
+#[derive(Debug, Clone)]
+pub struct LgwRunnerHandle {
+    cmd: Sender<LgwRunnerCmd>,
+}
+
+impl LgwRunnerHandle {
+    /* Post a command without waiting for its completion */
+    pub(crate) fn post(&self, cmd: LgwRunnerCmd) -> Result<()> {
+        self.cmd.send(cmd).map_err(|_| Error::RunnerStopped.into())
+    }
+
+    fn request<T: Send + 'static>(&self, build: impl FnOnce(LgwRunnerReply<T>) -> LgwRunnerCmd) -> Result<T> {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        self.post(build(Box::new(move |res| { let _ = reply_tx.send(res); })))?;
+        reply_rx.recv().map_err(|_| Error::RunnerStopped)?
+    }
+
+    /* Schedule or send immediately a packet, returns once the SX1302 accepted it.
+       With LBT enabled, only returns once the TX started, or was blocked (Error::LbtNotAllowed). */
+    pub fn send(&self, pkt: &LgwPktTx) -> Result<()> {
+        self.request(|reply| LgwRunnerCmd::Send(Box::new(*pkt), reply))
+    }
+
+    /* Queue a packet in the JIT queue, fails with Error::Jit if it is too late, too early or
+       collides with a queued packet. IMMEDIATE packets are given the first free slot. */
+    pub fn enqueue(&self, pkt: &LgwPktTx, pkt_type: JitPktType) -> Result<()> {
+        self.request(|reply| LgwRunnerCmd::Enqueue(Box::new(*pkt), pkt_type, reply))
+    }
+
+    pub fn abort_tx(&self, rf_chain: u8) -> Result<()> {
+        self.request(|reply| LgwRunnerCmd::AbortTx(rf_chain, reply))
+    }
+
+    /* TX_FREE, TX_SCHEDULED, TX_EMITTING... */
+    pub fn tx_status(&self, rf_chain: u8) -> Result<u8> {
+        self.request(|reply| LgwRunnerCmd::TxStatus(rf_chain, reply))
+    }
+
+    /* Current value of the concentrator counter (in µs) */
+    pub fn instcnt(&self) -> Result<u32> {
+        self.request(LgwRunnerCmd::Instcnt)
+    }
+
+    pub fn status(&self) -> Result<LgwRunnerStatus> {
+        self.request(LgwRunnerCmd::Status)
+    }
+
+    /* Ask the runner thread to stop the concentrator, does not wait for it */
+    pub fn stop(&self) {
+        let _ = self.cmd.send(LgwRunnerCmd::Stop);
+    }
+}
+
+/**
+@struct LgwRunner
+@brief Concentrator running on its own thread, RX packets are delivered over a channel
+*/
+#[derive(Debug)]
+pub struct LgwRunner {
+    handle: LgwRunnerHandle,
+    packets: Receiver<LgwPktRx>,
+    thread: Option<JoinHandle<Hal>>,
+}
+
+impl LgwRunner {
+    /* Move an already configured Hal to a new thread and start the concentrator.
+       If lgw_start fails, the Hal is given back with the error. */
+    pub fn spawn(hal: Hal, poll_interval: Duration) -> Result<Self, LgwRunnerStartError> {
+        let (pkt_tx, pkt_rx) = mpsc::channel();
+        let (handle, thread) = Self::spawn_with(hal, poll_interval, move |pkt| {
+            /* nobody listening anymore is not an error, keep serving TX */
+            let _ = pkt_tx.send(pkt);
+        })?;
+
+        Ok(Self {
+            handle,
+            packets: pkt_rx,
+            thread: Some(thread),
+        })
+    }
+
+    /* Same as spawn, with received packets given to a callback running on the runner thread */
+    pub(crate) fn spawn_with<F>(hal: Hal, poll_interval: Duration, on_rx: F) -> Result<(LgwRunnerHandle, JoinHandle<Hal>), LgwRunnerStartError>
+    where
+        F: FnMut(LgwPktRx) + Send + 'static,
+    {
+        let (cmd_tx, cmd_rx) = mpsc::channel();
+        let (started_tx, started_rx) = mpsc::channel();
+
+        let thread = thread::Builder::new()
+            .name("lgw-runner".to_string())
+            .spawn(move || runner_thread(hal, poll_interval, cmd_rx, on_rx, started_tx))
+            .map_err(|e| LgwRunnerStartError { hal: None, error: anyhow!("ERROR: failed to spawn runner thread: {}", e) })?;
+
+        match started_rx.recv() {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => {
+                return Err(LgwRunnerStartError { hal: thread.join().ok().map(Box::new), error });
+            }
+            Err(_) => {
+                return Err(LgwRunnerStartError { hal: None, error: anyhow!("ERROR: runner thread died during lgw_start") });
+            }
+        }
+
+        Ok((LgwRunnerHandle { cmd: cmd_tx }, thread))
+    }
+
+    pub fn handle(&self) -> LgwRunnerHandle {
+        self.handle.clone()
+    }
+
+    /* Received packets, the iterator ends when the runner stops */
+    pub fn packets(&self) -> &Receiver<LgwPktRx> {
+        &self.packets
+    }
+
+    pub fn send(&self, pkt: &LgwPktTx) -> Result<()> {
+        self.handle.send(pkt)
+    }
+
+    pub fn enqueue(&self, pkt: &LgwPktTx, pkt_type: JitPktType) -> Result<()> {
+        self.handle.enqueue(pkt, pkt_type)
+    }
+
+    pub fn status(&self) -> Result<LgwRunnerStatus> {
+        self.handle.status()
+    }
+
+    /* Stop the concentrator and give the Hal back, e.g. to reconfigure it */
+    pub fn stop(mut self) -> Result<Hal> {
+        self.handle.stop();
+        let thread = self.thread.take().ok_or(Error::RunnerStopped)?;
+        thread.join().map_err(|_| anyhow!("ERROR: runner thread panicked"))
+    }
+}
+
+impl Drop for LgwRunner {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.handle.stop();
+            let _ = thread.join();
+        }
+    }
+}
+
+fn runner_thread<F: FnMut(LgwPktRx)>(mut hal: Hal, poll_interval: Duration, cmd_rx: Receiver<LgwRunnerCmd>, mut on_rx: F, started_tx: Sender<Result<()>>) -> Hal {
+    if let Err(e) = hal.lgw_start() {
+        error!("ERROR: runner failed to start the concentrator: {:#}", e);
+        let _ = started_tx.send(Err(e));
+        return hal;
+    }
+    let _ = started_tx.send(Ok(()));
+    info!("INFO: runner started, polling every {:?}", poll_interval);
+
+    let mut status = LgwRunnerStatus::default();
+    let mut next_poll = Instant::now() + poll_interval;
+    let mut jit = JitQueue::new(hal.ctx.fsk_cfg.sync_word_size as u32);
+    let mut next_jit = Instant::now();
+
+    loop {
+        if !jit.is_empty() && Instant::now() >= next_jit {
+            next_jit = Instant::now() + JIT_CHECK_PERIOD;
+            jit_dispatch(&mut hal, &mut jit, &mut status);
+        }
+
+        /* a steady flow of commands must not delay RX polling nor the JIT queue */
+        let deadline = if jit.is_empty() { next_poll } else { next_poll.min(next_jit) };
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match cmd_rx.recv_timeout(timeout) {
+            Ok(LgwRunnerCmd::Send(pkt, reply)) => {
+                let res = hal.lgw_send(&pkt);
+                if res.is_ok() {
+                    status.nb_tx += 1;
+                }
+                reply(res);
+            }
+            Ok(LgwRunnerCmd::Enqueue(pkt, pkt_type, reply)) => {
+                let res = hal.lgw_get_instcnt().and_then(|count_us| {
+                    jit.enqueue(count_us, &pkt, pkt_type).map_err(|e| Error::Jit(e).into())
+                });
+                reply(res);
+            }
+            Ok(LgwRunnerCmd::AbortTx(rf_chain, reply)) => {
+                reply(hal.lgw_abort_tx(rf_chain));
+            }
+            Ok(LgwRunnerCmd::TxStatus(rf_chain, reply)) => {
+                reply(hal.lgw_status(rf_chain, TX_STATUS));
+            }
+            Ok(LgwRunnerCmd::Instcnt(reply)) => {
+                reply(hal.lgw_get_instcnt());
+            }
+            Ok(LgwRunnerCmd::Status(reply)) => {
+                let res = hal.lgw_get_instcnt().and_then(|count_us| {
+                    status.count_us = count_us;
+                    status.temperature = hal.lgw_get_temperature()?;
+                    status.nb_jit_queued = jit.len() as u32;
+                    Ok(status)
+                });
+                reply(res);
+            }
+            Ok(LgwRunnerCmd::Stop) | Err(RecvTimeoutError::Disconnected) => {
+                break;
+            }
+            Err(RecvTimeoutError::Timeout) if Instant::now() < next_poll => {}
+            Err(RecvTimeoutError::Timeout) => {
+                next_poll = Instant::now() + poll_interval;
+                match hal.lgw_receive() {
+                    Ok(pkts) => {
+                        for pkt in pkts {
+                            status.nb_rx += 1;
+                            on_rx(pkt);
+                        }
+                    }
+                    Err(e) => {
+                        status.nb_rx_errors += 1;
+                        warn!("WARNING: runner failed to receive packets: {:#}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    if !jit.is_empty() {
+        warn!("WARNING: runner stopping with {} packet(s) left in the JIT queue", jit.len());
+    }
+    debug!("runner stopping, rx:{} tx:{} rx_errors:{}", status.nb_rx, status.nb_tx, status.nb_rx_errors);
+    if let Err(e) = hal.lgw_stop() {
+        error!("ERROR: runner failed to stop the concentrator: {:#}", e);
+    }
+    hal
+}
+
+/* Give to lgw_send the queued packet due within TX_JIT_DELAY, if any */
+fn jit_dispatch(hal: &mut Hal, jit: &mut JitQueue, status: &mut LgwRunnerStatus) {
+    let time_us = match hal.lgw_get_instcnt() {
+        Ok(t) => t,
+        Err(e) => {
+            warn!("WARNING: runner failed to read the counter for the JIT queue: {:#}", e);
+            return;
+        }
+    };
+
+    let Some(node) = jit.peek(time_us).and_then(|i| jit.dequeue(i)) else {
+        return;
+    };
+    debug!("JIT: sending packet (count_us:{} now:{})", node.pkt.count_us, time_us);
+
+    /* the queue guarantees no overlap, only a packet sent directly can be in the way */
+    if let Ok(TX_EMITTING) = hal.lgw_status(node.pkt.rf_chain, TX_STATUS) {
+        warn!("WARNING: JIT: RF chain {} is still emitting, packet (count_us:{}) may be lost", node.pkt.rf_chain, node.pkt.count_us);
+    }
+    match hal.lgw_send(&node.pkt) {
+        Ok(()) => status.nb_tx += 1,
+        Err(e) => match Error::cause(&e) {
+            Some(Error::LbtNotAllowed { .. }) => warn!("WARNING: JIT: packet (count_us:{}) not sent: {:#}", node.pkt.count_us, e),
+            _ => error!("ERROR: JIT: lgw_send failed: {:#}", e),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::loragw_conf::LgwGlobalConf;
+    use crate::hal::loragw_jit::JitError;
+    use crate::hal::mcu::sim::{SimConcentrator, SimRxPacket};
+    use crate::hal::{Modulation, TxMode, BW_125KHZ, CR_LORA_4_5, STAT_CRC_OK, TX_FREE, TX_SCHEDULED};
+
+    const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+    const GLOBAL_CONF: &str = r#"{"SX130x_conf": {
+        "com_path": "/dev/ttyACM0", "clksrc": 0, "lorawan_public": true,
+        "radio_0": {
+            "enable": true, "type": "SX1250", "freq": 867500000, "rssi_offset": -215.4, "tx_enable": true,
+            "tx_gain_lut": [{"rf_power": 14, "pa_gain": 0, "pwr_idx": 17}]
+        },
+        "radio_1": {"enable": true, "type": "SX1250", "freq": 868500000, "rssi_offset": -215.4, "tx_enable": false},
+        "chan_multiSF_0": {"enable": true, "radio": 1, "if": -400000},
+        "chan_multiSF_3": {"enable": true, "radio": 0, "if": -400000}
+    }}"#;
+
+    fn sim_hal() -> (SimConcentrator, Hal) {
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        LgwGlobalConf::parse(GLOBAL_CONF).unwrap().apply(&mut hal).unwrap();
+        (sim, hal)
+    }
+
+    fn pkt(tx_mode: TxMode, count_us: u32) -> LgwPktTx {
+        LgwPktTx {
+            tx_mode,
+            count_us,
+            freq_hz: 869_525_000,
+            rf_power: 14,
+            modulation: Modulation::LORA,
+            bandwidth: BW_125KHZ,
+            datarate: 7,
+            coderate: CR_LORA_4_5,
+            invert_pol: true,
+            preamble: 8,
+            size: 4,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn start_error_gives_hal_back() {
+        let (_sim, mut hal) = sim_hal();
+        /* fine timestamping is not available on the simulated SX1302 */
+        hal.ctx.ftime_cfg.enable = true;
+
+        let e = LgwRunner::spawn(hal, POLL_INTERVAL).unwrap_err();
+        assert!(matches!(Error::chain(&e.error).next(), Some(Error::Start { .. })), "{:#}", e.error);
+        let mut hal = *e.hal.expect("Hal not given back");
+        assert!(!hal.ctx.is_started);
+
+        hal.ctx.ftime_cfg.enable = false;
+        let runner = LgwRunner::spawn(hal, POLL_INTERVAL).unwrap();
+        let hal = runner.stop().unwrap();
+        assert!(!hal.ctx.is_started);
+    }
+
+    #[test]
+    fn receive_send_enqueue() {
+        let (sim, hal) = sim_hal();
+        let runner = LgwRunner::spawn(hal, POLL_INTERVAL).unwrap();
+        let handle = runner.handle();
+
+        sim.inject_rx(&SimRxPacket { if_chain: 3, datarate: 9, payload: vec![0x40; 12], ..Default::default() }).unwrap();
+        let rx = runner.packets().recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!((rx.if_chain, rx.freq_hz, rx.datarate, rx.status, rx.size), (3, 867_100_000, 9, STAT_CRC_OK, 12));
+
+        /* from another thread, through a handle */
+        std::thread::spawn(move || handle.send(&pkt(TxMode::IMMEDIATE, 0))).join().unwrap().unwrap();
+        let tx = sim.take_tx();
+        assert_eq!(tx.len(), 1);
+        assert!(matches!(tx[0].tx_mode, TxMode::IMMEDIATE));
+
+        /* handed to lgw_send TX_JIT_DELAY before its time */
+        let count_us = runner.handle().instcnt().unwrap().wrapping_add(200_000);
+        runner.enqueue(&pkt(TxMode::TIMESTAMPED, count_us), JitPktType::DOWNLINK_CLASS_A).unwrap();
+        assert_eq!(runner.status().unwrap().nb_jit_queued, 1);
+        assert!(sim.take_tx().is_empty());
+        let e = runner.enqueue(&pkt(TxMode::TIMESTAMPED, count_us.wrapping_add(10_000)), JitPktType::DOWNLINK_CLASS_A).unwrap_err();
+        assert!(matches!(Error::cause(&e), Some(Error::Jit(JitError::COLLISION_PACKET))), "{:#}", e);
+        let e = runner.enqueue(&pkt(TxMode::TIMESTAMPED, runner.handle().instcnt().unwrap()), JitPktType::DOWNLINK_CLASS_A).unwrap_err();
+        assert!(matches!(Error::cause(&e), Some(Error::Jit(JitError::TOO_LATE))), "{:#}", e);
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let tx = loop {
+            let tx = sim.take_tx();
+            if !tx.is_empty() || Instant::now() > deadline {
+                break tx;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        };
+        assert_eq!(tx.len(), 1);
+        assert!(matches!(tx[0].tx_mode, TxMode::TIMESTAMPED));
+        assert!(tx[0].count_us.abs_diff(count_us) < 5_000, "TX at {}, requested {}", tx[0].count_us, count_us);
+        assert_eq!(runner.handle().tx_status(0).unwrap(), TX_SCHEDULED);
+
+        let status = runner.status().unwrap();
+        assert_eq!((status.nb_rx, status.nb_tx, status.nb_rx_errors, status.nb_jit_queued), (1, 2, 0, 0));
+        runner.handle().abort_tx(0).unwrap();
+        assert_eq!(runner.handle().tx_status(0).unwrap(), TX_FREE);
+    }
+
+    #[test]
+    fn stop_joins_thread() {
+        let (sim, hal) = sim_hal();
+        let runner = LgwRunner::spawn(hal, POLL_INTERVAL).unwrap();
+        let handle = runner.handle();
+        sim.inject_rx(&SimRxPacket { if_chain: 0, datarate: 7, payload: vec![1; 8], ..Default::default() }).unwrap();
+        let stopper = {
+            let handle = handle.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(100));
+                handle.stop();
+            })
+        };
+        let packets = runner.packets().iter().count();
+        stopper.join().unwrap();
+        /* the packets iterator ended with the thread */
+        assert_eq!(packets, 1);
+
+        let hal = runner.stop().unwrap();
+        assert!(!hal.ctx.is_started);
+        let e = handle.send(&pkt(TxMode::IMMEDIATE, 0)).unwrap_err();
+        assert!(matches!(Error::cause(&e), Some(Error::RunnerStopped)));
+        assert!(handle.status().is_err());
+    }
+}
@@ -0,0 +1,189 @@
+/*
+    Spectral scan with the SX1261.
+
+    For each frequency step the SX1261 is tuned with a 125kHz bandwidth and reads the channel RSSI
+    nb_scan times (~8.2µs apart), the reads being accumulated in a histogram of
+    LGW_SPECTRAL_SCAN_RESULT_SIZE bins, 4dB wide, from -128dBm (before rssi_offset correction).
+
+    The SX1261 is shared with LBT: a scan must not be running while downlinks are sent with LBT
+    enabled, LBT aborts any on-going scan.
+*/
+
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use tracing::{debug, error, warn};
+
+use super::error::Error;
+use super::helper::wait_ms;
+use super::loragw_sx1261::LoragwSx1261Trait;
+use super::{Hal, LgwSpectralScanStatus, BW_125KHZ, LGW_SPECTRAL_SCAN_RESULT_SIZE};
+
+const SCAN_STATUS_POLL_MS: u32 =    1;
+const SCAN_MARGIN: Duration =       Duration::from_millis(100);    /* on top of the expected scan duration */
+
+/**
+@struct LgwSpectralScanResult
+@brief RSSI histogram of one frequency step
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LgwSpectralScanResult {
+    pub freq_hz: u32,                                           //*!> center frequency of the scan (in Hz) */
+    pub levels_dbm: [i16; LGW_SPECTRAL_SCAN_RESULT_SIZE],       //*!> lower RSSI level of each bin, rssi_offset applied (in dBm) */
+    pub results: [u16; LGW_SPECTRAL_SCAN_RESULT_SIZE],          //*!> number of RSSI reads in each bin */
+}
+
+impl LgwSpectralScanResult {
+    /* Level of the bin holding the median read, a rough noise floor estimate */
+    pub fn median_dbm(&self) -> Option<i16> {
+        let total: u32 = self.results.iter().map(|&n| n as u32).sum();
+        let mut count = 0;
+        for (level, &n) in self.levels_dbm.iter().zip(self.results.iter()) {
+            count += n as u32;
+            if total > 0 && 2 * count >= total {
+                return Some(*level);
+            }
+        }
+        None
+    }
+}
+
+/* Expected duration of a scan of nb_scan RSSI reads */
+fn scan_duration(nb_scan: u16) -> Duration {
+    Duration::from_nanos(nb_scan as u64 * 8200)
+}
+
+pub trait LgwSpectralScanTrait {
+    fn lgw_spectral_scan_start(&mut self, freq_hz: u32, nb_scan: u16) -> Result<()>;
+    fn lgw_spectral_scan_get_status(&mut self) -> Result<LgwSpectralScanStatus>;
+    fn lgw_spectral_scan_get_results(&mut self) -> Result<([i16; LGW_SPECTRAL_SCAN_RESULT_SIZE], [u16; LGW_SPECTRAL_SCAN_RESULT_SIZE])>;
+    fn lgw_spectral_scan_abort(&mut self) -> Result<()>;
+    fn lgw_spectral_scan_sweep(&mut self, freq_start_hz: u32, nb_chan: u32, step_hz: u32, nb_scan: u16) -> Result<Vec<LgwSpectralScanResult>>;
+}
+
+impl LgwSpectralScanTrait for Hal {
+    fn lgw_spectral_scan_start(&mut self, freq_hz: u32, nb_scan: u16) -> Result<()> {
+        if !self.ctx.is_started {
+            error!("ERROR: CONCENTRATOR IS NOT RUNNING, START IT BEFORE SCANNING\n");
+            return Err(Error::ConcentratorNotStarted.into());
+        }
+        if !self.ctx.sx1261_cfg.enable {
+            error!("ERROR: spectral scan requires the SX1261 to be enabled\n");
+            return Err(Error::config("sx1261.enable", false, "spectral scan requires the SX1261").into());
+        }
+        if !self.ctx.sx1261_pram_loaded {
+            error!("ERROR: spectral scan requires the SX1261 PRAM patch\n");
+            return Err(Error::SpectralScan { freq_hz, reason: "SX1261 PRAM patch not loaded" }.into());
+        }
+        if nb_scan == 0 {
+            return Err(Error::config("nb_scan", nb_scan, "at least one RSSI read is needed").into());
+        }
+
+        /* Abort a scan still running on the SX1261 */
+        if self.lgw_spectral_scan_get_status()? == LgwSpectralScanStatus::LGW_SPECTRAL_SCAN_STATUS_ON_GOING {
+            warn!("WARNING: spectral scan already on-going, aborting it\n");
+            self.lgw_spectral_scan_abort()?;
+        }
+
+        self.sx1261_set_rx_params(freq_hz, BW_125KHZ)
+            .context("failed to set spectral scan frequency")?;
+        self.sx1261_spectral_scan_start(nb_scan)
+            .context("failed to start spectral scan")?;
+
+        debug!("Spectral scan: started on {}Hz ({} scans)", freq_hz, nb_scan);
+        Ok(())
+    }
+
+    fn lgw_spectral_scan_get_status(&mut self) -> Result<LgwSpectralScanStatus> {
+        if !self.ctx.sx1261_cfg.enable {
+            return Ok(LgwSpectralScanStatus::LGW_SPECTRAL_SCAN_STATUS_NONE);
+        }
+        self.sx1261_spectral_scan_status().context("failed to get spectral scan status")
+    }
+
+    fn lgw_spectral_scan_get_results(&mut self) -> Result<([i16; LGW_SPECTRAL_SCAN_RESULT_SIZE], [u16; LGW_SPECTRAL_SCAN_RESULT_SIZE])> {
+        let status = self.lgw_spectral_scan_get_status()?;
+        if status != LgwSpectralScanStatus::LGW_SPECTRAL_SCAN_STATUS_COMPLETED {
+            error!("ERROR: spectral scan results not available (status:{:?})\n", status);
+            anyhow::bail!("spectral scan results not available (status:{:?})", status);
+        }
+        let rssi_offset = self.ctx.sx1261_cfg.rssi_offset;
+        self.sx1261_spectral_scan_get_results(rssi_offset).context("failed to get spectral scan results")
+    }
+
+    fn lgw_spectral_scan_abort(&mut self) -> Result<()> {
+        self.sx1261_spectral_scan_abort().context("failed to abort spectral scan")
+    }
+
+    /* Scan nb_chan frequencies, step_hz apart, waiting for each scan to complete */
+    fn lgw_spectral_scan_sweep(&mut self, freq_start_hz: u32, nb_chan: u32, step_hz: u32, nb_scan: u16) -> Result<Vec<LgwSpectralScanResult>> {
+        let timeout = scan_duration(nb_scan) + SCAN_MARGIN;
+        let mut results = Vec::with_capacity(nb_chan as usize);
+
+        for i in 0 .. nb_chan {
+            let freq_hz = freq_start_hz + i * step_hz;
+            self.lgw_spectral_scan_start(freq_hz, nb_scan)?;
+
+            let start = Instant::now();
+            loop {
+                match self.lgw_spectral_scan_get_status()? {
+                    LgwSpectralScanStatus::LGW_SPECTRAL_SCAN_STATUS_COMPLETED => break,
+                    LgwSpectralScanStatus::LGW_SPECTRAL_SCAN_STATUS_ABORTED => {
+                        error!("ERROR: spectral scan at {}Hz aborted\n", freq_hz);
+                        return Err(Error::SpectralScan { freq_hz, reason: "aborted" }.into());
+                    }
+                    _ if start.elapsed() > timeout => {
+                        error!("ERROR: spectral scan at {}Hz timed out\n", freq_hz);
+                        self.lgw_spectral_scan_abort()?;
+                        return Err(Error::SpectralScan { freq_hz, reason: "timeout" }.into());
+                    }
+                    _ => wait_ms(SCAN_STATUS_POLL_MS),
+                }
+            }
+
+            let (levels_dbm, counts) = self.lgw_spectral_scan_get_results()?;
+            results.push(LgwSpectralScanResult { freq_hz, levels_dbm, results: counts });
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::loragw_conf::LgwGlobalConf;
+    use crate::hal::mcu::sim::{SimConcentrator, SIM_SX1261_PRAM_VERSION};
+    use crate::hal::{LgwHal, LgwSx1261Pram};
+
+    const GLOBAL_CONF: &str = r#"{"SX130x_conf": {
+        "com_path": "/dev/ttyACM0", "clksrc": 0, "lorawan_public": true,
+        "radio_0": {"enable": true, "type": "SX1250", "freq": 867500000, "rssi_offset": -215.4, "tx_enable": false},
+        "radio_1": {"enable": true, "type": "SX1250", "freq": 868500000, "rssi_offset": -215.4, "tx_enable": false}
+    }}"#;
+
+    #[test]
+    fn scan_requires_pram() {
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        LgwGlobalConf::parse(GLOBAL_CONF).unwrap().apply(&mut hal).unwrap();
+        hal.lgw_start().unwrap();
+
+        /* SX1261 enabled behind lgw_start, its PRAM patch was never loaded */
+        hal.ctx.sx1261_cfg.enable = true;
+        let err = hal.lgw_spectral_scan_start(868_100_000, 100).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::SpectralScan { freq_hz: 868_100_000, .. })));
+        hal.lgw_stop().unwrap();
+
+        /* lgw_start loads the patch when the SX1261 is enabled */
+        hal.lgw_sx1261_set_pram(LgwSx1261Pram { words: (0 .. 386).collect(), version: SIM_SX1261_PRAM_VERSION.to_string() }).unwrap();
+        hal.lgw_start().unwrap();
+        assert!(hal.ctx.sx1261_pram_loaded);
+        let results = hal.lgw_spectral_scan_sweep(868_100_000, 2, 200_000, 100).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.results.iter().map(|&n| n as u32).sum::<u32>() == 100));
+
+        hal.lgw_stop().unwrap();
+        assert!(!hal.ctx.sx1261_pram_loaded);
+    }
+}
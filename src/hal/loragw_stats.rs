@@ -0,0 +1,271 @@
+/*
+    RX/TX statistics counters.
+
+    Received packets are counted per (IF chain, modem, datarate) and by CRC status, TX requests
+    by result, and the RX buffer keeps its own counters of re-synchronizations and corrupted
+    packets. Counters run from the last reset; lgw_stats_reset returns a snapshot and starts a
+    new period, which is how the "stat" report of the packet forwarder is built:
+
+        let stats = hal.lgw_stats_reset();
+        let bad = stats.rx_sum(|k| k.if_chain == 3).crc_bad;
+*/
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+
+use super::error::Error;
+use super::loragw_sx1302::SX1302_FSK_MODEM_ID;
+use super::{Hal, LgwPktRx, STAT_CRC_BAD, STAT_CRC_OK, STAT_NO_CRC};
+
+/**
+@struct LgwRxStatKey
+@brief Breakdown of the RX counters
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LgwRxStatKey {
+    pub if_chain: u8,
+    pub modem_id: u8,
+    pub datarate: u32,      //*!> SF for LoRa, bps for FSK */
+}
+
+/**
+@struct LgwRxCounters
+@brief Received packets by CRC status
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LgwRxCounters {
+    pub nb: u64,            //*!> packets received */
+    pub crc_ok: u64,        //*!> packets with STAT_CRC_OK */
+    pub crc_bad: u64,       //*!> packets with STAT_CRC_BAD */
+    pub no_crc: u64,        //*!> packets with STAT_NO_CRC */
+}
+
+impl LgwRxCounters {
+    fn add(&mut self, other: &LgwRxCounters) {
+        self.nb += other.nb;
+        self.crc_ok += other.crc_ok;
+        self.crc_bad += other.crc_bad;
+        self.no_crc += other.no_crc;
+    }
+}
+
+/**
+@struct LgwTxCounters
+@brief lgw_send calls by result
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LgwTxCounters {
+    pub ok: u64,            //*!> packets accepted by the SX1302 */
+    pub rejected: u64,      //*!> invalid packets, or concentrator not started */
+    pub lbt_blocked: u64,   //*!> packets not allowed by LBT */
+    pub failed: u64,        //*!> packets that failed to be programmed */
+}
+
+/**
+@struct LgwRxBufferStats
+@brief Anomalies met while reading the SX1302 RX buffer
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LgwRxBufferStats {
+    pub resync: u64,            //*!> fetches that did not start on a packet syncword */
+    pub discarded: u64,         //*!> fetches discarded, no complete packet or no syncword */
+    pub truncated: u64,         //*!> packets cut at the end of the buffer */
+    pub checksum_errors: u64,   //*!> packets with a wrong metadata checksum */
+}
+
+/**
+@struct LgwStats
+@brief Snapshot of the counters since the last reset
+*/
+#[derive(Debug, Clone)]
+pub struct LgwStats {
+    pub since: SystemTime,                              //*!> start of the counting period */
+    pub rx: BTreeMap<LgwRxStatKey, LgwRxCounters>,      //*!> packets returned by lgw_receive */
+    pub rx_dropped: u64,                                //*!> packets fetched but not parsed */
+    pub rx_buffer: LgwRxBufferStats,
+    pub tx: LgwTxCounters,
+}
+
+impl Default for LgwStats {
+    fn default() -> Self {
+        Self {
+            since: SystemTime::now(),
+            rx: BTreeMap::new(),
+            rx_dropped: 0,
+            rx_buffer: Default::default(),
+            tx: Default::default(),
+        }
+    }
+}
+
+impl LgwStats {
+    /* Length of the counting period */
+    pub fn period(&self) -> Duration {
+        self.since.elapsed().unwrap_or_default()
+    }
+
+    /* RX counters summed over the keys selected by filter */
+    pub fn rx_sum<F: Fn(&LgwRxStatKey) -> bool>(&self, filter: F) -> LgwRxCounters {
+        let mut sum = LgwRxCounters::default();
+        for counters in self.rx.iter().filter(|(k, _)| filter(k)).map(|(_, c)| c) {
+            sum.add(counters);
+        }
+        sum
+    }
+
+    pub fn rx_total(&self) -> LgwRxCounters {
+        self.rx_sum(|_| true)
+    }
+
+    pub(crate) fn record_rx(&mut self, pkt: &LgwPktRx) {
+        let key = LgwRxStatKey { if_chain: pkt.if_chain, modem_id: pkt.modem_id, datarate: pkt.datarate };
+        let counters = self.rx.entry(key).or_default();
+        counters.nb += 1;
+        match pkt.status {
+            STAT_CRC_OK => counters.crc_ok += 1,
+            STAT_CRC_BAD => counters.crc_bad += 1,
+            STAT_NO_CRC => counters.no_crc += 1,
+            _ => {}
+        }
+    }
+
+    pub(crate) fn record_tx(&mut self, result: &Result<()>) {
+        let Err(e) = result else {
+            self.tx.ok += 1;
+            return;
+        };
+        match e.downcast_ref::<Error>() {
+            Some(Error::TxRejected { .. }) | Some(Error::ConcentratorNotStarted) => self.tx.rejected += 1,
+            Some(Error::LbtNotAllowed { .. }) => self.tx.lbt_blocked += 1,
+            _ => self.tx.failed += 1,
+        }
+    }
+}
+
+impl fmt::Display for LgwStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.rx_total();
+        writeln!(f, "### statistics over the last {}s ###", self.period().as_secs())?;
+        writeln!(f, "# RX received: {}, CRC OK: {}, CRC BAD: {}, NO CRC: {}, dropped: {}", total.nb, total.crc_ok, total.crc_bad, total.no_crc, self.rx_dropped)?;
+        for (k, c) in self.rx.iter() {
+            let dr = if k.modem_id == SX1302_FSK_MODEM_ID { format!("{}bps", k.datarate) } else { format!("SF{}", k.datarate) };
+            writeln!(f, "#   if_chain {} modem {} {}: {} ({} OK, {} BAD, {} NO CRC)", k.if_chain, k.modem_id, dr, c.nb, c.crc_ok, c.crc_bad, c.no_crc)?;
+        }
+        writeln!(f, "# RX buffer: {} re-sync, {} discarded, {} truncated, {} checksum errors",
+            self.rx_buffer.resync, self.rx_buffer.discarded, self.rx_buffer.truncated, self.rx_buffer.checksum_errors)?;
+        write!(f, "# TX ok: {}, rejected: {}, LBT blocked: {}, failed: {}", self.tx.ok, self.tx.rejected, self.tx.lbt_blocked, self.tx.failed)
+    }
+}
+
+impl Hal {
+    /* Snapshot of the counters since the last reset */
+    pub fn lgw_stats(&self) -> LgwStats {
+        let mut stats = self.stats.clone();
+        stats.rx_buffer = self.sx1302.rx_buffer.stats;
+        stats
+    }
+
+    /* Snapshot of the counters, then restart counting from 0 */
+    pub fn lgw_stats_reset(&mut self) -> LgwStats {
+        let stats = self.lgw_stats();
+        self.stats = LgwStats::default();
+        self.sx1302.rx_buffer.stats = LgwRxBufferStats::default();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use crate::hal::loragw_conf::LgwGlobalConf;
+    use crate::hal::mcu::sim::{SimConcentrator, SimRxPacket};
+    use crate::hal::{LgwHal, LgwPktTx, Modulation, BW_125KHZ, CR_LORA_4_5};
+
+    const GLOBAL_CONF: &str = r#"{"SX130x_conf": {
+        "com_path": "/dev/ttyACM0", "clksrc": 0, "lorawan_public": true,
+        "radio_0": {
+            "enable": true, "type": "SX1250", "freq": 867500000, "rssi_offset": -215.4, "tx_enable": true,
+            "tx_gain_lut": [{"rf_power": 14, "pa_gain": 0, "pwr_idx": 17}]
+        },
+        "radio_1": {"enable": true, "type": "SX1250", "freq": 868500000, "rssi_offset": -215.4, "tx_enable": false},
+        "chan_multiSF_0": {"enable": true, "radio": 1, "if": -400000},
+        "chan_multiSF_3": {"enable": true, "radio": 0, "if": -400000}
+    }}"#;
+
+    fn receive(hal: &mut Hal, nb: usize) -> Vec<LgwPktRx> {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut pkts = Vec::new();
+        while pkts.len() < nb && Instant::now() < deadline {
+            pkts.extend(hal.lgw_receive().unwrap());
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(pkts.len(), nb);
+        pkts
+    }
+
+    #[test]
+    fn counters() {
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        LgwGlobalConf::parse(GLOBAL_CONF).unwrap().apply(&mut hal).unwrap();
+        hal.lgw_start().unwrap();
+        hal.lgw_stats_reset();
+
+        /* per IF chain/datarate and CRC status */
+        sim.inject_rx(&SimRxPacket { if_chain: 3, datarate: 9, payload: vec![1; 10], ..Default::default() }).unwrap();
+        sim.inject_rx(&SimRxPacket { if_chain: 3, datarate: 9, crc_error: true, payload: vec![2; 10], ..Default::default() }).unwrap();
+        sim.inject_rx(&SimRxPacket { if_chain: 0, datarate: 7, crc_en: false, payload: vec![3; 10], ..Default::default() }).unwrap();
+        receive(&mut hal, 3);
+
+        /* noise in front of a packet */
+        sim.inject_rx_raw(&[0x00, 0x11, 0x22]);
+        sim.inject_rx(&SimRxPacket { if_chain: 3, datarate: 9, payload: vec![4; 10], ..Default::default() }).unwrap();
+        receive(&mut hal, 1);
+
+        /* corrupted metadata */
+        sim.inject_rx(&SimRxPacket { if_chain: 3, datarate: 9, checksum_error: true, payload: vec![5; 10], ..Default::default() }).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while hal.lgw_stats().rx_buffer.checksum_errors == 0 && Instant::now() < deadline {
+            assert!(hal.lgw_receive().unwrap().is_empty());
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        let mut tx = LgwPktTx {
+            freq_hz: 869_525_000,
+            rf_power: 14,
+            modulation: Modulation::LORA,
+            bandwidth: BW_125KHZ,
+            datarate: 7,
+            coderate: CR_LORA_4_5,
+            preamble: 8,
+            size: 4,
+            ..Default::default()
+        };
+        hal.lgw_send(&tx).unwrap();
+        tx.datarate = 13;
+        assert!(hal.lgw_send(&tx).is_err());
+
+        let stats = hal.lgw_stats();
+        let sf9 = LgwRxStatKey { if_chain: 3, modem_id: 3, datarate: 9 };
+        let sf7 = LgwRxStatKey { if_chain: 0, modem_id: 0, datarate: 7 };
+        assert_eq!(stats.rx.keys().copied().collect::<Vec<_>>(), [sf7, sf9]);
+        assert_eq!(stats.rx[&sf9], LgwRxCounters { nb: 3, crc_ok: 2, crc_bad: 1, no_crc: 0 });
+        assert_eq!(stats.rx[&sf7], LgwRxCounters { nb: 1, crc_ok: 0, crc_bad: 0, no_crc: 1 });
+        assert_eq!(stats.rx_total(), LgwRxCounters { nb: 4, crc_ok: 2, crc_bad: 1, no_crc: 1 });
+        assert_eq!((stats.rx_buffer.resync, stats.rx_buffer.checksum_errors), (1, 1));
+        assert_eq!(stats.tx, LgwTxCounters { ok: 1, rejected: 1, lbt_blocked: 0, failed: 0 });
+        assert!(stats.to_string().contains("# RX buffer: 1 re-sync"), "{}", stats);
+
+        /* the reset returns the same snapshot, then counts from 0 */
+        let snapshot = hal.lgw_stats_reset();
+        assert_eq!((snapshot.rx, snapshot.rx_buffer, snapshot.tx), (stats.rx, stats.rx_buffer, stats.tx));
+        let stats = hal.lgw_stats();
+        assert!(stats.rx.is_empty());
+        assert_eq!((stats.rx_dropped, stats.rx_buffer, stats.tx), (0, LgwRxBufferStats::default(), LgwTxCounters::default()));
+        hal.lgw_stop().unwrap();
+    }
+}
@@ -1,7 +1,8 @@
-use anyhow::{anyhow,Result};
+use anyhow::Result;
 use tracing::{debug, error, info};
 use super::helper::wait_ms;
 use super::{loragw_com::LgwSpiMuxTarget, mcu::command::{ECmdSpiTarget, MCU_SPI_REQ_TYPE_READ_WRITE}, Hal, LGW_RF_CHAIN_NB};
+use super::error::Error;
 
 
 const    STDBY_RC :u8               = 0x00;
@@ -76,7 +77,7 @@ impl LoragwSx1250Trait for Hal {
        
 
         if data.is_empty() {
-            return Err(anyhow!("LGW_REG_ERR"))
+            return Err(Error::config("size", size, "empty SX1250 command payload").into())
         }
     
         /* wait BUSY */
@@ -96,9 +97,9 @@ impl LoragwSx1250Trait for Hal {
         for i in 0 .. size {
             in_out_buf[i + 7] = data[i];
         }
-        if let Err(_) = self.mcu.mcu_spi_write( &mut in_out_buf ) {
+        if let Err(e) = self.mcu.mcu_spi_write( &mut in_out_buf ) {
             error!("ERROR: USB SX1250 READ FAILURE\n");
-            return Err(anyhow!("LGW_COM_ERR"))
+            return Err(e)
         }
         else{
             debug!("Note: USB SX1250 read success\n");
@@ -116,12 +117,12 @@ impl LoragwSx1250Trait for Hal {
         /* checking input parameters */
         if rf_chain >= LGW_RF_CHAIN_NB {
             error!("ERROR: INVALID RF_CHAIN\n");
-            return Err(anyhow!("LGW_REG_ERROR"))
+            return Err(Error::config("rf_chain", rf_chain, "invalid RF chain").into())
         }
 
-        if let Err(_) = self.sx1250_r( if rf_chain == 0 { LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_RADIOA } else{ LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_RADIOB}, op_code, data, size) {
+        if let Err(e) = self.sx1250_r( if rf_chain == 0 { LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_RADIOA } else{ LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_RADIOB}, op_code, data, size) {
             error!("ERROR: COM ERROR DURING RADIO REGISTER READ\n");
-            return Err(anyhow!("LGW_REG_ERROR"))
+            return Err(e)
         }
 
         Ok(())
@@ -133,7 +134,7 @@ impl LoragwSx1250Trait for Hal {
         
 
         if data.is_empty() {
-            return Err(anyhow!("LGW_COM_ERR"))
+            return Err(Error::config("size", size, "empty SX1250 command payload").into())
         }
 
         /* wait BUSY */
@@ -167,12 +168,12 @@ impl LoragwSx1250Trait for Hal {
         /* checking input parameters */
         if rf_chain >= LGW_RF_CHAIN_NB {
             error!("ERROR: INVALID RF_CHAIN\n");
-            return Err(anyhow!("LGW_REG_ERROR"));
+            return Err(Error::config("rf_chain", rf_chain, "invalid RF chain").into());
         }
     
-        if let Err(_) = self.sx1250_w( if rf_chain == 0 {  LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_RADIOA } else { LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_RADIOB}, op_code, data, size) {
+        if let Err(e) = self.sx1250_w( if rf_chain == 0 {  LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_RADIOA } else { LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_RADIOB}, op_code, data, size) {
             error!("ERROR: COM ERROR DURING RADIO REGISTER WRITE\n");
-            return Err(anyhow!("LGW_REG_ERROR"));
+            return Err(e);
         }
         Ok(())
     }
@@ -189,13 +190,14 @@ impl LoragwSx1250Trait for Hal {
         buff[0] = 0x00;
         let e2 = self.sx1250_reg_r(Sx1250OpCode::GET_STATUS, &mut buff, 1, rf_chain);
 
-        if e1.is_err() || e2.is_err() {
-            return Err(anyhow!("LGW_COM_ERR"))
+        if let Err(e) = e1.and(e2) {
+            error!("ERROR: Failed to get SX1250_{:} status\n", rf_chain);
+            return Err(e.context(Error::RadioSetup { rf_chain, reason: "communication failure" }));
         }
 
         if (take_n_bits_from!(buff[0], 4, 3)) as u8 != 0x02 {
             error!("ERROR: Failed to set SX1250_{:} in STANDBY_RC mode\n", rf_chain);
-            return Err(anyhow!("LGW_REG_ERROR"));
+            return Err(Error::RadioSetup { rf_chain, reason: "failed to set STANDBY_RC mode" }.into());
         }
 
         /* Run all calibrations (TCXO) */
@@ -213,7 +215,7 @@ impl LoragwSx1250Trait for Hal {
         self.sx1250_reg_r(Sx1250OpCode::GET_STATUS, &mut buff, 1, rf_chain)?;
         if take_n_bits_from!(buff[0], 4, 3) as u8 != 0x03 {
             error!("ERROR: Failed to set SX1250_{:} in STANDBY_XOSC mode\n", rf_chain);
-            return Err(anyhow!("LGW_REG_ERROR"))
+            return Err(Error::RadioSetup { rf_chain, reason: "failed to set STANDBY_XOSC mode" }.into())
         }
 
         /* Set Bitrate to maximum (to lower TX to FS switch time) */
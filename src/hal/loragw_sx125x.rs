@@ -0,0 +1,359 @@
+/*
+    SX1255/SX1257 radio driver.
+
+    The SX125x radios sit behind the SX1302 SPI mux, like the SX1250, but expose a plain register
+    map instead of a command interface. A raw frame is [spi_mux_target, RnW|address, data], the
+    register value being clocked out in place of the data byte on reads.
+*/
+
+use anyhow::Result;
+use tracing::{debug, error, info};
+
+use super::error::Error;
+use super::helper::wait_ms;
+use super::loragw_com::LgwSpiMuxTarget;
+use super::mcu::command::{ECmdSpiTarget, MCU_SPI_REQ_TYPE_READ_WRITE};
+use super::{Hal, LgwRadioType, LGW_RF_CHAIN_NB};
+
+/**
+@struct RadioReg
+@brief Register field of the SX125x register map
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct RadioReg {
+    addr: u8,   //*!> register address */
+    offs: u8,   //*!> position of the LSB of the field */
+    leng: u8,   //*!> number of bits of the field */
+}
+
+const fn reg(addr: u8, offs: u8, leng: u8) -> RadioReg {
+    RadioReg { addr, offs, leng }
+}
+
+const SX125X_REG_MODE: RadioReg =                       reg(0, 0, 8);
+const SX125X_REG_FRF_RX_MSB: RadioReg =                 reg(1, 0, 8);
+const SX125X_REG_FRF_RX_MID: RadioReg =                 reg(2, 0, 8);
+const SX125X_REG_FRF_RX_LSB: RadioReg =                 reg(3, 0, 8);
+const SX125X_REG_VERSION: RadioReg =                    reg(7, 0, 8);
+const SX125X_REG_TX_GAIN_DAC_GAIN: RadioReg =           reg(8, 4, 3);
+const SX125X_REG_TX_GAIN_MIX_GAIN: RadioReg =           reg(8, 0, 4);
+const SX125X_REG_TX_BW_PLL_BW: RadioReg =               reg(10, 5, 2);
+const SX125X_REG_TX_BW_ANA_BW: RadioReg =               reg(10, 0, 5);
+const SX125X_REG_TX_DAC_BW: RadioReg =                  reg(11, 0, 8);
+const SX125X_REG_RX_ANA_GAIN_LNA_GAIN: RadioReg =       reg(12, 5, 3);
+const SX125X_REG_RX_ANA_GAIN_BB_GAIN: RadioReg =        reg(12, 1, 4);
+const SX125X_REG_RX_ANA_GAIN_LNA_ZIN: RadioReg =        reg(12, 0, 1);
+const SX125X_REG_RX_BW_ADC_BW: RadioReg =               reg(13, 5, 3);
+const SX125X_REG_RX_BW_ADC_TRIM: RadioReg =             reg(13, 2, 3);
+const SX125X_REG_RX_BW_BB_BW: RadioReg =                reg(13, 0, 2);
+const SX125X_REG_RX_PLL_BW_PLL_BW: RadioReg =           reg(14, 1, 2);
+const SX125X_REG_RX_PLL_BW_ADC_TEMP_EN: RadioReg =      reg(14, 0, 1);
+const SX125X_REG_CLK_SELECT: RadioReg =                 reg(16, 0, 8);
+const SX125X_REG_MODE_STATUS_RX_PLL_LOCKED: RadioReg =  reg(17, 1, 1);
+const SX1257_REG_XOSC_TEST_DISABLE: RadioReg =          reg(38, 4, 3);
+const SX1257_REG_XOSC_TEST_GM_STARTUP: RadioReg =       reg(38, 0, 4);
+const SX1255_REG_XOSC_TEST_DISABLE: RadioReg =          reg(40, 4, 3);
+const SX1255_REG_XOSC_TEST_GM_STARTUP: RadioReg =       reg(40, 0, 4);
+
+const SX125X_32MHZ_FRAC: u32 =      15625;  /* irreductible fraction for PLL register caculation */
+
+const SX125X_TX_DAC_CLK_SEL: u8 =   0;      /* 0:int, 1:ext */
+const SX125X_TX_DAC_GAIN: u8 =      2;      /* 3:0, 2:-3, 1:-6, 0:-9 dBFS (default 2) */
+const SX125X_TX_MIX_GAIN: u8 =      14;     /* -38 + 2*TxMixGain dB (default 14) */
+const SX125X_TX_PLL_BW: u8 =        1;      /* 0:75, 1:150, 2:225, 3:300 kHz (default 3) */
+const SX125X_TX_ANA_BW: u8 =        0;      /* 17.5 / 2*(41-TxAnaBw) MHz (default 0) */
+const SX125X_TX_DAC_BW: u8 =        5;      /* 24 + 8*TxDacBw Nb FIR taps (default 2) */
+const SX125X_RX_LNA_GAIN: u8 =      1;      /* 1 to 6, 1 highest gain */
+const SX125X_RX_BB_GAIN: u8 =       15;     /* 0 to 15 , 15 highest gain */
+const SX125X_LNA_ZIN: u8 =          0;      /* 0:50, 1:200 Ohms (default 1) */
+const SX125X_RX_ADC_BW: u8 =        7;      /* 0 to 7, 2:100<BW<200, 5:200<BW<400,7:400<BW kHz SSB (default 7) */
+const SX125X_RX_ADC_TRIM: u8 =      6;      /* 0 to 7, 6 for 32MHz ref, 5 for 36MHz ref */
+const SX125X_RX_BB_BW: u8 =         0;      /* 0:750, 1:500, 2:375; 3:250 kHz SSB (default 1, max 3) */
+const SX125X_RX_PLL_BW: u8 =        0;      /* 0:75, 1:150, 2:225, 3:300 kHz (default 3, max 3) */
+const SX125X_ADC_TEMP: u8 =         0;      /* ADC temperature measurement mode (default 0) */
+const SX125X_XOSC_GM_STARTUP: u8 =  13;     /* (default 13) */
+const SX125X_XOSC_DISABLE: u8 =     2;      /* Disable of Xtal Oscillator blocks bit0:regulator, bit1:core(gm), bit2:amplifier */
+
+const PLL_LOCK_MAX_ATTEMPTS: u32 =  5;
+
+/* RX PLL register value (MSB, then MID/LSB) for a frequency */
+pub(crate) fn sx125x_freq_to_reg(radio_type: LgwRadioType, freq_hz: u32) -> Option<(u32, u32)> {
+    match radio_type {
+        LgwRadioType::LGW_RADIO_TYPE_SX1255 => Some((
+            freq_hz / (SX125X_32MHZ_FRAC << 7),
+            ((freq_hz % (SX125X_32MHZ_FRAC << 7)) << 9) / SX125X_32MHZ_FRAC,
+        )),
+        LgwRadioType::LGW_RADIO_TYPE_SX1257 => Some((
+            freq_hz / (SX125X_32MHZ_FRAC << 8),
+            ((freq_hz % (SX125X_32MHZ_FRAC << 8)) << 8) / SX125X_32MHZ_FRAC,
+        )),
+        _ => None,
+    }
+}
+
+fn spi_mux_target(rf_chain: u8) -> LgwSpiMuxTarget {
+    if rf_chain == 0 { LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_RADIOA } else { LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_RADIOB }
+}
+
+pub trait LoragwSx125xTrait {
+    fn sx125x_com_w(&mut self, spi_mux_target: LgwSpiMuxTarget, address: u8, data: u8) -> Result<()>;
+    fn sx125x_com_r(&mut self, spi_mux_target: LgwSpiMuxTarget, address: u8) -> Result<u8>;
+    fn sx125x_reg_w(&mut self, rf_chain: u8, r: RadioReg, value: u8) -> Result<()>;
+    fn sx125x_reg_r(&mut self, rf_chain: u8, r: RadioReg) -> Result<u8>;
+    fn sx125x_setup(&mut self, rf_chain: u8, rf_clkout: u8, rf_enable: bool, radio_type: LgwRadioType, freq_hz: u32) -> Result<()>;
+}
+
+impl Hal {
+    /* [req metadata (5 bytes)][spi_mux_target][RnW|address][data] */
+    fn sx125x_com(&mut self, spi_mux_target: LgwSpiMuxTarget, address: u8, data: u8) -> Result<u8> {
+        let mut in_out_buf = [0u8; 8];
+
+        in_out_buf[0] = 0; /* Req ID */
+        in_out_buf[1] = MCU_SPI_REQ_TYPE_READ_WRITE; /* Req type */
+        in_out_buf[2] = ECmdSpiTarget::MCU_SPI_TARGET_SX1302 as u8; /* MCU -> SX1302 */
+        in_out_buf[3] = 0; /* spi_mux_target + address + data */
+        in_out_buf[4] = 3;
+        /* RAW SPI frame */
+        in_out_buf[5] = spi_mux_target as u8; /* SX1302 -> RADIO_A or RADIO_B */
+        in_out_buf[6] = address;
+        in_out_buf[7] = data;
+
+        self.mcu.mcu_spi_write(&mut in_out_buf)?;
+
+        Ok(in_out_buf[7])
+    }
+}
+
+impl LoragwSx125xTrait for Hal {
+    fn sx125x_com_w(&mut self, spi_mux_target: LgwSpiMuxTarget, address: u8, data: u8) -> Result<()> {
+        if let Err(e) = self.sx125x_com(spi_mux_target, 0x80 | (address & 0x7F), data) {
+            error!("ERROR: USB SX125X WRITE FAILURE\n");
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn sx125x_com_r(&mut self, spi_mux_target: LgwSpiMuxTarget, address: u8) -> Result<u8> {
+        match self.sx125x_com(spi_mux_target, address & 0x7F, 0x00) {
+            Ok(data) => Ok(data),
+            Err(e) => {
+                error!("ERROR: USB SX125X READ FAILURE\n");
+                Err(e)
+            }
+        }
+    }
+
+    /* Fields narrower than the register are written with a read-modify-write */
+    fn sx125x_reg_w(&mut self, rf_chain: u8, r: RadioReg, value: u8) -> Result<()> {
+        if rf_chain >= LGW_RF_CHAIN_NB {
+            error!("ERROR: INVALID RF_CHAIN\n");
+            return Err(Error::RadioSetup { rf_chain, reason: "invalid RF chain" }.into());
+        }
+
+        let data = if r.leng == 8 && r.offs == 0 {
+            value
+        } else {
+            let mask = (((1u16 << r.leng) - 1) as u8) << r.offs;
+            let current = self.sx125x_com_r(spi_mux_target(rf_chain), r.addr)
+                .map_err(|e| Error::RadioRegisterCom { rf_chain, address: r.addr, source: e })?;
+            (current & !mask) | ((value << r.offs) & mask)
+        };
+
+        self.sx125x_com_w(spi_mux_target(rf_chain), r.addr, data)
+            .map_err(|e| Error::RadioRegisterCom { rf_chain, address: r.addr, source: e }.into())
+    }
+
+    fn sx125x_reg_r(&mut self, rf_chain: u8, r: RadioReg) -> Result<u8> {
+        if rf_chain >= LGW_RF_CHAIN_NB {
+            error!("ERROR: INVALID RF_CHAIN\n");
+            return Err(Error::RadioSetup { rf_chain, reason: "invalid RF chain" }.into());
+        }
+
+        let data = self.sx125x_com_r(spi_mux_target(rf_chain), r.addr)
+            .map_err(|e| Error::RadioRegisterCom { rf_chain, address: r.addr, source: e })?;
+        Ok((data >> r.offs) & (((1u16 << r.leng) - 1) as u8))
+    }
+
+    fn sx125x_setup(&mut self, rf_chain: u8, rf_clkout: u8, rf_enable: bool, radio_type: LgwRadioType, freq_hz: u32) -> Result<()> {
+        let Some((part_int, part_frac)) = sx125x_freq_to_reg(radio_type, freq_hz) else {
+            error!("ERROR: UNEXPECTED VALUE {} FOR RADIO TYPE\n", radio_type);
+            return Err(Error::config("radio_type", radio_type, "not a SX125x radio").into());
+        };
+
+        /* Get version to identify SX1255/57 silicon revision */
+        let version = self.sx125x_reg_r(rf_chain, SX125X_REG_VERSION)?;
+        debug!("Note: SX125x #{} version register returned 0x{:02X}", rf_chain, version);
+
+        /* General radio setup, only the clock source radio outputs its clock to the SX1302 */
+        if rf_clkout == rf_chain {
+            self.sx125x_reg_w(rf_chain, SX125X_REG_CLK_SELECT, SX125X_TX_DAC_CLK_SEL + 2)?;
+            debug!("Note: SX125x #{} clock output enabled", rf_chain);
+        } else {
+            self.sx125x_reg_w(rf_chain, SX125X_REG_CLK_SELECT, SX125X_TX_DAC_CLK_SEL)?;
+            debug!("Note: SX125x #{} clock output disabled", rf_chain);
+        }
+
+        match radio_type {
+            LgwRadioType::LGW_RADIO_TYPE_SX1255 => {
+                self.sx125x_reg_w(rf_chain, SX1255_REG_XOSC_TEST_GM_STARTUP, SX125X_XOSC_GM_STARTUP)?;
+                self.sx125x_reg_w(rf_chain, SX1255_REG_XOSC_TEST_DISABLE, SX125X_XOSC_DISABLE)?;
+            }
+            _ => {
+                self.sx125x_reg_w(rf_chain, SX1257_REG_XOSC_TEST_GM_STARTUP, SX125X_XOSC_GM_STARTUP)?;
+                self.sx125x_reg_w(rf_chain, SX1257_REG_XOSC_TEST_DISABLE, SX125X_XOSC_DISABLE)?;
+            }
+        }
+
+        if !rf_enable {
+            debug!("Note: SX125x #{} kept in standby mode", rf_chain);
+            return Ok(());
+        }
+
+        /* Tx gain and trim */
+        self.sx125x_reg_w(rf_chain, SX125X_REG_TX_GAIN_MIX_GAIN, SX125X_TX_MIX_GAIN)?;
+        self.sx125x_reg_w(rf_chain, SX125X_REG_TX_GAIN_DAC_GAIN, SX125X_TX_DAC_GAIN)?;
+        self.sx125x_reg_w(rf_chain, SX125X_REG_TX_BW_ANA_BW, SX125X_TX_ANA_BW)?;
+        self.sx125x_reg_w(rf_chain, SX125X_REG_TX_BW_PLL_BW, SX125X_TX_PLL_BW)?;
+        self.sx125x_reg_w(rf_chain, SX125X_REG_TX_DAC_BW, SX125X_TX_DAC_BW)?;
+
+        /* Rx gain and trim */
+        self.sx125x_reg_w(rf_chain, SX125X_REG_RX_ANA_GAIN_LNA_ZIN, SX125X_LNA_ZIN)?;
+        self.sx125x_reg_w(rf_chain, SX125X_REG_RX_ANA_GAIN_BB_GAIN, SX125X_RX_BB_GAIN)?;
+        self.sx125x_reg_w(rf_chain, SX125X_REG_RX_ANA_GAIN_LNA_GAIN, SX125X_RX_LNA_GAIN)?;
+        self.sx125x_reg_w(rf_chain, SX125X_REG_RX_BW_BB_BW, SX125X_RX_BB_BW)?;
+        self.sx125x_reg_w(rf_chain, SX125X_REG_RX_BW_ADC_TRIM, SX125X_RX_ADC_TRIM)?;
+        self.sx125x_reg_w(rf_chain, SX125X_REG_RX_BW_ADC_BW, SX125X_RX_ADC_BW)?;
+        self.sx125x_reg_w(rf_chain, SX125X_REG_RX_PLL_BW_ADC_TEMP_EN, SX125X_ADC_TEMP)?;
+        self.sx125x_reg_w(rf_chain, SX125X_REG_RX_PLL_BW_PLL_BW, SX125X_RX_PLL_BW)?;
+
+        /* Set RX PLL frequency */
+        self.sx125x_reg_w(rf_chain, SX125X_REG_FRF_RX_MSB, part_int as u8)?;
+        self.sx125x_reg_w(rf_chain, SX125X_REG_FRF_RX_MID, (part_frac >> 8) as u8)?;
+        self.sx125x_reg_w(rf_chain, SX125X_REG_FRF_RX_LSB, part_frac as u8)?;
+
+        /* Start and PLL lock */
+        for attempt in 1 ..= PLL_LOCK_MAX_ATTEMPTS {
+            self.sx125x_reg_w(rf_chain, SX125X_REG_MODE, 1)?;
+            self.sx125x_reg_w(rf_chain, SX125X_REG_MODE, 3)?;
+            debug!("Note: SX125x #{} PLL start (attempt {})", rf_chain, attempt);
+            wait_ms(1);
+            if self.sx125x_reg_r(rf_chain, SX125X_REG_MODE_STATUS_RX_PLL_LOCKED)? != 0 {
+                info!("INFO: SX125x #{} PLL locked ({}Hz)", rf_chain, freq_hz);
+                return Ok(());
+            }
+        }
+
+        error!("ERROR: FAIL TO LOCK PLL\n");
+        Err(Error::RadioSetup { rf_chain, reason: "PLL not locked" }.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::loragw_reg::LoragwRegTrait;
+    use crate::hal::loragw_sx1302::LorgwSx1302Trait;
+    use crate::hal::mcu::sim::SimConcentrator;
+
+    const RADIO_A: LgwSpiMuxTarget = LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_RADIOA;
+    const RADIO_B: LgwSpiMuxTarget = LgwSpiMuxTarget::LGW_SPI_MUX_TARGET_RADIOB;
+
+    fn connected_hal() -> (SimConcentrator, Hal) {
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        hal.lgw_connect().unwrap();
+        for rf_chain in 0 .. LGW_RF_CHAIN_NB {
+            hal.sx1302_radio_set_mode(rf_chain, LgwRadioType::LGW_RADIO_TYPE_SX1257).unwrap();
+        }
+        (sim, hal)
+    }
+
+    fn frf(hal: &mut Hal, rf_chain: u8) -> [u8; 3] {
+        [SX125X_REG_FRF_RX_MSB, SX125X_REG_FRF_RX_MID, SX125X_REG_FRF_RX_LSB].map(|r| hal.sx125x_reg_r(rf_chain, r).unwrap())
+    }
+
+    #[test]
+    fn freq_to_reg() {
+        /* SX1257: 32MHz / 2^19 step, SX1255: 32MHz / 2^20 step */
+        assert_eq!(sx125x_freq_to_reg(LgwRadioType::LGW_RADIO_TYPE_SX1257, 867_500_000), Some((216, 0xE000)));
+        assert_eq!(sx125x_freq_to_reg(LgwRadioType::LGW_RADIO_TYPE_SX1257, 868_100_000), Some((217, 1638)));
+        assert_eq!(sx125x_freq_to_reg(LgwRadioType::LGW_RADIO_TYPE_SX1257, 915_000_000), Some((228, 0xC000)));
+        assert_eq!(sx125x_freq_to_reg(LgwRadioType::LGW_RADIO_TYPE_SX1255, 433_500_000), Some((216, 0xC000)));
+        assert_eq!(sx125x_freq_to_reg(LgwRadioType::LGW_RADIO_TYPE_SX1255, 470_300_000), Some((235, 0x2666)));
+        assert_eq!(sx125x_freq_to_reg(LgwRadioType::LGW_RADIO_TYPE_SX1250, 867_500_000), None);
+
+        /* the register value is the frequency rounded down to the PLL step */
+        for (radio_type, shift) in [(LgwRadioType::LGW_RADIO_TYPE_SX1257, 19), (LgwRadioType::LGW_RADIO_TYPE_SX1255, 20)] {
+            for freq_hz in [433_175_000u32, 470_300_000, 863_000_000, 868_100_000, 923_300_000] {
+                let (int, frac) = sx125x_freq_to_reg(radio_type, freq_hz).unwrap();
+                let reg = ((int << 16) | frac) as u64;
+                let back = (reg * 32_000_000) >> shift;
+                assert!(back <= freq_hz as u64 && freq_hz as u64 - back < 62, "{:?} {}Hz: {}Hz", radio_type, freq_hz, back);
+            }
+        }
+    }
+
+    #[test]
+    fn reg_w_read_modify_write() {
+        let (_sim, mut hal) = connected_hal();
+        hal.sx125x_com_w(RADIO_A, 8, 0xA5).unwrap();
+
+        /* DAC gain: bits 4..6, mixer gain: bits 0..3 */
+        hal.sx125x_reg_w(0, SX125X_REG_TX_GAIN_DAC_GAIN, 5).unwrap();
+        assert_eq!(hal.sx125x_com_r(RADIO_A, 8).unwrap(), 0xD5);
+        hal.sx125x_reg_w(0, SX125X_REG_TX_GAIN_MIX_GAIN, 3).unwrap();
+        assert_eq!(hal.sx125x_com_r(RADIO_A, 8).unwrap(), 0xD3);
+        assert_eq!((hal.sx125x_reg_r(0, SX125X_REG_TX_GAIN_DAC_GAIN).unwrap(), hal.sx125x_reg_r(0, SX125X_REG_TX_GAIN_MIX_GAIN).unwrap()), (5, 3));
+
+        /* a value wider than the field does not spill over the neighbours */
+        hal.sx125x_reg_w(0, SX125X_REG_TX_GAIN_DAC_GAIN, 0xFF).unwrap();
+        assert_eq!(hal.sx125x_com_r(RADIO_A, 8).unwrap(), 0xF3);
+
+        /* whole register, the other radio untouched */
+        hal.sx125x_reg_w(1, SX125X_REG_CLK_SELECT, 2).unwrap();
+        assert_eq!((hal.sx125x_com_r(RADIO_A, 16).unwrap(), hal.sx125x_com_r(RADIO_B, 16).unwrap()), (0, 2));
+
+        let err = hal.sx125x_reg_w(2, SX125X_REG_MODE, 1).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::RadioSetup { rf_chain: 2, .. })));
+        assert!(hal.sx125x_reg_r(2, SX125X_REG_MODE).is_err());
+    }
+
+    #[test]
+    fn setup() {
+        let (_sim, mut hal) = connected_hal();
+
+        /* radio A gives the clock and receives, radio B is kept in standby */
+        hal.sx125x_setup(0, 0, true, LgwRadioType::LGW_RADIO_TYPE_SX1257, 867_500_000).unwrap();
+        hal.sx125x_setup(1, 0, false, LgwRadioType::LGW_RADIO_TYPE_SX1255, 433_500_000).unwrap();
+
+        assert_eq!(frf(&mut hal, 0), [216, 0xE0, 0x00]);
+        assert_eq!(hal.sx125x_com_r(RADIO_A, 16).unwrap(), 2);
+        assert_eq!(hal.sx125x_reg_r(0, SX1257_REG_XOSC_TEST_GM_STARTUP).unwrap(), SX125X_XOSC_GM_STARTUP);
+        assert_eq!(hal.sx125x_reg_r(0, SX125X_REG_TX_GAIN_MIX_GAIN).unwrap(), SX125X_TX_MIX_GAIN);
+        assert_eq!(hal.sx125x_reg_r(0, SX125X_REG_RX_BW_ADC_TRIM).unwrap(), SX125X_RX_ADC_TRIM);
+        assert_eq!(hal.sx125x_com_r(RADIO_A, 0).unwrap(), 3);
+        assert_eq!(hal.sx125x_reg_r(0, SX125X_REG_MODE_STATUS_RX_PLL_LOCKED).unwrap(), 1);
+
+        assert_eq!(frf(&mut hal, 1), [0, 0, 0]);
+        assert_eq!(hal.sx125x_com_r(RADIO_B, 16).unwrap(), 0);
+        assert_eq!(hal.sx125x_reg_r(1, SX1255_REG_XOSC_TEST_DISABLE).unwrap(), SX125X_XOSC_DISABLE);
+        assert_eq!(hal.sx125x_com_r(RADIO_B, 0).unwrap(), 0);
+
+        let err = hal.sx125x_setup(0, 0, true, LgwRadioType::LGW_RADIO_TYPE_SX1250, 867_500_000).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::InvalidConfig { param: "radio_type", .. })));
+    }
+
+    #[test]
+    fn pll_lock_retry() {
+        let (sim, mut hal) = connected_hal();
+
+        /* locks on the last attempt */
+        sim.set_pll_lock_failures(PLL_LOCK_MAX_ATTEMPTS - 1);
+        hal.sx125x_setup(0, 0, true, LgwRadioType::LGW_RADIO_TYPE_SX1257, 867_500_000).unwrap();
+
+        sim.set_pll_lock_failures(PLL_LOCK_MAX_ATTEMPTS);
+        let err = hal.sx125x_setup(0, 0, true, LgwRadioType::LGW_RADIO_TYPE_SX1257, 867_500_000).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::RadioSetup { rf_chain: 0, reason: "PLL not locked" })));
+        assert_eq!(hal.sx125x_reg_r(0, SX125X_REG_MODE_STATUS_RX_PLL_LOCKED).unwrap(), 0);
+    }
+}
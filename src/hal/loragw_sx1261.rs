@@ -0,0 +1,527 @@
+/*
+    SX1261 companion radio, used for Listen-Before-Talk and spectral scan.
+
+    The SX1261 is not behind the SX1302 SPI mux: the MCU talks to it directly through its own
+    SPI target (MCU_SPI_TARGET_SX1261). A raw frame is [op_code, data...], the chip status being
+    clocked out with the first data byte.
+
+    LBT and spectral scan rely on a patch loaded in the SX1261 program RAM (PRAM), which is lost
+    on reset: lgw_start loads it after the reset (the image given with lgw_sx1261_set_pram, or the
+    bundled one of sx1261_pram). The driver can be used without lgw_start and without the patch for
+    diagnostics:
+        sx1261_connect, sx1261_reset, sx1261_calibrate, sx1261_set_frequency, SET_RX,
+        sx1261_get_rssi_inst, ..., sx1261_disconnect
+*/
+
+use anyhow::{Context, Result};
+use tracing::{debug, error, info};
+
+use super::error::{Error, LgwFirmware};
+use super::helper::wait_ms;
+use super::loragw_com::LoragwComTrait;
+use super::mcu::command::{ECmdSpiTarget, MCU_SPI_REQ_TYPE_READ_WRITE};
+use super::mcu::McuTrait;
+use super::sx1261_pram::{SX1261_PRAM, SX1261_PRAM_VERSION_STRING};
+use super::{Hal, LgwLbtScanTime, LgwSpectralScanStatus, BW_125KHZ, BW_250KHZ, BW_500KHZ, LGW_SPECTRAL_SCAN_RESULT_SIZE, LGW_XTAL_FREQU};
+
+#[allow(non_camel_case_types)]
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+#[repr(u8)]
+pub enum Sx1261OpCode {
+    CALIBRATE               = 0x89,
+    CALIBRATE_IMAGE         = 0x98,
+    GET_RSSI_INST           = 0x15,
+    SET_PACKET_TYPE         = 0x8A,
+    SET_MODULATION_PARAMS   = 0x8B,
+    SET_PACKET_PARAMS       = 0x8C,
+    SET_RF_FREQUENCY        = 0x86,
+    SET_STANDBY             = 0x80,
+    SET_SLEEP               = 0x84,
+    SET_RX                  = 0x82,
+    SET_FS                  = 0xC1,
+    SET_REGULATORMODE       = 0x96,
+    WRITE_REGISTER          = 0x0D,
+    READ_REGISTER           = 0x1D,
+    GET_STATUS              = 0xC0,
+    SET_LBT                 = 0x9A,     /* LBT/spectral scan patch only */
+    SET_SPECTRAL_SCAN       = 0x9B,     /* LBT/spectral scan patch only */
+    APPLY_PRAM              = 0xD9,     /* start the patch loaded in PRAM */
+}
+
+pub const STDBY_RC: u8 =            0x00;
+pub const STDBY_XOSC: u8 =          0x01;
+
+/* Chip mode, bits 4..6 of the status byte */
+pub const SX1261_MODE_STDBY_RC: u8 =    0x02;
+pub const SX1261_MODE_STDBY_XOSC: u8 =  0x03;
+pub const SX1261_MODE_FS: u8 =          0x04;
+pub const SX1261_MODE_RX: u8 =          0x05;
+pub const SX1261_MODE_TX: u8 =          0x06;
+
+const SX1261_REG_VERSION: u16 =     0x0320;     /* 16 bytes version string, updated by the PRAM patch */
+const SX1261_REG_PRAM_UPDATE: u16 = 0x0610;     /* bit 4: PRAM write enable */
+const SX1261_PRAM_ADDR: u16 =       0x8000;
+const SX1261_VERSION_SIZE: usize =  16;
+const PRAM_CHUNK_WORDS: usize =     16;         /* 32-bit words written per SPI command */
+
+const SX1261_RESET_DELAY_MS: u32 =  5;
+const SX1261_CALIB_DELAY_MS: u32 =  5;
+
+const SX1261_REG_RSSI_AVERAGE: u16 = 0x089B;    /* RSSI averaging window, also enables the LBT output */
+const SX1261_REG_SCAN_STATUS: u16 =  0x07CD;    /* spectral scan state, see sx1261_spectral_scan_status */
+const SX1261_REG_SCAN_RESULTS: u16 = 0x0401;    /* spectral scan histogram, 33 big-endian u16 counters */
+
+/* Spectral scan histogram: bin i counts the RSSI reads at SCAN_LEVEL_MIN + i * SCAN_LEVEL_STEP dBm */
+const SCAN_LEVEL_MIN: i16 =         -128;
+const SCAN_LEVEL_STEP: i16 =        4;
+
+const WAIT_BUSY_SX1261_MS: u32 =    1;
+
+fn sx1261_freq_to_reg(f: u32) -> u32 {
+    ((f as u64 * (1 << 25)) / LGW_XTAL_FREQU as u64) as u32
+}
+
+/* Image calibration band, in units of 4MHz, for a frequency */
+fn sx1261_image_cal_band(freq_hz: u32) -> Option<[u8; 2]> {
+    match freq_hz {
+        430_000_000 ..= 440_000_000 => Some([0x6B, 0x6F]),
+        470_000_000 ..= 510_000_000 => Some([0x75, 0x81]),
+        779_000_000 ..= 787_000_000 => Some([0xC1, 0xC5]),
+        863_000_000 ..= 870_000_000 => Some([0xD7, 0xDB]),
+        902_000_000 ..= 928_000_000 => Some([0xE1, 0xE9]),
+        _ => None,
+    }
+}
+
+/* Chip mode from a status byte */
+pub fn sx1261_chip_mode(status: u8) -> u8 {
+    (status >> 4) & 0x07
+}
+
+pub trait LoragwSx1261Trait {
+    fn sx1261_w(&mut self, op_code: Sx1261OpCode, data: &[u8]) -> Result<()>;
+    fn sx1261_r(&mut self, op_code: Sx1261OpCode, data: &mut [u8]) -> Result<()>;
+    fn sx1261_connect(&mut self) -> Result<()>;
+    fn sx1261_disconnect(&mut self) -> Result<()>;
+    fn sx1261_reset(&mut self) -> Result<()>;
+    fn sx1261_get_status(&mut self) -> Result<u8>;
+    fn sx1261_set_standby(&mut self, mode: u8) -> Result<()>;
+    fn sx1261_calibrate(&mut self, freq_hz: u32) -> Result<()>;
+    fn sx1261_set_frequency(&mut self, freq_hz: u32) -> Result<()>;
+    fn sx1261_get_rssi_inst(&mut self) -> Result<f32>;
+    fn sx1261_get_version(&mut self) -> Result<String>;
+    fn sx1261_load_pram(&mut self, pram: &[u32], version: &str) -> Result<()>;
+    fn sx1261_load_patch(&mut self) -> Result<()>;
+    fn sx1261_check_pram(&mut self, version: &str) -> Result<()>;
+    fn sx1261_setup(&mut self) -> Result<()>;
+    fn sx1261_set_rx_params(&mut self, freq_hz: u32, bandwidth: u8) -> Result<()>;
+    fn sx1261_lbt_start(&mut self, scan_time_us: LgwLbtScanTime, threshold_dbm: i8) -> Result<()>;
+    fn sx1261_lbt_stop(&mut self) -> Result<()>;
+    fn sx1261_reg_r(&mut self, address: u16, data: &mut [u8]) -> Result<()>;
+    fn sx1261_reg_w(&mut self, address: u16, data: &[u8]) -> Result<()>;
+    fn sx1261_spectral_scan_start(&mut self, nb_scan: u16) -> Result<()>;
+    fn sx1261_spectral_scan_status(&mut self) -> Result<LgwSpectralScanStatus>;
+    fn sx1261_spectral_scan_abort(&mut self) -> Result<()>;
+    fn sx1261_spectral_scan_get_results(&mut self, rssi_offset: f32) -> Result<([i16; LGW_SPECTRAL_SCAN_RESULT_SIZE], [u16; LGW_SPECTRAL_SCAN_RESULT_SIZE])>;
+}
+
+impl Hal {
+    /* [req metadata (5 bytes)][op_code][data], data being replaced by the bytes clocked out */
+    fn sx1261_com(&mut self, op_code: Sx1261OpCode, data: &mut [u8]) -> Result<()> {
+        let size = data.len() + 1;
+        let mut in_out_buf = vec![0u8; size + 5];
+
+        /* wait BUSY */
+        wait_ms(WAIT_BUSY_SX1261_MS);
+
+        in_out_buf[0] = 0; /* Req ID */
+        in_out_buf[1] = MCU_SPI_REQ_TYPE_READ_WRITE; /* Req type */
+        in_out_buf[2] = ECmdSpiTarget::MCU_SPI_TARGET_SX1261 as u8; /* MCU -> SX1261 */
+        in_out_buf[3] = (size >> 8) as u8; /* payload size + op_code */
+        in_out_buf[4] = size as u8;
+        /* RAW SPI frame */
+        in_out_buf[5] = op_code as u8;
+        in_out_buf[6 ..].copy_from_slice(data);
+
+        self.mcu.mcu_spi_write(&mut in_out_buf)
+            .with_context(|| format!("SX1261 command {:?} failed", op_code))?;
+
+        data.copy_from_slice(&in_out_buf[6 ..]);
+        Ok(())
+    }
+}
+
+impl LoragwSx1261Trait for Hal {
+    fn sx1261_w(&mut self, op_code: Sx1261OpCode, data: &[u8]) -> Result<()> {
+        let mut buf = data.to_vec();
+        self.sx1261_com(op_code, &mut buf)
+    }
+
+    fn sx1261_r(&mut self, op_code: Sx1261OpCode, data: &mut [u8]) -> Result<()> {
+        self.sx1261_com(op_code, data)
+    }
+
+    /* Open the MCU link when the concentrator is not started, for standalone use */
+    fn sx1261_connect(&mut self) -> Result<()> {
+        if self.ctx.is_started {
+            return Ok(());
+        }
+        self.lgw_com_open().context("failed to connect to the MCU")?;
+
+        let status = self.sx1261_get_status()?;
+        info!("INFO: SX1261 connected (status:0x{:02X})", status);
+        Ok(())
+    }
+
+    fn sx1261_disconnect(&mut self) -> Result<()> {
+        if self.ctx.is_started {
+            return Ok(());
+        }
+        self.mcu.lgw_disconnect()
+    }
+
+    /* Hardware reset through the MCU, clearing the PRAM patch (see sx1261_load_patch) */
+    fn sx1261_reset(&mut self) -> Result<()> {
+        self.ctx.sx1261_pram_loaded = false;
+        self.mcu.mcu_gpio_write(0, 8, 0)?; /*   set PA8 : SX1261_NRESET active */
+        wait_ms(1);
+        self.mcu.mcu_gpio_write(0, 8, 1)?; /* unset PA8 : SX1261_NRESET inactive */
+        wait_ms(SX1261_RESET_DELAY_MS);
+
+        self.sx1261_set_standby(STDBY_RC)?;
+        debug!("SX1261: reset done");
+        Ok(())
+    }
+
+    fn sx1261_get_status(&mut self) -> Result<u8> {
+        let mut buff = [0u8; 1];
+        self.sx1261_r(Sx1261OpCode::GET_STATUS, &mut buff)?;
+        Ok(buff[0])
+    }
+
+    fn sx1261_set_standby(&mut self, mode: u8) -> Result<()> {
+        let expected = if mode == STDBY_RC { SX1261_MODE_STDBY_RC } else { SX1261_MODE_STDBY_XOSC };
+
+        self.sx1261_w(Sx1261OpCode::SET_STANDBY, &[mode])?;
+        let status = self.sx1261_get_status()?;
+        if sx1261_chip_mode(status) != expected {
+            error!("ERROR: failed to set SX1261 in standby mode {} (status:0x{:02X})\n", mode, status);
+            anyhow::bail!("SX1261 not in standby mode {} (status:0x{:02X})", mode, status);
+        }
+        Ok(())
+    }
+
+    /* Calibrate all the blocks, then the image rejection for the band of freq_hz */
+    fn sx1261_calibrate(&mut self, freq_hz: u32) -> Result<()> {
+        let Some(band) = sx1261_image_cal_band(freq_hz) else {
+            error!("ERROR: failed to calibrate SX1261, frequency {}Hz not supported\n", freq_hz);
+            return Err(Error::config("sx1261 freq_hz", freq_hz, "no SX1261 image calibration band for this frequency").into());
+        };
+
+        self.sx1261_w(Sx1261OpCode::CALIBRATE, &[0x7F])?;
+        wait_ms(SX1261_CALIB_DELAY_MS);
+        self.sx1261_w(Sx1261OpCode::CALIBRATE_IMAGE, &band)?;
+        wait_ms(SX1261_CALIB_DELAY_MS);
+
+        debug!("SX1261: calibration done (freq:{})", freq_hz);
+        Ok(())
+    }
+
+    fn sx1261_set_frequency(&mut self, freq_hz: u32) -> Result<()> {
+        let freq_reg = sx1261_freq_to_reg(freq_hz);
+        self.sx1261_w(Sx1261OpCode::SET_RF_FREQUENCY, &freq_reg.to_be_bytes())
+    }
+
+    /* Instantaneous RSSI in dBm, without rssi_offset. The radio must be in RX. */
+    fn sx1261_get_rssi_inst(&mut self) -> Result<f32> {
+        let mut buff = [0u8; 2];
+        self.sx1261_r(Sx1261OpCode::GET_RSSI_INST, &mut buff)?;
+        Ok(-(buff[1] as f32) / 2.0)
+    }
+
+    fn sx1261_get_version(&mut self) -> Result<String> {
+        let mut buff = [0u8; SX1261_VERSION_SIZE];
+        self.sx1261_reg_r(SX1261_REG_VERSION, &mut buff)?;
+        let len = buff.iter().position(|&c| c == 0).unwrap_or(buff.len());
+        Ok(String::from_utf8_lossy(&buff[.. len]).trim().to_string())
+    }
+
+    /* Load the LBT/spectral scan patch (32-bit words) in PRAM, and check its version */
+    fn sx1261_load_pram(&mut self, pram: &[u32], version: &str) -> Result<()> {
+        self.ctx.sx1261_pram_loaded = false;
+        if pram.is_empty() {
+            error!("ERROR: no SX1261 PRAM patch to load\n");
+            return Err(Error::Firmware { fw: LgwFirmware::Sx1261Pram, reason: "empty patch image" }.into());
+        }
+
+        self.sx1261_set_standby(STDBY_RC)?;
+        debug!("SX1261: version before patch: {}", self.sx1261_get_version()?);
+
+        /* Enable patch update */
+        self.sx1261_reg_w(SX1261_REG_PRAM_UPDATE, &[0x10])?;
+
+        for (i, chunk) in pram.chunks(PRAM_CHUNK_WORDS).enumerate() {
+            let addr = SX1261_PRAM_ADDR + (i * PRAM_CHUNK_WORDS * 4) as u16;
+            let data: Vec<u8> = chunk.iter().flat_map(|w| w.to_be_bytes()).collect();
+            self.sx1261_reg_w(addr, &data)
+                .with_context(|| format!("failed to write SX1261 PRAM at 0x{:04X}", addr))?;
+        }
+
+        /* Disable patch update, and start the patch */
+        self.sx1261_reg_w(SX1261_REG_PRAM_UPDATE, &[0x00])?;
+        self.sx1261_w(Sx1261OpCode::APPLY_PRAM, &[])?;
+
+        self.sx1261_check_pram(version)?;
+        self.ctx.sx1261_pram_loaded = true;
+        info!("INFO: SX1261 PRAM patch loaded ({} words)", pram.len());
+        Ok(())
+    }
+
+    /* Load the patch given with lgw_sx1261_set_pram, or else the one bundled with the HAL */
+    fn sx1261_load_patch(&mut self) -> Result<()> {
+        match self.ctx.sx1261_pram.clone() {
+            Some(pram) => self.sx1261_load_pram(&pram.words, &pram.version),
+            None => self.sx1261_load_pram(&SX1261_PRAM, SX1261_PRAM_VERSION_STRING),
+        }
+    }
+
+    fn sx1261_check_pram(&mut self, version: &str) -> Result<()> {
+        let got = self.sx1261_get_version()?;
+        if !got.contains(version) {
+            error!("ERROR: SX1261 PRAM version mismatch (expected {}, got {})\n", version, got);
+            self.ctx.sx1261_pram_loaded = false;
+            return Err(Error::PramVersion { expected: version.to_string(), got }.into());
+        }
+        debug!("SX1261: PRAM version {}", got);
+        Ok(())
+    }
+
+    fn sx1261_setup(&mut self) -> Result<()> {
+        /* Set Radio in Standby mode */
+        self.sx1261_set_standby(STDBY_RC)?;
+
+        /* Use the DC-DC regulator */
+        self.sx1261_w(Sx1261OpCode::SET_REGULATORMODE, &[0x01])?;
+
+        /* Run on the XOSC, needed by the RSSI measurements */
+        self.sx1261_set_standby(STDBY_XOSC)?;
+
+        debug!("SX1261: setup done");
+        Ok(())
+    }
+
+    fn sx1261_set_rx_params(&mut self, freq_hz: u32, bandwidth: u8) -> Result<()> {
+        let bw_reg = match bandwidth {
+            BW_125KHZ => 0x04,
+            BW_250KHZ => 0x05,
+            BW_500KHZ => 0x06,
+            _ => {
+                error!("ERROR: bandwidth not supported by SX1261 ({})\n", bandwidth);
+                anyhow::bail!("bandwidth 0x{:02X} not supported by SX1261", bandwidth);
+            }
+        };
+
+        /* Set FS */
+        self.sx1261_w(Sx1261OpCode::SET_FS, &[])?;
+
+        /* Set frequency */
+        self.sx1261_set_frequency(freq_hz)?;
+
+        /* Configure RSSI averaging window */
+        self.sx1261_reg_w(SX1261_REG_RSSI_AVERAGE, &[0x05 << 2])?;
+
+        /* Set PacketType LoRa */
+        self.sx1261_w(Sx1261OpCode::SET_PACKET_TYPE, &[0x01])?;
+
+        /* Set modulation params: SF5, BW, CR 4/5, no LDRO */
+        self.sx1261_w(Sx1261OpCode::SET_MODULATION_PARAMS, &[0x05, bw_reg, 0x01, 0x00])?;
+
+        /* Set packet params: 8 symbols preamble, explicit header, 255 bytes, CRC on, standard IQ */
+        self.sx1261_w(Sx1261OpCode::SET_PACKET_PARAMS, &[0x00, 0x08, 0x00, 0xFF, 0x01, 0x00])?;
+
+        /* Set RX continuous */
+        self.sx1261_w(Sx1261OpCode::SET_RX, &[0xFF, 0xFF, 0xFF])?;
+
+        debug!("SX1261: RX params set (freq:{} bw:0x{:02X})", freq_hz, bandwidth);
+        Ok(())
+    }
+
+    fn sx1261_lbt_start(&mut self, scan_time_us: LgwLbtScanTime, threshold_dbm: i8) -> Result<()> {
+        /* number of RSSI reads, ~8.2µs apart */
+        let nb_scan: u16 = match scan_time_us {
+            LgwLbtScanTime::LGW_LBT_SCAN_TIME_128_US => 24,
+            LgwLbtScanTime::LGW_LBT_SCAN_TIME_5000_US => 715,
+        };
+
+        /* intervalRssiRead (11 => 8.2µs), nb_scan, threshold, gpioId */
+        let [nb_h, nb_l] = nb_scan.to_be_bytes();
+        self.sx1261_w(Sx1261OpCode::SET_LBT, &[11, nb_h, nb_l, threshold_dbm as u8, 1])?;
+
+        /* Wait for Scan Time before TX trigger request */
+        std::thread::sleep(std::time::Duration::from_micros(scan_time_us as u64));
+
+        debug!("SX1261: LBT started (scan:{}us threshold:{}dBm)", scan_time_us as u32, threshold_dbm);
+        Ok(())
+    }
+
+    fn sx1261_lbt_stop(&mut self) -> Result<()> {
+        /* Disable LBT */
+        self.sx1261_reg_w(SX1261_REG_RSSI_AVERAGE, &[0x00])?;
+
+        /* Set Radio in Standby mode */
+        self.sx1261_w(Sx1261OpCode::SET_STANDBY, &[STDBY_RC])?;
+
+        debug!("SX1261: LBT stopped");
+        Ok(())
+    }
+
+    /* READ_REGISTER frame: [addr_h, addr_l, status, data...] */
+    fn sx1261_reg_r(&mut self, address: u16, data: &mut [u8]) -> Result<()> {
+        let mut buff = vec![0u8; data.len() + 3];
+        buff[.. 2].copy_from_slice(&address.to_be_bytes());
+        self.sx1261_r(Sx1261OpCode::READ_REGISTER, &mut buff)?;
+        data.copy_from_slice(&buff[3 ..]);
+        Ok(())
+    }
+
+    /* WRITE_REGISTER frame: [addr_h, addr_l, data...] */
+    fn sx1261_reg_w(&mut self, address: u16, data: &[u8]) -> Result<()> {
+        let mut buff = Vec::with_capacity(data.len() + 2);
+        buff.extend_from_slice(&address.to_be_bytes());
+        buff.extend_from_slice(data);
+        self.sx1261_w(Sx1261OpCode::WRITE_REGISTER, &buff)
+    }
+
+    fn sx1261_spectral_scan_start(&mut self, nb_scan: u16) -> Result<()> {
+        /* nb_scan, interval between RSSI reads (11 => 8.2µs) */
+        let [nb_h, nb_l] = nb_scan.to_be_bytes();
+        self.sx1261_w(Sx1261OpCode::SET_SPECTRAL_SCAN, &[nb_h, nb_l, 11])?;
+
+        debug!("SX1261: spectral scan started ({} scans)", nb_scan);
+        Ok(())
+    }
+
+    fn sx1261_spectral_scan_status(&mut self) -> Result<LgwSpectralScanStatus> {
+        let mut buff = [0u8; 1];
+        self.sx1261_reg_r(SX1261_REG_SCAN_STATUS, &mut buff)?;
+
+        Ok(match buff[0] {
+            0x00 => LgwSpectralScanStatus::LGW_SPECTRAL_SCAN_STATUS_NONE,
+            0x0F => LgwSpectralScanStatus::LGW_SPECTRAL_SCAN_STATUS_ON_GOING,
+            0xF0 => LgwSpectralScanStatus::LGW_SPECTRAL_SCAN_STATUS_ABORTED,
+            0xFF => LgwSpectralScanStatus::LGW_SPECTRAL_SCAN_STATUS_COMPLETED,
+            _ => LgwSpectralScanStatus::LGW_SPECTRAL_SCAN_STATUS_UNKNOWN,
+        })
+    }
+
+    fn sx1261_spectral_scan_abort(&mut self) -> Result<()> {
+        /* leaving RX stops the scan */
+        self.sx1261_set_standby(STDBY_XOSC)?;
+
+        debug!("SX1261: spectral scan aborted");
+        Ok(())
+    }
+
+    fn sx1261_spectral_scan_get_results(&mut self, rssi_offset: f32) -> Result<([i16; LGW_SPECTRAL_SCAN_RESULT_SIZE], [u16; LGW_SPECTRAL_SCAN_RESULT_SIZE])> {
+        let mut buff = [0u8; 2 * LGW_SPECTRAL_SCAN_RESULT_SIZE];
+        self.sx1261_reg_r(SX1261_REG_SCAN_RESULTS, &mut buff)?;
+
+        let mut levels_dbm = [0i16; LGW_SPECTRAL_SCAN_RESULT_SIZE];
+        let mut results = [0u16; LGW_SPECTRAL_SCAN_RESULT_SIZE];
+        for i in 0 .. LGW_SPECTRAL_SCAN_RESULT_SIZE {
+            levels_dbm[i] = SCAN_LEVEL_MIN + SCAN_LEVEL_STEP * i as i16 + rssi_offset.round() as i16;
+            results[i] = u16::from_be_bytes([buff[2 * i], buff[2 * i + 1]]);
+        }
+
+        Ok((levels_dbm, results))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::loragw_conf::LgwGlobalConf;
+    use crate::hal::mcu::sim::{SimConcentrator, SIM_SX1261_PRAM_VERSION};
+    use crate::hal::{LgwHal, LgwSx1261Pram};
+
+    const GLOBAL_CONF: &str = r#"{"SX130x_conf": {
+        "com_path": "/dev/ttyACM0", "clksrc": 0, "lorawan_public": true,
+        "radio_0": {"enable": true, "type": "SX1250", "freq": 867500000, "rssi_offset": -215.4, "tx_enable": false},
+        "radio_1": {"enable": true, "type": "SX1250", "freq": 868500000, "rssi_offset": -215.4, "tx_enable": false},
+        "sx1261_conf": {"enable": true, "rssi_offset": 0}
+    }}"#;
+
+    #[test]
+    fn pram_load_and_check() {
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        hal.sx1261_connect().unwrap();
+
+        let err = hal.sx1261_check_pram(SIM_SX1261_PRAM_VERSION).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::PramVersion { .. })));
+
+        let err = hal.sx1261_load_pram(&[], SIM_SX1261_PRAM_VERSION).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::Firmware { fw: LgwFirmware::Sx1261Pram, .. })));
+
+        let pram: Vec<u32> = (0 .. 386).collect();
+        hal.sx1261_load_pram(&pram, SIM_SX1261_PRAM_VERSION).unwrap();
+        assert!(hal.ctx.sx1261_pram_loaded);
+        assert!(hal.sx1261_get_version().unwrap().ends_with(SIM_SX1261_PRAM_VERSION));
+
+        let err = hal.sx1261_check_pram("1A00").unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::PramVersion { expected, .. }) if expected == "1A00"));
+        assert!(!hal.ctx.sx1261_pram_loaded);
+        hal.sx1261_disconnect().unwrap();
+    }
+
+    #[test]
+    fn diagnostics_without_patch() {
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        sim.set_channel_rssi(868_100_000, -70);
+        hal.sx1261_connect().unwrap();
+
+        hal.sx1261_reset().unwrap();
+        assert!(!hal.ctx.sx1261_pram_loaded);
+        hal.sx1261_set_standby(STDBY_RC).unwrap();
+        hal.sx1261_calibrate(868_100_000).unwrap();
+        hal.sx1261_set_frequency(868_100_000).unwrap();
+        hal.sx1261_w(Sx1261OpCode::SET_RX, &[0xFF, 0xFF, 0xFF]).unwrap();
+        assert_eq!(hal.sx1261_get_rssi_inst().unwrap(), -70.0);
+        assert!(!hal.sx1261_get_version().unwrap().contains(SIM_SX1261_PRAM_VERSION));
+
+        /* the reset clears a loaded patch */
+        let pram: Vec<u32> = (0 .. 386).collect();
+        hal.sx1261_load_pram(&pram, SIM_SX1261_PRAM_VERSION).unwrap();
+        hal.sx1261_reset().unwrap();
+        assert!(!hal.ctx.sx1261_pram_loaded);
+        assert!(!hal.sx1261_get_version().unwrap().contains(SIM_SX1261_PRAM_VERSION));
+        hal.sx1261_disconnect().unwrap();
+    }
+
+    #[test]
+    fn start_loads_patch() {
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        LgwGlobalConf::parse(GLOBAL_CONF).unwrap().apply(&mut hal).unwrap();
+
+        /* no image bundled yet */
+        let err = hal.lgw_start().unwrap_err();
+        assert!(matches!(Error::chain(&err).next(), Some(Error::Start { step: "SX1261 PRAM patch", .. })), "{err:#}");
+        assert!(!hal.ctx.is_started);
+
+        let err = hal.lgw_sx1261_set_pram(LgwSx1261Pram::default()).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::Firmware { fw: LgwFirmware::Sx1261Pram, .. })));
+        hal.lgw_sx1261_set_pram(LgwSx1261Pram { words: (0 .. 386).collect(), version: SIM_SX1261_PRAM_VERSION.to_string() }).unwrap();
+
+        hal.lgw_start().unwrap();
+        assert!(hal.ctx.sx1261_pram_loaded);
+        assert!(hal.sx1261_get_version().unwrap().ends_with(SIM_SX1261_PRAM_VERSION));
+        let err = hal.lgw_sx1261_set_pram(LgwSx1261Pram { words: vec![0], version: "2D06".to_string() }).unwrap_err();
+        assert!(matches!(Error::cause(&err), Some(Error::ConcentratorRunning)));
+
+        hal.lgw_stop().unwrap();
+        assert!(!hal.ctx.sx1261_pram_loaded);
+    }
+}
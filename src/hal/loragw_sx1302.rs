@@ -1,12 +1,14 @@
 #![allow(unused_macros)]
 #![allow(non_snake_case)]
 
-use anyhow::{anyhow,Result};
+use anyhow::Result;
 use tracing::{debug, error, info, trace, warn};
 use crate::hal::{ cal_fw::CAL_FIRMWARE_SX125X, LgwFtimeMode, loragw_agc_params::{AGC_PARAMS_SX1250, AGC_PARAMS_SX125X}, loragw_sx1250::LoragwSx1250Trait, loragw_sx1302_timestamp::timestamp_counter_correction, mcu::McuTrait, Modulation, TxMode, BW_125KHZ, BW_250KHZ, BW_500KHZ, BW_UNDEFINED, CR_LORA_4_5, CR_LORA_4_6, CR_LORA_4_7, CR_LORA_4_8, CR_UNDEFINED, DR_UNDEFINED, LGW_MULTI_NB, STAT_CRC_BAD, STAT_CRC_OK, STAT_NO_CRC, STAT_UNDEFINED};
-use super::{LgwConfigBoard, LgwConfDemod, LgwConfigFtime, LgwConfRxIf, LgwConfRxrf, LgwPktRx, LgwPktTx, LgwRadioType, LgwTxGainLut, loragw_sx1302_timestamp::{TimestampCounter, TimestampPpsHistory, SX1302TimestampTrait, MAX_TIMESTAMP_PPS_HISTORY}, mcu::command::EComWriteMode, Hal, LoragwRegTrait, DR_LORA_SF10, DR_LORA_SF11, DR_LORA_SF12, DR_LORA_SF5, DR_LORA_SF6, DR_LORA_SF7, DR_LORA_SF8, DR_LORA_SF9, LGW_IF_CHAIN_NB, LGW_RF_CHAIN_NB, RX_STATUS_UNKNOWN, TX_EMITTING, TX_FREE, TX_SCHEDULED, TX_STATUS_UNKNOWN};
+use super::{LgwConfigBoard, LgwConfDemod, LgwConfigFtime, LgwConfRxIf, LgwConfRxrf, LgwPktRx, LgwPktTx, LgwRadioType, LgwRssiTcomp, LgwTxGainLut, loragw_sx1302_timestamp::{TimestampCounter, TimestampPpsHistory, SX1302TimestampTrait, MAX_TIMESTAMP_PPS_HISTORY}, mcu::command::EComWriteMode, Hal, LoragwRegTrait, DR_LORA_SF10, DR_LORA_SF11, DR_LORA_SF12, DR_LORA_SF5, DR_LORA_SF6, DR_LORA_SF7, DR_LORA_SF8, DR_LORA_SF9, LGW_IF_CHAIN_NB, LGW_RF_CHAIN_NB, RX_STATUS_UNKNOWN, TX_EMITTING, TX_FREE, TX_SCHEDULED, TX_STATUS_UNKNOWN};
 use crate::hal::helper::wait_ms;
-use super::error::Error;
+use crate::hal::loragw_cal::LoragwCalTrait;
+use super::error::{Error, LgwFirmware};
+use super::loragw_stats::LgwRxBufferStats;
 use libm::{ceil, fabs};
 use crate::hal::loragw_reg::*;
 
@@ -1355,7 +1357,7 @@ const SX1302_PKT_TAIL_METADATA:u8 =    14;
 /* modem IDs */
 const SX1302_LORA_MODEM_ID_MAX:u8 =    15;
 const SX1302_LORA_STD_MODEM_ID:u8 =    16;
-const SX1302_FSK_MODEM_ID:u8 =         17;
+pub(crate) const SX1302_FSK_MODEM_ID:u8 =         17;
 
 
 fn reg_select(rf_chain: u8, a: u16, b: u16) -> u16 {
@@ -1381,7 +1383,7 @@ fn calculate_freq_to_time_drift( freq_hz:u32,  bw:u8 )-> Result<(u16, u8)> {
     bw_hz = Hal::lgw_bw_getval(bw);
     if bw_hz < 0 {
         error!("ERROR: Unsupported bandwidth for frequency to time drift calculation");
-        return Err(anyhow!("LGW_HAL_ERR"));
+        return Err(Error::config("bandwidth", bw, "unsupported bandwidth for frequency to time drift calculation").into());
     }
 
     mantissa_u64 = bw_hz as u64 * (2 << (20-1)) / freq_hz as u64;
@@ -1461,7 +1463,8 @@ pub struct RxBuffer {
     pub buffer:[u8;4096],   /* byte array to hald the data fetched from the RX buffer */
     pub buffer_size: usize,   /* The number of bytes currently stored in the buffer */
     pub buffer_index:usize,       /* Current parsing index in the buffer */
-    pub buffer_pkt_nb:u8
+    pub buffer_pkt_nb:u8,
+    pub stats: LgwRxBufferStats,  /* anomalies met while parsing, kept across fetches */
 } 
 
 
@@ -1472,7 +1475,8 @@ impl RxBuffer {
             buffer: [0u8; 4096],
             buffer_size: 0,
             buffer_index: 0,
-            buffer_pkt_nb: 0
+            buffer_pkt_nb: 0,
+            stats: Default::default(),
         }
     }
 
@@ -1498,12 +1502,12 @@ impl RxBuffer {
         /* Is there any data to be parsed ? */
         if self.buffer_index >= self.buffer_size  {
             error!("INFO: No more data to be parsed\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::RxPacket { reason: "no more data to be parsed" }.into());
         }
     
         /* Get pkt sync words */
         if (self.buffer[self.buffer_index] != SX1302_PKT_SYNCWORD_BYTE_0) || (self.buffer[self.buffer_index + 1] != SX1302_PKT_SYNCWORD_BYTE_1) {
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::RxPacket { reason: "no packet syncword" }.into());
         }
 
         trace!("INFO: pkt syncword found at index {:}", self.buffer_index);
@@ -1520,7 +1524,8 @@ impl RxBuffer {
         /* Check if we have a complete packet in the rx buffer fetched */
         if(self.buffer_index + pkt_num_bytes as usize) > self.buffer_size {
             warn!("WARNING: aborting truncated message (size={:})\n", self.buffer_size);
-            return Err(anyhow!("LGW_REG_ERR"));
+            self.stats.truncated += 1;
+            return Err(Error::RxPacket { reason: "truncated packet" }.into());
         }
     
         /* Get the checksum as received in the RX buffer */
@@ -1538,7 +1543,8 @@ impl RxBuffer {
         /* Check if the checksum is correct */
         if checksum_rcv != checksum_calc as u8 {
             warn!("WARNING: checksum failed (got:0x{:02X} calc:0x{:02X})\n", checksum_rcv, checksum_calc);
-            return Err(anyhow!("LGW_REG_ERR"));
+            self.stats.checksum_errors += 1;
+            return Err(Error::RxPacket { reason: "wrong metadata checksum" }.into());
         } else {
             trace!("Packet checksum OK (0x{:02X})\n", checksum_rcv);
         }
@@ -1580,16 +1586,16 @@ impl RxBuffer {
         /* Sanity checks: check the range of few metadata */
         if pkt.modem_id > SX1302_FSK_MODEM_ID {
             error!("ERROR: modem_id is out of range - {:}\n", pkt.modem_id);
-            return Err(anyhow!("LGW_REG_ERROR"));
+            return Err(Error::RxPacket { reason: "modem_id out of range" }.into());
         } else {
             if pkt.modem_id <= SX1302_LORA_STD_MODEM_ID { /* LoRa modems */
                 if pkt.rx_channel_in > 9 {
                     error!("ERROR: channel is out of range - {:}\n", pkt.rx_channel_in);
-                    return Err(anyhow!("LGW_REG_ERROR"));
+                    return Err(Error::RxPacket { reason: "channel out of range" }.into());
                 }
                 if (pkt.rx_rate_sf < 5) || (pkt.rx_rate_sf > 12) {
                     error!("ERROR: SF is out of range - {:}\n", pkt.rx_rate_sf);
-                    return Err(anyhow!("LGW_REG_ERROR"));
+                    return Err(Error::RxPacket { reason: "SF out of range" }.into());
                 }
             } else { /* FSK modem */
                 /* TODO: not checked */
@@ -1637,7 +1643,7 @@ pub trait LorgwSx1302Trait {
     
     fn sx1302_set_gpio(&mut self, gpio_reg_val:u8) -> Result<()>;
     
-    fn sx1302_radio_calibrate(&mut self, context_rf_chain: &[LgwConfRxrf],  clksrc:u8, txgain_lut: &[LgwTxGainLut])->Result<()>;
+    fn sx1302_radio_calibrate(&mut self, context_rf_chain: &[LgwConfRxrf],  clksrc:u8, txgain_lut: &mut [LgwTxGainLut])->Result<()>;
 
     fn sx1302_radio_reset(&mut self, radio:u8,  radio_type:LgwRadioType) -> Result<()>;
 
@@ -1647,7 +1653,6 @@ pub trait LorgwSx1302Trait {
 
     fn sx1302_agc_load_firmware(&mut self, firmware: &[u8]) -> Result<()>;
 
-    fn sx1302_cal_start(&mut self, fw_version:u8, context_rf_chain: &[LgwConfRxrf], txgain_lut: &[LgwTxGainLut])->Result<()>;
 
     fn sx1302_get_ifmod_config(if_chain:u8)-> u8;
 
@@ -1655,6 +1660,8 @@ pub trait LorgwSx1302Trait {
 
     fn sx1302_get_model_id(&mut self) -> Result<u8>;
 
+    fn sx1302_get_eui(&mut self) -> Result<u64>;
+
     fn sx1302_config_gpio(&mut self) -> Result<()>; 
 
     fn sx1302_pa_lna_lut_configure(&mut self, context_board: &LgwConfigBoard) -> Result<()>;
@@ -1780,7 +1787,7 @@ impl LorgwSx1302Trait for Hal {
             /* handle timeout */
             if start.elapsed().ge(&to) {
                 error!("ERROR: TIMEOUT on TX abort\n");
-                return Err(Error::LGW_REG_ERROR.into());
+                return Err(Error::TxAbortTimeout { rf_chain }.into());
             }
 
             /* get tx status */
@@ -1829,7 +1836,7 @@ impl LorgwSx1302Trait for Hal {
                     radio_bw_delay = 21;
                 } else {
                     error!("ERROR: bandwidth not supported\n");
-                    return Err(Error::LGW_REG_ERROR.into());
+                    return Err(Error::tx_rejected("bandwidth", bandwidth, "bandwidth not supported").into());
                 }
             },
             LgwRadioType::LGW_RADIO_TYPE_SX1255 | LgwRadioType::LGW_RADIO_TYPE_SX1257 => {
@@ -1842,12 +1849,12 @@ impl LorgwSx1302Trait for Hal {
                     radio_bw_delay += 0;
                 } else {
                     error!("ERROR: bandwidth not supported\n");
-                    return Err(Error::LGW_REG_ERROR.into());
+                    return Err(Error::tx_rejected("bandwidth", bandwidth, "bandwidth not supported").into());
                 }
             },
             _ => {
                 error!("ERROR: radio type not supported\n");
-                return Err(Error::LGW_REG_ERROR.into());
+                return Err(Error::tx_rejected("radio_type", radio_type, "radio type not supported").into());
             }
         }
     
@@ -1914,7 +1921,7 @@ impl LorgwSx1302Trait for Hal {
             }
             _ => {
                 error!("ERROR: modulation type not supported\n");
-                return Err(Error::LGW_REG_ERROR.into());
+                return Err(Error::tx_rejected("modulation", pkt_data.modulation, "modulation not supported").into());
             }
         }
 
@@ -1948,7 +1955,7 @@ impl LorgwSx1302Trait for Hal {
             },
             _ => {
                 error!("ERROR: radio type not supported\n");
-                return Err(Error::LGW_REG_ERROR.into());
+                return Err(Error::tx_rejected("radio_type", radio_type, "radio type not supported").into());
             }
         }
         self.lgw_reg_w(SX1302_REG_TX_TOP_AGC_TX_PWR_AGC_TX_PWR!(pkt_data.rf_chain), power as i32)?;
@@ -1977,7 +1984,7 @@ impl LorgwSx1302Trait for Hal {
             Modulation::CW | Modulation::FSK => (0x01 << 7) | pkt_data.bandwidth,
             _ => {
                 println!("ERROR: Modulation not supported");
-                return Err(Error::LGW_REG_ERROR.into());
+                return Err(Error::tx_rejected("modulation", pkt_data.modulation, "modulation not supported").into());
             }
         };
 
@@ -2189,7 +2196,7 @@ impl LorgwSx1302Trait for Hal {
             },
             _ => {
                 debug!("ERROR: Modulation not supported\n");
-                return Err(Error::LGW_REG_ERROR.into());
+                return Err(Error::tx_rejected("modulation", pkt_data.modulation, "modulation not supported").into());
             }
         }
         /* Set TX start delay */
@@ -2259,7 +2266,7 @@ impl LorgwSx1302Trait for Hal {
         */
         if let Err(e) = self.lgw_reg_rb(SX1302_REG_TIMESTAMP_TIMESTAMP_PPS_MSB2_TIMESTAMP_PPS, &mut buff, 8){
             error!("ERROR: Failed to get timestamp counter value: {}", e);
-            return Err(anyhow!("LGW_HAL_ERROR"))
+            return Err(e)
         }
 
         /* Workaround concentrator chip issue:
@@ -2269,13 +2276,13 @@ impl LorgwSx1302Trait for Hal {
         if let Err(e) = self.lgw_reg_rb(SX1302_REG_TIMESTAMP_TIMESTAMP_PPS_MSB2_TIMESTAMP_PPS, &mut buff_wa, 8){
       
             error!(e=%e, "ERROR: Failed to get timestamp counter MSB value");
-            return Err(anyhow!("LGW_HAL_ERROR"))
+            return Err(e)
         }
 
         if (buff[0] != buff_wa[0]) || (buff[4] != buff_wa[4]) {
             if let Err(e) = self.lgw_reg_rb(SX1302_REG_TIMESTAMP_TIMESTAMP_PPS_MSB2_TIMESTAMP_PPS, &mut buff_wa, 8){
                 error!(e=%e, "ERROR: Failed to get timestamp counter MSB value\n");
-                return Err(anyhow!("LGW_HAL_ERROR"))
+                return Err(e)
             }
             buff.copy_from_slice(&buff_wa); /* use the new read value */
         }
@@ -2328,7 +2335,7 @@ impl LorgwSx1302Trait for Hal {
             if let Err(err) = self.lgw_mem_rb(0x4000, &mut data_buff, data_size, true){
 
                 error!("ERROR: Failed to read RX buffer, SPI error: {}", err);
-                return Err(anyhow!("LGW_HAL_ERR"));
+                return Err(err);
             }
     
             /* print debug info */
@@ -2337,6 +2344,7 @@ impl LorgwSx1302Trait for Hal {
             /* Sanity check: is there at least 1 complete packet in the buffer */
             if data_size < (SX1302_PKT_HEAD_METADATA + SX1302_PKT_TAIL_METADATA) as usize {
                 warn!("WARNING: not enough data to have a complete packet, discard rx_buffer\n");
+                self.sx1302.rx_buffer.stats.discarded += 1;
                 return Ok(());
             }
 
@@ -2354,12 +2362,14 @@ impl LorgwSx1302Trait for Hal {
 
             if idx > data_size - 2 {
                 debug!("WARNING: no syncword found, discard rx_buffer\n");
+                self.sx1302.rx_buffer.stats.discarded += 1;
                 return Ok(())
             }
 
             if idx != 0 {
                 debug!("INFO: re-sync rx_buffer at idx {:}\n", idx);
-                self.sx1302.rx_buffer.buffer[.. (data_size - idx)].copy_from_slice(&data_buff[ idx .. (data_size - idx)]);
+                self.sx1302.rx_buffer.stats.resync += 1;
+                self.sx1302.rx_buffer.buffer[.. (data_size - idx)].copy_from_slice(&data_buff[idx .. data_size]);
                 
                 self.sx1302.rx_buffer.buffer_size = data_size - idx;
             }
@@ -2374,6 +2384,7 @@ impl LorgwSx1302Trait for Hal {
                 if self.sx1302.rx_buffer.buffer[idx] != SX1302_PKT_SYNCWORD_BYTE_0 || (self.sx1302.rx_buffer.buffer[idx + 1] != SX1302_PKT_SYNCWORD_BYTE_1) {
                     debug!("WARNING: syncword not found at idx {:}, discard the rx_buffer\n", idx);
                     self.sx1302.rx_buffer.del();
+                    self.sx1302.rx_buffer.stats.discarded += 1;
                     return Ok(());
                 }
 
@@ -2405,12 +2416,12 @@ impl LorgwSx1302Trait for Hal {
         let val = self.lgw_reg_r(SX1302_REG_AGC_MCU_CTRL_PARITY_ERROR)?;
         if val != 0 {
             error!("ERROR: Parity error check failed on AGC firmware\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "parity error" }.into());
         }
         let val = self.lgw_reg_r(SX1302_REG_ARB_MCU_CTRL_PARITY_ERROR)?;
         if (val != 0) {
             error!("ERROR: Parity error check failed on ARB firmware\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::ARB, reason: "parity error" }.into());
         }
         */
     
@@ -2431,7 +2442,7 @@ impl LorgwSx1302Trait for Hal {
             if let Err(err) = self.rx_buffer_fetch(){
           
                 error!("ERROR: Failed to fetch RX buffer: {}", err);
-                return Err(anyhow!("LGW_REG_ERR"));
+                return Err(err);
             }
             else{
                 trace!("Note: fetch {:} pkts from sx1302", self.sx1302.rx_buffer.buffer_pkt_nb);
@@ -2480,7 +2491,7 @@ impl LorgwSx1302Trait for Hal {
             },
             _ => {
                 debug!("ERROR: radio type not supported");
-                return Err(anyhow!("LGW_REG_ERR"));
+                return Err(Error::config("radio_type", radio_type, "radio type not supported").into());
             }
         }
     
@@ -2518,7 +2529,7 @@ impl LorgwSx1302Trait for Hal {
         /* Check parameters */
         if reg_id > 15 {
             error!("ERROR: invalid ARB debug register ID\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::config("reg_id", reg_id, "invalid ARB debug register ID").into());
         }
 
         let reg = SX1302_REG_ARB_MCU_ARB_DEBUG_STS_0_ARB_DEBUG_STS_0 + reg_id as u16;
@@ -2542,7 +2553,7 @@ impl LorgwSx1302Trait for Hal {
         /* Check parameters */
         if reg_id > 3 {
             error!("ERROR: invalid ARB debug register ID");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::config("reg_id", reg_id, "invalid ARB debug register ID").into());
         }
     
         let reg = SX1302_REG_ARB_MCU_ARB_DEBUG_CFG_0_ARB_DEBUG_CFG_0 + reg_id as u16;
@@ -2560,7 +2571,7 @@ impl LorgwSx1302Trait for Hal {
         let val = self.sx1302_arb_debug_read(0)?;
         if val != version{
             error!("ERROR: wrong ARB fw version ({})\n", val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::FirmwareVersion { fw: LgwFirmware::ARB, expected: version, got: val }.into());
         }
 
         debug!("ARB FW VERSION: {:}\n", val);
@@ -2581,7 +2592,7 @@ impl LorgwSx1302Trait for Hal {
                 self.sx1302_arb_debug_write(3, 0x3F)?; /* double demod enabled for SF10 <- SF5 */
             } else {
                 error!("ERROR: fine timestamp mode is not supported ({})\n", ftime_context.mode as u8);
-                return Err(anyhow!("LGW_REG_ERR"));
+                return Err(Error::config("ftime.mode", ftime_context.mode as u8, "fine timestamp mode is not supported").into());
             }
         }
 
@@ -2616,7 +2627,7 @@ impl LorgwSx1302Trait for Hal {
 
         if firmware != fw_check {
             error!("ERROR: ARB fw read/write check failed\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::FirmwareLoad { fw: LgwFirmware::ARB, reason: "read/write check failed" }.into());
         }
 
         /* Release control over ARB MCU */
@@ -2626,7 +2637,7 @@ impl LorgwSx1302Trait for Hal {
         let val = self.lgw_reg_r(SX1302_REG_ARB_MCU_CTRL_PARITY_ERROR)?;
         if val != 0 {
             error!("ERROR: Failed to load ARB fw: parity error check failed\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::FirmwareLoad { fw: LgwFirmware::ARB, reason: "parity error check failed" }.into());
         }
 
         info!("ARB fw loaded\n");
@@ -2639,13 +2650,13 @@ impl LorgwSx1302Trait for Hal {
         /* Check parameters */
         if mailbox > 3 {
             error!("ERROR: invalid AGC mailbox ID\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::config("mailbox", mailbox, "invalid AGC mailbox ID").into());
         }
     
         let reg = SX1302_REG_AGC_MCU_MCU_MAIL_BOX_WR_DATA_BYTE0_MCU_MAIL_BOX_WR_DATA - mailbox as u16;
-        if let Err(_) = self.lgw_reg_w(reg, value as i32) {
+        if let Err(e) = self.lgw_reg_w(reg, value as i32) {
             error!("ERROR: failed to write AGC mailbox\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(e);
         }
     
        Ok(())
@@ -2657,41 +2668,32 @@ impl LorgwSx1302Trait for Hal {
         /* Check parameters */
         if mailbox > 3 {
             error!("ERROR: invalid AGC mailbox ID");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::config("mailbox", mailbox, "invalid AGC mailbox ID").into());
         }
     
         let reg = SX1302_REG_AGC_MCU_MCU_MAIL_BOX_RD_DATA_BYTE0_MCU_MAIL_BOX_RD_DATA - mailbox as u16;
-        if let Ok(val) = self.lgw_reg_r(reg)  {
-            return Ok(val as u8)
-        }
-        else{
-            error!("ERROR: failed to read AGC mailbox {}", mailbox);
-            return Err(anyhow!("LGW_REG_ERR"));
+        match self.lgw_reg_r(reg) {
+            Ok(val) => Ok(val as u8),
+            Err(e) => {
+                error!("ERROR: failed to read AGC mailbox {}", mailbox);
+                Err(e)
+            }
         }
         
     }
 
     fn sx1302_agc_status(&mut self) -> Result<u8> {
     
-        if let Ok(val) = self.lgw_reg_r(SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS){
-            Ok(val as u8)
-        }
-        else{
-            Err(anyhow!("LGW_REG_ERR"))
-        }
+        let val = self.lgw_reg_r(SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS)?;
+        Ok(val as u8)
 
     }
 
     fn sx1302_agc_wait_status(&mut self, status: u8) -> Result<()>{
 
         loop {
-            if let Ok(val) = self.sx1302_agc_status() {
-                if val == status {
-                    break;
-                }
-            }
-            else{
-                return Err(anyhow!("LGW_HAL_ERR"));
+            if self.sx1302_agc_status()? == status {
+                break;
             }
         }
 
@@ -2706,7 +2708,7 @@ impl LorgwSx1302Trait for Hal {
         /* Check parameters */
         if (radio_type != LgwRadioType::LGW_RADIO_TYPE_SX1255) && (radio_type != LgwRadioType::LGW_RADIO_TYPE_SX1257) && (radio_type != LgwRadioType::LGW_RADIO_TYPE_SX1250) {
             error!("ERROR: invalid radio type\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::config("radio_type", radio_type, "invalid radio type").into());
         }
 
         /* Wait for AGC fw to be started, and VERSION available in mailbox */
@@ -2716,7 +2718,7 @@ impl LorgwSx1302Trait for Hal {
 
         if val != version {
             error!("ERROR: wrong AGC fw version ({:})", val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::FirmwareVersion { fw: LgwFirmware::AGC, expected: version, got: val }.into());
         }
 
         info!("AGC FW VERSION: {:}\n", val);
@@ -2742,7 +2744,7 @@ impl LorgwSx1302Trait for Hal {
          
         if val != ana_gain {
             error!("ERROR: Analog gain of Radio A has not been set properly\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "analog gain of Radio A not set" }.into());
         }
         
         
@@ -2751,14 +2753,14 @@ impl LorgwSx1302Trait for Hal {
         let val = self.sx1302_agc_mailbox_read(1)?;
         if val != dec_gain {
             error!("ERROR: Decimator gain of Radio A has not been set properly\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "decimator gain of Radio A not set" }.into());
         }
 
         /* Check FDD mode setting */
         let val = self.sx1302_agc_mailbox_read(2)?;
         if val != fdd_mode {
             error!("ERROR: FDD mode of Radio A has not been set properly\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "FDD mode of Radio A not set" }.into());
         }
 
         info!("AGC: Radio A config done\n");
@@ -2782,21 +2784,21 @@ impl LorgwSx1302Trait for Hal {
         let val = self.sx1302_agc_mailbox_read(0)?;
         if val != ana_gain {
             error!("ERROR: Analog gain of Radio B has not been set properly\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "analog gain of Radio B not set" }.into());
         }
 
         /* Check dec_gain setting */
         let val = self.sx1302_agc_mailbox_read(1)?;
         if val != dec_gain {
             error!("ERROR: Decimator gain of Radio B has not been set properly\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "decimator gain of Radio B not set" }.into());
         }
 
         /* Check FDD mode setting */
         let val = self.sx1302_agc_mailbox_read(2)?;
         if val != fdd_mode {
             error!("ERROR: FDD mode of Radio B has not been set properly\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "FDD mode of Radio B not set" }.into());
         }
 
         info!("AGC: Radio B config done\n");
@@ -2820,12 +2822,12 @@ impl LorgwSx1302Trait for Hal {
         let val = self.sx1302_agc_mailbox_read(0)?;
         if val != agc_params.ana_min {
             error!("ERROR: wrong ana_min (w:{:} r:{:})\n", agc_params.ana_min, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "ana_min readback mismatch" }.into());
         }
         let val = self.sx1302_agc_mailbox_read(1)?;
         if val != agc_params.ana_max {
             error!("ERROR: ana_max (w:{:} r:{:})\n", agc_params.ana_max, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "ana_max readback mismatch" }.into());
         }
 
         info!("AGC: config of analog gain min/max done\n");
@@ -2846,12 +2848,12 @@ impl LorgwSx1302Trait for Hal {
         let val = self.sx1302_agc_mailbox_read(0)?;
         if val != agc_params.ana_thresh_l {
             error!("ERROR: wrong ana_thresh_l (w:{:} r:{:})\n", agc_params.ana_thresh_l, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "ana_thresh_l readback mismatch" }.into());
         }
         let val = self.sx1302_agc_mailbox_read(1)?;
         if val != agc_params.ana_thresh_h {
             error!("ERROR: wrong ana_thresh_h (w:{:} r:{:})\n", agc_params.ana_thresh_h, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "ana_thresh_h readback mismatch" }.into());
         }
 
         info!("AGC: config of analog threshold done\n");
@@ -2872,12 +2874,12 @@ impl LorgwSx1302Trait for Hal {
         let val = self.sx1302_agc_mailbox_read(0)?;
         if val != agc_params.dec_attn_min {
             error!("ERROR: wrong dec_attn_min (w:{:} r:{:})\n", agc_params.dec_attn_min, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "dec_attn_min readback mismatch" }.into());
         }
         let val = self.sx1302_agc_mailbox_read(1)?;
         if val != agc_params.dec_attn_max {
             error!("ERROR: wrong dec_attn_max (w:{:} r:{:})\n", agc_params.dec_attn_max, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "dec_attn_max readback mismatch" }.into());
         }
 
         info!("AGC: config of decimator atten min/max done\n");
@@ -2899,17 +2901,17 @@ impl LorgwSx1302Trait for Hal {
         let val = self.sx1302_agc_mailbox_read(0)?;
         if val != agc_params.dec_thresh_l {
             error!("ERROR: wrong dec_thresh_l (w:{:} r:{:})\n", agc_params.dec_thresh_l, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "dec_thresh_l readback mismatch" }.into());
         }
         let val = self.sx1302_agc_mailbox_read(1)?;
         if val != agc_params.dec_thresh_h1 {
             error!("ERROR: wrong dec_thresh_h1 (w:{:} r:{:})\n", agc_params.dec_thresh_h1, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "dec_thresh_h1 readback mismatch" }.into());
         }
         let val = self.sx1302_agc_mailbox_read(2)?;
         if val != agc_params.dec_thresh_h2 {
             error!("ERROR: wrong dec_thresh_h2 (w:{:} r:{:})\n", agc_params.dec_thresh_h2, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "dec_thresh_h2 readback mismatch" }.into());
         }
 
         info!("AGC: config of decimator threshold done\n");
@@ -2930,12 +2932,12 @@ impl LorgwSx1302Trait for Hal {
         let val = self.sx1302_agc_mailbox_read(0)?;
         if val != agc_params.chan_attn_min {
             error!("ERROR: wrong chan_attn_min (w:{:} r:{:})\n", agc_params.chan_attn_min, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "chan_attn_min readback mismatch" }.into());
         }
         let val = self.sx1302_agc_mailbox_read(1)?;
         if val != agc_params.chan_attn_max {
             error!("ERROR: wrong chan_attn_max (w:{:} r:{:})\n", agc_params.chan_attn_max, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "chan_attn_max readback mismatch" }.into());
         }
 
         info!("AGC: config of channel atten min/max done\n");
@@ -2956,12 +2958,12 @@ impl LorgwSx1302Trait for Hal {
         let val = self.sx1302_agc_mailbox_read(0)?;
         if val != agc_params.chan_thresh_l {
             error!("ERROR: wrong chan_thresh_l (w:{:} r:{:})\n", agc_params.chan_thresh_l, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "chan_thresh_l readback mismatch" }.into());
         }
         let val = self.sx1302_agc_mailbox_read(1)?;
         if val != agc_params.chan_thresh_h {
             error!("ERROR: wrong chan_thresh_h (w:{:} r:{:})\n", agc_params.chan_thresh_h, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "chan_thresh_h readback mismatch" }.into());
         }
 
         info!("AGC: config of channel atten threshold done\n");
@@ -2984,17 +2986,17 @@ impl LorgwSx1302Trait for Hal {
             let val = self.sx1302_agc_mailbox_read(0)?;
             if val != agc_params.device_sel {
                 error!("ERROR: wrong device_sel (w:{:} r:{:})\n", agc_params.device_sel, val);
-                return Err(anyhow!("LGW_REG_ERR"));
+                return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "device_sel readback mismatch" }.into());
             }
             let val = self.sx1302_agc_mailbox_read(1)?;
             if val != agc_params.hp_max {
                 error!("ERROR: wrong hp_max (w:{:} r:{:})\n", agc_params.hp_max, val);
-                return Err(anyhow!("LGW_REG_ERR"));
+                return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "hp_max readback mismatch" }.into());
             }
             let val = self.sx1302_agc_mailbox_read(2)?;
             if val != agc_params.pa_duty_cycle {
                 error!("ERROR: wrong pa_duty_cycle (w:{:} r:{:})\n", agc_params.pa_duty_cycle, val);
-                return Err(anyhow!("LGW_REG_ERR"));
+                return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "pa_duty_cycle readback mismatch" }.into());
             }
 
             info!("AGC: config of sx1250 PA optimal settings done\n");
@@ -3016,7 +3018,7 @@ impl LorgwSx1302Trait for Hal {
         let val = self.sx1302_agc_mailbox_read(0)?;
         if val != pa_start_delay {
             error!("ERROR: wrong PA start delay (w:{:} r:{:})\n", pa_start_delay, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "PA start delay readback mismatch" }.into());
         }
 
         info!("AGC: config of PA start delay done\n");
@@ -3036,7 +3038,7 @@ impl LorgwSx1302Trait for Hal {
         let val = self.sx1302_agc_mailbox_read(0)?;
         if (val != 0) != lbt_enable {
             error!("ERROR: wrong LBT configuration (w:{:} r:{:})\n", lbt_enable, val);
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::Firmware { fw: LgwFirmware::AGC, reason: "LBT configuration readback mismatch" }.into());
         }
 
         info!("AGC: LBT is {:}",  if lbt_enable == true { "enabled" } else { "disabled"} );
@@ -3205,13 +3207,13 @@ impl LorgwSx1302Trait for Hal {
         self.lgw_reg_w(SX1302_REG_RX_TOP_LORA_SERVICE_FSK_TXRX_CFG6_PREAMBLE_SYMB_NB, (preamble_nb_symb as i32 >> 0) & 0xFF)?; /* LSB */
     
         /* Freq2TimeDrift computation */
-        let draft = calculate_freq_to_time_drift(radio_freq_hz, cfg.bandwidth);
-        if draft.is_err() {
-            error!("ERROR: failed to calculate frequency to time drift for LoRa service modem\n");
-            return Err(anyhow!("LGW_REG_ERR"));
-        }
-
-        let (mantissa, exponent ) = draft.unwrap();
+        let (mantissa, exponent) = match calculate_freq_to_time_drift(radio_freq_hz, cfg.bandwidth) {
+            Ok(draft) => draft,
+            Err(e) => {
+                error!("ERROR: failed to calculate frequency to time drift for LoRa service modem\n");
+                return Err(e);
+            }
+        };
         self.lgw_reg_w(SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TO_TIME0_FREQ_TO_TIME_DRIFT_MANT, (mantissa as i32 >> 8) & 0x00FF)?;
         self.lgw_reg_w(SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TO_TIME1_FREQ_TO_TIME_DRIFT_MANT, (mantissa as i32) & 0x00FF)?;
         self.lgw_reg_w(SX1302_REG_RX_TOP_LORA_SERVICE_FSK_FREQ_TO_TIME2_FREQ_TO_TIME_DRIFT_EXP, exponent as i32)?;
@@ -3275,7 +3277,7 @@ impl LorgwSx1302Trait for Hal {
             },
             _ => {
                 error!("ERROR: Failed to configure LoRa service modem correlators");
-                return Err(anyhow!("LGW_REG_ERR"));
+                return Err(Error::config("datarate", cfg.datarate, "unsupported LoRa service modem datarate").into());
             }
         }
     
@@ -3371,13 +3373,13 @@ impl LorgwSx1302Trait for Hal {
         self.lgw_reg_w(SX1302_REG_RX_TOP_TXRX_CFG6_PREAMBLE_SYMB_NB, 10)?; /* LSB */
     
         /* Freq2TimeDrift computation */
-        let draft = calculate_freq_to_time_drift(radio_freq_hz, BW_125KHZ);
-        if draft.is_err() {
-            error!("ERROR: failed to calculate frequency to time drift for LoRa modem\n");
-            return Err(anyhow!("LGW_REG_ERR"));
-        }
-
-        let ( mantissa, exponent) = draft.unwrap();
+        let (mantissa, exponent) = match calculate_freq_to_time_drift(radio_freq_hz, BW_125KHZ) {
+            Ok(draft) => draft,
+            Err(e) => {
+                error!("ERROR: failed to calculate frequency to time drift for LoRa modem\n");
+                return Err(e);
+            }
+        };
 
         debug!("Freq2TimeDrift MultiSF: Mantissa = {} (0x{:02X}, 0x{:02X}), Exponent = {} (0x{:02X})\n", mantissa, (mantissa >> 8) & 0x00FF, (mantissa) & 0x00FF, exponent, exponent);
         self.lgw_reg_w(SX1302_REG_RX_TOP_FREQ_TO_TIME0_FREQ_TO_TIME_DRIFT_MANT, (mantissa as i32 >> 8) & 0x00FF)?;
@@ -3599,6 +3601,20 @@ impl LorgwSx1302Trait for Hal {
         Ok(val as u8)
     }
 
+    /* The chip EUI is stored MSB first in the first 8 bytes of the OTP */
+    fn sx1302_get_eui(&mut self) -> Result<u64> {
+        let mut eui: u64 = 0;
+
+        for i in 0 .. 8u8 {
+            self.lgw_reg_w(SX1302_REG_OTP_BYTE_ADDR_ADDR, i as i32)?;
+            wait_ms(1);
+            let val = self.lgw_reg_r(SX1302_REG_OTP_RD_DATA_RD_DATA)?;
+            eui |= (val as u8 as u64) << (56 - (8 * i as u32));
+        }
+
+        Ok(eui)
+    }
+
     /* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ */
 
     fn sx1302_config_gpio(&mut self) -> Result<()> {
@@ -3635,29 +3651,29 @@ impl LorgwSx1302Trait for Hal {
 
         /* Configure timestamping mode */
         if ftime_context.enable == true {
-            if let Ok(model_id) = self.sx1302_get_model_id(){
-                if model_id != CHIP_MODEL_ID_SX1303 {
+            match self.sx1302_get_model_id() {
+                Ok(model_id) if model_id != CHIP_MODEL_ID_SX1303 => {
                     error!("ERROR: Fine Timestamping is not supported on this Chip Model ID 0x{:02X}\n", model_id);
-                    return Err(anyhow!("LGW_REG_ERR"));
+                    return Err(Error::config("ftime.enable", model_id, "fine timestamping requires a SX1303").into());
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("ERROR: failed to get Chip Model ID\n");
+                    return Err(e);
                 }
-                
-            }
-            else{
-                error!("ERROR: failed to get Chip Model ID\n");
-                return Err(anyhow!("LGW_REG_ERR"));
             }
 
             
         }
-        if let Err(_) = self.timestamp_counter_mode(ftime_context.enable){
+        if let Err(e) = self.timestamp_counter_mode(ftime_context.enable){
 
             error!("ERROR: failed to configure timestamp counter mode\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(e);
         }
 
-        if let Err(_) = self.sx1302_config_gpio() {
+        if let Err(e) = self.sx1302_config_gpio() {
             error!("ERROR: failed to configure sx1302 GPIOs\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(e);
         }
         
         Ok(())
@@ -3679,7 +3695,7 @@ impl LorgwSx1302Trait for Hal {
         Ok(())
     }
     
-    fn sx1302_radio_calibrate(&mut self, context_rf_chain: &[LgwConfRxrf],  clksrc:u8, txgain_lut: &[LgwTxGainLut])->Result<()> 
+    fn sx1302_radio_calibrate(&mut self, context_rf_chain: &[LgwConfRxrf],  clksrc:u8, txgain_lut: &mut [LgwTxGainLut])->Result<()> 
     {
         /* -- Reset radios */
         for i in 0 .. LGW_RF_CHAIN_NB {
@@ -3748,12 +3764,12 @@ impl LorgwSx1302Trait for Hal {
         /* Check input parameters */
         if rf_chain >= LGW_RF_CHAIN_NB {
             error!("Invalid RF chain");
-            return Err(anyhow!("Invalid RF chain"));
+            return Err(Error::config("rf_chain", rf_chain, "invalid RF chain").into());
         }
         
         if (radio_type != LgwRadioType::LGW_RADIO_TYPE_SX1255) && (radio_type != LgwRadioType::LGW_RADIO_TYPE_SX1257) && (radio_type != LgwRadioType::LGW_RADIO_TYPE_SX1250) {
             error!("ERROR: invalid radio type\n");
-            return Err(anyhow!("Invalid radio type"));
+            return Err(Error::config("radio_type", radio_type, "invalid radio type").into());
         }
 
         /* Switch to SPI clock before reseting the radio */
@@ -3787,7 +3803,7 @@ impl LorgwSx1302Trait for Hal {
             },
             _ => {
                 error!("Invalid radio type");
-                return Err(anyhow!("Invalid radio type"));
+                return Err(Error::config("radio_type", radio_type, "invalid radio type").into());
             }
         }
         info!("INFO: radio {} reset done", rf_chain);
@@ -3802,11 +3818,11 @@ impl LorgwSx1302Trait for Hal {
         /* Check input parameters */
         if rf_chain >= LGW_RF_CHAIN_NB {
             error!("ERROR: invalid RF chain\n");
-            return Err(anyhow!("invalid RF chain"))
+            return Err(Error::config("rf_chain", rf_chain, "invalid RF chain").into())
         }
         if (radio_type != LgwRadioType::LGW_RADIO_TYPE_SX1255) && (radio_type != LgwRadioType::LGW_RADIO_TYPE_SX1257) && (radio_type != LgwRadioType::LGW_RADIO_TYPE_SX1250) {
             error!("ERROR: invalid radio type\n");
-            return Err(anyhow!("invalid radio type"));
+            return Err(Error::config("radio_type", radio_type, "invalid radio type").into());
         }
 
         /* Set the radio mode */
@@ -3836,7 +3852,7 @@ impl LorgwSx1302Trait for Hal {
     fn sx1302_radio_clock_select(&mut self, rf_chain:u8) -> Result<()> {
         if rf_chain >= LGW_RF_CHAIN_NB {
             error!("ERROR: invalid RF chain\n");
-            return Err(anyhow!("invalid RF chain"));
+            return Err(Error::config("rf_chain", rf_chain, "invalid RF chain").into());
         }
         
 
@@ -3853,7 +3869,7 @@ impl LorgwSx1302Trait for Hal {
                 self.lgw_reg_w(SX1302_REG_CLK_CTRL_CLK_SEL_CLK_RADIO_B_SEL, 0x01)?;
             },
             _ => {
-                return Err(anyhow!("Invalid RF chain"));
+                return Err(Error::config("rf_chain", rf_chain, "invalid RF chain").into());
             }
         }
 
@@ -3866,7 +3882,7 @@ impl LorgwSx1302Trait for Hal {
         /* Check if something went wrong */
         if r1.is_err() || r2.is_err() {
             error!("ERROR: failed to select radio clock for radio_{:}", rf_chain);
-            return Err(anyhow!("failed to select radio clock for radio_{:}", rf_chain));
+            return Err(Error::RadioSetup { rf_chain, reason: "failed to select radio clock" }.into());
         }
 
         Ok(())
@@ -3893,7 +3909,7 @@ impl LorgwSx1302Trait for Hal {
         
         if firmware != fw_check {
             error!("ERROR: AGC fw read/write check failed\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::FirmwareLoad { fw: LgwFirmware::AGC, reason: "read/write check failed" }.into());
         }
 
         /* Release control over AGC MCU */
@@ -3903,7 +3919,7 @@ impl LorgwSx1302Trait for Hal {
         let val = self.lgw_reg_r(SX1302_REG_AGC_MCU_CTRL_PARITY_ERROR)?;
         if val != 0 {
             error!("ERROR: Failed to load AGC fw: parity error check failed\n");
-            return Err(anyhow!("LGW_REG_ERR"));
+            return Err(Error::FirmwareLoad { fw: LgwFirmware::AGC, reason: "parity error check failed" }.into());
         }
 
         info!("AGC fw loaded\n");
@@ -3911,10 +3927,6 @@ impl LorgwSx1302Trait for Hal {
         Ok(())
     }
     
-    fn sx1302_cal_start(&mut self, _fw_version:u8, _context_rf_chain: &[LgwConfRxrf], _txgain_lut: &[LgwTxGainLut])->Result<()> {
-        todo!()
-    }
-    
     fn sx1302_get_ifmod_config(if_chain:u8)-> u8 {
         debug!(if_chain=%if_chain, "{:02X}", IFMOD_CONFIG[if_chain as usize]);
         IFMOD_CONFIG[if_chain as usize]
@@ -3930,14 +3942,13 @@ impl LorgwSx1302Trait for Hal {
         let timestamp_correction = 0;
         
         /* get packet from RX buffer */
-        let pkt = rx_buffer.pop(); 
-        
-        if pkt.is_err() {
-            rx_buffer.del();
-            return Err(anyhow!("LGW_HAL_ERR"))
-        }
-
-        let pkt = pkt.unwrap();
+        let pkt = match rx_buffer.pop() {
+            Ok(pkt) => pkt,
+            Err(e) => {
+                rx_buffer.del();
+                return Err(e);
+            }
+        };
         let mut p = LgwPktRx::new();
         
         /* copy payload to result struct */
@@ -3950,7 +3961,7 @@ impl LorgwSx1302Trait for Hal {
         p.if_chain = pkt.rx_channel_in;
         if p.if_chain >= LGW_IF_CHAIN_NB {
             warn!("WARNING: {:} NOT A VALID IF_CHAIN NUMBER, ABORTING\n", p.if_chain);
-            return Err(anyhow!("LGW_REG_ERROR"));
+            return Err(Error::RxPacket { reason: "IF chain out of range" }.into());
         }
         
         let ifmod = IFMOD_CONFIG[p.if_chain as usize];
@@ -3984,7 +3995,7 @@ impl LorgwSx1302Trait for Hal {
                         if payload_crc16_calc != pkt.rx_crc16_value {
                             error!("ERROR: Payload CRC16 check failed (got:0x{:04X} calc:0x{:04X})\n", pkt.rx_crc16_value, payload_crc16_calc);
                             
-                            return Err(anyhow!("LGW_REG_ERROR"));
+                            return Err(Error::RxPacket { reason: "payload CRC16 check failed" }.into());
                         } else {
                             trace!("Payload CRC check OK (0x{:04X})\n", pkt.rx_crc16_value);
                         }
@@ -4165,7 +4176,7 @@ impl LorgwSx1302Trait for Hal {
         /* Check if we can calculate a ftime */
         if self.sx1302.timestamp_pps_history.size < MAX_TIMESTAMP_PPS_HISTORY {
             error!("INFO: Cannot compute ftime yet, PPS history is too short\n");
-            return Err(anyhow!("LGW_HAL_ERROR"));
+            return Err(Error::FineTimestamp { reason: "PPS history is too short" }.into());
         }
 
         /* Coarse timestamp correction to match with GW v2 (end of header -> end of preamble) */
@@ -4201,10 +4212,9 @@ impl LorgwSx1302Trait for Hal {
         ftime_mean = ftime_sum as f32 / (2 * ts_metrics_nb_clipped) as f32;
 
         /* Find the last timestamp_pps before packet to use as reference for ftime */
-        let x = self.lgw_reg_rb(SX1302_REG_TIMESTAMP_TIMESTAMP_PPS_MSB2_TIMESTAMP_PPS , &mut buff, 4);
-        if x.is_err() {
+        if let Err(e) = self.lgw_reg_rb(SX1302_REG_TIMESTAMP_TIMESTAMP_PPS_MSB2_TIMESTAMP_PPS , &mut buff, 4) {
             error!("ERROR: Failed to get timestamp counter value\n");
-            return Err(anyhow!("LGW_REG_ERROR"));
+            return Err(e);
         }
         timestamp_pps_reg  = ((buff[0] as u32) << 24) & 0xFF000000;
         timestamp_pps_reg |= ((buff[1] as u32) << 16) & 0x00FF0000;
@@ -4228,7 +4238,7 @@ impl LorgwSx1302Trait for Hal {
             }
             if timestamp_pps_idx == self.sx1302.timestamp_pps_history.size {
                 error!("ERROR: failed to find the reference timestamp_pps, cannot compute ftime\n");
-                return Err(anyhow!("LGW_REG_ERROR"));
+                return Err(Error::FineTimestamp { reason: "reference PPS timestamp not found" }.into());
             }
 
             /* Calculate the Xtal error between the reference PPS we just found and the next one */
@@ -4250,7 +4260,7 @@ impl LorgwSx1302Trait for Hal {
         /* Sanity Check on xtal_correct */
         if (xtal_correct > 1.2) || (xtal_correct < 0.8) {
             error!("ERROR: xtal_error is invalid ({:})\n", xtal_correct);
-            return Err(anyhow!("LGW_REG_ERROR"));
+            return Err(Error::FineTimestamp { reason: "invalid XTAL correction" }.into());
         }
 
         /* Coarse timestamp based on PPS reference */
@@ -4276,7 +4286,7 @@ impl LorgwSx1302Trait for Hal {
         let result_ftime = pkt_ftime as u32;
         if result_ftime > 1000000000 {
             error!("ERROR: fine timestamp is out of range ({:})\n", result_ftime);
-            return Err(anyhow!("LGW_REG_ERROR"));
+            return Err(Error::FineTimestamp { reason: "fine timestamp out of range" }.into());
         }
 
         trace!("==> ftime = {:} ns since last PPS ({:})\n", result_ftime, pkt_ftime);
@@ -4312,7 +4322,7 @@ fn lora_crc16(data: u8, crc: &mut u16) {
 
 
 
-fn sx1302_lora_payload_crc(data: &[u8], size:usize) -> u16 {
+pub(crate) fn sx1302_lora_payload_crc(data: &[u8], size:usize) -> u16 {
     let mut crc = 0;
 
     for i in 0 .. size {
@@ -4323,6 +4333,12 @@ fn sx1302_lora_payload_crc(data: &[u8], size:usize) -> u16 {
     crc as u16
 }
 
+/* RSSI correction for the board temperature, in dB, see LgwRssiTcomp */
+pub(crate) fn sx1302_rssi_get_temperature_offset(context: &LgwRssiTcomp, temperature: f32) -> f32 {
+    let t = temperature;
+    (context.coeff_a * t.powi(4) + context.coeff_b * t.powi(3) + context.coeff_c * t.powi(2) + context.coeff_d * t + context.coeff_e) / 65536.0
+}
+
 fn sx1302_dc_notch_delay(if_freq_khz: f64) -> f64 {
     if if_freq_khz < -75.0 || if_freq_khz > 75.0 {
         0.0
@@ -4334,3 +4350,32 @@ fn sx1302_dc_notch_delay(if_freq_khz: f64) -> f64 {
             + 10.2922
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rssi_temperature_offset() {
+        let zero = LgwRssiTcomp { coeff_a: 0.0, coeff_b: 0.0, coeff_c: 0.0, coeff_d: 0.0, coeff_e: 0.0 };
+        assert_eq!(sx1302_rssi_get_temperature_offset(&zero, 42.0), 0.0);
+
+        /* one coefficient per power of the temperature, scaled by 2^16 */
+        let unit = |i: usize| {
+            let mut c = [0.0f32; 5];
+            c[i] = 65536.0;
+            LgwRssiTcomp { coeff_a: c[0], coeff_b: c[1], coeff_c: c[2], coeff_d: c[3], coeff_e: c[4] }
+        };
+        assert_eq!(sx1302_rssi_get_temperature_offset(&unit(0), 2.0), 16.0);
+        assert_eq!(sx1302_rssi_get_temperature_offset(&unit(1), 2.0), 8.0);
+        assert_eq!(sx1302_rssi_get_temperature_offset(&unit(2), 2.0), 4.0);
+        assert_eq!(sx1302_rssi_get_temperature_offset(&unit(3), -2.0), -2.0);
+        assert_eq!(sx1302_rssi_get_temperature_offset(&unit(4), 2.0), 1.0);
+
+        /* default SX1250 compensation: (20.41 * t^2 + 2162.56 * t) / 65536 */
+        let tcomp = LgwRssiTcomp::default();
+        assert_eq!(sx1302_rssi_get_temperature_offset(&tcomp, 0.0), 0.0);
+        assert!((sx1302_rssi_get_temperature_offset(&tcomp, 25.0) - 1.01960).abs() < 1e-4);
+        assert!((sx1302_rssi_get_temperature_offset(&tcomp, -20.0) - -0.53539).abs() < 1e-4);
+    }
+}
@@ -1,8 +1,9 @@
 use super::{LoragwRegTrait, BW_125KHZ, BW_250KHZ, BW_500KHZ, SX1302_REG_RX_TOP_RX_BUFFER_LEGACY_TIMESTAMP, SX1302_REG_RX_TOP_RXBUFFER_TIMESTAMP_CFG_MAX_TS_METRICS, SX1302_REG_RX_TOP_TIMESTAMP_ENABLE, SX1302_REG_RX_TOP_TIMESTAMP_NB_SYMB};
 
 use super::{LgwContext, loragw_sx1302::{RX_DFT_PEAK_MODE_DISABLED, SET_PPM_ON}, Hal};
-use anyhow::{anyhow,Result};
+use anyhow::Result;
 use tracing::{debug, error, trace};
+use super::error::Error;
 
 
 pub const PRECISION_TIMESTAMP_TS_METRICS_MAX:u8 =  32; /* reduce number of metrics to better match GW v2 fine timestamp (max is 255) */
@@ -373,15 +374,15 @@ pub fn lora_packet_time_on_air(
     /* Check input parameters */
     if !Hal::is_lora_dr(sf as u32) {
         error!("ERROR: wrong datarate");
-        return Err(anyhow!("LGW_HAL_ERR"));
+        return Err(Error::config("datarate", sf, "not a LoRa datarate").into());
     }
     if !Hal::is_lora_bw(bw) {
         error!("ERROR: wrong bandwidth");
-        return Err(anyhow!("LGW_HAL_ERR"));
+        return Err(Error::config("bandwidth", bw, "not a LoRa bandwidth").into());
     }
     if !Hal::is_lora_cr(cr) {
         error!("ERROR: wrong coding rate");
-        return Err(anyhow!("LGW_HAL_ERR"));
+        return Err(Error::config("coderate", cr, "not a LoRa coding rate").into());
     }
 
     /* Get bandwidth 125KHz divider*/
@@ -391,7 +392,7 @@ pub fn lora_packet_time_on_air(
         BW_500KHZ => 4,
         _ => {
             error!("ERROR: unsupported bandwidth 0x{:02X}", bw);
-            return Err(anyhow!("LGW_HAL_ERR"));
+            return Err(Error::config("bandwidth", bw, "unsupported bandwidth").into());
         }
     };
 
@@ -5,6 +5,8 @@ use serialport::{FlowControl, Parity, SerialPort, StopBits};
 use tracing::trace;
 use anyhow::Result;
 
+use super::loragw_com::LgwComTransport;
+
 #[derive(Debug)]
 pub struct UsbComPort {
     pub port: Mutex<Option<Box<dyn SerialPort>>>,
@@ -16,8 +18,16 @@ impl UsbComPort {
             port: Mutex::new(None)
         }
     }
+}
+
+impl Default for UsbComPort {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    pub fn open(&mut self, port_name: String) -> Result<()> {
+impl LgwComTransport for UsbComPort {
+    fn open(&mut self, port_name: String) -> Result<()> {
         let port = serialport::new(port_name.as_str(), 115200)
             .flow_control(FlowControl::None)
             .parity(Parity::None)
@@ -30,16 +40,18 @@ impl UsbComPort {
         Ok(())
     }
 
-    pub fn close(&mut self) {
+    fn close(&mut self) {
         let port = self.port.lock().unwrap();
 
         drop(port);
         *self.port.lock().unwrap() = None;
     }
-}
 
-impl UsbComPort {
-    pub fn write(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
+    fn is_open(&self) -> bool {
+        self.port.lock().unwrap().is_some()
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
         trace!(size=%data.len(), "WR: {:02X?}", data);
         
         let mut p = self.port.lock().unwrap();
@@ -50,7 +62,7 @@ impl UsbComPort {
         
     }
 
-    pub fn read(&mut self, data: &mut [u8]) -> Result<usize, std::io::Error> {
+    fn read(&mut self, data: &mut [u8]) -> Result<usize, std::io::Error> {
         let mut p = self.port.lock().unwrap();
 
         if let Some(ref mut port) = *p {
@@ -59,7 +71,7 @@ impl UsbComPort {
         Err(std::io::Error::new(std::io::ErrorKind::Other, "Port not open"))
     }
 
-    pub fn read_exact(&mut self, data: &mut [u8]) -> Result<(), std::io::Error> {
+    fn read_exact(&mut self, data: &mut [u8]) -> Result<(), std::io::Error> {
         
         let mut p = self.port.lock().unwrap();
         if let Some(ref mut port) = *p {
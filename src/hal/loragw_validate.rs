@@ -0,0 +1,399 @@
+/*
+    Whole-configuration validator.
+
+    lgw_*_setconf check each chain on its own, the checks below look at the configuration as a
+    whole, as it is stored in LgwContext once every setconf function has been called, and
+    report every problem found instead of stopping at the first one. Issues name the
+    global_conf.json key to change ("radio_1", "chan_multiSF_3"...) and suggest a fix.
+
+    Two LoRa channels with different bandwidths may overlap, the demodulators tell them apart,
+    this is the case of the LoRa service channel of the EU868 reference configuration.
+*/
+
+use std::fmt;
+
+use super::loragw_planner::rf_rx_bandwidth;
+use super::loragw_sx1302::{IF_FSK_STD, IF_LORA_MULTI, IF_LORA_STD};
+use super::{Hal, LgwContext, LorgwSx1302Trait, BW_125KHZ, BW_250KHZ, BW_UNDEFINED, LGW_IF_CHAIN_NB, LGW_RF_CHAIN_NB};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum LgwConfSeverity {
+    ERROR,      /* lgw_start fails, or packets are lost or duplicated */
+    WARNING,    /* works, but is most likely not what was intended */
+}
+
+/**
+@struct LgwConfIssue
+@brief Problem found in the configuration, with a suggested fix
+*/
+#[derive(Debug, Clone)]
+pub struct LgwConfIssue {
+    pub severity: LgwConfSeverity,
+    pub key: String,        //*!> global_conf.json key of the offending setting, e.g. "chan_multiSF_3" */
+    pub problem: String,
+    pub fix: String,
+}
+
+impl fmt::Display for LgwConfIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}: {} (fix: {})", self.severity, self.key, self.problem, self.fix)
+    }
+}
+
+/* Enabled IF chain, with its absolute frequency and the bandwidth actually used by the modem */
+struct Chan {
+    if_chain: usize,
+    key: String,
+    modem: u8,
+    rf_chain: usize,
+    freq_hz: i64,
+    bw_hz: i64,
+}
+
+fn chan_key(if_chain: usize) -> String {
+    match <Hal as LorgwSx1302Trait>::sx1302_get_ifmod_config(if_chain as u8) {
+        IF_LORA_STD => "chan_Lora_std".to_string(),
+        IF_FSK_STD => "chan_FSK".to_string(),
+        _ => format!("chan_multiSF_{}", if_chain),
+    }
+}
+
+/* Bandwidth filled by lgw_rxif_setconf when the configuration leaves it undefined */
+fn chan_bandwidth(ctx: &LgwContext, modem: u8) -> u8 {
+    let bw = match modem {
+        IF_LORA_STD => ctx.lora_service_cfg.bandwidth,
+        IF_FSK_STD => ctx.fsk_cfg.bandwidth,
+        _ => BW_125KHZ,
+    };
+    if bw == BW_UNDEFINED { BW_250KHZ } else { bw }
+}
+
+struct Issues(Vec<LgwConfIssue>);
+
+impl Issues {
+    fn push(&mut self, severity: LgwConfSeverity, key: impl Into<String>, problem: String, fix: String) {
+        self.0.push(LgwConfIssue { severity, key: key.into(), problem, fix });
+    }
+
+    fn error(&mut self, key: impl Into<String>, problem: String, fix: String) {
+        self.push(LgwConfSeverity::ERROR, key, problem, fix);
+    }
+
+    fn warning(&mut self, key: impl Into<String>, problem: String, fix: String) {
+        self.push(LgwConfSeverity::WARNING, key, problem, fix);
+    }
+}
+
+/* Check the whole configuration held in ctx, returns every issue found (none if it is valid) */
+pub fn lgw_validate_context(ctx: &LgwContext) -> Vec<LgwConfIssue> {
+    let mut issues = Issues(Vec::new());
+    let rf = &ctx.rf_chain_cfg;
+    let enabled_radios: Vec<usize> = (0 .. LGW_RF_CHAIN_NB as usize).filter(|&i| rf[i].enable).collect();
+
+    let chans: Vec<Chan> = (0 .. LGW_IF_CHAIN_NB as usize)
+        .filter(|&i| ctx.if_chain_cfg[i].enable)
+        .map(|i| {
+            let ifc = &ctx.if_chain_cfg[i];
+            let modem = <Hal as LorgwSx1302Trait>::sx1302_get_ifmod_config(i as u8);
+            let rf_freq = rf.get(ifc.rf_chain as usize).map(|r| r.freq_hz).unwrap_or(0);
+            Chan {
+                if_chain: i,
+                key: chan_key(i),
+                modem,
+                rf_chain: ifc.rf_chain as usize,
+                freq_hz: rf_freq as i64 + ifc.freq_hz as i64,
+                bw_hz: Hal::lgw_bw_getval(chan_bandwidth(ctx, modem)) as i64,
+            }
+        })
+        .collect();
+
+    /* clock source */
+    let clksrc = ctx.board_cfg.clksrc as usize;
+    if clksrc >= LGW_RF_CHAIN_NB as usize {
+        issues.error("clksrc", format!("radio_{} does not exist", clksrc),
+            format!("set clksrc to the radio wired to the SX1302 clock input (0 to {})", LGW_RF_CHAIN_NB - 1));
+    } else if !rf[clksrc].enable {
+        let fix = match enabled_radios.first() {
+            Some(i) => format!("enable radio_{}, or set clksrc to {} if that radio provides the clock", clksrc, i),
+            None => format!("enable radio_{}", clksrc),
+        };
+        issues.error("clksrc", format!("clock source radio_{} is disabled, the SX1302 would have no clock", clksrc), fix);
+    }
+
+    /* radios */
+    for (i, radio) in rf.iter().enumerate() {
+        let key = format!("radio_{}", i);
+        if radio.enable && radio.tx_enable && ctx.tx_gain_lut[i].size == 0 {
+            issues.error(key.as_str(), "TX is enabled but there is no TX gain table, lgw_send cannot pick a power setting".to_string(),
+                format!("add a tx_gain_lut to {}, or set its tx_enable to false", key));
+        }
+        if radio.enable && i != clksrc && !radio.tx_enable && !chans.iter().any(|c| c.rf_chain == i) {
+            issues.warning(key.as_str(), "radio is enabled but no IF chain uses it".to_string(),
+                format!("disable {}, or assign channels to it", key));
+        }
+    }
+
+    /* IF chains against their radio */
+    for c in chans.iter() {
+        let ifc = &ctx.if_chain_cfg[c.if_chain];
+        if c.rf_chain >= LGW_RF_CHAIN_NB as usize {
+            issues.error(c.key.as_str(), format!("bound to radio_{} which does not exist", c.rf_chain),
+                format!("set its radio to 0 to {}", LGW_RF_CHAIN_NB - 1));
+            continue;
+        }
+        if !rf[c.rf_chain].enable {
+            let fix = match enabled_radios.first() {
+                Some(i) => format!("enable radio_{}, or bind the channel to radio_{}", c.rf_chain, i),
+                None => format!("enable radio_{}", c.rf_chain),
+            };
+            issues.error(c.key.as_str(), format!("bound to radio_{} which is disabled, it will never receive", c.rf_chain), fix);
+            continue;
+        }
+
+        let half_band = rf_rx_bandwidth(chan_bandwidth(ctx, c.modem)) as i64 / 2;
+        let max_if = half_band - c.bw_hz / 2;
+        if (ifc.freq_hz as i64).abs() > max_if {
+            issues.error(c.key.as_str(),
+                format!("IF offset {:+} Hz ({} Hz) is outside of radio_{} usable band, |IF| must not exceed {} Hz for a {} Hz channel",
+                    ifc.freq_hz, c.freq_hz, c.rf_chain, max_if, c.bw_hz),
+                format!("move radio_{} center closer to {} Hz, or bind the channel to the other radio", c.rf_chain, c.freq_hz));
+        }
+    }
+
+    /* multi-SF demodulators */
+    let multisf_used = chans.iter().any(|c| c.modem == IF_LORA_MULTI);
+    if multisf_used && ctx.demod_cfg.multisf_datarate == 0 {
+        issues.error("chan_multiSF_All", "no spreading factor is enabled for the multi-SF demodulators, multi-SF channels receive nothing".to_string(),
+            "set spreading_factor_enable, e.g. [7, 8, 9, 10, 11, 12]".to_string());
+    }
+
+    /* LoRa service channel */
+    let lora_std = &ctx.lora_service_cfg;
+    if chans.iter().any(|c| c.modem == IF_LORA_STD) && lora_std.implicit_hdr && lora_std.implicit_payload_length == 0 {
+        issues.error("chan_Lora_std", "implicit header mode with a payload length of 0".to_string(),
+            "set implicit_payload_length, or set implicit_hdr to false".to_string());
+    }
+
+    /* FSK channel */
+    let fsk = &ctx.fsk_cfg;
+    if chans.iter().any(|c| c.modem == IF_FSK_STD) && fsk.sync_word_size < 8 && fsk.sync_word >> (8 * fsk.sync_word_size as u32) != 0 {
+        issues.warning("chan_FSK", format!("sync word 0x{:X} is wider than sync_word_size ({} bytes), upper bytes are ignored", fsk.sync_word, fsk.sync_word_size),
+            "increase sync_word_size, or shorten sync_word".to_string());
+    }
+
+    /* channels overlapping each other, among those actually received */
+    let received: Vec<&Chan> = chans.iter().filter(|c| rf.get(c.rf_chain).is_some_and(|r| r.enable)).collect();
+    for (n, a) in received.iter().enumerate() {
+        for b in received[n + 1 ..].iter() {
+            let spacing = (a.freq_hz - b.freq_hz).abs();
+            let needed = (a.bw_hz + b.bw_hz) / 2;
+            if spacing >= needed {
+                continue;
+            }
+
+            let lora = |c: &Chan| c.modem == IF_LORA_STD || c.modem == IF_LORA_MULTI;
+            if lora(a) && lora(b) && a.bw_hz != b.bw_hz {
+                continue;
+            }
+
+            let problem = if lora(a) && lora(b) {
+                format!("overlaps {} ({} Hz and {} Hz, {} Hz apart), packets are lost or reported twice", b.key, a.freq_hz, b.freq_hz, spacing)
+            } else {
+                format!("overlaps {} ({} Hz and {} Hz, {} Hz apart), LoRa and FSK traffic interfere", b.key, a.freq_hz, b.freq_hz, spacing)
+            };
+            issues.error(a.key.as_str(), problem,
+                format!("space the two channels by at least {} Hz, or disable one of them", needed));
+        }
+    }
+
+    issues.0
+}
+
+impl Hal {
+    /* Check the configuration set through the lgw_*_setconf functions, see lgw_validate_context */
+    pub fn lgw_validate_conf(&self) -> Vec<LgwConfIssue> {
+        lgw_validate_context(&self.ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::loragw_region::{LgwChannelPlan, LgwRegion};
+    use crate::hal::{LgwConfigBoard, LgwRadioType};
+
+    /* EU868 reference plan: multi-SF channels 0 to 7, the LoRa service channel on 868.3MHz
+       (over chan_multiSF_1) and the FSK channel, radio_0 is the clock source */
+    fn eu868() -> LgwContext {
+        let conf = LgwChannelPlan::new(LgwRegion::EU868).unwrap()
+            .global_conf(LgwConfigBoard { lorawan_public: true, ..Default::default() }, LgwRadioType::LGW_RADIO_TYPE_SX1250);
+        let mut hal = Hal::new();
+        conf.apply(&mut hal).unwrap();
+        hal.ctx
+    }
+
+    fn only_issue(ctx: &LgwContext) -> LgwConfIssue {
+        let issues = lgw_validate_context(ctx);
+        assert_eq!(issues.len(), 1, "{:#?}", issues);
+        issues[0].clone()
+    }
+
+    #[test]
+    fn valid_conf() {
+        let ctx = eu868();
+        assert!(lgw_validate_context(&ctx).is_empty(), "{:#?}", lgw_validate_context(&ctx));
+    }
+
+    #[test]
+    fn overlap() {
+        let mut ctx = eu868();
+        ctx.if_chain_cfg[1].rf_chain = ctx.if_chain_cfg[0].rf_chain;
+        ctx.if_chain_cfg[1].freq_hz = ctx.if_chain_cfg[0].freq_hz + 100_000;
+
+        let issue = only_issue(&ctx);
+        assert_eq!((issue.severity, issue.key.as_str()), (LgwConfSeverity::ERROR, "chan_multiSF_0"));
+        assert!(issue.problem.starts_with("overlaps chan_multiSF_1"), "{}", issue);
+        assert!(issue.problem.contains("100000 Hz apart"), "{}", issue);
+        assert!(issue.fix.contains("at least 125000 Hz"), "{}", issue);
+    }
+
+    #[test]
+    fn overlap_lora_bandwidths() {
+        /* LoRa service channel (250kHz) over chan_multiSF_1 (125kHz): allowed */
+        let mut ctx = eu868();
+        let std = &ctx.if_chain_cfg[8];
+        let multisf_1 = &ctx.if_chain_cfg[1];
+        assert_eq!(ctx.rf_chain_cfg[std.rf_chain as usize].freq_hz as i64 + std.freq_hz as i64,
+            ctx.rf_chain_cfg[multisf_1.rf_chain as usize].freq_hz as i64 + multisf_1.freq_hz as i64);
+        assert_eq!(ctx.lora_service_cfg.bandwidth, BW_250KHZ);
+        assert!(lgw_validate_context(&ctx).is_empty());
+
+        /* same bandwidth: the two demodulators receive the same packets */
+        ctx.lora_service_cfg.bandwidth = BW_125KHZ;
+        let issue = only_issue(&ctx);
+        assert_eq!((issue.severity, issue.key.as_str()), (LgwConfSeverity::ERROR, "chan_multiSF_1"));
+        assert!(issue.problem.starts_with("overlaps chan_Lora_std") && issue.problem.contains("reported twice"), "{}", issue);
+
+        /* LoRa and FSK never share a band, whatever the bandwidths */
+        let mut ctx = eu868();
+        ctx.if_chain_cfg[9].rf_chain = ctx.if_chain_cfg[7].rf_chain;
+        ctx.if_chain_cfg[9].freq_hz = ctx.if_chain_cfg[7].freq_hz;
+        let issue = only_issue(&ctx);
+        assert_eq!(issue.key, "chan_multiSF_7");
+        assert!(issue.problem.starts_with("overlaps chan_FSK") && issue.problem.contains("LoRa and FSK"), "{}", issue);
+    }
+
+    #[test]
+    fn disabled_radio() {
+        let mut ctx = eu868();
+        let bound: Vec<String> = (0 .. LGW_IF_CHAIN_NB as usize)
+            .filter(|&i| ctx.if_chain_cfg[i].enable && ctx.if_chain_cfg[i].rf_chain == 1)
+            .map(chan_key)
+            .collect();
+        assert!(!bound.is_empty());
+        ctx.rf_chain_cfg[1].enable = false;
+
+        /* one error per channel, and no overlap reported with channels that are not received */
+        let issues = lgw_validate_context(&ctx);
+        assert_eq!(issues.iter().map(|i| i.key.clone()).collect::<Vec<_>>(), bound);
+        for issue in issues.iter() {
+            assert_eq!(issue.severity, LgwConfSeverity::ERROR);
+            assert!(issue.problem.contains("radio_1 which is disabled"), "{}", issue);
+            assert_eq!(issue.fix, "enable radio_1, or bind the channel to radio_0");
+        }
+
+        ctx.if_chain_cfg[0].rf_chain = 2;
+        let issues = lgw_validate_context(&ctx);
+        assert!(issues.iter().any(|i| i.key == "chan_multiSF_0" && i.problem.contains("radio_2 which does not exist")), "{:#?}", issues);
+    }
+
+    #[test]
+    fn unused_radio() {
+        let mut ctx = eu868();
+        for ifc in ctx.if_chain_cfg.iter_mut() {
+            ifc.rf_chain = 0;
+            ifc.freq_hz = 0;
+            ifc.enable = false;
+        }
+        ctx.if_chain_cfg[0].enable = true;
+
+        let issue = only_issue(&ctx);
+        assert_eq!((issue.severity, issue.key.as_str()), (LgwConfSeverity::WARNING, "radio_1"));
+        assert_eq!(issue.fix, "disable radio_1, or assign channels to it");
+    }
+
+    #[test]
+    fn clksrc() {
+        let mut ctx = eu868();
+        ctx.board_cfg.clksrc = 2;
+        let issue = only_issue(&ctx);
+        assert_eq!((issue.severity, issue.key.as_str(), issue.problem.as_str()), (LgwConfSeverity::ERROR, "clksrc", "radio_2 does not exist"));
+
+        /* clock source disabled, the channels of radio_0 moved to radio_1 */
+        let mut ctx = eu868();
+        ctx.rf_chain_cfg[0].enable = false;
+        for ifc in ctx.if_chain_cfg.iter_mut().filter(|ifc| ifc.enable) {
+            let freq = ctx.rf_chain_cfg[ifc.rf_chain as usize].freq_hz as i32 + ifc.freq_hz;
+            ifc.rf_chain = 1;
+            ifc.freq_hz = freq - ctx.rf_chain_cfg[1].freq_hz as i32;
+        }
+        let issues = lgw_validate_context(&ctx);
+        let issue = issues.iter().find(|i| i.key == "clksrc").unwrap_or_else(|| panic!("{:#?}", issues));
+        assert!(issue.problem.contains("clock source radio_0 is disabled"), "{}", issue);
+        assert_eq!(issue.fix, "enable radio_0, or set clksrc to 1 if that radio provides the clock");
+    }
+
+    #[test]
+    fn missing_gain_lut() {
+        let mut ctx = eu868();
+        ctx.rf_chain_cfg[0].tx_enable = true;
+        let issue = only_issue(&ctx);
+        assert_eq!((issue.severity, issue.key.as_str()), (LgwConfSeverity::ERROR, "radio_0"));
+        assert!(issue.problem.contains("no TX gain table"), "{}", issue);
+
+        /* a disabled radio does not transmit */
+        ctx.rf_chain_cfg[0].tx_enable = false;
+        ctx.rf_chain_cfg[1].tx_enable = true;
+        ctx.rf_chain_cfg[1].enable = false;
+        assert!(lgw_validate_context(&ctx).iter().all(|i| i.key != "radio_1"));
+    }
+
+    #[test]
+    fn empty_multisf_mask() {
+        let mut ctx = eu868();
+        ctx.demod_cfg.multisf_datarate = 0;
+        let issue = only_issue(&ctx);
+        assert_eq!((issue.severity, issue.key.as_str()), (LgwConfSeverity::ERROR, "chan_multiSF_All"));
+
+        /* nothing to report without multi-SF channels */
+        for ifc in ctx.if_chain_cfg[.. 8].iter_mut() {
+            ifc.enable = false;
+        }
+        assert!(lgw_validate_context(&ctx).iter().all(|i| i.key != "chan_multiSF_All"));
+    }
+
+    #[test]
+    fn lora_std_implicit_header() {
+        let mut ctx = eu868();
+        ctx.lora_service_cfg.implicit_hdr = true;
+        let issue = only_issue(&ctx);
+        assert_eq!((issue.severity, issue.key.as_str()), (LgwConfSeverity::ERROR, "chan_Lora_std"));
+
+        ctx.lora_service_cfg.implicit_payload_length = 17;
+        assert!(lgw_validate_context(&ctx).is_empty());
+    }
+
+    #[test]
+    fn fsk_sync_word() {
+        let mut ctx = eu868();
+        ctx.fsk_cfg.sync_word_size = 2;
+        ctx.fsk_cfg.sync_word = 0xC194C1;
+        let issue = only_issue(&ctx);
+        assert_eq!((issue.severity, issue.key.as_str()), (LgwConfSeverity::WARNING, "chan_FSK"));
+        assert!(issue.problem.starts_with("sync word 0xC194C1 is wider than sync_word_size (2 bytes)"), "{}", issue);
+
+        ctx.fsk_cfg.sync_word_size = 3;
+        assert!(lgw_validate_context(&ctx).is_empty());
+    }
+}
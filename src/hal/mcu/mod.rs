@@ -1,12 +1,15 @@
 pub mod command;
 mod helper;
+pub mod sim;
+pub mod record;
 
 
 use command::{*};
 use rand::Rng;
 use tracing::{debug, error, trace};
 use super::loragw_usb_com::UsbComPort;
-use anyhow::{anyhow,Result};
+use super::loragw_com::LgwComTransport;
+use anyhow::Result;
 use crate::hal::{error::Error, loragw_com::LgwSpiMuxTarget};
 
 const MAX_SIZE_COMMAND:usize =  4200;
@@ -34,12 +37,12 @@ pub struct Status{
 impl Status {
     pub fn from_get_status(hdr: &[u8], payload:&[u8]) -> Result<Status> {
         if hdr[CommandOrderOffset::Cmd as usize] != OrderId::AckGetStatus as u8 {
-            return Err(anyhow!("ERROR: received wrong ACK type {:}\n", hdr[CommandOrderOffset::Cmd as usize]));
+            return Err(Error::WrongAck { expected: OrderId::AckGetStatus as u8, received: hdr[CommandOrderOffset::Cmd as usize] }.into());
         }
 
         Ok(Status {
             system_time_ms: helper::bytes_be_to_uint32_le(&payload[ECmdOffsetAckGetStatus::AckGetStatusSystemTime31_24 as usize..])?,
-            temperature: (((payload[ECmdOffsetAckGetStatus::AckGetStatusTemperature15_8 as usize] as u16) << 8) | (payload[ECmdOffsetAckGetStatus::AckGetStatusTemperature7_0 as usize] as u16)) as f32 / 100.0
+            temperature: (((payload[ECmdOffsetAckGetStatus::AckGetStatusTemperature15_8 as usize] as u16) << 8) | (payload[ECmdOffsetAckGetStatus::AckGetStatusTemperature7_0 as usize] as u16)) as i16 as f32 / 100.0
         })
     }
 }
@@ -48,7 +51,7 @@ impl PingInfo {
     pub fn from_ping(hdr: &[u8], payload:&[u8]) -> Result<PingInfo> {
 
         if hdr[CommandOrderOffset::Cmd as usize] != OrderId::AckPing as u8 {
-            return Err(anyhow!("ERROR: received wrong ACK type {:}\n", hdr[CommandOrderOffset::Cmd as usize]));
+            return Err(Error::WrongAck { expected: OrderId::AckPing as u8, received: hdr[CommandOrderOffset::Cmd as usize] }.into());
         }
 
         Ok(PingInfo {
@@ -71,18 +74,24 @@ pub struct SpiReqBulk {
 
 #[derive(Debug)]
 pub struct Mcu {
-    pub com: UsbComPort,
+    pub com: Box<dyn LgwComTransport>,
     pub ping_info: Option<PingInfo>,
     pub status: Option<Status>,
     pub spi_req_bulk: SpiReqBulk,
     pub lgw_write_mode: EComWriteMode,
-    pub lgw_spi_req_nb: u8
+    pub lgw_spi_req_nb: u8,
+    #[cfg(feature = "prometheus")]
+    pub metrics: Option<std::sync::Arc<super::loragw_metrics::LgwMetrics>>,     /* SPI round trips, see lgw_metrics_enable */
 }
 
 impl Mcu {
     pub fn new () -> Self {
+        Self::with_transport(Box::new(UsbComPort::new()))
+    }
+
+    pub fn with_transport(com: Box<dyn LgwComTransport>) -> Self {
         Self {
-            com: UsbComPort::new(),
+            com,
             ping_info: None,
             status: None,
             spi_req_bulk: SpiReqBulk {
@@ -91,12 +100,17 @@ impl Mcu {
                 buffer: [0u8; LGW_USB_BURST_CHUNK]
             },
             lgw_write_mode: EComWriteMode::LGW_COM_WRITE_MODE_SINGLE,
-            lgw_spi_req_nb: 0
+            lgw_spi_req_nb: 0,
+            #[cfg(feature = "prometheus")]
+            metrics: None,
         }
     }
     
     pub fn open(&mut self, port_name: String) -> Result<()> {
-        self.com.open(port_name)?;
+        /* transports connected beforehand (e.g. simulator stream) are used as is */
+        if !self.com.is_open() {
+            self.com.open(port_name)?;
+        }
 
         let ping_info = self.mcu_ping()?;
 
@@ -116,7 +130,7 @@ impl Mcu {
         let buf_w = &mut [0u8; HEADER_CMD_SIZE];
 
         if payload.len() > MAX_SPI_COMMAND {
-            return Err(anyhow!("Data too long"));
+            return Err(Error::RequestTooLarge { size: payload.len(), max: MAX_SPI_COMMAND }.into());
         }
 
         let mut rng = rand::thread_rng();
@@ -125,16 +139,16 @@ impl Mcu {
         buf_w[2] = (payload.len() & 0xFF) as u8; // LSB
         buf_w[3] = order as u8;
 
-        let _n = self.com.write(buf_w)?;
+        let _n = self.com.write(buf_w).map_err(|e| Error::Transport { op: "writing request header", source: e })?;
 
         if _n < HEADER_CMD_SIZE {
-            return Err(anyhow!("Failed to write to serial port"));
+            return Err(Error::Transport { op: "writing request header", source: std::io::ErrorKind::WriteZero.into() }.into());
         }
         //wait_ms(10);
         if payload.len() > 0 {
-            let _n = self.com.write(payload)?;
+            let _n = self.com.write(payload).map_err(|e| Error::Transport { op: "writing request payload", source: e })?;
             if _n < payload.len() {
-                return Err(anyhow!("Failed to write to serial port"));
+                return Err(Error::Transport { op: "writing request payload", source: std::io::ErrorKind::WriteZero.into() }.into());
             }
         }
 
@@ -142,24 +156,27 @@ impl Mcu {
         Ok(())
     }
 
-    fn read_ack(&mut self, hdr: &mut [u8], buf: &mut [u8]) -> Result<()> {
+    fn read_ack(&mut self, expected: OrderId, hdr: &mut [u8], buf: &mut [u8]) -> Result<()> {
         
-        self.com.read_exact(hdr).map_err(|e| anyhow!("Failed to read HDR: {}", e))?;
+        self.com.read_exact(hdr).map_err(|e| Error::Transport { op: "reading ACK header", source: e })?;
         
         trace!("READ HD: {:02X?}", hdr);
 
-        if  hdr[CommandOrderOffset::Cmd as usize] < OrderId::AckPing as u8 ||
-            hdr[CommandOrderOffset::Cmd as usize] > OrderId::AckMultipleSpi as u8 
-        {
-            return Err(anyhow!("ERROR: received wrong ACK type {:}\n", hdr[CommandOrderOffset::Cmd as usize]))
+        let expected = expected as u8;
+        if hdr[CommandOrderOffset::Cmd as usize] != expected {
+            return Err(Error::WrongAck { expected, received: hdr[CommandOrderOffset::Cmd as usize] }.into())
         }
 
         let size = (((hdr[1] as u16) << 8) | (hdr[2] as u16)) as usize;
         trace!("READ SIZE: {:}", size);
 
+        if size > buf.len() {
+            return Err(Error::AckTooLarge { size, max: buf.len() }.into());
+        }
+
         let mut read_buf = vec![0u8; size];
         if size > 0 {
-            self.com.read_exact(&mut read_buf).map_err(|e| anyhow!("Failed to read Payload: {}", e))?;
+            self.com.read_exact(&mut read_buf).map_err(|e| Error::Transport { op: "reading ACK payload", source: e })?;
             buf[..size].copy_from_slice(&read_buf);
         }
 
@@ -172,7 +189,7 @@ impl Mcu {
         let mut buf_hdr = [0u8; HEADER_CMD_SIZE];
 
         self.write_req(OrderId::ReqPing, &buf_w)?;
-        self.read_ack(&mut buf_hdr, &mut buf_r)?;
+        self.read_ack(OrderId::AckPing, &mut buf_hdr, &mut buf_r)?;
 
         Ok(PingInfo::from_ping(&buf_hdr, &buf_r)?)
     }
@@ -183,7 +200,7 @@ impl Mcu {
         let mut buf_hdr = [0u8; HEADER_CMD_SIZE];
 
         self.write_req(OrderId::ReqGetStatus, &buf_w)?;
-        self.read_ack(&mut buf_hdr, &mut buf_r)?;
+        self.read_ack(OrderId::AckGetStatus, &mut buf_hdr, &mut buf_r)?;
 
 
         Ok(Status::from_get_status(&buf_hdr, &buf_r)?)
@@ -191,7 +208,7 @@ impl Mcu {
 
     fn decode_ack_gpio_access(hdr: &[u8], payload: &[u8]) -> Result<u8> {
         if hdr[CommandOrderOffset::Cmd as usize] != OrderId::AckWriteGpio as u8 {
-            return Err(anyhow!("ERROR: received wrong ACK type {:}\n", hdr[CommandOrderOffset::Cmd as usize]));
+            return Err(Error::WrongAck { expected: OrderId::AckWriteGpio as u8, received: hdr[CommandOrderOffset::Cmd as usize] }.into());
         }
 
         Ok(payload[ECmdOffsetAckGpioWrite::AckGpioWriteStatus as usize])
@@ -205,22 +222,22 @@ impl Mcu {
         let mut i = 0;
 
         if hdr.is_empty() || payload.is_empty() {
-            return Err(anyhow!("ERROR: invalid parameter"));
+            return Err(Error::InvalidAck { reason: "empty header or payload" }.into());
         }
 
         if hdr[CommandOrderOffset::Cmd as usize] != OrderId::AckMultipleSpi as u8 {
-            return Err(anyhow!("ERROR: wrong ACK type for ACK_MULTIPLE_SPI (expected:0x{:02X}, got 0x{:02X})", OrderId::AckMultipleSpi as u8, hdr[CommandOrderOffset::Cmd as usize]));
+            return Err(Error::WrongAck { expected: OrderId::AckMultipleSpi as u8, received: hdr[CommandOrderOffset::Cmd as usize] }.into());
         }
 
         while i < Self::cmd_get_size(hdr) {
             let req_id = payload[i];
             let req_type = payload[i + 1];
             if req_type != MCU_SPI_REQ_TYPE_READ_MODIFY_WRITE && req_type != MCU_SPI_REQ_TYPE_READ_WRITE {
-                return Err(anyhow!("ERROR: wrong type for SPI request {} (0x{:02X})", req_id, req_type));
+                return Err(Error::SpiRequestType { req_id, req_type }.into());
             }
             let req_status = payload[i + 2];
             if req_status != 0 {
-                return Err(anyhow!("ERROR: SPI request {} failed with {:?}", req_id, req_status));
+                return Err(Error::SpiRequest { req_id, status: req_status }.into());
             }
 
             if req_type == MCU_SPI_REQ_TYPE_READ_WRITE {
@@ -245,12 +262,12 @@ impl Mcu {
 
         self.write_req(OrderId::ReqWriteGpio, &buf_req)?;
 
-        self.read_ack(&mut buf_hdr, &mut buf_ack)?;
+        self.read_ack(OrderId::AckWriteGpio, &mut buf_hdr, &mut buf_ack)?;
 
         let status = Mcu::decode_ack_gpio_access(&buf_hdr, &buf_ack)?;
 
         if status != 0 {
-            return Err(anyhow!("ERROR: Failed to write GPIO (port:{} id:{} value:{})", gpio_port, gpio_id, gpio_value));
+            return Err(Error::Gpio { port: gpio_port, pin: gpio_id, value: gpio_value }.into());
         }
 
         Ok(())
@@ -275,14 +292,22 @@ impl Mcu {
         trace!(in_out_buf=?in_out_buf, "mcu_spi_write");
         /* Check input parameters */
         if in_out_buf.is_empty() {
-            return Err(anyhow!("Input buffer is null"));
+            return Err(Error::InvalidSpiRequest { reason: "empty request" }.into());
         }
 
+        #[cfg(feature = "prometheus")]
+        let start = std::time::Instant::now();
+
         self.write_req(OrderId::ReqMultipleSpi, in_out_buf)?;
 
         let mut buf_hdr = [0u8; HEADER_CMD_SIZE];
 
-        self.read_ack(&mut buf_hdr, in_out_buf)?;
+        self.read_ack(OrderId::AckMultipleSpi, &mut buf_hdr, in_out_buf)?;
+
+        #[cfg(feature = "prometheus")]
+        if let Some(metrics) = &self.metrics {
+            metrics.record_spi_rtt(start.elapsed());
+        }
 
         Mcu::decode_ack_spi_bulk(&buf_hdr, in_out_buf)?;
 
@@ -291,11 +316,11 @@ impl Mcu {
 
     fn spi_req_bulk_insert(bulk_buffer: &mut SpiReqBulk, req: &[u8]) -> Result<()> {
         if bulk_buffer.nb_req == 255 {
-            return Err(anyhow!("ERROR: cannot insert a new SPI request in bulk buffer - too many requests"));
+            return Err(Error::InvalidSpiRequest { reason: "too many requests in bulk buffer" }.into());
         }
     
         if (bulk_buffer.size + req.len()) > LGW_USB_BURST_CHUNK {
-            return Err(anyhow!("ERROR: cannot insert a new SPI request in bulk buffer - buffer full"));
+            return Err(Error::InvalidSpiRequest { reason: "bulk buffer full" }.into());
         }
     
         bulk_buffer.buffer[bulk_buffer.size..bulk_buffer.size + req.len()].copy_from_slice(req);
@@ -308,7 +333,7 @@ impl Mcu {
 
     pub fn mcu_spi_store( &mut self, in_out_buf: &[u8]) -> Result<()> {
         if in_out_buf.is_empty() {
-            return Err(anyhow!("Input buffer is null"));
+            return Err(Error::InvalidSpiRequest { reason: "empty request" }.into());
         }
     
         Mcu::spi_req_bulk_insert(&mut self.spi_req_bulk, in_out_buf)?;
@@ -361,9 +386,9 @@ impl McuTrait for Mcu {
         /* Reset SX1261 (LBT / Spectral Scan) */
         let e4 = self.mcu_gpio_write( 0, 8, 0); /*   set PA8 : SX1261_NRESET active */
         let e5 = self.mcu_gpio_write( 0, 8, 1); /* unset PA8 : SX1261_NRESET inactive */
-        if e1.is_err() || e2.is_err() || e3.is_err() || e4.is_err() || e5.is_err() {
+        if let Err(e) = e1.and(e2).and(e3).and(e4).and(e5) {
             error!("ERROR: failed to reset SX1302\n");
-            return Err(Error::LGW_USB_ERROR.into());
+            return Err(e);
         }
 
         /* close file & deallocate file descriptor */
@@ -379,7 +404,7 @@ impl McuTrait for Mcu {
 
         if self.lgw_write_mode != EComWriteMode::LGW_COM_WRITE_MODE_BULK {
             error!("ERROR: cannot flush in single write mode\n");
-            return Err(Error::LGW_COM_ERROR.into());
+            return Err(Error::config("write_mode", "single", "can not flush in single write mode").into());
         }
     
         /* Restore single mode after flushing */
@@ -393,9 +418,9 @@ impl McuTrait for Mcu {
 
     
         debug!("INFO: flushing USB write buffer\n");
-        if let Err(_) = self.mcu_spi_flush(){
+        if let Err(e) = self.mcu_spi_flush(){
             error!("ERROR: Failed to flush USB write buffer\n");
-            return Err(Error::LGW_COM_ERROR.into());
+            return Err(e);
         }
     
         /* reset the pending request number */
@@ -429,7 +454,7 @@ impl McuTrait for Mcu {
 
         if self.lgw_write_mode == EComWriteMode::LGW_COM_WRITE_MODE_BULK{
             /* makes no sense to read in bulk mode, as we can't get the result */
-            return Err(anyhow!("ERROR: USB READ BURST FAILURE - bulk mode is enabled"))
+            return Err(Error::Burst { address, reason: "can not read in bulk write mode" }.into())
         } else {
             if let Err(e) = self.mcu_spi_write( &mut in_out_buf) {
                 error!("ERROR: USB READ BURST FAILURE");
@@ -477,7 +502,7 @@ impl McuTrait for Mcu {
         let mut in_out_buf = vec![0u8;command_size];
 
         if data.is_empty() {
-            return Err(anyhow!("ERROR: empty data array"))
+            return Err(Error::Burst { address, reason: "empty data array" }.into())
         }
 
 
@@ -497,10 +522,10 @@ impl McuTrait for Mcu {
         }
 
         if self.lgw_write_mode == EComWriteMode::LGW_COM_WRITE_MODE_BULK {
-            self.mcu_spi_store(&mut in_out_buf).map_err(|e| anyhow!("ERROR: USB WRITE BURST FAILURE: {:}", e))?;
+            self.mcu_spi_store(&mut in_out_buf).map_err(|e| e.context("USB WRITE BURST FAILURE"))?;
             self.lgw_spi_req_nb += 1;
         } else {
-            self.mcu_spi_write(&mut in_out_buf).map_err(|e| anyhow!("ERROR: USB WRITE BURST FAILURE: {:}", e))?;
+            self.mcu_spi_write(&mut in_out_buf).map_err(|e| e.context("USB WRITE BURST FAILURE"))?;
         }
 
         trace!("Note: USB write burst success\n");
@@ -0,0 +1,394 @@
+/*
+    Record and replay of MCU sessions.
+
+    RecordComPort wraps any transport and logs every request/ACK frame exchanged with the MCU,
+    one JSON object per line:
+        {"t_us":1234,"dir":"Req","data":"5A000100"}
+    ReplayComPort answers the requests of a new session with the ACKs of a recording, so that
+    lgw_start/lgw_receive can be reproduced offline. Requests that differ from the recorded ones
+    are reported as divergences.
+*/
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use super::command::CommandOrderOffset;
+use super::HEADER_CMD_SIZE;
+use crate::hal::loragw_com::LgwComTransport;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum McuFrameDir {
+    Req,
+    Ack,
+}
+
+/**
+@struct McuFrameRecord
+@brief One frame of a recorded session, t_us is relative to the start of the recording
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McuFrameRecord {
+    pub t_us: u64,
+    pub dir: McuFrameDir,
+    #[serde(with = "hex_serde")]
+    pub data: Vec<u8>,
+}
+
+mod hex_serde {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let s: String = data.iter().map(|b| format!("{:02X}", b)).collect();
+        serializer.serialize_str(&s)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        if s.len() % 2 != 0 {
+            return Err(serde::de::Error::custom("odd number of hex digits"));
+        }
+        (0 .. s.len()).step_by(2)
+            .map(|i| u8::from_str_radix(&s[i .. i + 2], 16).map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+/* Reassembles MCU frames (header + payload) from a byte stream */
+#[derive(Debug, Default)]
+struct FrameAssembler {
+    buf: Vec<u8>,
+}
+
+impl FrameAssembler {
+    fn push(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        self.buf.extend_from_slice(data);
+
+        while self.buf.len() >= HEADER_CMD_SIZE {
+            let size = HEADER_CMD_SIZE + (((self.buf[1] as usize) << 8) | (self.buf[2] as usize));
+            if self.buf.len() < size {
+                break;
+            }
+            frames.push(self.buf.drain(.. size).collect());
+        }
+
+        frames
+    }
+
+    fn clear(&mut self) {
+        self.buf.clear();
+    }
+}
+
+/**
+@struct RecordComPort
+@brief Transport wrapper logging every frame exchanged through the inner transport
+*/
+#[derive(Debug)]
+pub struct RecordComPort<T: LgwComTransport> {
+    inner: T,
+    out: BufWriter<File>,
+    start: Instant,
+    req: FrameAssembler,
+    ack: FrameAssembler,
+}
+
+impl<T: LgwComTransport> RecordComPort<T> {
+    pub fn create<P: AsRef<Path>>(inner: T, path: P) -> Result<Self> {
+        let file = File::create(path.as_ref())
+            .map_err(|e| anyhow!("ERROR: failed to create recording {}: {}", path.as_ref().display(), e))?;
+
+        Ok(Self {
+            inner,
+            out: BufWriter::new(file),
+            start: Instant::now(),
+            req: FrameAssembler::default(),
+            ack: FrameAssembler::default(),
+        })
+    }
+
+    fn log(&mut self, dir: McuFrameDir, data: Vec<u8>) -> io::Result<()> {
+        let record = McuFrameRecord {
+            t_us: self.start.elapsed().as_micros() as u64,
+            dir,
+            data,
+        };
+        serde_json::to_writer(&mut self.out, &record)?;
+        self.out.write_all(b"\n")?;
+        /* keep the recording usable if the gateway process dies */
+        self.out.flush()
+    }
+}
+
+impl<T: LgwComTransport> LgwComTransport for RecordComPort<T> {
+    fn open(&mut self, port_name: String) -> Result<()> {
+        self.inner.open(port_name)
+    }
+
+    fn close(&mut self) {
+        self.inner.close();
+        self.req.clear();
+        self.ack.clear();
+    }
+
+    fn is_open(&self) -> bool {
+        self.inner.is_open()
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(data)?;
+        for frame in self.req.push(&data[.. n]) {
+            self.log(McuFrameDir::Req, frame)?;
+        }
+        Ok(n)
+    }
+
+    fn read(&mut self, data: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(data)?;
+        for frame in self.ack.push(&data[.. n]) {
+            self.log(McuFrameDir::Ack, frame)?;
+        }
+        Ok(n)
+    }
+}
+
+/**
+@struct ReplayDivergence
+@brief Request of the replayed session which does not match the recording
+*/
+#[derive(Debug, Clone)]
+pub struct ReplayDivergence {
+    pub index: usize,           //*!> index of the request in the recording */
+    pub expected: Vec<u8>,      //*!> recorded request */
+    pub actual: Vec<u8>,        //*!> request written by the host */
+}
+
+/**
+@struct ReplayComPort
+@brief Transport answering requests with the ACKs of a recorded session
+*/
+#[derive(Debug)]
+pub struct ReplayComPort {
+    exchanges: VecDeque<(Vec<u8>, Vec<u8>)>,
+    index: usize,
+    strict: bool,
+    req: FrameAssembler,
+    output: VecDeque<u8>,
+    divergences: Arc<Mutex<Vec<ReplayDivergence>>>,
+}
+
+impl ReplayComPort {
+    /* In strict mode a divergence fails the write, otherwise the recorded ACK is returned anyway */
+    pub fn open_file<P: AsRef<Path>>(path: P, strict: bool) -> Result<Self> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| anyhow!("ERROR: failed to open recording {}: {}", path.as_ref().display(), e))?;
+
+        let mut records = Vec::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: McuFrameRecord = serde_json::from_str(&line)
+                .map_err(|e| anyhow!("ERROR: invalid record at line {}: {}", i + 1, e))?;
+            records.push(record);
+        }
+
+        Self::from_records(records, strict)
+    }
+
+    pub fn from_records(records: Vec<McuFrameRecord>, strict: bool) -> Result<Self> {
+        let mut exchanges = VecDeque::new();
+        let mut pending: Option<Vec<u8>> = None;
+
+        for record in records {
+            match (record.dir, pending.take()) {
+                (McuFrameDir::Req, None) => pending = Some(record.data),
+                (McuFrameDir::Ack, Some(req)) => exchanges.push_back((req, record.data)),
+                (McuFrameDir::Req, Some(_)) => return Err(anyhow!("ERROR: request without ACK in recording (t_us={})", record.t_us)),
+                (McuFrameDir::Ack, None) => return Err(anyhow!("ERROR: ACK without request in recording (t_us={})", record.t_us)),
+            }
+        }
+        if pending.is_some() {
+            /* session interrupted while waiting for the last ACK */
+            warn!("WARNING: recording ends with an unanswered request, ignored");
+        }
+
+        debug!("replay: {} exchanges loaded", exchanges.len());
+        Ok(Self {
+            exchanges,
+            index: 0,
+            strict,
+            req: FrameAssembler::default(),
+            output: VecDeque::new(),
+            divergences: Arc::new(Mutex::new(Vec::new())),
+        })
+    }
+
+    /* Shared handle on the divergences, still readable once the port is boxed into the Mcu */
+    pub fn divergences(&self) -> Arc<Mutex<Vec<ReplayDivergence>>> {
+        self.divergences.clone()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.exchanges.len()
+    }
+
+    fn answer(&mut self, request: Vec<u8>) -> io::Result<()> {
+        let Some((expected, mut ack)) = self.exchanges.pop_front() else {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "end of recording"));
+        };
+        let index = self.index;
+        self.index += 1;
+
+        /* the request id is random, only compare the rest of the frame */
+        let id = CommandOrderOffset::Id as usize;
+        if expected[id + 1 ..] != request[id + 1 ..] {
+            warn!("WARNING: replay divergence on request #{}", index);
+            self.divergences.lock().unwrap().push(ReplayDivergence {
+                index,
+                expected,
+                actual: request.clone(),
+            });
+            if self.strict {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("replay divergence on request #{}", index)));
+            }
+        }
+
+        ack[id] = request[id];
+        self.output.extend(ack);
+        Ok(())
+    }
+}
+
+impl LgwComTransport for ReplayComPort {
+    fn open(&mut self, _port_name: String) -> Result<()> {
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.req.clear();
+        self.output.clear();
+    }
+
+    fn is_open(&self) -> bool {
+        true
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        for frame in self.req.push(data) {
+            self.answer(frame)?;
+        }
+        Ok(data.len())
+    }
+
+    fn read(&mut self, data: &mut [u8]) -> io::Result<usize> {
+        if self.output.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "Operation timed out"));
+        }
+        let n = data.len().min(self.output.len());
+        for (b, v) in data.iter_mut().zip(self.output.drain(.. n)) {
+            *b = v;
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::loragw_reg::{LoragwRegTrait, SX1302_REG_AGC_MCU_CTRL_HOST_PROG, SX1302_REG_COMMON_VERSION_VERSION};
+    use crate::hal::mcu::sim::SimConcentrator;
+    use crate::hal::Hal;
+
+    /* Connect, then a few register accesses */
+    fn session(hal: &mut Hal) -> Result<(i32, i32)> {
+        hal.lgw_connect()?;
+        let version = hal.lgw_reg_r(SX1302_REG_COMMON_VERSION_VERSION)?;
+        hal.lgw_reg_w(SX1302_REG_AGC_MCU_CTRL_HOST_PROG, 1)?;
+        let host_prog = hal.lgw_reg_r(SX1302_REG_AGC_MCU_CTRL_HOST_PROG)?;
+        Ok((version, host_prog))
+    }
+
+    /* Request with the given id, command and payload */
+    fn frame(id: u8, cmd: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![id, (payload.len() >> 8) as u8, payload.len() as u8, cmd];
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn exchange(req: Vec<u8>, ack: Vec<u8>) -> Vec<McuFrameRecord> {
+        vec![
+            McuFrameRecord { t_us: 0, dir: McuFrameDir::Req, data: req },
+            McuFrameRecord { t_us: 10, dir: McuFrameDir::Ack, data: ack },
+        ]
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let path = std::env::temp_dir().join(format!("loragw-record-{}.jsonl", std::process::id()));
+
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(RecordComPort::create(sim.stream(), &path).unwrap()));
+        let recorded = session(&mut hal).unwrap();
+        drop(hal);
+
+        /* request ids are random: the replayed session uses different ones */
+        let replay = ReplayComPort::open_file(&path, true).unwrap();
+        let divergences = replay.divergences();
+        let mut hal = Hal::with_transport(Box::new(replay));
+        assert_eq!(session(&mut hal).unwrap(), recorded);
+        assert!(divergences.lock().unwrap().is_empty());
+
+        /* nothing left to answer */
+        assert!(hal.lgw_reg_r(SX1302_REG_COMMON_VERSION_VERSION).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn replay_ignores_request_id() {
+        let mut replay = ReplayComPort::from_records(exchange(frame(0x11, 0x05, &[1, 2]), frame(0x11, 0x85, &[3])), true).unwrap();
+
+        replay.write(&frame(0x42, 0x05, &[1, 2])).unwrap();
+        let mut ack = [0u8; 5];
+        assert_eq!(replay.read(&mut ack).unwrap(), 5);
+        assert_eq!(ack.to_vec(), frame(0x42, 0x85, &[3]));
+        assert!(replay.divergences().lock().unwrap().is_empty());
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    #[test]
+    fn replay_divergence() {
+        let records = exchange(frame(0x11, 0x05, &[1, 2]), frame(0x11, 0x85, &[3]));
+
+        let mut strict = ReplayComPort::from_records(records.clone(), true).unwrap();
+        let err = strict.write(&frame(0x11, 0x05, &[1, 3])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let divergences = strict.divergences();
+        let divergences = divergences.lock().unwrap();
+        assert_eq!(divergences.len(), 1);
+        assert_eq!((divergences[0].index, &divergences[0].actual), (0, &frame(0x11, 0x05, &[1, 3])));
+
+        /* not strict: reported, and answered with the recorded ACK */
+        let mut lenient = ReplayComPort::from_records(records, false).unwrap();
+        lenient.write(&frame(0x22, 0x05, &[1, 3])).unwrap();
+        let mut ack = [0u8; 5];
+        assert_eq!(lenient.read(&mut ack).unwrap(), 5);
+        assert_eq!(ack.to_vec(), frame(0x22, 0x85, &[3]));
+        assert_eq!(lenient.divergences().lock().unwrap().len(), 1);
+    }
+}
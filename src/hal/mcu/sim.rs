@@ -0,0 +1,1480 @@
+/*
+    Software model of a CoreCell concentrator (STM32 USB bridge + SX1302 + SX1250 or SX125x radios).
+
+    The model answers the exact frames built by Mcu::write_req and expected by Mcu::read_ack
+    (ReqPing, ReqGetStatus, ReqWriteGpio and ReqMultipleSpi with read/write and read-modify-write
+    sub-requests), on top of a register file initialized from LOREGS. It emulates just enough of
+    the chip behaviour (AGC/ARB firmware handshakes, radio status, timestamp counters, RX buffer
+    FIFO, TX state machine) for lgw_start, lgw_receive and lgw_send to run end to end.
+    The SX1261 model covers standby/RX modes, RSSI (set per channel with set_channel_rssi), LBT,
+    spectral scan and the PRAM patch handshake.
+    SX125x boards go through the CAL firmware mailbox protocol, the signal analyser reporting a
+    residual which is minimal for the fixed IQ imbalance and TX DC offsets of the simulated radios.
+*/
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use tracing::{debug, trace, warn};
+
+use super::command::*;
+use super::HEADER_CMD_SIZE;
+use crate::hal::{agc_firmware::{AGC_FIRMWARE_SX1250, AGC_FIRMWARE_SX125X}, arb_firmware::ARB_FIRMWARE, cal_fw::CAL_FIRMWARE_SX125X};
+use crate::hal::loragw_com::LgwComTransport;
+use crate::hal::loragw_reg::*;
+use crate::hal::loragw_sx1302::sx1302_lora_payload_crc;
+use crate::hal::loragw_sx1302_timestamp::lora_packet_time_on_air;
+use crate::hal::{Modulation, TxMode, FW_VERSION_AGC_SX1250, FW_VERSION_AGC_SX125X, FW_VERSION_ARB};
+
+const SIM_MEM_SIZE: usize =         0x8000; /* 15-bit SX1302 address space */
+const SIM_MCU_FW_SIZE: usize =      8192;
+const SIM_AGC_MEM_ADDR: usize =     0x0000;
+const SIM_ARB_MEM_ADDR: usize =     0x2000;
+const SIM_RX_BUFFER_ADDR: u16 =     0x4000;
+const SIM_RX_BUFFER_SIZE: usize =   4096;
+const SIM_TX_BUFFER_ADDR: [usize; 2] = [0x5300, 0x5500];
+
+const SIM_FW_VERSION_CAL: u8 =      1;
+const SIM_MCU_VERSION: &[u8; 9] =   b"V00.02.06";
+const SIM_UNIQUE_ID: [u8; 12] =     [0x53, 0x49, 0x4D, 0x00, 0x13, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+
+/* Same layout as the RX buffer decoded by RxBuffer::pop */
+const SIM_PKT_SYNCWORD: [u8; 2] =   [0xA5, 0xC0];
+const SIM_PKT_HEAD_METADATA: usize = 9;
+const SIM_PKT_TAIL_METADATA: usize = 14;
+const SIM_LORA_STD_MODEM_ID: u8 =   16;
+const SIM_FSK_MODEM_ID: u8 =        17;
+
+/* SX1250 status, GET_STATUS bits 4..6 */
+const SX1250_MODE_STDBY_RC: u8 =    0x02;
+const SX1250_MODE_STDBY_XOSC: u8 =  0x03;
+const SX1250_MODE_FS: u8 =          0x04;
+const SX1250_MODE_RX: u8 =          0x05;
+const SX1250_MODE_TX: u8 =          0x06;
+
+/* SX1255/SX1257 register map */
+const SIM_SX125X_REGS: usize =      0x40;
+const SX125X_VERSION: u8 =          0x21;
+const SX125X_REG_MODE: usize =      0;
+const SX125X_REG_VERSION: usize =   7;
+const SX125X_REG_MODE_STATUS: usize = 17;
+
+/* Calibration: signal analyser bin of the CAL tone, correlation levels and radio impairments */
+const SIM_CAL_TONE_BIN: i8 =        16;     /* 250kHz / 15625Hz */
+const SIM_CAL_TONE_CORR: u32 =      20000;
+const SIM_CAL_NOISE_CORR: u32 =     30;
+pub const SIM_CAL_RX_IQ_COMP: [(i32, i32); 2] = [(6, -3), (-4, 9)]; /* ideal amp/phi coefficients */
+
+/* SX1261 status, GET_STATUS bits 4..6 */
+const SX1261_MODE_STDBY_RC: u8 =    0x02;
+const SX1261_MODE_STDBY_XOSC: u8 =  0x03;
+const SX1261_MODE_FS: u8 =          0x04;
+const SX1261_MODE_RX: u8 =          0x05;
+
+/* SX1261 version register, the patch version being appended once the PRAM patch runs */
+const SIM_SX1261_VERSION: &str =        "SX1261 V1A";
+pub const SIM_SX1261_PRAM_VERSION: &str = "2D06";
+
+/* SX1261 spectral scan state register */
+const SX1261_SCAN_NONE: u8 =        0x00;
+const SX1261_SCAN_ON_GOING: u8 =    0x0F;
+const SX1261_SCAN_ABORTED: u8 =     0xF0;
+const SX1261_SCAN_COMPLETED: u8 =   0xFF;
+
+/* Channel RSSI seen by the SX1261 when not set with set_channel_rssi */
+const SIM_NOISE_FLOOR_DBM: i8 =     -120;
+
+/* Values reported by TX_FSM_STATUS, see sx1302_tx_status */
+const SIM_TX_STATUS_FREE: u8 =      0x80;
+const SIM_TX_STATUS_EMITTING: u8 =  0x30;
+const SIM_TX_STATUS_SCHEDULED: u8 = 0x91;
+
+/* Offset between chain A and chain B TX_TOP register ids */
+const TX_TOP_B_OFFSET: u16 = SX1302_REG_TX_TOP_B_TX_TRIG_TX_TRIG_GPS - SX1302_REG_TX_TOP_A_TX_TRIG_TX_TRIG_GPS;
+
+const fn reg_addr(register_id: u16) -> u16 {
+    LOREGS[register_id as usize].addr
+}
+
+const AGC_CTRL_ADDR: u16 =          reg_addr(SX1302_REG_AGC_MCU_CTRL_HOST_PROG);
+const AGC_MAILBOX_WR0_ADDR: u16 =   reg_addr(SX1302_REG_AGC_MCU_MCU_MAIL_BOX_WR_DATA_BYTE0_MCU_MAIL_BOX_WR_DATA);
+const AGC_MAILBOX_WR3_ADDR: u16 =   reg_addr(SX1302_REG_AGC_MCU_MCU_MAIL_BOX_WR_DATA_BYTE3_MCU_MAIL_BOX_WR_DATA);
+const AGC_STATUS_ADDR: u16 =        reg_addr(SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS);
+const AGC_RF_EN_A_ADDR: u16 =       reg_addr(SX1302_REG_AGC_MCU_RF_EN_A_RADIO_RST);
+const AGC_RF_EN_B_ADDR: u16 =       reg_addr(SX1302_REG_AGC_MCU_RF_EN_B_RADIO_RST);
+const ARB_CTRL_ADDR: u16 =          reg_addr(SX1302_REG_ARB_MCU_CTRL_HOST_PROG);
+const ARB_DEBUG_CFG_1_ADDR: u16 =   reg_addr(SX1302_REG_ARB_MCU_ARB_DEBUG_CFG_1_ARB_DEBUG_CFG_1);
+const SIG_ANA_CFG_ADDR: u16 =       reg_addr(SX1302_REG_RADIO_FE_SIG_ANA_CFG_START);
+const OTP_BYTE_ADDR_ADDR: u16 =     reg_addr(SX1302_REG_OTP_BYTE_ADDR_ADDR);
+const TX_TRIG_A_ADDR: u16 =         reg_addr(SX1302_REG_TX_TOP_A_TX_TRIG_TX_TRIG_IMMEDIATE);
+const TX_TRIG_B_ADDR: u16 =         reg_addr(SX1302_REG_TX_TOP_B_TX_TRIG_TX_TRIG_IMMEDIATE);
+const TX_STATUS_A_ADDR: u16 =       reg_addr(SX1302_REG_TX_TOP_A_TX_FSM_STATUS_TX_STATUS);
+const TX_STATUS_B_ADDR: u16 =       reg_addr(SX1302_REG_TX_TOP_B_TX_FSM_STATUS_TX_STATUS);
+const TIMESTAMP_PPS_ADDR: u16 =     reg_addr(SX1302_REG_TIMESTAMP_TIMESTAMP_PPS_MSB2_TIMESTAMP_PPS);
+const TIMESTAMP_INST_ADDR: u16 =    reg_addr(SX1302_REG_TIMESTAMP_TIMESTAMP_MSB2_TIMESTAMP);
+const RX_NB_BYTES_MSB_ADDR: u16 =   reg_addr(SX1302_REG_RX_TOP_RX_BUFFER_NB_BYTES_MSB_RX_BUFFER_NB_BYTES);
+const RX_NB_BYTES_LSB_ADDR: u16 =   reg_addr(SX1302_REG_RX_TOP_RX_BUFFER_NB_BYTES_LSB_RX_BUFFER_NB_BYTES);
+
+/* Ideal TX I/Q offsets of a simulated SX125x, which depend on its DAC and mixer gains */
+pub fn sim_cal_tx_dc_offset(radio: u8, dac_gain: u8, mix_gain: u8) -> (i8, i8) {
+    let (radio, dac, mix) = (radio as i32, dac_gain as i32, mix_gain as i32);
+    ((3 * mix - 5 * dac - 10 + 4 * radio) as i8, (12 - 2 * mix + 3 * dac - 6 * radio) as i8)
+}
+
+/**
+@struct SimRxPacket
+@brief Packet to be pushed in the simulated RX buffer, as if demodulated by the SX1302
+*/
+#[derive(Debug, Clone)]
+pub struct SimRxPacket {
+    pub if_chain: u8,       //*!> IF chain (0..7 multi-SF, 8 LoRa service, 9 FSK) */
+    pub datarate: u8,       //*!> spreading factor, 5..12 (ignored for FSK) */
+    pub coderate: u8,       //*!> coding rate, 1 (4/5) to 4 (4/8) */
+    pub crc_en: bool,       //*!> packet has a payload CRC */
+    pub crc_error: bool,    //*!> report a payload CRC error */
+    pub snr: f32,           //*!> average SNR in dB, 0.25 dB step */
+    pub rssi_chan: u8,      //*!> raw channel RSSI, rssi_offset is added by the host */
+    pub rssi_sig: u8,       //*!> raw signal RSSI, rssi_offset is added by the host */
+    pub freq_offset: i32,   //*!> raw frequency offset error (20 bits signed) */
+    pub count_us: Option<u32>, //*!> timestamp in us, current counter value if None */
+    pub checksum_error: bool, //*!> corrupt the metadata checksum */
+    pub payload: Vec<u8>,
+}
+
+impl Default for SimRxPacket {
+    fn default() -> Self {
+        Self {
+            if_chain: 0,
+            datarate: 7,
+            coderate: 1,
+            crc_en: true,
+            crc_error: false,
+            snr: 10.0,
+            rssi_chan: 100,
+            rssi_sig: 100,
+            freq_offset: 0,
+            count_us: None,
+            checksum_error: false,
+            payload: Vec::new(),
+        }
+    }
+}
+
+impl SimRxPacket {
+    fn encode(&self, count_32mhz: u32) -> Vec<u8> {
+        let size = self.payload.len();
+        let modem_id = match self.if_chain {
+            0..=7 => self.if_chain,
+            8 => SIM_LORA_STD_MODEM_ID,
+            _ => SIM_FSK_MODEM_ID,
+        };
+        let timestamp = match self.count_us {
+            Some(count_us) => count_us.wrapping_mul(32),
+            None => count_32mhz,
+        };
+        let crc = sx1302_lora_payload_crc(&self.payload, size);
+        let freq_offset = (self.freq_offset as u32) & 0x000FFFFF;
+
+        let mut buf = Vec::with_capacity(SIM_PKT_HEAD_METADATA + size + SIM_PKT_TAIL_METADATA);
+        /* head */
+        buf.extend_from_slice(&SIM_PKT_SYNCWORD);
+        buf.push(size as u8);
+        buf.push(self.if_chain);
+        buf.push((self.crc_en as u8) | ((self.coderate & 0x07) << 1) | ((self.datarate & 0x0F) << 4));
+        buf.push(modem_id);
+        buf.push(freq_offset as u8);
+        buf.push((freq_offset >> 8) as u8);
+        buf.push((freq_offset >> 16) as u8);
+        /* payload */
+        buf.extend_from_slice(&self.payload);
+        /* tail */
+        buf.push(self.crc_error as u8);
+        buf.push((self.snr * 4.0) as i8 as u8);
+        buf.push(self.rssi_chan);
+        buf.push(self.rssi_sig);
+        buf.push(0); /* rssi_chan max deltas */
+        buf.push(0); /* rssi_sig max deltas */
+        buf.extend_from_slice(&timestamp.to_le_bytes());
+        buf.extend_from_slice(&crc.to_le_bytes());
+        buf.push(0); /* no fine timestamp metrics */
+        let checksum = buf.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+        buf.push(if self.checksum_error { !checksum } else { checksum });
+
+        buf
+    }
+}
+
+/**
+@struct SimTxPacket
+@brief Packet captured when the host triggers a transmission on the simulated concentrator
+*/
+#[derive(Debug, Clone)]
+pub struct SimTxPacket {
+    pub rf_chain: u8,
+    pub tx_mode: TxMode,
+    pub count_us: u32,      //*!> programmed trigger time for TIMESTAMPED mode (start delay included) */
+    pub freq_hz: u32,
+    pub rf_power: u8,       //*!> raw AGC_TX_PWR register value */
+    pub modulation: Modulation,
+    pub bandwidth: u8,
+    pub datarate: u32,      //*!> SF for LoRa, bits per second for FSK */
+    pub coderate: u8,
+    pub invert_pol: bool,
+    pub preamble: u16,
+    pub no_crc: bool,
+    pub no_header: bool,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SimMcuFw {
+    None,
+    AgcSx1250,
+    AgcSx125x,
+    Cal,
+    Arb,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SimTxState {
+    Idle,
+    Delayed { count_32mhz: u32, toa_us: u32 },
+    OnGps { second: u64, toa_us: u32 },
+    Emitting { end: Option<Instant> },
+}
+
+/* SX1261 companion radio, only what LBT and the spectral scan need */
+#[derive(Debug, Clone, Copy)]
+struct SimSx1261 {
+    mode: u8,
+    freq_hz: u32,
+    lbt_threshold: Option<i8>,  /* set by the LBT patch command, None when LBT is off */
+    scan_status: u8,            /* spectral scan state register */
+    scan_nb: u16,
+    scan_end: Option<Instant>,  /* end of the on-going spectral scan */
+    pram_update: bool,          /* PRAM write enabled */
+    pram_words: usize,          /* 32-bit words written in PRAM */
+    patched: bool,              /* PRAM patch started */
+}
+
+impl Default for SimSx1261 {
+    fn default() -> Self {
+        Self { mode: SX1261_MODE_STDBY_RC, freq_hz: 0, lbt_threshold: None, scan_status: SX1261_SCAN_NONE, scan_nb: 0, scan_end: None,
+               pram_update: false, pram_words: 0, patched: false }
+    }
+}
+
+#[derive(Debug)]
+struct SimState {
+    mem: Vec<u8>,
+    boot: Instant,
+    agc_fw: SimMcuFw,
+    agc_ready: bool,
+    agc_lbt: bool,
+    arb_fw: SimMcuFw,
+    cal_cmd: (u8, u8),          /* CAL fw command and TX gains latched by the host */
+    cal_test: Option<(u8, u8)>, /* on-going CAL fw test */
+    radio_mode: [u8; 2],
+    sx125x_regs: [[u8; SIM_SX125X_REGS]; 2],
+    pll_lock_failures: u32,     /* SX125x RX PLL starts failing to lock before it does */
+    sx1261: SimSx1261,
+    channel_rssi: Vec<(u32, i8)>,
+    tx_state: [SimTxState; 2],
+    rx_pending: VecDeque<Vec<u8>>,
+    rx_fifo: VecDeque<u8>,
+    tx_log: Vec<SimTxPacket>,
+    frame_cnt: u32,
+    temperature: f32,
+    status_error: bool,         /* ReqGetStatus answered with CmdError */
+    chip_model: u8,
+    pps: bool,
+}
+
+impl SimState {
+    fn new() -> Self {
+        let mut s = Self {
+            mem: vec![0u8; SIM_MEM_SIZE],
+            boot: Instant::now(),
+            agc_fw: SimMcuFw::None,
+            agc_ready: false,
+            agc_lbt: false,
+            arb_fw: SimMcuFw::None,
+            cal_cmd: (0, 0),
+            cal_test: None,
+            radio_mode: [SX1250_MODE_STDBY_RC; 2],
+            sx125x_regs: [[0; SIM_SX125X_REGS]; 2],
+            pll_lock_failures: 0,
+            sx1261: SimSx1261::default(),
+            channel_rssi: Vec::new(),
+            tx_state: [SimTxState::Idle; 2],
+            rx_pending: VecDeque::new(),
+            rx_fifo: VecDeque::new(),
+            tx_log: Vec::new(),
+            frame_cnt: 0,
+            temperature: 25.0,
+            status_error: false,
+            chip_model: 0x02, /* CHIP_MODEL_ID_SX1302 */
+            pps: false,
+        };
+        s.reset();
+        s
+    }
+
+    /* SX1302 reset: registers back to their LOREGS default value */
+    fn reset(&mut self) {
+        self.mem.iter_mut().for_each(|b| *b = 0);
+        for r in LOREGS.iter().take(LGW_TOTALREGS as usize) {
+            let mask = ((1u16 << r.leng) - 1) as u8;
+            let byte = &mut self.mem[r.addr as usize];
+            *byte = (*byte & !(mask << r.offs)) | (((r.dflt as u8) & mask) << r.offs);
+        }
+        self.boot = Instant::now();
+        self.agc_fw = SimMcuFw::None;
+        self.agc_ready = false;
+        self.agc_lbt = false;
+        self.arb_fw = SimMcuFw::None;
+        self.cal_test = None;
+        self.radio_mode = [SX1250_MODE_STDBY_RC; 2];
+        self.sx125x_regs = [[0; SIM_SX125X_REGS]; 2];
+        self.tx_state = [SimTxState::Idle; 2];
+        self.rx_fifo.clear();
+    }
+
+    fn get(&self, register_id: u16) -> u8 {
+        let r = &LOREGS[register_id as usize];
+        (self.mem[r.addr as usize] >> r.offs) & (((1u16 << r.leng) - 1) as u8)
+    }
+
+    fn set(&mut self, register_id: u16, value: u8) {
+        let r = &LOREGS[register_id as usize];
+        let mask = ((1u16 << r.leng) - 1) as u8;
+        let byte = &mut self.mem[r.addr as usize];
+        *byte = (*byte & !(mask << r.offs)) | ((value & mask) << r.offs);
+    }
+
+    fn tx_reg(rf_chain: u8, register_id_a: u16) -> u16 {
+        if rf_chain == 0 { register_id_a } else { register_id_a + TX_TOP_B_OFFSET }
+    }
+
+    /* 32MHz free running counter */
+    fn counter_32mhz(&self) -> u32 {
+        (self.boot.elapsed().as_nanos() * 32 / 1000) as u32
+    }
+
+    /* 32MHz counter latched on the last PPS edge (one every second when enabled) */
+    fn counter_pps_32mhz(&self) -> u32 {
+        if self.pps {
+            (self.boot.elapsed().as_secs() as u128 * 32_000_000) as u32
+        } else {
+            0
+        }
+    }
+
+    fn uptime_ms(&self) -> u32 {
+        self.boot.elapsed().as_millis() as u32
+    }
+
+    /* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ */
+    /* MCU commands */
+
+    fn process(&mut self, hdr: &[u8], payload: &[u8]) -> Vec<u8> {
+        let cmd = hdr[CommandOrderOffset::Cmd as usize];
+        let ack: (u8, Vec<u8>) = if cmd == OrderId::ReqPing as u8 {
+            let mut data = SIM_UNIQUE_ID.to_vec();
+            data.extend_from_slice(SIM_MCU_VERSION);
+            (OrderId::AckPing as u8, data)
+        } else if cmd == OrderId::ReqGetStatus as u8 && self.status_error {
+            (OrderId::CmdError as u8, Vec::new())
+        } else if cmd == OrderId::ReqGetStatus as u8 {
+            let mut data = self.uptime_ms().to_be_bytes().to_vec();
+            data.extend_from_slice(&((self.temperature * 100.0) as i16).to_be_bytes());
+            (OrderId::AckGetStatus as u8, data)
+        } else if cmd == OrderId::ReqWriteGpio as u8 && payload.len() >= ECmdOffsetReqWriteGpio::ReqWriteGpioSize as usize {
+            self.gpio_write(payload[0], payload[1], payload[2]);
+            (OrderId::AckWriteGpio as u8, vec![0])
+        } else if cmd == OrderId::ReqMultipleSpi as u8 {
+            (OrderId::AckMultipleSpi as u8, self.multiple_spi(payload))
+        } else if cmd == OrderId::ReqReset as u8 {
+            self.reset();
+            (OrderId::AckReset as u8, vec![0])
+        } else {
+            warn!("SIM: unsupported command 0x{:02X}", cmd);
+            (OrderId::CmdError as u8, Vec::new())
+        };
+
+        let mut frame = Vec::with_capacity(HEADER_CMD_SIZE + ack.1.len());
+        frame.push(hdr[CommandOrderOffset::Id as usize]);
+        frame.push((ack.1.len() >> 8) as u8);
+        frame.push((ack.1.len() & 0xFF) as u8);
+        frame.push(ack.0);
+        frame.extend_from_slice(&ack.1);
+        frame
+    }
+
+    fn gpio_write(&mut self, port: u8, pin: u8, state: u8) {
+        trace!("SIM: GPIO P{}{} <= {}", (b'A' + port) as char, pin, state);
+        /* PA2 : SX1302_RESET */
+        if port == 0 && pin == 2 && state == 1 {
+            debug!("SIM: SX1302 reset");
+            self.reset();
+        }
+        /* PA8 : SX1261_NRESET */
+        if port == 0 && pin == 8 && state == 0 {
+            debug!("SIM: SX1261 reset");
+            self.sx1261 = SimSx1261::default();
+        }
+    }
+
+    fn multiple_spi(&mut self, payload: &[u8]) -> Vec<u8> {
+        let mut ack = Vec::with_capacity(payload.len());
+        let mut i = 0;
+
+        self.frame_cnt = self.counter_32mhz();
+
+        while i + 2 <= payload.len() {
+            let req_id = payload[i];
+            let req_type = payload[i + 1];
+
+            if req_type == MCU_SPI_REQ_TYPE_READ_WRITE && i + 5 <= payload.len() {
+                let target = payload[i + 2];
+                let size = ((payload[i + 3] as usize) << 8) | (payload[i + 4] as usize);
+                if i + 5 + size > payload.len() {
+                    warn!("SIM: truncated SPI request {}", req_id);
+                    break;
+                }
+                let mut frame = payload[i + 5 .. i + 5 + size].to_vec();
+                let status = if target == ECmdSpiTarget::MCU_SPI_TARGET_SX1302 as u8 {
+                    self.sx1302_spi(&mut frame)
+                } else if target == ECmdSpiTarget::MCU_SPI_TARGET_SX1261 as u8 {
+                    self.sx1261_spi(&mut frame);
+                    ESpiStatus::SpiStatusOk
+                } else {
+                    ESpiStatus::SpiStatusWrongParam
+                };
+                ack.extend_from_slice(&[req_id, req_type, status as u8, payload[i + 3], payload[i + 4]]);
+                ack.extend_from_slice(&frame);
+                i += 5 + size;
+            } else if req_type == MCU_SPI_REQ_TYPE_READ_MODIFY_WRITE && i + 6 <= payload.len() {
+                let addr = ((payload[i + 2] as u16) << 8) | (payload[i + 3] as u16);
+                let mask = payload[i + 4];
+                let value = payload[i + 5];
+                let old = self.read_byte(addr);
+                self.write_byte(addr, (old & !mask) | (value & mask));
+                ack.extend_from_slice(&[req_id, req_type, ESpiStatus::SpiStatusOk as u8, 0, 0]);
+                i += 6;
+            } else {
+                warn!("SIM: invalid SPI request {} (type 0x{:02X})", req_id, req_type);
+                ack.extend_from_slice(&[req_id, req_type, ESpiStatus::SpiStatusWrongParam as u8, 0, 0]);
+                break;
+            }
+        }
+
+        ack
+    }
+
+    /* RAW SPI frame behind the SX1302 mux: [mux, addr/opcode, ...] */
+    fn sx1302_spi(&mut self, frame: &mut [u8]) -> ESpiStatus {
+        if frame.len() < 2 {
+            return ESpiStatus::SpiStatusWrongParam;
+        }
+
+        match frame[0] {
+            0x00 => {
+                if frame.len() < 3 {
+                    return ESpiStatus::SpiStatusWrongParam;
+                }
+                let write = (frame[1] & 0x80) != 0;
+                let addr = (((frame[1] & 0x7F) as u16) << 8) | (frame[2] as u16);
+                let fifo = addr == SIM_RX_BUFFER_ADDR;
+                if write {
+                    for (k, b) in frame[3 ..].iter().enumerate() {
+                        let a = if fifo { addr } else { addr + k as u16 };
+                        self.write_byte(a, *b);
+                    }
+                } else {
+                    /* one dummy byte after the address */
+                    for (k, b) in frame[4 ..].iter_mut().enumerate() {
+                        let a = if fifo { addr } else { addr + k as u16 };
+                        *b = self.read_byte(a);
+                    }
+                }
+                ESpiStatus::SpiStatusOk
+            }
+            mux @ (0x01 | 0x02) => {
+                /* the radio interface follows the mode set by sx1302_radio_set_mode */
+                let radio = (mux - 1) as usize;
+                let reg_mode = if radio == 0 { SX1302_REG_COMMON_CTRL0_SX1261_MODE_RADIO_A } else { SX1302_REG_COMMON_CTRL0_SX1261_MODE_RADIO_B };
+                if self.get(reg_mode) == 1 {
+                    self.sx1250_spi(radio, frame);
+                } else {
+                    self.sx125x_spi(radio, frame);
+                }
+                ESpiStatus::SpiStatusOk
+            }
+            _ => ESpiStatus::SpiStatusWrongParam,
+        }
+    }
+
+    fn sx1250_spi(&mut self, radio: usize, frame: &mut [u8]) {
+        let op_code = frame[1];
+        let arg = if frame.len() > 2 { frame[2] } else { 0 };
+
+        match op_code {
+            0x80 => self.radio_mode[radio] = if arg == 0 { SX1250_MODE_STDBY_RC } else { SX1250_MODE_STDBY_XOSC }, /* SET_STANDBY */
+            0xC1 => self.radio_mode[radio] = SX1250_MODE_FS, /* SET_FS */
+            0x82 => self.radio_mode[radio] = SX1250_MODE_RX, /* SET_RX */
+            0x83 | 0xD1 | 0xD2 => self.radio_mode[radio] = SX1250_MODE_TX, /* SET_TX, SET_TXCONTINUOUSWAVE, SET_TXCONTINUOUSPREAMBLE */
+            0x84 => self.radio_mode[radio] = SX1250_MODE_STDBY_RC, /* SET_SLEEP */
+            _ => {}
+        }
+
+        /* every byte clocked out carries the chip status, except register/buffer reads */
+        let status = self.radio_mode[radio] << 4;
+        match op_code {
+            0x1D | 0x1E => frame[2 ..].iter_mut().for_each(|b| *b = 0), /* READ_REGISTER, READ_BUFFER */
+            0xC0 => frame[2 ..].iter_mut().for_each(|b| *b = status), /* GET_STATUS */
+            _ => {}
+        }
+    }
+
+    /* [RnW|address, data], the PLLs lock as soon as they are enabled in MODE, unless told to fail */
+    fn sx125x_spi(&mut self, radio: usize, frame: &mut [u8]) {
+        if frame.len() < 3 {
+            return;
+        }
+        let addr = (frame[1] & 0x7F) as usize % SIM_SX125X_REGS;
+        let regs = &mut self.sx125x_regs[radio];
+
+        if frame[1] & 0x80 != 0 {
+            if addr != SX125X_REG_VERSION && addr != SX125X_REG_MODE_STATUS {
+                regs[addr] = frame[2];
+            }
+            if addr == SX125X_REG_MODE {
+                let mode = regs[SX125X_REG_MODE];
+                let mut rx_pll_locked = (mode >> 1) & 0x01;
+                if rx_pll_locked != 0 && self.pll_lock_failures > 0 {
+                    self.pll_lock_failures -= 1;
+                    rx_pll_locked = 0;
+                }
+                regs[SX125X_REG_MODE_STATUS] = rx_pll_locked << 1 | ((mode >> 2) & 0x01);
+                trace!("SIM: SX125x #{} mode 0x{:02X}", radio, mode);
+            }
+        } else {
+            frame[2] = if addr == SX125X_REG_VERSION { SX125X_VERSION } else { regs[addr] };
+        }
+    }
+
+    /* RAW SPI frame on the SX1261 target: [opcode, ...] */
+    fn sx1261_spi(&mut self, frame: &mut [u8]) {
+        if frame.is_empty() {
+            return;
+        }
+        let op_code = frame[0];
+        let arg = |i: usize| frame.get(i).copied().unwrap_or(0);
+
+        self.sx1261_scan_update();
+
+        match op_code {
+            0x80 | 0x84 if self.sx1261.scan_status == SX1261_SCAN_ON_GOING => { /* leaving RX aborts the scan */
+                self.sx1261.scan_status = SX1261_SCAN_ABORTED;
+                self.sx1261.scan_end = None;
+                self.sx1261.mode = if op_code == 0x80 && arg(1) != 0 { SX1261_MODE_STDBY_XOSC } else { SX1261_MODE_STDBY_RC };
+            }
+            0x80 => self.sx1261.mode = if arg(1) == 0 { SX1261_MODE_STDBY_RC } else { SX1261_MODE_STDBY_XOSC }, /* SET_STANDBY */
+            0x84 => self.sx1261.mode = SX1261_MODE_STDBY_RC, /* SET_SLEEP */
+            0xC1 => self.sx1261.mode = SX1261_MODE_FS, /* SET_FS */
+            0x82 => self.sx1261.mode = SX1261_MODE_RX, /* SET_RX */
+            0x86 if frame.len() >= 5 => { /* SET_RF_FREQUENCY */
+                let freq_reg = u32::from_be_bytes([frame[1], frame[2], frame[3], frame[4]]);
+                self.sx1261.freq_hz = ((freq_reg as u64 * 32_000_000) >> 25) as u32;
+            }
+            0x9A if frame.len() >= 5 => { /* LBT patch: interval, nb_scan, threshold, gpio */
+                self.sx1261.lbt_threshold = Some(frame[4] as i8);
+            }
+            0x0D if frame.len() >= 4 && arg(1) == 0x08 && arg(2) == 0x9B && arg(3) == 0 => { /* RSSI window reset: LBT off */
+                self.sx1261.lbt_threshold = None;
+            }
+            0x0D if frame.len() >= 4 && arg(1) == 0x06 && arg(2) == 0x10 => { /* PRAM write enable */
+                self.sx1261.pram_update = arg(3) & 0x10 != 0;
+            }
+            0x0D if self.sx1261.pram_update && arg(1) >= 0x80 => { /* PRAM write */
+                self.sx1261.pram_words += (frame.len() - 3) / 4;
+            }
+            0xD9 => { /* start the PRAM patch */
+                self.sx1261.patched = self.sx1261.pram_words > 0;
+                debug!("SIM: SX1261 PRAM patch started ({} words)", self.sx1261.pram_words);
+            }
+            0x9B if frame.len() >= 4 => { /* spectral scan patch: nb_scan, interval */
+                let nb_scan = u16::from_be_bytes([frame[1], frame[2]]);
+                self.sx1261.scan_status = SX1261_SCAN_ON_GOING;
+                self.sx1261.scan_nb = nb_scan;
+                self.sx1261.scan_end = Some(Instant::now() + Duration::from_nanos(nb_scan as u64 * 8200));
+            }
+            0x1D if frame.len() >= 4 => { /* READ_REGISTER: addr_h, addr_l, status, data... */
+                let addr = u16::from_be_bytes([frame[1], frame[2]]);
+                self.sx1261_read_registers(addr, &mut frame[4 ..]);
+            }
+            _ => {}
+        }
+
+        let status = self.sx1261.mode << 4;
+        if op_code == 0xC0 {
+            frame[1 ..].iter_mut().for_each(|b| *b = status);
+        }
+        if op_code == 0x15 && frame.len() >= 3 { /* GET_RSSI_INST: status, -2*rssi */
+            let rssi = if self.sx1261.mode == SX1261_MODE_RX { self.channel_rssi(self.sx1261.freq_hz) } else { SIM_NOISE_FLOOR_DBM };
+            frame[1] = status;
+            frame[2] = (-2 * rssi as i16).clamp(0, 255) as u8;
+        }
+    }
+
+    fn sx1261_scan_update(&mut self) {
+        if self.sx1261.scan_end.is_some_and(|end| Instant::now() >= end) {
+            self.sx1261.scan_status = SX1261_SCAN_COMPLETED;
+            self.sx1261.scan_end = None;
+        }
+    }
+
+    /* Only the spectral scan registers are modelled */
+    fn sx1261_read_registers(&mut self, addr: u16, data: &mut [u8]) {
+        data.fill(0);
+        match addr {
+            0x07CD => if let Some(b) = data.first_mut() { *b = self.sx1261.scan_status },
+            0x0320 => {
+                let version = if self.sx1261.patched {
+                    format!("{} {}", SIM_SX1261_VERSION, SIM_SX1261_PRAM_VERSION)
+                } else {
+                    SIM_SX1261_VERSION.to_string()
+                };
+                let n = version.len().min(data.len());
+                data[.. n].copy_from_slice(&version.as_bytes()[.. n]);
+            }
+            0x0401 if self.sx1261.scan_status == SX1261_SCAN_COMPLETED => {
+                /* all the reads fall in the bin of the channel RSSI, 4dB bins from -128dBm */
+                let rssi = self.channel_rssi(self.sx1261.freq_hz) as i16;
+                let bin = ((rssi + 128) / 4).clamp(0, 32) as usize;
+                if let Some(b) = data.get_mut(2 * bin .. 2 * bin + 2) {
+                    b.copy_from_slice(&self.sx1261.scan_nb.to_be_bytes());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /* Channel RSSI as measured by the SX1261, freq_hz being rounded by the PLL register */
+    fn channel_rssi(&self, freq_hz: u32) -> i8 {
+        self.channel_rssi.iter().find(|(f, _)| f.abs_diff(freq_hz) < 1000).map(|(_, rssi)| *rssi).unwrap_or(SIM_NOISE_FLOOR_DBM)
+    }
+
+    /* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ */
+    /* Register file */
+
+    fn read_byte(&mut self, addr: u16) -> u8 {
+        let a = (addr as usize) % SIM_MEM_SIZE;
+
+        match addr {
+            SIM_RX_BUFFER_ADDR => self.rx_fifo.pop_front().unwrap_or(0),
+            RX_NB_BYTES_MSB_ADDR => {
+                self.rx_fifo_fill();
+                ((self.rx_fifo.len() >> 8) & 0x1F) as u8
+            }
+            RX_NB_BYTES_LSB_ADDR => {
+                self.rx_fifo_fill();
+                (self.rx_fifo.len() & 0xFF) as u8
+            }
+            _ if (TIMESTAMP_PPS_ADDR .. TIMESTAMP_PPS_ADDR + 4).contains(&addr) => {
+                self.counter_pps_32mhz().to_be_bytes()[(addr - TIMESTAMP_PPS_ADDR) as usize]
+            }
+            _ if (TIMESTAMP_INST_ADDR .. TIMESTAMP_INST_ADDR + 4).contains(&addr) => {
+                self.frame_cnt.to_be_bytes()[(addr - TIMESTAMP_INST_ADDR) as usize]
+            }
+            TX_STATUS_A_ADDR => self.tx_status(0),
+            TX_STATUS_B_ADDR => self.tx_status(1),
+            AGC_STATUS_ADDR if self.agc_ready => {
+                /* delayed TX start is evaluated lazily, so is the LBT report */
+                self.tx_update(0);
+                self.tx_update(1);
+                self.mem[a]
+            }
+            _ => self.mem[a],
+        }
+    }
+
+    fn write_byte(&mut self, addr: u16, value: u8) {
+        let a = (addr as usize) % SIM_MEM_SIZE;
+        let old = self.mem[a];
+        self.mem[a] = value;
+
+        match addr {
+            AGC_CTRL_ADDR => self.agc_ctrl(),
+            AGC_MAILBOX_WR3_ADDR => self.agc_mailbox(value),
+            /* once started, mailbox 0 clears the LBT TX status */
+            AGC_MAILBOX_WR0_ADDR if self.agc_ready && value == 0xFF => {
+                self.set(SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS, 0x00);
+            }
+            AGC_RF_EN_A_ADDR => self.radio_rst(0, SX1302_REG_AGC_MCU_RF_EN_A_RADIO_RST, old),
+            AGC_RF_EN_B_ADDR => self.radio_rst(1, SX1302_REG_AGC_MCU_RF_EN_B_RADIO_RST, old),
+            ARB_CTRL_ADDR => self.arb_ctrl(),
+            /* ARB resumes once the host has written its debug configuration */
+            ARB_DEBUG_CFG_1_ADDR if self.arb_fw == SimMcuFw::Arb && value == 1 => {
+                self.set(SX1302_REG_ARB_MCU_MCU_ARB_STATUS_MCU_ARB_STATUS, 0x00);
+            }
+            SIG_ANA_CFG_ADDR => self.sig_ana(old),
+            OTP_BYTE_ADDR_ADDR => {
+                let data = if value == 0xD0 { self.chip_model } else { 0x00 };
+                self.set(SX1302_REG_OTP_RD_DATA_RD_DATA, data);
+            }
+            TX_TRIG_A_ADDR => self.tx_trig(0, old),
+            TX_TRIG_B_ADDR => self.tx_trig(1, old),
+            _ => {}
+        }
+    }
+
+    fn rx_fifo_fill(&mut self) {
+        if !self.rx_fifo.is_empty() {
+            return;
+        }
+        /* only expose complete packets, as the real RX buffer does */
+        while let Some(pkt) = self.rx_pending.front() {
+            if self.rx_fifo.len() + pkt.len() > SIM_RX_BUFFER_SIZE {
+                break;
+            }
+            let pkt = self.rx_pending.pop_front().unwrap();
+            self.rx_fifo.extend(pkt);
+        }
+    }
+
+    fn radio_rst(&mut self, radio: usize, reg_rst: u16, old: u8) {
+        let offs = LOREGS[reg_rst as usize].offs;
+        if (old >> offs) & 0x01 == 0 && self.get(reg_rst) == 1 {
+            self.radio_mode[radio] = SX1250_MODE_STDBY_RC;
+            self.sx125x_regs[radio] = [0; SIM_SX125X_REGS];
+        }
+    }
+
+    /* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ */
+    /* AGC/ARB firmwares */
+
+    fn loaded_fw(&self, mem_addr: usize) -> SimMcuFw {
+        let fw = &self.mem[mem_addr .. mem_addr + SIM_MCU_FW_SIZE];
+        if fw == &AGC_FIRMWARE_SX1250[..] {
+            SimMcuFw::AgcSx1250
+        } else if fw == &AGC_FIRMWARE_SX125X[..] {
+            SimMcuFw::AgcSx125x
+        } else if fw == &CAL_FIRMWARE_SX125X[..] {
+            SimMcuFw::Cal
+        } else if fw == &ARB_FIRMWARE[..] {
+            SimMcuFw::Arb
+        } else {
+            SimMcuFw::None
+        }
+    }
+
+    fn agc_ctrl(&mut self) {
+        let running = self.get(SX1302_REG_AGC_MCU_CTRL_HOST_PROG) == 0 && self.get(SX1302_REG_AGC_MCU_CTRL_MCU_CLEAR) == 0;
+        if !running {
+            self.agc_fw = SimMcuFw::None;
+            self.set(SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS, 0x00);
+            return;
+        }
+        if self.agc_fw != SimMcuFw::None {
+            return;
+        }
+
+        self.agc_fw = self.loaded_fw(SIM_AGC_MEM_ADDR);
+        let version = match self.agc_fw {
+            SimMcuFw::AgcSx1250 => FW_VERSION_AGC_SX1250,
+            SimMcuFw::AgcSx125x => FW_VERSION_AGC_SX125X,
+            SimMcuFw::Cal => SIM_FW_VERSION_CAL,
+            _ => {
+                warn!("SIM: unknown firmware in AGC memory, AGC not started");
+                return;
+            }
+        };
+        debug!("SIM: AGC started with {:?} firmware (v{})", self.agc_fw, version);
+        self.set(SX1302_REG_AGC_MCU_MCU_MAIL_BOX_RD_DATA_BYTE0_MCU_MAIL_BOX_RD_DATA, version);
+        self.set(SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS, 0x01);
+    }
+
+    fn agc_mailbox(&mut self, code: u8) {
+        if self.agc_fw == SimMcuFw::Cal {
+            self.cal_mailbox(code);
+            return;
+        }
+        if self.agc_fw != SimMcuFw::AgcSx1250 && self.agc_fw != SimMcuFw::AgcSx125x {
+            return;
+        }
+
+        if code == 0x0F {
+            /* configuration finished, the status register now reports LBT TX events */
+            self.agc_ready = true;
+            self.set(SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS, 0x00);
+            debug!("SIM: AGC configuration done (LBT:{})", self.agc_lbt);
+            return;
+        }
+        if code == 0x0B {
+            self.agc_lbt = self.get(SX1302_REG_AGC_MCU_MCU_MAIL_BOX_WR_DATA_BYTE0_MCU_MAIL_BOX_WR_DATA) != 0;
+        }
+
+        /* the firmware acknowledges by echoing the parameters, see sx1302_agc_start */
+        let status = match code {
+            0x80 => 0x02,                   /* AGC_RADIO_A_INIT_DONE */
+            0x20 => 0x03,                   /* AGC_RADIO_B_INIT_DONE */
+            0x03 ..= 0x0A => code + 1,
+            0x0B => 0x0F,
+            _ => return,
+        };
+        for m in 0 .. 3 {
+            let v = self.get(SX1302_REG_AGC_MCU_MCU_MAIL_BOX_WR_DATA_BYTE0_MCU_MAIL_BOX_WR_DATA - m);
+            self.set(SX1302_REG_AGC_MCU_MCU_MAIL_BOX_RD_DATA_BYTE0_MCU_MAIL_BOX_RD_DATA - m, v);
+        }
+        self.set(SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS, status);
+    }
+
+    /* CAL firmware handshake, see loragw_cal */
+    fn cal_mailbox(&mut self, code: u8) {
+        let mailbox = |s: &Self, m: u16| s.get(SX1302_REG_AGC_MCU_MCU_MAIL_BOX_WR_DATA_BYTE0_MCU_MAIL_BOX_WR_DATA - m);
+        let status = match code {
+            0xFF => 0x00,
+            0x01 => {
+                self.cal_cmd = (mailbox(self, 2), mailbox(self, 0));
+                0x01
+            }
+            0x02 => {
+                self.cal_test = Some(self.cal_cmd);
+                debug!("SIM: CAL test 0x{:02X} started (gains 0x{:02X})", self.cal_cmd.0, self.cal_cmd.1);
+                0x02
+            }
+            0x03 => {
+                self.cal_test = None;
+                0x03
+            }
+            _ => return,
+        };
+        self.set(SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS, status);
+    }
+
+    /* Signal analyser, run on the rising edge of START */
+    fn sig_ana(&mut self, old: u8) {
+        let offs = LOREGS[SX1302_REG_RADIO_FE_SIG_ANA_CFG_START as usize].offs;
+        if self.get(SX1302_REG_RADIO_FE_SIG_ANA_CFG_START) == 0 {
+            self.set(SX1302_REG_RADIO_FE_SIG_ANA_CFG_VALID, 0);
+            return;
+        }
+        if (old >> offs) & 0x01 == 1 || self.get(SX1302_REG_RADIO_FE_SIG_ANA_CFG_EN) == 0 {
+            return;
+        }
+        let radio = self.get(SX1302_REG_RADIO_FE_SIG_ANA_CFG_RADIO_SEL);
+        let bin = self.get(SX1302_REG_RADIO_FE_SIG_ANA_FREQ_FREQ) as i8;
+        let corr = self.cal_correlation(radio, bin).min(0xFFFF);
+        self.set(SX1302_REG_RADIO_FE_SIG_ANA_ABS_MSB_CORR_ABS_OUT, (corr >> 8) as u8);
+        self.set(SX1302_REG_RADIO_FE_SIG_ANA_ABS_LSB_CORR_ABS_OUT, corr as u8);
+        self.set(SX1302_REG_RADIO_FE_SIG_ANA_CFG_VALID, 1);
+    }
+
+    fn cal_correlation(&self, radio: u8, bin: i8) -> u32 {
+        let Some((cmd, gains)) = self.cal_test else {
+            return SIM_CAL_NOISE_CORR;
+        };
+        if cmd & 0x0F != radio {
+            return SIM_CAL_NOISE_CORR;
+        }
+        let sq = |d: i32| (d * d) as u32;
+        match (cmd & 0xF0, bin) {
+            /* RX image: tone at +bin, image at -bin */
+            (0x10, SIM_CAL_TONE_BIN) => SIM_CAL_TONE_CORR,
+            (0x10, b) if b == -SIM_CAL_TONE_BIN => {
+                let (reg_amp, reg_phi) = if radio == 0 {
+                    (SX1302_REG_RADIO_FE_IQ_COMP_AMP_COEFF_RADIO_A_AMP_COEFF, SX1302_REG_RADIO_FE_IQ_COMP_PHI_COEFF_RADIO_A_PHI_COEFF)
+                } else {
+                    (SX1302_REG_RADIO_FE_IQ_COMP_AMP_COEFF_RADIO_B_AMP_COEFF, SX1302_REG_RADIO_FE_IQ_COMP_PHI_COEFF_RADIO_B_PHI_COEFF)
+                };
+                let amp = ((self.get(reg_amp) << 2) as i8 >> 2) as i32; /* 6 bits signed */
+                let phi = ((self.get(reg_phi) << 2) as i8 >> 2) as i32;
+                let (amp0, phi0) = SIM_CAL_RX_IQ_COMP[radio as usize];
+                60 + 8 * (sq(amp - amp0) + sq(phi - phi0))
+            }
+            /* TX DC: LO leakage at +bin, depends on the radio DAC/mixer gains */
+            (0x20, SIM_CAL_TONE_BIN) => {
+                let offset = TX_TOP_B_OFFSET * radio as u16;
+                let i = self.get(SX1302_REG_TX_TOP_A_TX_RFFE_IF_I_OFFSET_I_OFFSET + offset) as i8 as i32;
+                let q = self.get(SX1302_REG_TX_TOP_A_TX_RFFE_IF_Q_OFFSET_Q_OFFSET + offset) as i8 as i32;
+                let (i0, q0) = sim_cal_tx_dc_offset(radio, gains >> 4, gains & 0x0F);
+                40 + 16 * (sq(i - i0 as i32) + sq(q - q0 as i32))
+            }
+            _ => SIM_CAL_NOISE_CORR,
+        }
+    }
+
+    fn arb_ctrl(&mut self) {
+        let running = self.get(SX1302_REG_ARB_MCU_CTRL_HOST_PROG) == 0 && self.get(SX1302_REG_ARB_MCU_CTRL_MCU_CLEAR) == 0;
+        if !running {
+            self.arb_fw = SimMcuFw::None;
+            self.set(SX1302_REG_ARB_MCU_MCU_ARB_STATUS_MCU_ARB_STATUS, 0x00);
+            return;
+        }
+        if self.arb_fw != SimMcuFw::None {
+            return;
+        }
+
+        if self.loaded_fw(SIM_ARB_MEM_ADDR) != SimMcuFw::Arb {
+            warn!("SIM: unknown firmware in ARB memory, ARB not started");
+            return;
+        }
+        debug!("SIM: ARB started (v{})", FW_VERSION_ARB);
+        self.arb_fw = SimMcuFw::Arb;
+        self.set(SX1302_REG_ARB_MCU_ARB_DEBUG_STS_0_ARB_DEBUG_STS_0, FW_VERSION_ARB);
+        self.set(SX1302_REG_ARB_MCU_MCU_ARB_STATUS_MCU_ARB_STATUS, 0x01);
+    }
+
+    /* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ */
+    /* TX path */
+
+    fn tx_trig(&mut self, rf_chain: u8, old: u8) {
+        let rising = |s: &Self, reg_a: u16| {
+            let reg = Self::tx_reg(rf_chain, reg_a);
+            (old >> LOREGS[reg as usize].offs) & 0x01 == 0 && s.get(reg) == 1
+        };
+        let immediate = rising(self, SX1302_REG_TX_TOP_A_TX_TRIG_TX_TRIG_IMMEDIATE);
+        let delayed = rising(self, SX1302_REG_TX_TOP_A_TX_TRIG_TX_TRIG_DELAYED);
+        let gps = rising(self, SX1302_REG_TX_TOP_A_TX_TRIG_TX_TRIG_GPS);
+
+        if self.get(Self::tx_reg(rf_chain, SX1302_REG_TX_TOP_A_TX_TRIG_TX_TRIG_IMMEDIATE)) == 0 &&
+           self.get(Self::tx_reg(rf_chain, SX1302_REG_TX_TOP_A_TX_TRIG_TX_TRIG_DELAYED)) == 0 &&
+           self.get(Self::tx_reg(rf_chain, SX1302_REG_TX_TOP_A_TX_TRIG_TX_TRIG_GPS)) == 0 {
+            /* state machine reset / TX abort */
+            self.tx_state[rf_chain as usize] = SimTxState::Idle;
+            return;
+        }
+
+        let tx_mode = if immediate {
+            TxMode::IMMEDIATE
+        } else if delayed {
+            TxMode::TIMESTAMPED
+        } else if gps {
+            TxMode::ON_GPS
+        } else {
+            return;
+        };
+
+        let pkt = self.tx_capture(rf_chain, tx_mode);
+        let toa_us = Self::tx_time_on_air(&pkt, self.get(Self::tx_reg(rf_chain, SX1302_REG_TX_TOP_A_FSK_MOD_FSK_REF_PATTERN_SIZE)) as u32 + 1);
+        debug!("SIM: TX on chain {} ({:?}, {} Hz, {} bytes, ToA {} us)", rf_chain, pkt.tx_mode, pkt.freq_hz, pkt.payload.len(), toa_us);
+
+        let end = Self::tx_end(&pkt, toa_us);
+        let count_32mhz = pkt.count_us.wrapping_mul(32);
+        self.tx_log.push(pkt);
+
+        self.tx_state[rf_chain as usize] = match tx_mode {
+            TxMode::IMMEDIATE if self.tx_start(rf_chain as usize) => SimTxState::Emitting { end },
+            TxMode::IMMEDIATE => SimTxState::Idle,
+            TxMode::TIMESTAMPED => SimTxState::Delayed { count_32mhz, toa_us },
+            TxMode::ON_GPS => SimTxState::OnGps { second: self.boot.elapsed().as_secs(), toa_us },
+        };
+    }
+
+    /* With LBT enabled, the AGC only starts a TX if the SX1261 found the channel free */
+    fn tx_start(&mut self, rf_chain: usize) -> bool {
+        if !self.agc_lbt {
+            return true;
+        }
+        let freq_hz = self.sx1261.freq_hz;
+        let rssi = self.channel_rssi(freq_hz);
+        let allowed = self.sx1261.lbt_threshold.is_some_and(|threshold| rssi <= threshold);
+
+        let mut status = self.get(SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS) | (1 << rf_chain);
+        if !allowed {
+            debug!("SIM: TX on chain {} blocked by LBT ({} Hz, rssi {} dBm, threshold {:?})", rf_chain, freq_hz, rssi, self.sx1261.lbt_threshold);
+            status |= 1 << (6 + rf_chain);
+            if let Some(i) = self.tx_log.iter().rposition(|p| p.rf_chain as usize == rf_chain) {
+                self.tx_log.remove(i);
+            }
+        }
+        self.set(SX1302_REG_AGC_MCU_MCU_AGC_STATUS_MCU_AGC_STATUS, status);
+        allowed
+    }
+
+    fn tx_capture(&self, rf_chain: u8, tx_mode: TxMode) -> SimTxPacket {
+        let reg = |reg_a: u16| self.get(Self::tx_reg(rf_chain, reg_a));
+
+        let freq_reg = ((reg(SX1302_REG_TX_TOP_A_TX_RFFE_IF_FREQ_RF_H_FREQ_RF) as u64) << 16) |
+                       ((reg(SX1302_REG_TX_TOP_A_TX_RFFE_IF_FREQ_RF_M_FREQ_RF) as u64) << 8) |
+                       (reg(SX1302_REG_TX_TOP_A_TX_RFFE_IF_FREQ_RF_L_FREQ_RF) as u64);
+        let timer = ((reg(SX1302_REG_TX_TOP_A_TIMER_TRIG_BYTE3_TIMER_DELAYED_TRIG) as u32) << 24) |
+                    ((reg(SX1302_REG_TX_TOP_A_TIMER_TRIG_BYTE2_TIMER_DELAYED_TRIG) as u32) << 16) |
+                    ((reg(SX1302_REG_TX_TOP_A_TIMER_TRIG_BYTE1_TIMER_DELAYED_TRIG) as u32) << 8) |
+                    (reg(SX1302_REG_TX_TOP_A_TIMER_TRIG_BYTE0_TIMER_DELAYED_TRIG) as u32);
+        let mem_addr = SIM_TX_BUFFER_ADDR[rf_chain as usize];
+
+        let mut pkt = SimTxPacket {
+            rf_chain,
+            tx_mode,
+            count_us: timer / 32,
+            freq_hz: (((freq_reg * 32000000) + (1 << 17)) >> 18) as u32,
+            rf_power: reg(SX1302_REG_TX_TOP_A_AGC_TX_PWR_AGC_TX_PWR),
+            modulation: Modulation::CW,
+            bandwidth: 0,
+            datarate: 0,
+            coderate: 0,
+            invert_pol: false,
+            preamble: 0,
+            no_crc: true,
+            no_header: false,
+            payload: Vec::new(),
+        };
+
+        match reg(SX1302_REG_TX_TOP_A_TX_RFFE_IF_CTRL_TX_IF_SRC) {
+            0x01 => {
+                let size = reg(SX1302_REG_TX_TOP_A_TXRX_CFG0_3_PAYLOAD_LENGTH) as usize;
+                pkt.modulation = Modulation::LORA;
+                pkt.bandwidth = reg(SX1302_REG_TX_TOP_A_TXRX_CFG0_0_MODEM_BW);
+                pkt.datarate = reg(SX1302_REG_TX_TOP_A_TXRX_CFG0_0_MODEM_SF) as u32;
+                pkt.coderate = reg(SX1302_REG_TX_TOP_A_TXRX_CFG0_1_CODING_RATE);
+                pkt.invert_pol = reg(SX1302_REG_TX_TOP_A_TX_CFG0_0_CHIRP_INVERT) != 0;
+                pkt.preamble = ((reg(SX1302_REG_TX_TOP_A_TXRX_CFG1_3_PREAMBLE_SYMB_NB) as u16) << 8) | (reg(SX1302_REG_TX_TOP_A_TXRX_CFG1_2_PREAMBLE_SYMB_NB) as u16);
+                pkt.no_crc = reg(SX1302_REG_TX_TOP_A_TXRX_CFG0_2_CRC_EN) == 0;
+                pkt.no_header = reg(SX1302_REG_TX_TOP_A_TXRX_CFG0_2_IMPLICIT_HEADER) != 0;
+                pkt.payload = self.mem[mem_addr .. mem_addr + size].to_vec();
+            }
+            0x02 => {
+                let size = reg(SX1302_REG_TX_TOP_A_FSK_PKT_LEN_PKT_LENGTH) as usize;
+                let br_reg = ((reg(SX1302_REG_TX_TOP_A_FSK_BIT_RATE_MSB_BIT_RATE) as u32) << 8) | (reg(SX1302_REG_TX_TOP_A_FSK_BIT_RATE_LSB_BIT_RATE) as u32);
+                pkt.modulation = Modulation::FSK;
+                pkt.datarate = 32000000u32.checked_div(br_reg).unwrap_or(0);
+                pkt.preamble = ((reg(SX1302_REG_TX_TOP_A_FSK_PREAMBLE_SIZE_MSB_PREAMBLE_SIZE) as u16) << 8) | (reg(SX1302_REG_TX_TOP_A_FSK_PREAMBLE_SIZE_LSB_PREAMBLE_SIZE) as u16);
+                pkt.no_crc = reg(SX1302_REG_TX_TOP_A_FSK_CFG_0_CRC_EN) == 0;
+                /* payload size is inserted before the payload in variable length mode */
+                pkt.payload = self.mem[mem_addr + 1 .. mem_addr + 1 + size].to_vec();
+            }
+            _ => {}
+        }
+
+        pkt
+    }
+
+    fn tx_time_on_air(pkt: &SimTxPacket, fsk_sync_word_size: u32) -> u32 {
+        match pkt.modulation {
+            Modulation::LORA => {
+                lora_packet_time_on_air(pkt.bandwidth, pkt.datarate as u8, pkt.coderate, pkt.preamble, pkt.no_header, pkt.no_crc, pkt.payload.len() as u8)
+                    .map(|toa| toa.0)
+                    .unwrap_or(0)
+            }
+            Modulation::FSK if pkt.datarate > 0 => {
+                let nb_bytes = pkt.preamble as u32 + fsk_sync_word_size + 1 + pkt.payload.len() as u32 + if pkt.no_crc { 0 } else { 2 };
+                ((nb_bytes as u64 * 8 * 1000000) / pkt.datarate as u64) as u32
+            }
+            _ => 0,
+        }
+    }
+
+    fn tx_end(pkt: &SimTxPacket, toa_us: u32) -> Option<Instant> {
+        /* CW keeps emitting until aborted */
+        if pkt.modulation == Modulation::CW {
+            None
+        } else {
+            Some(Instant::now() + Duration::from_micros(toa_us as u64))
+        }
+    }
+
+    /* The TX state is only updated when polled */
+    fn tx_update(&mut self, rf_chain: usize) {
+        let state = self.tx_state[rf_chain];
+        self.tx_state[rf_chain] = match state {
+            SimTxState::Delayed { count_32mhz, toa_us } if self.counter_32mhz().wrapping_sub(count_32mhz) < 0x80000000 => {
+                /* the emission started at the trigger */
+                let elapsed_us = self.counter_32mhz().wrapping_sub(count_32mhz) / 32;
+                let allowed = self.tx_start(rf_chain);
+                match toa_us.checked_sub(elapsed_us) {
+                    Some(left_us) if left_us > 0 && allowed => SimTxState::Emitting { end: Some(Instant::now() + Duration::from_micros(left_us as u64)) },
+                    _ => SimTxState::Idle,
+                }
+            }
+            SimTxState::OnGps { second, toa_us } if self.pps && self.boot.elapsed().as_secs() > second => {
+                if self.tx_start(rf_chain) {
+                    SimTxState::Emitting { end: Some(Instant::now() + Duration::from_micros(toa_us as u64)) }
+                } else {
+                    SimTxState::Idle
+                }
+            }
+            SimTxState::Emitting { end: Some(end) } if Instant::now() >= end => SimTxState::Idle,
+            _ => state,
+        };
+    }
+
+    fn tx_status(&mut self, rf_chain: usize) -> u8 {
+        self.tx_update(rf_chain);
+
+        match self.tx_state[rf_chain] {
+            SimTxState::Idle => SIM_TX_STATUS_FREE,
+            SimTxState::Delayed { .. } | SimTxState::OnGps { .. } => SIM_TX_STATUS_SCHEDULED,
+            SimTxState::Emitting { .. } => SIM_TX_STATUS_EMITTING,
+        }
+    }
+}
+
+/**
+@struct SimConcentrator
+@brief Handle on a simulated concentrator, cheap to clone and shared between the host side and the test
+*/
+#[derive(Debug, Clone)]
+pub struct SimConcentrator {
+    state: Arc<Mutex<SimState>>,
+}
+
+impl Default for SimConcentrator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimConcentrator {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SimState::new())),
+        }
+    }
+
+    /* Queue a packet in the RX buffer, it will be returned by a subsequent lgw_receive */
+    pub fn inject_rx(&self, pkt: &SimRxPacket) -> Result<()> {
+        if pkt.if_chain > 9 {
+            return Err(anyhow!("ERROR: invalid IF chain {}", pkt.if_chain));
+        }
+        if pkt.payload.len() > 255 {
+            return Err(anyhow!("ERROR: payload too long ({} bytes)", pkt.payload.len()));
+        }
+
+        let mut s = self.state.lock().unwrap();
+        let cnt = s.counter_32mhz();
+        s.rx_pending.push_back(pkt.encode(cnt));
+        Ok(())
+    }
+
+    /* Queue raw bytes in the RX buffer, e.g. noise in front of the next injected packet */
+    pub fn inject_rx_raw(&self, bytes: &[u8]) {
+        self.state.lock().unwrap().rx_pending.push_back(bytes.to_vec());
+    }
+
+    /* Drain the packets transmitted so far */
+    pub fn take_tx(&self) -> Vec<SimTxPacket> {
+        std::mem::take(&mut self.state.lock().unwrap().tx_log)
+    }
+
+    pub fn set_temperature(&self, temperature: f32) {
+        self.state.lock().unwrap().temperature = temperature;
+    }
+
+    /* Number of SX125x RX PLL starts that fail to lock, before the next ones lock again */
+    pub fn set_pll_lock_failures(&self, nb: u32) {
+        self.state.lock().unwrap().pll_lock_failures = nb;
+    }
+
+    /* Fail the MCU status requests (and so the temperature reads) */
+    pub fn set_status_error(&self, error: bool) {
+        self.state.lock().unwrap().status_error = error;
+    }
+
+    /* Chip model reported in OTP, CHIP_MODEL_ID_SX1302 by default */
+    pub fn set_chip_model(&self, model_id: u8) {
+        self.state.lock().unwrap().chip_model = model_id;
+    }
+
+    /* Generate a PPS edge every second (latches the PPS counter, releases ON_GPS TX) */
+    pub fn set_pps(&self, enable: bool) {
+        self.state.lock().unwrap().pps = enable;
+    }
+
+    /* RSSI measured by the SX1261 on a channel, the noise floor elsewhere */
+    pub fn set_channel_rssi(&self, freq_hz: u32, rssi_dbm: i8) {
+        let mut state = self.state.lock().unwrap();
+        state.channel_rssi.retain(|(f, _)| *f != freq_hz);
+        state.channel_rssi.push((freq_hz, rssi_dbm));
+    }
+
+    /* Current value of the internal counter, in us, as returned by lgw_get_instcnt (modulo 2^27) */
+    pub fn counter_us(&self) -> u32 {
+        self.state.lock().unwrap().counter_32mhz() / 32
+    }
+
+    /* Peek a register of the simulated register file */
+    pub fn reg_r(&self, register_id: u16) -> Result<i32> {
+        if register_id >= LGW_TOTALREGS {
+            return Err(anyhow!("ERROR: REGISTER NUMBER OUT OF DEFINED RANGE"));
+        }
+        let s = self.state.lock().unwrap();
+        let r = &LOREGS[register_id as usize];
+        let v = s.get(register_id);
+        if r.sign && (v >> (r.leng - 1)) & 0x01 == 1 {
+            Ok(v as i32 - (1 << r.leng))
+        } else {
+            Ok(v as i32)
+        }
+    }
+
+    /* Handle one complete request frame (header + payload) and return the ACK frame */
+    pub fn process(&self, request: &[u8]) -> Result<Vec<u8>> {
+        if request.len() < HEADER_CMD_SIZE {
+            return Err(anyhow!("ERROR: request too short ({} bytes)", request.len()));
+        }
+        let size = ((request[1] as usize) << 8) | (request[2] as usize);
+        if request.len() < HEADER_CMD_SIZE + size {
+            return Err(anyhow!("ERROR: truncated request (expected {} bytes, got {})", HEADER_CMD_SIZE + size, request.len()));
+        }
+
+        let mut s = self.state.lock().unwrap();
+        Ok(s.process(&request[.. HEADER_CMD_SIZE], &request[HEADER_CMD_SIZE .. HEADER_CMD_SIZE + size]))
+    }
+
+    /* In-memory transport answering requests synchronously, see Hal::with_transport */
+    pub fn stream(&self) -> SimStream {
+        SimStream {
+            sim: self.clone(),
+            open: true,
+            input: Vec::new(),
+            output: VecDeque::new(),
+        }
+    }
+
+    /* Answer requests received on a byte stream until `stop` is set */
+    pub fn serve<S: Read + Write>(&self, stream: &mut S, stop: &AtomicBool) -> Result<()> {
+        let mut request = vec![0u8; HEADER_CMD_SIZE];
+
+        loop {
+            request.truncate(HEADER_CMD_SIZE);
+            if !Self::read_full(stream, &mut request[.. HEADER_CMD_SIZE], stop)? {
+                return Ok(());
+            }
+            let size = ((request[1] as usize) << 8) | (request[2] as usize);
+            request.resize(HEADER_CMD_SIZE + size, 0);
+            if !Self::read_full(stream, &mut request[HEADER_CMD_SIZE ..], stop)? {
+                return Ok(());
+            }
+
+            let ack = self.process(&request)?;
+            stream.write_all(&ack)?;
+            stream.flush()?;
+        }
+    }
+
+    fn read_full<S: Read>(stream: &mut S, buf: &mut [u8], stop: &AtomicBool) -> Result<bool> {
+        let mut n = 0;
+        while n < buf.len() {
+            if stop.load(Ordering::Relaxed) {
+                return Ok(false);
+            }
+            match stream.read(&mut buf[n ..]) {
+                Ok(0) => return Ok(false),
+                Ok(k) => n += k,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::Interrupted => {}
+                /* pty master side while no process has the slave side open */
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => std::thread::sleep(Duration::from_millis(10)),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(true)
+    }
+
+    /* Serve the simulator on a pseudo terminal, its path can be used as com_path */
+    #[cfg(unix)]
+    pub fn spawn_pty(&self) -> Result<SimPty> {
+        use serialport::SerialPort;
+
+        let (mut master, slave) = serialport::TTYPort::pair()?;
+        let path = slave.name().ok_or(anyhow!("ERROR: failed to get pty name"))?;
+        /* the slave side is reopened by the host through com_path */
+        drop(slave);
+        master.set_timeout(Duration::from_millis(50))?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let sim = self.clone();
+        let thread_stop = stop.clone();
+        let thread = std::thread::spawn(move || {
+            if let Err(e) = sim.serve(&mut master, &thread_stop) {
+                warn!("SIM: pty server stopped: {}", e);
+            }
+        });
+
+        debug!("SIM: serving on {}", path);
+        Ok(SimPty { path, stop, thread: Some(thread) })
+    }
+}
+
+/**
+@struct SimPty
+@brief Simulator served on a pseudo terminal, stopped when dropped
+*/
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct SimPty {
+    path: String,
+    stop: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(unix)]
+impl SimPty {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+#[cfg(unix)]
+impl Drop for SimPty {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/**
+@struct SimStream
+@brief In-memory transport: requests written by the host are answered synchronously
+*/
+#[derive(Debug)]
+pub struct SimStream {
+    sim: SimConcentrator,
+    open: bool,
+    input: Vec<u8>,
+    output: VecDeque<u8>,
+}
+
+impl LgwComTransport for SimStream {
+    fn open(&mut self, _port_name: String) -> Result<()> {
+        self.open = true;
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.open = false;
+        self.input.clear();
+        self.output.clear();
+    }
+
+    fn is_open(&self) -> bool {
+        self.open
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.input.extend_from_slice(data);
+
+        /* answer every complete request */
+        while self.input.len() >= HEADER_CMD_SIZE {
+            let size = HEADER_CMD_SIZE + (((self.input[1] as usize) << 8) | (self.input[2] as usize));
+            if self.input.len() < size {
+                break;
+            }
+            let request: Vec<u8> = self.input.drain(.. size).collect();
+            let ack = self.sim.process(&request).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            self.output.extend(ack);
+        }
+
+        Ok(data.len())
+    }
+
+    fn read(&mut self, data: &mut [u8]) -> io::Result<usize> {
+        if self.output.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "Operation timed out"));
+        }
+        let n = data.len().min(self.output.len());
+        for (b, v) in data.iter_mut().zip(self.output.drain(.. n)) {
+            *b = v;
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::{Hal, LgwConfigBoard, LgwConfRxIf, LgwConfRxrf, LgwHal, LgwPktRx, LgwPktTx, LgwTxGain, BW_125KHZ, CR_LORA_4_5, STAT_CRC_BAD, STAT_CRC_OK, TX_FREE};
+    use crate::hal::loragw_sx1302::LorgwSx1302Trait;
+
+    /* Radio 0 at 867.5MHz with TX, radio 1 at 868.5MHz, multi-SF channels at 867.1, 867.5 and 868.1MHz */
+    fn setconf(hal: &mut Hal) {
+        hal.lgw_board_setconf(&LgwConfigBoard { lorawan_public: true, ..Default::default() }).unwrap();
+        let radio = LgwConfRxrf { enable: true, rssi_offset: -215.4, ..Default::default() };
+        hal.lgw_rxrf_setconf(0, &LgwConfRxrf { freq_hz: 867_500_000, tx_enable: true, ..radio.clone() }).unwrap();
+        hal.lgw_rxrf_setconf(1, &LgwConfRxrf { freq_hz: 868_500_000, ..radio }).unwrap();
+        hal.lgw_txgain_setconf(0, &[LgwTxGain { rf_power: 14, pa_gain: 0, pwr_idx: 17, ..Default::default() }]).unwrap();
+        for (if_chain, rf_chain, freq_hz) in [(0, 1, -400_000), (3, 0, -400_000), (5, 0, 0)] {
+            hal.lgw_rxif_setconf(if_chain, &LgwConfRxIf { enable: true, rf_chain, freq_hz, ..Default::default() }).unwrap();
+        }
+    }
+
+    fn receive(hal: &mut Hal, nb: usize) -> Vec<LgwPktRx> {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut pkts = Vec::new();
+        while pkts.len() < nb && Instant::now() < deadline {
+            pkts.extend(hal.lgw_receive().unwrap());
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        pkts
+    }
+
+    fn wait_tx_free(hal: &mut Hal) {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while hal.sx1302_tx_status(0) != TX_FREE {
+            assert!(Instant::now() < deadline, "TX did not complete");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn start_receive_send() {
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        setconf(&mut hal);
+        hal.lgw_start().unwrap();
+
+        let payload: Vec<u8> = (0 .. 24).collect();
+        sim.inject_rx(&SimRxPacket { if_chain: 3, datarate: 9, payload: payload.clone(), ..Default::default() }).unwrap();
+        sim.inject_rx(&SimRxPacket { if_chain: 5, datarate: 7, crc_error: true, payload: vec![0xA5; 30], ..Default::default() }).unwrap();
+
+        let pkts = receive(&mut hal, 2);
+        assert_eq!(pkts.len(), 2);
+        assert_eq!((pkts[0].if_chain, pkts[0].rf_chain, pkts[0].freq_hz), (3, 0, 867_100_000));
+        assert_eq!((pkts[0].datarate, pkts[0].bandwidth, pkts[0].status), (9, BW_125KHZ, STAT_CRC_OK));
+        assert_eq!(&pkts[0].payload[.. pkts[0].size as usize], &payload[..]);
+        assert_eq!((pkts[1].if_chain, pkts[1].freq_hz, pkts[1].status), (5, 867_500_000, STAT_CRC_BAD));
+        assert!(hal.lgw_receive().unwrap().is_empty());
+
+        let mut pkt = LgwPktTx {
+            freq_hz: 869_525_000,
+            rf_power: 14,
+            modulation: Modulation::LORA,
+            bandwidth: BW_125KHZ,
+            datarate: 9,
+            coderate: CR_LORA_4_5,
+            invert_pol: true,
+            preamble: 8,
+            size: 4,
+            ..Default::default()
+        };
+        pkt.payload[.. 4].copy_from_slice(&[1, 2, 3, 4]);
+        hal.lgw_send(&pkt).unwrap();
+        wait_tx_free(&mut hal);
+
+        let count_us = hal.lgw_get_instcnt().unwrap().wrapping_add(50_000);
+        hal.lgw_send(&LgwPktTx { tx_mode: TxMode::TIMESTAMPED, count_us, ..pkt }).unwrap();
+        wait_tx_free(&mut hal);
+
+        let tx = sim.take_tx();
+        assert_eq!(tx.len(), 2);
+        assert_eq!(tx[0].rf_chain, 0);
+        assert!(matches!((tx[0].tx_mode, tx[0].modulation), (TxMode::IMMEDIATE, Modulation::LORA)));
+        assert!(tx[0].freq_hz.abs_diff(869_525_000) < 100);   /* PLL step */
+        assert_eq!((tx[0].datarate, tx[0].bandwidth, tx[0].coderate), (9, BW_125KHZ, CR_LORA_4_5));
+        assert!(tx[0].invert_pol);
+        assert_eq!(tx[0].payload, [1, 2, 3, 4]);
+        assert!(matches!(tx[1].tx_mode, TxMode::TIMESTAMPED));
+        assert!(tx[1].count_us.abs_diff(count_us) < 5_000, "TX at {}, requested {}", tx[1].count_us, count_us);
+        assert!(sim.take_tx().is_empty());
+
+        hal.lgw_stop().unwrap();
+        assert!(hal.lgw_send(&pkt).is_err());
+    }
+}
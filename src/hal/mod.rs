@@ -1,29 +1,54 @@
 
 mod loragw_sx1250;
+mod loragw_sx125x;
+pub mod loragw_sx1261;
+mod loragw_lbt;
 mod cal_fw;
+mod loragw_cal;
 mod loragw_agc_params;
 mod arb_firmware;
-mod loragw_reg;
+mod sx1261_pram;
+pub mod loragw_reg;
+pub mod loragw_regdump;
 pub mod mcu;
 pub mod helper;
 pub mod loragw_sx1302_timestamp;
 pub mod error;
 mod loragw_sx1302;
 mod agc_firmware;
-mod loragw_usb_com;
+pub mod loragw_usb_com;
 pub mod loragw_com;
+pub mod loragw_conf;
+pub mod loragw_jit;
+pub mod loragw_capture;
+pub mod loragw_stats;
+#[cfg(feature = "prometheus")]
+pub mod loragw_metrics;
+pub mod loragw_planner;
+pub mod loragw_region;
+pub mod loragw_regulatory;
+pub mod loragw_validate;
+pub mod loragw_gps;
+pub mod loragw_spectral_scan;
+pub mod loragw_runner;
+#[cfg(feature = "tokio")]
+pub mod loragw_async;
 
 use agc_firmware::{AGC_FIRMWARE_SX1250, AGC_FIRMWARE_SX125X};
-use anyhow::{anyhow,Result};
+use anyhow::Result;
 use loragw_com::LgwComType;
 use loragw_reg::*;
 use loragw_sx1250::LoragwSx1250Trait;
-use loragw_sx1302::{LorgwSx1302Trait, Sx1302, IF_FSK_STD, IF_LORA_MULTI, IF_LORA_STD, IF_UNDEFINED, SX1302_AGC_RADIO_GAIN_AUTO};
+use loragw_sx125x::LoragwSx125xTrait;
+use loragw_sx1261::LoragwSx1261Trait;
+use loragw_lbt::LoragwLbtTrait;
+use loragw_sx1302::{sx1302_rssi_get_temperature_offset, LorgwSx1302Trait, Sx1302, IF_FSK_STD, IF_LORA_MULTI, IF_LORA_STD, IF_UNDEFINED, SX1302_AGC_RADIO_GAIN_AUTO};
 use loragw_sx1302_timestamp::lora_packet_time_on_air;
 use mcu::McuTrait;
 use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, trace, warn};
-use error::Error;
+use error::{Error, LgwFirmware};
 
 
 
@@ -109,8 +134,14 @@ const FW_VERSION_AGC_SX1250:u8=   10; /* Expected version of AGC firmware for sx
 const FW_VERSION_AGC_SX125X:u8=   6;  /* Expected version of AGC firmware for sx1255/sx1257 based gateway */
 const FW_VERSION_ARB:u8=          2;  /* Expected version of arbiter firmware */
 
+const TEMPERATURE_REFRESH: Duration = Duration::from_secs(10); /* board temperature cache for the RSSI compensation */
 
 
+
+/* values available for the 'select' parameter of lgw_status */
+pub const TX_STATUS:u8 =           1;
+pub const RX_STATUS:u8 =           2;
+
 /* status code for TX_STATUS */
 /* NOTE: arbitrary values */
 pub const TX_STATUS_UNKNOWN:u8 =  0;
@@ -144,6 +175,16 @@ pub const LGW_LBT_CHANNEL_NB_MAX:usize= 16; /* Maximum number of LBT channels */
 /* Spectral Scan */
 pub const LGW_SPECTRAL_SCAN_RESULT_SIZE:usize= 33; /* The number of results returned by spectral scan function, to be used for memory allocation */
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[allow(non_camel_case_types)]
+pub enum LgwSpectralScanStatus {
+    LGW_SPECTRAL_SCAN_STATUS_NONE,
+    LGW_SPECTRAL_SCAN_STATUS_ON_GOING,
+    LGW_SPECTRAL_SCAN_STATUS_ABORTED,
+    LGW_SPECTRAL_SCAN_STATUS_COMPLETED,
+    LGW_SPECTRAL_SCAN_STATUS_UNKNOWN,
+}
+
 
 const LGW_RF_RX_BANDWIDTH_125KHZ:u32 =  1600000;     /* for 125KHz channels */
 const LGW_RF_RX_BANDWIDTH_250KHZ:u32 =  1600000;     /* for 250KHz channels */
@@ -550,6 +591,13 @@ impl Default for LgwConfSx1261 {
     }
 }
 
+/* SX1261 PRAM patch image: 32-bit words, and the version it reports once started */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LgwSx1261Pram {
+    pub words: Vec<u32>,
+    pub version: String,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct ConfRefPayload {
     pub id:u32,
@@ -607,14 +655,15 @@ pub struct LgwPktRx {
     pub size: u16,           /* payload size in bytes */
     pub payload: [u8; 256],   /* buffer containing the payload */
     pub ftime_received: bool, /* a fine timestamp has been received */
-    pub ftime: u32          /* packet fine timestamp (nanoseconds since last PPS) */
+    pub ftime: u32,         /* packet fine timestamp (nanoseconds since last PPS) */
+    pub rssi_tcomp: f32,    /* temperature compensation included in rssic/rssis, in dB */
 }
 
 impl std::fmt::Display for LgwPktRx {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "LgwPktRx {{ freq_hz: {}, freq_offset: {}, if_chain: {}, status: {}, count_us: {}, rf_chain: {}, modem_id: {}, modulation: {}, bandwidth: {}, datarate: {}, coderate: {}, rssic: {}, rssis: {}, snr: {}, snr_min: {}, snr_max: {}, crc: {}, size: {}, payload: {:02X?}, ftime_received: {}, ftime: {} }}",
+            "LgwPktRx {{ freq_hz: {}, freq_offset: {}, if_chain: {}, status: {}, count_us: {}, rf_chain: {}, modem_id: {}, modulation: {}, bandwidth: {}, datarate: {}, coderate: {}, rssic: {}, rssis: {}, snr: {}, snr_min: {}, snr_max: {}, crc: {}, size: {}, payload: {:02X?}, ftime_received: {}, ftime: {}, rssi_tcomp: {} }}",
             self.freq_hz,
             self.freq_offset,
             self.if_chain,
@@ -635,7 +684,8 @@ impl std::fmt::Display for LgwPktRx {
             self.size,
             &self.payload[..self.size as usize],
             self.ftime_received,
-            self.ftime
+            self.ftime,
+            self.rssi_tcomp
         )
     }
 }
@@ -663,6 +713,7 @@ impl LgwPktRx {
             payload: [0; 256],
             ftime_received: false,
             ftime: 0,
+            rssi_tcomp: 0.0,
         }
     }
 }
@@ -742,8 +793,12 @@ pub struct LgwContext {
     /* Misc */
     pub      ftime_cfg:LgwConfigFtime,                              /* Fine timestamp configuration */
     pub      sx1261_cfg:LgwConfSx1261,                             /* SX1261 configuration */
+    pub      sx1261_pram: Option<LgwSx1261Pram>,                   /* SX1261 PRAM patch loaded by lgw_start, None for the bundled one */
+    pub      sx1261_pram_loaded: bool,                             /* SX1261 PRAM patch loaded and checked, cleared by sx1261_reset */
     /* Debug */
     pub      debug_cfg:LgwConfDebug,                              /* Debug configuration */
+    /* Board temperature, cached for the RSSI compensation */
+    pub      temperature: Option<(f32, Instant)>,
 }
 
 impl Default for LgwContext {
@@ -776,11 +831,18 @@ impl Default for LgwContext {
             if_chain_cfg: Default::default(), 
             demod_cfg: Default::default(), 
             lora_service_cfg: Default::default(), 
-            fsk_cfg: Default::default(), 
+            fsk_cfg: LgwConfRxIf {
+                sync_word_size: 3,          /* default number of bytes for FSK sync word */
+                sync_word: 0xC194C1,        /* default FSK sync word (ALIGNED RIGHT, MSbit first) */
+                ..Default::default()
+            },
             tx_gain_lut: Default::default(), 
             ftime_cfg: Default::default(), 
             sx1261_cfg: Default::default(), 
-            debug_cfg: Default::default() 
+            sx1261_pram: None,
+            sx1261_pram_loaded: false,
+            debug_cfg: Default::default(),
+            temperature: None,
         }
     }
 }
@@ -791,6 +853,10 @@ pub struct Hal {
     pub mcu: mcu::Mcu,
     pub ctx:LgwContext,
     pub sx1302: Sx1302,
+    pub capture: Option<loragw_capture::LgwCapture>,     /* pcapng capture of RX/TX packets, see lgw_capture_start */
+    pub stats: loragw_stats::LgwStats,                  /* RX/TX counters, see lgw_stats */
+    #[cfg(feature = "prometheus")]
+    pub metrics: Option<std::sync::Arc<loragw_metrics::LgwMetrics>>,     /* see lgw_metrics_enable */
 }
 
 impl Hal {
@@ -800,6 +866,22 @@ impl Hal {
             mcu: mcu::Mcu::new(),
             ctx: Default::default(),
             sx1302: Sx1302::new(),
+            capture: None,
+            stats: Default::default(),
+            #[cfg(feature = "prometheus")]
+            metrics: None,
+        }
+    }
+
+    pub fn with_transport(com: Box<dyn loragw_com::LgwComTransport>) -> Self {
+        Self {
+            mcu: mcu::Mcu::with_transport(com),
+            ctx: Default::default(),
+            sx1302: Sx1302::new(),
+            capture: None,
+            stats: Default::default(),
+            #[cfg(feature = "prometheus")]
+            metrics: None,
         }
     }
 
@@ -815,9 +897,15 @@ pub trait LgwHal {
     fn lgw_get_temperature(&mut self) -> Result<f32>;
     fn lgw_send(&mut self, pkt_data:&LgwPktTx) -> Result<()>;
     fn lgw_get_instcnt(&mut self) -> Result<u32>;
+    fn lgw_get_trigcnt(&mut self) -> Result<u32>;
     fn lgw_txgain_setconf(&mut self,  rf_chain:u8, conf: &[LgwTxGain]) -> Result<()>;
+    fn lgw_ftime_setconf(&mut self, conf: &LgwConfigFtime) -> Result<()>;
+    fn lgw_sx1261_setconf(&mut self, conf: &LgwConfSx1261) -> Result<()>;
+    fn lgw_sx1261_set_pram(&mut self, pram: LgwSx1261Pram) -> Result<()>;
     fn lgw_stop(&mut self) -> Result<()> ;
     fn lgw_abort_tx(&mut self, rf_chain: u8) -> Result<()>;
+    fn lgw_status(&mut self, rf_chain: u8, select: u8) -> Result<u8>;
+    fn lgw_get_eui(&mut self) -> Result<u64>;
 }
 
 impl LgwHal for Hal {
@@ -829,7 +917,7 @@ impl LgwHal for Hal {
         /* check input variables */
         if rf_chain >= LGW_RF_CHAIN_NB {
             error!("ERROR: NOT A VALID RF_CHAIN NUMBER");
-            return Err(Error::LGW_HAL_ERROR.into());
+            return Err(Error::config("rf_chain", rf_chain, "not a valid RF chain number").into());
         }
 
         /* Abort current TX */
@@ -837,6 +925,26 @@ impl LgwHal for Hal {
 
     }
 
+    fn lgw_status(&mut self, rf_chain: u8, select: u8) -> Result<u8> {
+
+        /* check input variables */
+        if rf_chain >= LGW_RF_CHAIN_NB {
+            error!("ERROR: NOT A VALID RF_CHAIN NUMBER");
+            return Err(Error::config("rf_chain", rf_chain, "not a valid RF chain number").into());
+        }
+
+        match select {
+            TX_STATUS if !self.ctx.is_started => Ok(TX_OFF),
+            TX_STATUS => Ok(self.sx1302_tx_status(rf_chain)),
+            RX_STATUS if !self.ctx.is_started => Ok(RX_OFF),
+            RX_STATUS => Ok(self.sx1302_rx_status(rf_chain)),
+            _ => {
+                error!("ERROR: SELECTION INVALID, NO STATUS TO RETURN\n");
+                Err(Error::config("select", select, "no status to return").into())
+            }
+        }
+    }
+
     fn lgw_stop(&mut self) -> Result<()> {
        
         if self.ctx.is_started == false {
@@ -859,171 +967,85 @@ impl LgwHal for Hal {
         }
 
         self.ctx.is_started = false;
+        self.ctx.sx1261_pram_loaded = false;
+        #[cfg(feature = "prometheus")]
+        self.lgw_metrics_started(false);
 
 
         return Ok(());
     }
     fn lgw_send(&mut self, pkt_data:&LgwPktTx) -> Result<()>{
-
-        //let lbt_tx_allowed:bool;
-        /* performances variables */
-        let ctx = self.ctx.clone();
-
-        debug!(" --- {:}\n", "IN");
-
-        /* check if the concentrator is running */
-        if self.ctx.is_started == false {
-            error!("ERROR: CONCENTRATOR IS NOT RUNNING, START IT BEFORE SENDING\n");
-            return Err(Error::LGW_HAL_ERROR.into());
-        }
-
-        /* check input range (segfault prevention) */
-        if pkt_data.rf_chain >= LGW_RF_CHAIN_NB {
-            error!("ERROR: INVALID RF_CHAIN TO SEND PACKETS\n");
-            return Err(Error::LGW_HAL_ERROR.into());
-        }
-
-        /* check input variables */
-        if ctx.rf_chain_cfg[pkt_data.rf_chain as usize].tx_enable == false {
-            error!("ERROR: SELECTED RF_CHAIN IS DISABLED FOR TX ON SELECTED BOARD\n");
-            return Err(Error::LGW_HAL_ERROR.into());
-        }
-        if ctx.rf_chain_cfg[pkt_data.rf_chain as usize].enable == false {
-            error!("ERROR: SELECTED RF_CHAIN IS DISABLED\n");
-            return Err(Error::LGW_HAL_ERROR.into());
-        }
-
-        match pkt_data.modulation {
-            Modulation::LORA => {
-                if !Hal::is_lora_bw(pkt_data.bandwidth) {
-                    error!("ERROR: BANDWIDTH NOT SUPPORTED BY LORA TX\n");
-                    return Err(Error::LGW_HAL_ERROR.into());
-                }
-                if !Hal::is_lora_dr(pkt_data.datarate) {
-                    error!("ERROR: DATARATE NOT SUPPORTED BY LORA TX\n");
-                    return Err(Error::LGW_HAL_ERROR.into());
-                }
-                if !Hal::is_lora_cr(pkt_data.coderate) {
-                    error!("ERROR: CODERATE NOT SUPPORTED BY LORA TX\n");
-                    return Err(Error::LGW_HAL_ERROR.into());
-                }
-                if pkt_data.size > 255 {
-                    error!("ERROR: PAYLOAD LENGTH TOO BIG FOR LORA TX\n");
-                    return Err(Error::LGW_HAL_ERROR.into());
-                }
-            },
-            Modulation::FSK => {
-                if (pkt_data.f_dev < 1) || (pkt_data.f_dev > 200) {
-                    error!("ERROR: TX FREQUENCY DEVIATION OUT OF ACCEPTABLE RANGE\n");
-                    return Err(Error::LGW_HAL_ERROR.into());
-                }
-                if !Hal::is_fsk_dr(pkt_data.datarate) {
-                    error!("ERROR: DATARATE NOT SUPPORTED BY FSK IF CHAIN\n");
-                    return Err(Error::LGW_HAL_ERROR.into());
-                }
-                if pkt_data.size > 255 {
-                    error!("ERROR: PAYLOAD LENGTH TOO BIG FOR FSK TX\n");
-                    return Err(Error::LGW_HAL_ERROR.into());
-                }
-            },
-            Modulation::CW => {
-            /* do nothing */
-            },
-            _ => {
-                error!("ERROR: INVALID TX MODULATION\n");
-                return Err(Error::LGW_HAL_ERROR.into());
-            }
-        }
-    
-        /* Start Listen-Before-Talk 
-        if (ctx.sx1261_cfg.lbt_conf.enable == true) {
-            err = lgw_lbt_start(&CONTEXT_SX1261, pkt_data);
-            if (err != 0) {
-                error!("ERROR: failed to start LBT\n");
-                return Err(Error::LGW_HAL_ERROR.into());
-            }
-        }
-        */
-        /* Send the TX request to the concentrator */
-        let mut tx = pkt_data.clone();
-        if let Err(err) =  self.sx1302_send(ctx.rf_chain_cfg[pkt_data.rf_chain as usize]._type, &ctx.tx_gain_lut[pkt_data.rf_chain as usize], ctx.board_cfg.lorawan_public, &ctx.fsk_cfg, &mut tx) {
-   
-            error!(e=%err, "ERROR: Failed to send packet\n");
-            /* 
-            if (CONTEXT_SX1261.lbt_conf.enable == true) {
-                err = lgw_lbt_stop();
-                if (err != 0) {
-                    error!("ERROR: %s: Failed to stop LBT\n", __FUNCTION__);
-                }
-            }
-            */
-            return Err(Error::LGW_HAL_ERROR.into());
-        }
-
-       // _meas_time_stop(1, tm, __FUNCTION__);
-
-        /* Stop Listen-Before-Talk 
-        if (CONTEXT_SX1261.lbt_conf.enable == true) {
-            err = lgw_lbt_tx_status(pkt_data.rf_chain, &lbt_tx_allowed);
-            if (err != 0) {
-                error!("ERROR: %s: Failed to get LBT TX status, TX aborted\n", __FUNCTION__);
-                err = sx1302_tx_abort(pkt_data.rf_chain);
-                if (err != 0) {
-                    error!("ERROR: %s: Failed to abort TX\n", __FUNCTION__);
-                }
-                err = lgw_lbt_stop();
-                if (err != 0) {
-                    error!("ERROR: %s: Failed to stop LBT\n", __FUNCTION__);
-                }
-                return Err(Error::LGW_HAL_ERROR.into());
-            }
-            if (lbt_tx_allowed == true) {
-                error!("LBT: packet is allowed to be transmitted\n");
-            } else {
-                error!("LBT: (ERROR) packet is NOT allowed to be transmitted\n");
-            }
-
-            err = lgw_lbt_stop();
-            if (err != 0) {
-                error!("ERROR: %s: Failed to stop LBT\n", __FUNCTION__);
-                return Err(Error::LGW_HAL_ERROR.into());
-            }
-            
-        }
-        */
-        debug!(" --- {}\n", "OUT");
-
-        Ok(())
+        let res = self.lgw_send_pkt(pkt_data);
+        self.stats.record_tx(&res);
+        #[cfg(feature = "prometheus")]
+        self.lgw_metrics_tx(pkt_data, &res);
+        if res.is_ok() {
+            self.lgw_capture_tx(pkt_data);
+        }
+        res
     }
 
     fn lgw_get_instcnt(&mut self) -> Result<u32> {
         self.sx1302_timestamp_counter(false)
     }
 
+    /* Counter value latched on the last PPS edge, see loragw_gps */
+    fn lgw_get_trigcnt(&mut self) -> Result<u32> {
+        self.sx1302_timestamp_counter(true)
+    }
+
     fn lgw_receive(&mut self) -> Result<Vec<LgwPktRx>> {
 
         let mut pkts = Vec::<LgwPktRx>::new();
-        let nb_pkg_fetched = self.sx1302_fetch();
-        if nb_pkg_fetched.is_err() {
-            error!("ERROR: failed to fetch packets from SX1302\n");
-            return Err(anyhow!("LGW_HAL_ERROR"));
-        }
-        let nb_pkg_fetched = nb_pkg_fetched.unwrap();
+        let nb_pkg_fetched = match self.sx1302_fetch() {
+            Ok(nb) => nb,
+            Err(e) => {
+                error!("ERROR: failed to fetch packets from SX1302\n");
+                return Err(e.context("failed to fetch packets from SX1302"));
+            }
+        };
 
         /* Update internal counter */
         /* WARNING: this needs to be called regularly by the upper layer */
         self.sx1302_update()?;
 
-        let _temp = self.lgw_get_temperature()?;
+        /* a failed temperature read only costs the RSSI temperature compensation, not the packets */
+        let temperature = match nb_pkg_fetched {
+            0 => None,
+            _ => match self.lgw_get_temperature_cached() {
+                Ok(temperature) => Some(temperature),
+                Err(e) => {
+                    warn!("WARNING: failed to get the board temperature, RSSI not compensated: {:?}\n", e);
+                    None
+                }
+            },
+        };
 
         for _i in 0 .. nb_pkg_fetched {
-            if let  Ok(mut pkt) = self.sx1302_parse(){
-                pkt.rssic += self.ctx.rf_chain_cfg[pkt.rf_chain as usize].rssi_offset;
-                pkt.rssis += self.ctx.rf_chain_cfg[pkt.rf_chain as usize].rssi_offset;
-                debug!(pkt=%pkt);
-                pkts.push(pkt);
-            }
+            let mut pkt = match self.sx1302_parse() {
+                Ok(pkt) => pkt,
+                Err(_) => {
+                    self.stats.rx_dropped += 1;
+                    continue;
+                }
+            };
+
+            let rf_chain_cfg = &self.ctx.rf_chain_cfg[pkt.rf_chain as usize];
+            /* Apply RSSI offset calibrated for the board, and its temperature compensation */
+            pkt.rssi_tcomp = temperature.map_or(0.0, |t| sx1302_rssi_get_temperature_offset(&rf_chain_cfg.rssi_tcomp, t));
+            pkt.rssic += rf_chain_cfg.rssi_offset + pkt.rssi_tcomp;
+            pkt.rssis += rf_chain_cfg.rssi_offset + pkt.rssi_tcomp;
+            trace!("INFO: RSSI temperature offset applied: {:.3} dB (current temperature {:?} C)\n", pkt.rssi_tcomp, temperature);
+            debug!(pkt=%pkt);
+            self.stats.record_rx(&pkt);
+            pkts.push(pkt);
+        }
+
+        if !pkts.is_empty() {
+            self.lgw_capture_rx(&pkts);
         }
+        #[cfg(feature = "prometheus")]
+        self.lgw_metrics_rx(&pkts);
         Ok(pkts)
     }
 
@@ -1035,11 +1057,14 @@ impl LgwHal for Hal {
             return Ok(())
         }
 
-        self.lgw_connect()?;
+        self.lgw_connect().map_err(|e| Error::Start { step: "connect", source: e })?;
 
-        self.sx1302_set_gpio(0)?;
+        self.sx1302_set_gpio(0).map_err(|e| Error::Start { step: "GPIO reset", source: e })?;
 
-        self.sx1302_radio_calibrate(&ctx.rf_chain_cfg, ctx.board_cfg.clksrc, &ctx.tx_gain_lut)?;
+        /* sx125x calibration fills the TX DC offsets of the gain tables */
+        let mut tx_gain_lut = ctx.tx_gain_lut.clone();
+        self.sx1302_radio_calibrate(&ctx.rf_chain_cfg, ctx.board_cfg.clksrc, &mut tx_gain_lut).map_err(|e| Error::Start { step: "radio calibration", source: e })?;
+        self.ctx.tx_gain_lut = tx_gain_lut;
 
 
         /* Setup radios for RX */
@@ -1048,47 +1073,56 @@ impl LgwHal for Hal {
                 /* Reset the radio */
                 if let Err(e) = self.sx1302_radio_reset(i, self.ctx.rf_chain_cfg[i as usize]._type) {
                     error!("ERROR: failed to reset radio {}\n", i);
-                    return Err(anyhow!("LGW_HAL_ERROR: {}", e));
+                    return Err(Error::Start { step: "radio reset", source: e }.into());
                 }
 
                 
                 /* Setup the radio */
                 match self.ctx.rf_chain_cfg[i as usize]._type {
                     LgwRadioType:: LGW_RADIO_TYPE_SX1250 => {
-                        if let Err(_) = self.sx1250_setup(i, self.ctx.rf_chain_cfg[i as usize].freq_hz, self.ctx.rf_chain_cfg[i as usize].single_input_mode){
+                        if let Err(err) = self.sx1250_setup(i, self.ctx.rf_chain_cfg[i as usize].freq_hz, self.ctx.rf_chain_cfg[i as usize].single_input_mode){
                             error!("ERROR: failed to setup SX1250: {}", i);
-                            return Err(anyhow!("LGW_HAL_ERR"));
+                            return Err(Error::Start { step: "SX1250 setup", source: err }.into());
+                        }
+                    }
+                    LgwRadioType::LGW_RADIO_TYPE_SX1255 | LgwRadioType::LGW_RADIO_TYPE_SX1257 => {
+                        if let Err(err) = self.sx125x_setup(i, self.ctx.board_cfg.clksrc, true, self.ctx.rf_chain_cfg[i as usize]._type, self.ctx.rf_chain_cfg[i as usize].freq_hz){
+                            error!("ERROR: failed to setup SX125x: {}", i);
+                            return Err(Error::Start { step: "SX125x setup", source: err }.into());
                         }
                     }
-                    /* 
-                    case LGW_RADIO_TYPE_SX1255:
-                    case LGW_RADIO_TYPE_SX1257:
-                        if let Err(err) = sx125x_setup(i, CONTEXT_BOARD.clksrc, true, ctx.rf_chain_cfg[i].type, ctx.rf_chain_cfg[i].freq_hz);
-                        break;
-                    */
                     _ =>{
                         error!("ERROR: RADIO TYPE NOT SUPPORTED (RF_CHAIN {:})\n", i);
-                        return Err(anyhow!("RADIO TYPE NOT SUPPORTED"));
+                        return Err(Error::config("radio type", self.ctx.rf_chain_cfg[i as usize]._type, "not supported").into());
                     }
                 }
                 
                 
 
                 /* Set radio mode */
-                if let Err(_) = self.sx1302_radio_set_mode(i, self.ctx.rf_chain_cfg[i as usize]._type){
+                if let Err(err) = self.sx1302_radio_set_mode(i, self.ctx.rf_chain_cfg[i as usize]._type){
              
                     error!("ERROR: failed to set mode for radio {:}\n", i);
-                    return Err(anyhow!("LGW_HAL_ERROR"))
+                    return Err(Error::Start { step: "radio set mode", source: err }.into());
                 }
             }
         }
 
+        /* SX1261 companion radio, for LBT and spectral scan */
+        if self.ctx.sx1261_cfg.enable {
+            /* LBT and spectral scan commands only exist in the PRAM patch */
+            self.sx1261_load_patch().map_err(|e| Error::Start { step: "SX1261 PRAM patch", source: e })?;
+            let freq_hz = self.ctx.rf_chain_cfg[0].freq_hz;
+            self.sx1261_calibrate(freq_hz).map_err(|e| Error::Start { step: "SX1261 calibration", source: e })?;
+            self.sx1261_setup().map_err(|e| Error::Start { step: "SX1261 setup", source: e })?;
+        }
+
         /* Select the radio which provides the clock to the sx1302 */
-        self.sx1302_radio_clock_select(self.ctx.board_cfg.clksrc).map_err(|e| anyhow!("LGW_HAL_ERR: failed to get clock from radio {}",e))?;
+        self.sx1302_radio_clock_select(self.ctx.board_cfg.clksrc).map_err(|e| Error::Start { step: "radio clock select", source: e })?;
         
 
         /* Release host control on radio (will be controlled by AGC) */
-        self.sx1302_radio_host_ctrl(false).map_err(|e| anyhow!("ERROR: failed to release control over radios {}",e))?;
+        self.sx1302_radio_host_ctrl(false).map_err(|e| Error::Start { step: "radio host control release", source: e })?;
     
 
         /* Basic initialization of the sx1302 */
@@ -1096,7 +1130,7 @@ impl LgwHal for Hal {
 
         if let Err(err) = self.sx1302_init(&ftime_cfg){
             error!("ERROR: failed to initialize SX1302: {}", err);
-            return Err(anyhow!("LGW_HAL_ERROR"));
+            return Err(Error::Start { step: "SX1302 init", source: err }.into());
         }
         
         /* Configure PA/LNA LUTs */
@@ -1104,37 +1138,37 @@ impl LgwHal for Hal {
         if let Err(err) = self.sx1302_pa_lna_lut_configure(&board_cfg){
  
             error!("ERROR: failed to configure SX1302 PA/LNA LUT: {}", err);
-            return Err(anyhow!("LGW_HAL_ERROR"));
+            return Err(Error::Start { step: "PA/LNA LUT configuration", source: err }.into());
         }
 
 
         /* Configure Radio FE */
-        if let Err(_) = self.sx1302_radio_fe_configure() {
+        if let Err(err) = self.sx1302_radio_fe_configure() {
             error!("ERROR: failed to configure SX1302 radio frontend\n");
-            return Err(anyhow!("LGW_HAL_ERROR"));
+            return Err(Error::Start { step: "radio frontend configuration", source: err }.into());
         }
 
         /* Configure the Channelizer */
         let if_chain_cfg = self.ctx.if_chain_cfg;
-        if let Err(_) = self.sx1302_channelizer_configure(&if_chain_cfg, false){
+        if let Err(err) = self.sx1302_channelizer_configure(&if_chain_cfg, false){
             error!("ERROR: failed to configure SX1302 channelizer\n");
-            return Err(anyhow!("LGW_HAL_ERROR"));
+            return Err(Error::Start { step: "channelizer configuration", source: err }.into());
         }
         
         let demod_cfg = self.ctx.demod_cfg;
         /* configure LoRa 'multi-sf' modems */
-        if let Err(_) = self.sx1302_lora_correlator_configure(&if_chain_cfg, &demod_cfg)
+        if let Err(err) = self.sx1302_lora_correlator_configure(&if_chain_cfg, &demod_cfg)
         {
             error!("ERROR: failed to configure SX1302 LoRa modem correlators\n");
-            return Err(anyhow!("LGW_HAL_ERR"))
+            return Err(Error::Start { step: "LoRa correlators configuration", source: err }.into());
         }
 
         let rf_chain_cfg = &self.ctx.rf_chain_cfg;
 
-        if let Err(_) = self.sx1302_lora_modem_configure(rf_chain_cfg[0].freq_hz)
+        if let Err(err) = self.sx1302_lora_modem_configure(rf_chain_cfg[0].freq_hz)
         {
             error!("ERROR: failed to configure SX1302 LoRa modems\n");
-            return Err(anyhow!("LGW_HAL_ERR"))
+            return Err(Error::Start { step: "LoRa modems configuration", source: err }.into());
         }
 
         /* configure LoRa 'single-sf' modem */
@@ -1142,12 +1176,12 @@ impl LgwHal for Hal {
             let lora_service_cfg = self.ctx.lora_service_cfg;
             if let Err(e) = self.sx1302_lora_service_correlator_configure(&lora_service_cfg) {
                 error!(e=%e, "ERROR: failed to configure SX1302 LoRa Service modem correlators\n");
-                return Err(anyhow!("LGW_HAL_ERR"));
+                return Err(Error::Start { step: "LoRa service correlators configuration", source: e }.into());
             }
 
             if let Err(e) =self.sx1302_lora_service_modem_configure(&lora_service_cfg, self.ctx.rf_chain_cfg[0].freq_hz){
                 error!(e=%e,"ERROR: failed to configure SX1302 LoRa Service modem\n");
-                return Err(anyhow!("LGW_HAL_ERR"));
+                return Err(Error::Start { step: "LoRa service modem configuration", source: e }.into());
             }
         }
 
@@ -1155,22 +1189,22 @@ impl LgwHal for Hal {
         if if_chain_cfg[9].enable == true {
             let fsk_cfg = self.ctx.fsk_cfg;
 
-            if let Err(_) =self.sx1302_fsk_configure(&fsk_cfg){
+            if let Err(err) =self.sx1302_fsk_configure(&fsk_cfg){
                 error!("ERROR: failed to configure SX1302 FSK modem\n");
-                return Err(anyhow!("LGW_HAL_ERR"));
+                return Err(Error::Start { step: "FSK modem configuration", source: err }.into());
             }
         }
 
         /* configure syncword */
-        if let Err(_) =self.sx1302_lora_syncword(self.ctx.board_cfg.lorawan_public, self.ctx.lora_service_cfg.datarate as u8){
+        if let Err(err) =self.sx1302_lora_syncword(self.ctx.board_cfg.lorawan_public, self.ctx.lora_service_cfg.datarate as u8){
             error!("ERROR: failed to configure SX1302 LoRa syncword\n");
-            return Err(anyhow!("LGW_HAL_ERR"));
+            return Err(Error::Start { step: "LoRa syncword configuration", source: err }.into());
         }
 
         /* enable demodulators - to be done before starting AGC/ARB */
-        if let Err(_) =self.sx1302_modem_enable(){
+        if let Err(err) =self.sx1302_modem_enable(){
             error!("ERROR: failed to enable SX1302 modems\n");
-            return Err(anyhow!("LGW_HAL_ERR"));
+            return Err(Error::Start { step: "modems enable", source: err }.into());
         }
         
         let fw_version_agc:u8;
@@ -1181,7 +1215,7 @@ impl LgwHal for Hal {
                 debug!("Loading AGC fw for sx1250\n");
                 if let Err(e) = self.sx1302_agc_load_firmware(&AGC_FIRMWARE_SX1250){
                     error!("ERROR: failed to load AGC firmware for sx1250: {}\n", e);
-                    return Err(anyhow!("LGW_HAL_ERROR"));
+                    return Err(Error::Start { step: "AGC firmware load", source: e }.into());
                 }
                 fw_version_agc = FW_VERSION_AGC_SX1250;
             }
@@ -1191,7 +1225,7 @@ impl LgwHal for Hal {
                 debug!("Loading AGC fw for sx125x\n");
                 if let Err(err) = self.sx1302_agc_load_firmware(&AGC_FIRMWARE_SX125X){
                     error!("ERROR: failed to load AGC firmware for sx125x {}\n", err);
-                    return Err(anyhow!("LGW_HAL_ERROR"));
+                    return Err(Error::Start { step: "AGC firmware load", source: err }.into());
                 }
                 fw_version_agc = FW_VERSION_AGC_SX125X;
                 
@@ -1199,13 +1233,13 @@ impl LgwHal for Hal {
                 
             _ =>{
                 error!("ERROR: failed to load AGC firmware, radio type not supported ({:})\n", self.ctx.rf_chain_cfg[self.ctx.board_cfg.clksrc as usize]._type);
-                return Err(anyhow!("LGW_HAL_ERROR"));
+                return Err(Error::config("radio type", self.ctx.rf_chain_cfg[self.ctx.board_cfg.clksrc as usize]._type, "no AGC firmware for this radio").into());
             }
         }
 
         if let Err(err) = self.sx1302_agc_start(fw_version_agc, self.ctx.rf_chain_cfg[self.ctx.board_cfg.clksrc as usize]._type, SX1302_AGC_RADIO_GAIN_AUTO, SX1302_AGC_RADIO_GAIN_AUTO, self.ctx.board_cfg.full_duplex, self.ctx.sx1261_cfg.lbt_conf.enable){
             error!("ERROR: failed to start AGC firmware: {}", err);
-            return Err(anyhow!("LGW_HAL_ERROR"));
+            return Err(Error::Start { step: "AGC start", source: err }.into());
         }
 
         /* Load ARB firmware */
@@ -1213,38 +1247,40 @@ impl LgwHal for Hal {
         if let Err(err) = self.sx1302_arb_load_firmware(&arb_firmware::ARB_FIRMWARE)
         {
             error!("ERROR: failed to load ARB firmware: {}", err);
-            return Err(anyhow!("LGW_HAL_ERROR"));
+            return Err(Error::Start { step: "ARB firmware load", source: err }.into());
         }
         
         let ftime_cfg = self.ctx.ftime_cfg;
-        if let Err(_) = self.sx1302_arb_start(FW_VERSION_ARB, &ftime_cfg){
+        if let Err(err) = self.sx1302_arb_start(FW_VERSION_ARB, &ftime_cfg){
             error!("ERROR: failed to start ARB firmware\n");
-            return Err(anyhow!("LGW_HAL_ERROR"));
+            return Err(Error::Start { step: "ARB start", source: err }.into());
         }
 
         
-        if let Err(_) = self.sx1302_tx_configure(self.ctx.rf_chain_cfg[self.ctx.board_cfg.clksrc as usize]._type){
+        if let Err(err) = self.sx1302_tx_configure(self.ctx.rf_chain_cfg[self.ctx.board_cfg.clksrc as usize]._type){
 
             error!("ERROR: failed to configure SX1302 TX path\n");
-            return Err(anyhow!("LGW_HAL_ERROR"));
+            return Err(Error::Start { step: "TX path configuration", source: err }.into());
         }
 
 
         if let Err(err) = self.sx1302_gps_enable(true){
 
             error!("ERROR: failed to enable GPS on sx1302: {}", err);
-            return Err(anyhow!("LGW_HAL_ERROR"));
+            return Err(Error::Start { step: "GPS enable", source: err }.into());
         }
         
 
         /* Set CONFIG_DONE GPIO to 1 (turn on the corresponding LED) */
-        if let Err(_) = self.sx1302_set_gpio(0x01){
+        if let Err(err) = self.sx1302_set_gpio(0x01){
             
             error!("ERROR: failed to set CONFIG_DONE GPIO\n");
-            return Err(anyhow!("LGW_HAL_ERROR"));   
+            return Err(Error::Start { step: "CONFIG_DONE GPIO", source: err }.into());
         }
 
         self.ctx.is_started = true;
+        #[cfg(feature = "prometheus")]
+        self.lgw_metrics_started(true);
 
 
         info!("lgw_start done");
@@ -1257,13 +1293,13 @@ impl LgwHal for Hal {
 
         if self.ctx.is_started {
             error!("ERROR: CONCENTRATOR IS RUNNING, STOP IT BEFORE TOUCHING CONFIGURATION");
-            return Err(anyhow!("CONCENTRATOR IS RUNNING"));
+            return Err(Error::ConcentratorRunning.into());
         }
 
         /* Check input parameters */
         if (conf.com_type != LgwComType::LGW_COM_SPI) && (conf.com_type != LgwComType::LGW_COM_USB) {
             error!("ERROR: WRONG COM TYPE\n");
-            return Err(anyhow!("ERROR: WRONG COM TYPE"))
+            return Err(Error::config("com_type", format!("{:?}", conf.com_type), "wrong COM type").into())
         }
 
         /* set internal config according to parameters */
@@ -1281,11 +1317,14 @@ impl LgwHal for Hal {
 
         if self.ctx.is_started {
             error!("ERROR: CONCENTRATOR IS RUNNING, STOP IT BEFORE TOUCHING CONFIGURATION");
-            return Err(anyhow!("CONCENTRATOR IS RUNNING"));
+            return Err(Error::ConcentratorRunning.into());
         }
 
         if conf.enable == false {
             /* nothing to do */
+            if rf_chain < LGW_RF_CHAIN_NB {
+                self.ctx.rf_chain_cfg[rf_chain as usize].enable = false;
+            }
             debug!("Note: rf_chain {:} disabled\n", rf_chain);
             return Ok(());
         }
@@ -1293,19 +1332,19 @@ impl LgwHal for Hal {
         /* check input range (segfault prevention) */
         if rf_chain >= LGW_RF_CHAIN_NB {
             error!("ERROR: NOT A VALID RF_CHAIN NUMBER\n");
-            return Err(anyhow!("NOT A VALID RF_CHAIN NUMBER"));
+            return Err(Error::config("rf_chain", rf_chain, "not a valid RF chain number").into());
         }
 
         /* check if radio type is supported */
         if (conf._type != LgwRadioType::LGW_RADIO_TYPE_SX1255) && (conf._type != LgwRadioType::LGW_RADIO_TYPE_SX1257) && (conf._type != LgwRadioType::LGW_RADIO_TYPE_SX1250) {
             error!("ERROR: NOT A VALID RADIO TYPE {:}", conf._type);
-            return Err(anyhow!("NOT A VALID RADIO TYPE"));
+            return Err(Error::config("type", conf._type, "not a valid radio type").into());
         }
 
         /* check if the radio central frequency is valid */
         if (conf.freq_hz < LGW_RF_RX_FREQ_MIN) || (conf.freq_hz > LGW_RF_RX_FREQ_MAX) {
             error!("ERROR: NOT A VALID RADIO CENTER FREQUENCY, PLEASE CHECK IF IT HAS BEEN GIVEN IN HZ ({:})\n", conf.freq_hz);
-            return Err(anyhow!("NOT A VALID RADIO CENTER FREQUENCY"));
+            return Err(Error::config("freq_hz", conf.freq_hz, "not a valid radio center frequency, check it is given in Hz").into());
         }
 
         let ctx_rf_chain = &mut self.ctx.rf_chain_cfg[rf_chain as usize];
@@ -1336,11 +1375,17 @@ impl LgwHal for Hal {
 
     fn lgw_txgain_setconf(&mut self,  rf_chain:u8, conf: &[LgwTxGain]) -> Result<()> {
 
+        /* check input range (segfault prevention) */
+        if rf_chain >= LGW_RF_CHAIN_NB {
+            error!("ERROR: NOT A VALID RF_CHAIN NUMBER\n");
+            return Err(Error::config("rf_chain", rf_chain, "not a valid RF chain number").into());
+        }
+
         let ctx = &mut self.ctx.tx_gain_lut;
         /* Check LUT size */
         if (conf.len() < 1) || (conf.len() > TX_GAIN_LUT_SIZE_MAX) {
             error!("ERROR: TX gain LUT must have at least one entry and  maximum {} entries\n", TX_GAIN_LUT_SIZE_MAX);
-            return Err(Error::LGW_HAL_ERROR.into());
+            return Err(Error::config("tx_lut", conf.len(), "TX gain LUT must have between 1 and 16 entries").into());
         }
     
         ctx[rf_chain as usize].size = conf.len();
@@ -1349,23 +1394,23 @@ impl LgwHal for Hal {
             /* Check gain range */
             if lut.dig_gain > 3 {
                 error!("ERROR: TX gain LUT: SX1302 digital gain must be between 0 and 3\n");
-                return Err(Error::LGW_HAL_ERROR.into());
+                return Err(Error::config("tx_lut.dig_gain", lut.dig_gain, "must be between 0 and 3").into());
             }
             if lut.dac_gain > 3 {
                 error!("ERROR: TX gain LUT: SX1257 DAC gains must not exceed 3\n");
-                return Err(Error::LGW_HAL_ERROR.into());
+                return Err(Error::config("tx_lut.dac_gain", lut.dac_gain, "must not exceed 3").into());
             }
             if (lut.mix_gain < 5) || (lut.mix_gain > 15) {
                 error!("ERROR: TX gain LUT: SX1257 mixer gain must be betwen [5..15]\n");
-                return Err(Error::LGW_HAL_ERROR.into());
+                return Err(Error::config("tx_lut.mix_gain", lut.mix_gain, "must be between 5 and 15").into());
             }
             if lut.pa_gain > 3 {
                 error!("ERROR: TX gain LUT: External PA gain must not exceed 3\n");
-                return Err(Error::LGW_HAL_ERROR.into());
+                return Err(Error::config("tx_lut.pa_gain", lut.pa_gain, "must not exceed 3").into());
             }
             if lut.pwr_idx > 22 {
                 error!("ERROR: TX gain LUT: SX1250 power index must not exceed 22\n");
-                return Err(Error::LGW_HAL_ERROR.into());
+                return Err(Error::config("tx_lut.pwr_idx", lut.pwr_idx, "must not exceed 22").into());
             }
     
             /* Set internal LUT */
@@ -1393,13 +1438,13 @@ impl LgwHal for Hal {
         /* check if the concentrator is running */
         if self.ctx.is_started {
             error!("ERROR: CONCENTRATOR IS RUNNING, STOP IT BEFORE TOUCHING CONFIGURATION");
-            return Err(anyhow!("CONCENTRATOR IS RUNNING"));
+            return Err(Error::ConcentratorRunning.into());
         }
 
         /* check input range (segfault prevention) */
         if if_chain >= LGW_IF_CHAIN_NB {
             error!("ERROR: {:} NOT A VALID IF_CHAIN NUMBER\n", if_chain);
-            return Err(anyhow!("Invalid IF_CHAIN"));
+            return Err(Error::config("if_chain", if_chain, "not a valid IF chain number").into());
         }
 
         let ctx_if_chain = &mut self.ctx.if_chain_cfg[if_chain as usize];
@@ -1415,12 +1460,12 @@ impl LgwHal for Hal {
         /* check 'general' parameters */
         if <Hal as LorgwSx1302Trait>::sx1302_get_ifmod_config(if_chain) == IF_UNDEFINED as u8 {
             error!("ERROR: IF CHAIN {:} NOT CONFIGURABLE\n", if_chain);
-            return Err(anyhow!("IF CHAIN NOT CONFIGURABLE"))
+            return Err(Error::config("if_chain", if_chain, "IF chain not configurable").into())
         }
 
         if conf.rf_chain >= LGW_RF_CHAIN_NB {
             error!("ERROR: INVALID RF_CHAIN TO ASSOCIATE WITH A LORA_STD IF CHAIN\n");
-            return Err(anyhow!("INVALID RF_CHAIN TO ASSOCIATE WITH A LORA_STD IF CHAIN"))
+            return Err(Error::config("rf_chain", conf.rf_chain, "invalid RF chain to associate with an IF chain").into())
         }
 
         /* check if IF frequency is optimal based on channel and radio bandwidths */
@@ -1441,10 +1486,10 @@ impl LgwHal for Hal {
 
         if (conf.freq_hz + if bw_hz == -1 { LGW_REF_BW as i32 } else { bw_hz } / 2) > (rf_rx_bandwidth as i32 / 2) {
             error!("ERROR: IF FREQUENCY {:} TOO HIGH\n", conf.freq_hz);
-            return Err(anyhow!("IF FREQUENCY TOO HIGH"));
+            return Err(Error::config("freq_hz", conf.freq_hz, "IF frequency too high").into());
         } else if (conf.freq_hz - if bw_hz == -1 { LGW_REF_BW as i32 } else { bw_hz } / 2) < -(rf_rx_bandwidth as i32 / 2) {
             error!("ERROR: IF FREQUENCY {:} TOO LOW\n", conf.freq_hz);
-            return Err(anyhow!("IF FREQUENCY TOO LOW"));
+            return Err(Error::config("freq_hz", conf.freq_hz, "IF frequency too low").into());
         }
 
         /* check parameters according to the type of IF chain + modem,
@@ -1465,19 +1510,19 @@ impl LgwHal for Hal {
                 /* check BW & DR */
                 if !Hal::is_lora_bw(bandwidth) {
                     error!("ERROR: BANDWIDTH NOT SUPPORTED BY LORA_STD IF CHAIN");
-                    return Err(anyhow!("BANDWIDTH NOT SUPPORTED BY LORA_STD IF CHAIN"));
+                    return Err(Error::config("bandwidth", bandwidth, "not supported by LoRa std IF chain").into());
                 }
 
                 if !Hal::is_lora_dr(datarate) {
                     error!("ERROR: DATARATE NOT SUPPORTED BY LORA_STD IF CHAIN: {:}\n", conf.datarate);
-                    return Err(anyhow!("DATARATE NOT SUPPORTED BY LORA_STD IF CHAIN"));
+                    return Err(Error::config("datarate", datarate, "not supported by LoRa std IF chain").into());
                 }
 
                 /* set internal configuration  */
                 ctx_if_chain.enable = conf.enable;
                 ctx_if_chain.rf_chain = conf.rf_chain;
                 ctx_if_chain.freq_hz = conf.freq_hz;
-                let mut ctx_lora_service = self.ctx.lora_service_cfg;
+                let ctx_lora_service = &mut self.ctx.lora_service_cfg;
 
                 ctx_lora_service.bandwidth = bandwidth;
                 ctx_lora_service.datarate = datarate;
@@ -1506,12 +1551,12 @@ impl LgwHal for Hal {
                 /* check BW & DR */
                 if bandwidth != BW_125KHZ {
                     error!("ERROR: BANDWIDTH NOT SUPPORTED BY LORA_MULTI IF CHAIN\n");
-                    return Err(anyhow!("BANDWIDTH NOT SUPPORTED BY LORA_MULTI IF CHAIN"))
+                    return Err(Error::config("bandwidth", bandwidth, "not supported by LoRa multi IF chain").into())
                 }
 
                 if !Hal::is_lora_dr(datarate) {
                     error!("ERROR: DATARATE(S) NOT SUPPORTED BY LORA_MULTI IF CHAIN\n");
-                    return Err(anyhow!("DATARATE(S) NOT SUPPORTED BY LORA_MULTI IF CHAIN"));
+                    return Err(Error::config("datarate", datarate, "not supported by LoRa multi IF chain").into());
                 }
 
                 /* set internal configuration  */
@@ -1538,14 +1583,14 @@ impl LgwHal for Hal {
                 /* check BW & DR */
                 if !Hal::is_fsk_bw(bandwidth as u8) {
                     error!("ERROR: BANDWIDTH NOT SUPPORTED BY FSK IF CHAIN\n");
-                    return Err(anyhow!("BANDWIDTH NOT SUPPORTED BY FSK IF CHAIN"));
+                    return Err(Error::config("bandwidth", bandwidth, "not supported by FSK IF chain").into());
                 }
 
                 if !Hal::is_fsk_dr(datarate) {
                     error!("ERROR: DATARATE NOT SUPPORTED BY FSK IF CHAIN\n");
-                    return Err(anyhow!("DATARATE NOT SUPPORTED BY FSK IF CHAIN"));
+                    return Err(Error::config("datarate", datarate, "not supported by FSK IF chain").into());
                 }
-                let mut ctx_fsk = self.ctx.fsk_cfg;
+                let ctx_fsk = &mut self.ctx.fsk_cfg;
                 /* set internal configuration  */
                 ctx_if_chain.enable = conf.enable;
                 ctx_if_chain.rf_chain = conf.rf_chain;
@@ -1570,7 +1615,7 @@ impl LgwHal for Hal {
             }
             _ =>{
                 error!("ERROR: IF CHAIN {:} TYPE NOT SUPPORTED\n", if_chain);
-                return Err(anyhow!("IF CHAIN TYPE NOT SUPPORTED"));
+                return Err(Error::config("if_chain", if_chain, "IF chain type not supported").into());
             }
                 
         }
@@ -1583,16 +1628,217 @@ impl LgwHal for Hal {
         self.ctx.demod_cfg.multisf_datarate = conf.multisf_datarate
     }
 
+    fn lgw_ftime_setconf(&mut self, conf: &LgwConfigFtime) -> Result<()> {
+
+        if self.ctx.is_started {
+            error!("ERROR: CONCENTRATOR IS RUNNING, STOP IT BEFORE TOUCHING CONFIGURATION");
+            return Err(Error::ConcentratorRunning.into());
+        }
+
+        self.ctx.ftime_cfg.enable = conf.enable;
+        self.ctx.ftime_cfg.mode = conf.mode;
+
+        debug!("Note: fine timestamp configuration; en:{:} mode:{:?}\n", conf.enable, conf.mode);
+        Ok(())
+    }
+
+    fn lgw_sx1261_setconf(&mut self, conf: &LgwConfSx1261) -> Result<()> {
+
+        if self.ctx.is_started {
+            error!("ERROR: CONCENTRATOR IS RUNNING, STOP IT BEFORE TOUCHING CONFIGURATION");
+            return Err(Error::ConcentratorRunning.into());
+        }
+
+        /* check input parameters */
+        if conf.lbt_conf.enable && (conf.lbt_conf.nb_channel < 1 || conf.lbt_conf.nb_channel as usize > LGW_LBT_CHANNEL_NB_MAX) {
+            error!("ERROR: WRONG NUMBER OF LBT CHANNELS ({:})\n", conf.lbt_conf.nb_channel);
+            return Err(Error::config("lbt.nb_channel", conf.lbt_conf.nb_channel, "wrong number of LBT channels").into());
+        }
+
+        self.ctx.sx1261_cfg = conf.clone();
+
+        debug!("Note: sx1261 configuration; en:{:} rssi_offset:{:} lbt_en:{:}\n", conf.enable, conf.rssi_offset, conf.lbt_conf.enable);
+        Ok(())
+    }
+
+    /* PRAM patch loaded in the SX1261 by lgw_start, instead of the one bundled with the HAL */
+    fn lgw_sx1261_set_pram(&mut self, pram: LgwSx1261Pram) -> Result<()> {
+
+        if self.ctx.is_started {
+            error!("ERROR: CONCENTRATOR IS RUNNING, STOP IT BEFORE TOUCHING CONFIGURATION");
+            return Err(Error::ConcentratorRunning.into());
+        }
+
+        if pram.words.is_empty() {
+            error!("ERROR: EMPTY SX1261 PRAM PATCH\n");
+            return Err(Error::Firmware { fw: LgwFirmware::Sx1261Pram, reason: "empty patch image" }.into());
+        }
+
+        debug!("Note: sx1261 PRAM patch; {:} words, version {:}\n", pram.words.len(), pram.version);
+        self.ctx.sx1261_pram = Some(pram);
+        Ok(())
+    }
+
     fn lgw_get_temperature(&mut self) -> Result<f32> {
         
         let status = self.mcu.get_mcu_status()?;
+        self.ctx.temperature = Some((status.temperature, Instant::now()));
         Ok(status.temperature)
  
     }
+
+    /* Unique identifier of the SX1302, the concentrator must be connected */
+    fn lgw_get_eui(&mut self) -> Result<u64> {
+        self.sx1302_get_eui()
+    }
 }
 
 
 impl Hal {
+    /* lgw_send without the statistics and capture, which only see the final result */
+    fn lgw_send_pkt(&mut self, pkt_data:&LgwPktTx) -> Result<()>{
+
+        /* performances variables */
+        let ctx = self.ctx.clone();
+
+        debug!(" --- {:}\n", "IN");
+
+        /* check if the concentrator is running */
+        if self.ctx.is_started == false {
+            error!("ERROR: CONCENTRATOR IS NOT RUNNING, START IT BEFORE SENDING\n");
+            return Err(Error::ConcentratorNotStarted.into());
+        }
+
+        /* check input range (segfault prevention) */
+        if pkt_data.rf_chain >= LGW_RF_CHAIN_NB {
+            error!("ERROR: INVALID RF_CHAIN TO SEND PACKETS\n");
+            return Err(Error::tx_rejected("rf_chain", pkt_data.rf_chain, "not a valid RF chain number").into());
+        }
+
+        /* check input variables */
+        if ctx.rf_chain_cfg[pkt_data.rf_chain as usize].tx_enable == false {
+            error!("ERROR: SELECTED RF_CHAIN IS DISABLED FOR TX ON SELECTED BOARD\n");
+            return Err(Error::tx_rejected("rf_chain", pkt_data.rf_chain, "TX disabled on this RF chain").into());
+        }
+        if ctx.rf_chain_cfg[pkt_data.rf_chain as usize].enable == false {
+            error!("ERROR: SELECTED RF_CHAIN IS DISABLED\n");
+            return Err(Error::tx_rejected("rf_chain", pkt_data.rf_chain, "RF chain disabled").into());
+        }
+
+        match pkt_data.modulation {
+            Modulation::LORA => {
+                if !Hal::is_lora_bw(pkt_data.bandwidth) {
+                    error!("ERROR: BANDWIDTH NOT SUPPORTED BY LORA TX\n");
+                    return Err(Error::tx_rejected("bandwidth", pkt_data.bandwidth, "not supported by LoRa TX").into());
+                }
+                if !Hal::is_lora_dr(pkt_data.datarate) {
+                    error!("ERROR: DATARATE NOT SUPPORTED BY LORA TX\n");
+                    return Err(Error::tx_rejected("datarate", pkt_data.datarate, "not supported by LoRa TX").into());
+                }
+                if !Hal::is_lora_cr(pkt_data.coderate) {
+                    error!("ERROR: CODERATE NOT SUPPORTED BY LORA TX\n");
+                    return Err(Error::tx_rejected("coderate", pkt_data.coderate, "not supported by LoRa TX").into());
+                }
+                if pkt_data.size > 255 {
+                    error!("ERROR: PAYLOAD LENGTH TOO BIG FOR LORA TX\n");
+                    return Err(Error::tx_rejected("size", pkt_data.size, "payload too big for LoRa TX").into());
+                }
+            },
+            Modulation::FSK => {
+                if (pkt_data.f_dev < 1) || (pkt_data.f_dev > 200) {
+                    error!("ERROR: TX FREQUENCY DEVIATION OUT OF ACCEPTABLE RANGE\n");
+                    return Err(Error::tx_rejected("f_dev", pkt_data.f_dev, "frequency deviation out of range [1, 200] kHz").into());
+                }
+                if !Hal::is_fsk_dr(pkt_data.datarate) {
+                    error!("ERROR: DATARATE NOT SUPPORTED BY FSK IF CHAIN\n");
+                    return Err(Error::tx_rejected("datarate", pkt_data.datarate, "not supported by FSK TX").into());
+                }
+                if pkt_data.size > 255 {
+                    error!("ERROR: PAYLOAD LENGTH TOO BIG FOR FSK TX\n");
+                    return Err(Error::tx_rejected("size", pkt_data.size, "payload too big for FSK TX").into());
+                }
+            },
+            Modulation::CW => {
+            /* do nothing */
+            },
+            _ => {
+                error!("ERROR: INVALID TX MODULATION\n");
+                return Err(Error::tx_rejected("modulation", format!("{:?}", pkt_data.modulation), "invalid TX modulation").into());
+            }
+        }
+    
+        /* Start Listen-Before-Talk */
+        let lbt_enable = ctx.sx1261_cfg.lbt_conf.enable;
+        if lbt_enable {
+            if let Err(err) = self.lgw_lbt_start(&ctx.sx1261_cfg, pkt_data) {
+                error!("ERROR: failed to start LBT\n");
+                return Err(err);
+            }
+        }
+
+        /* Send the TX request to the concentrator */
+        let mut tx = pkt_data.clone();
+        if let Err(err) =  self.sx1302_send(ctx.rf_chain_cfg[pkt_data.rf_chain as usize]._type, &ctx.tx_gain_lut[pkt_data.rf_chain as usize], ctx.board_cfg.lorawan_public, &ctx.fsk_cfg, &mut tx) {
+   
+            error!(e=%err, "ERROR: Failed to send packet\n");
+            if lbt_enable {
+                if let Err(e) = self.lgw_lbt_stop() {
+                    error!(e=%e, "ERROR: Failed to stop LBT\n");
+                }
+            }
+            return Err(Error::Tx { rf_chain: pkt_data.rf_chain, source: err }.into());
+        }
+
+        /* Stop Listen-Before-Talk, once the AGC told whether the channel was free */
+        if lbt_enable {
+            let lbt_tx_allowed = match self.lgw_lbt_tx_status(pkt_data.rf_chain) {
+                Ok(allowed) => allowed,
+                Err(err) => {
+                    error!(e=%err, "ERROR: Failed to get LBT TX status, TX aborted\n");
+                    if let Err(e) = self.sx1302_tx_abort(pkt_data.rf_chain) {
+                        error!(e=%e, "ERROR: Failed to abort TX\n");
+                    }
+                    if let Err(e) = self.lgw_lbt_stop() {
+                        error!(e=%e, "ERROR: Failed to stop LBT\n");
+                    }
+                    return Err(Error::Tx { rf_chain: pkt_data.rf_chain, source: err }.into());
+                }
+            };
+
+            if let Err(err) = self.lgw_lbt_stop() {
+                error!(e=%err, "ERROR: Failed to stop LBT\n");
+                return Err(Error::Tx { rf_chain: pkt_data.rf_chain, source: err }.into());
+            }
+
+            if lbt_tx_allowed {
+                debug!("LBT: packet is allowed to be transmitted\n");
+            } else {
+                warn!("LBT: packet is NOT allowed to be transmitted\n");
+                return Err(Error::LbtNotAllowed { rf_chain: pkt_data.rf_chain, freq_hz: pkt_data.freq_hz }.into());
+            }
+        }
+
+        debug!(" --- {}\n", "OUT");
+
+        Ok(())
+    }
+
+    /* Board temperature, read from the MCU at most every TEMPERATURE_REFRESH. A failed refresh
+       keeps the last value, the temperature changing slowly. */
+    fn lgw_get_temperature_cached(&mut self) -> Result<f32> {
+        match self.ctx.temperature {
+            Some((temperature, time)) if time.elapsed() < TEMPERATURE_REFRESH => Ok(temperature),
+            Some((temperature, _)) => match self.lgw_get_temperature() {
+                Ok(t) => Ok(t),
+                Err(e) => {
+                    warn!("WARNING: failed to refresh the board temperature, using {:.1} C: {:?}\n", temperature, e);
+                    Ok(temperature)
+                }
+            },
+            None => self.lgw_get_temperature(),
+        }
+    }
+
     pub fn lgw_bw_getval( bw: u8)->i32 {
         match bw {
             BW_500KHZ => { return 500000 }
@@ -1670,4 +1916,91 @@ pub fn lgw_time_on_air(packet: &LgwPktTx, sync_word_size: u32) -> u32 {
     }
 
     return toa_ms;
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use loragw_conf::LgwGlobalConf;
+    use mcu::sim::{SimConcentrator, SimRxPacket};
+
+    const GLOBAL_CONF: &str = r#"{"SX130x_conf": {
+        "com_path": "/dev/ttyACM0", "clksrc": 0, "lorawan_public": true,
+        "radio_0": {
+            "enable": true, "type": "SX1250", "freq": 867500000, "rssi_offset": -215.4, "tx_enable": false,
+            "rssi_tcomp": {"coeff_a": 0, "coeff_b": 0, "coeff_c": 20.41, "coeff_d": 2162.56, "coeff_e": 0}
+        },
+        "radio_1": {"enable": true, "type": "SX1250", "freq": 868500000, "rssi_offset": -215.4, "tx_enable": false},
+        "chan_multiSF_3": {"enable": true, "radio": 0, "if": -400000}
+    }}"#;
+
+    fn started_hal() -> (SimConcentrator, Hal) {
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        LgwGlobalConf::parse(GLOBAL_CONF).unwrap().apply(&mut hal).unwrap();
+        hal.lgw_start().unwrap();
+        (sim, hal)
+    }
+
+    fn receive_one(hal: &mut Hal) -> LgwPktRx {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        loop {
+            let mut pkts = hal.lgw_receive().unwrap();
+            if let Some(pkt) = pkts.pop() {
+                assert!(pkts.is_empty());
+                return pkt;
+            }
+            assert!(Instant::now() < deadline, "no packet received");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn temperature_cache() {
+        let (sim, mut hal) = started_hal();
+        sim.set_temperature(30.0);
+        hal.ctx.temperature = None;
+        assert_eq!(hal.lgw_get_temperature_cached().unwrap(), 30.0);
+
+        /* not read again before TEMPERATURE_REFRESH */
+        sim.set_temperature(40.0);
+        assert_eq!(hal.lgw_get_temperature_cached().unwrap(), 30.0);
+        let read_at = Instant::now() - TEMPERATURE_REFRESH;
+        hal.ctx.temperature = Some((30.0, read_at));
+        assert_eq!(hal.lgw_get_temperature_cached().unwrap(), 40.0);
+        assert!(hal.ctx.temperature.is_some_and(|(_, time)| time > read_at));
+
+        /* a failed refresh keeps the last value, without a value it is an error */
+        sim.set_status_error(true);
+        hal.ctx.temperature = Some((40.0, read_at));
+        assert_eq!(hal.lgw_get_temperature_cached().unwrap(), 40.0);
+        hal.ctx.temperature = None;
+        assert!(hal.lgw_get_temperature_cached().is_err());
+
+        sim.set_status_error(false);
+        hal.lgw_stop().unwrap();
+    }
+
+    #[test]
+    fn receive_without_temperature() {
+        let (sim, mut hal) = started_hal();
+        let rx = SimRxPacket { if_chain: 3, datarate: 9, payload: vec![1, 2, 3], ..Default::default() };
+
+        /* packets are kept, without RSSI temperature compensation */
+        sim.set_status_error(true);
+        hal.ctx.temperature = None;
+        sim.inject_rx(&rx).unwrap();
+        let pkt = receive_one(&mut hal);
+        assert_eq!(pkt.rssi_tcomp, 0.0);
+        assert_eq!(&pkt.payload[.. 3], &[1, 2, 3]);
+
+        sim.set_status_error(false);
+        sim.set_temperature(25.0);
+        sim.inject_rx(&rx).unwrap();
+        let compensated = receive_one(&mut hal);
+        let tcomp = sx1302_rssi_get_temperature_offset(&hal.ctx.rf_chain_cfg[0].rssi_tcomp, 25.0);
+        assert!(tcomp > 1.0);
+        assert_eq!(compensated.rssi_tcomp, tcomp);
+        assert!((compensated.rssic - pkt.rssic - tcomp).abs() < 1e-3);
+        hal.lgw_stop().unwrap();
+    }
+}
@@ -0,0 +1,13 @@
+/*
+    SX1261 PRAM patch enabling LBT and spectral scan, pram[] and PRAM_VERSION_STRING of
+    sx1261_pram.var in the reference HAL (libloragw/inc). The words are written as is, 32-bit
+    big-endian, from SX1261_PRAM_ADDR.
+
+    The image is not distributed with this crate yet: until its words are copied here, the
+    application gives it with lgw_sx1261_set_pram, otherwise lgw_start fails with Error::Firmware
+    when the SX1261 is enabled.
+*/
+
+pub const SX1261_PRAM_VERSION_STRING: &str = "2D06";
+
+pub const SX1261_PRAM: [u32; 0] = [];
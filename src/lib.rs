@@ -1,4 +1,5 @@
 #![macro_use]
 extern crate anyhow;
 
-pub mod hal;
\ No newline at end of file
+pub mod hal;
+pub mod pkt_fwd;
\ No newline at end of file
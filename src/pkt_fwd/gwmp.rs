@@ -0,0 +1,364 @@
+/*
+    Semtech UDP protocol (GWMP, version 2) between the gateway and the network server.
+
+    Every datagram starts with a 4 bytes header: protocol version, random token (2 bytes) and
+    identifier. PUSH_DATA, PULL_DATA and TX_ACK are followed by the 8 bytes gateway EUI.
+
+        PUSH_DATA   gw -> ns    header + EUI + {"rxpk":[...],"stat":{...}}
+        PUSH_ACK    ns -> gw    header
+        PULL_DATA   gw -> ns    header + EUI (keepalive, opens the downlink route)
+        PULL_RESP   ns -> gw    header + {"txpk":{...}}
+        PULL_ACK    ns -> gw    header
+        TX_ACK      gw -> ns    header + EUI + {"txpk_ack":{"error":"..."}}
+*/
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::hal::{LgwPktRx, LgwPktTx, Modulation, TxMode, BW_125KHZ, BW_250KHZ, BW_500KHZ, CR_LORA_4_5, CR_LORA_4_6, CR_LORA_4_7, CR_LORA_4_8,
+                 DR_LORA_SF12, DR_LORA_SF5, STAT_CRC_BAD, STAT_CRC_OK, STAT_NO_CRC};
+
+pub const PROTOCOL_VERSION: u8 = 2;
+
+pub const PKT_PUSH_DATA: u8 = 0;
+pub const PKT_PUSH_ACK: u8 = 1;
+pub const PKT_PULL_DATA: u8 = 2;
+pub const PKT_PULL_RESP: u8 = 3;
+pub const PKT_PULL_ACK: u8 = 4;
+pub const PKT_TX_ACK: u8 = 5;
+
+pub const HEADER_SIZE: usize = 4;
+pub const HEADER_EUI_SIZE: usize = 12;
+
+/* Default preamble lengths when txpk.prea is missing */
+const STD_LORA_PREAMBLE: u16 = 8;
+const STD_FSK_PREAMBLE: u16 = 5;
+
+/**
+@struct GwmpHeader
+@brief Header common to all datagrams
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GwmpHeader {
+    pub version: u8,
+    pub token: u16,
+    pub ident: u8,
+}
+
+impl GwmpHeader {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < HEADER_SIZE {
+            return Err(anyhow!("ERROR: datagram too short ({} bytes)", data.len()));
+        }
+        Ok(Self {
+            version: data[0],
+            token: u16::from_be_bytes([data[1], data[2]]),
+            ident: data[3],
+        })
+    }
+}
+
+/* Header, followed by the gateway EUI for gateway-initiated datagrams */
+pub fn encode_header(token: u16, ident: u8, gateway_id: Option<u64>) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(HEADER_EUI_SIZE);
+    buf.push(PROTOCOL_VERSION);
+    buf.extend_from_slice(&token.to_be_bytes());
+    buf.push(ident);
+    if let Some(id) = gateway_id {
+        buf.extend_from_slice(&id.to_be_bytes());
+    }
+    buf
+}
+
+/* "datr" is a string for LoRa ("SF7BW125") and a number (bit/s) for FSK */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Datr {
+    Lora(String),
+    Fsk(u32),
+}
+
+/**
+@struct Rxpk
+@brief Uplink packet, as sent to the network server
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rxpk {
+    pub tmst: u32,
+    pub chan: u8,
+    pub rfch: u8,
+    pub freq: f64,
+    pub stat: i8,
+    pub modu: String,
+    pub datr: Datr,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codr: Option<String>,
+    pub rssi: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rssis: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lsnr: Option<f32>,
+    pub foff: i32,
+    pub size: u16,
+    pub data: String,
+}
+
+impl Rxpk {
+    /* None for packets with an unknown modulation, status or datarate */
+    pub fn from_pkt(pkt: &LgwPktRx) -> Option<Self> {
+        let stat = match pkt.status {
+            STAT_CRC_OK => 1,
+            STAT_CRC_BAD => -1,
+            STAT_NO_CRC => 0,
+            _ => return None,
+        };
+
+        let (modu, datr, codr, rssis, lsnr) = match pkt.modulation {
+            Modulation::LORA => {
+                if !(DR_LORA_SF5 ..= DR_LORA_SF12).contains(&pkt.datarate) {
+                    return None;
+                }
+                let bw = match pkt.bandwidth {
+                    BW_125KHZ => 125,
+                    BW_250KHZ => 250,
+                    BW_500KHZ => 500,
+                    _ => return None,
+                };
+                let codr = match pkt.coderate {
+                    CR_LORA_4_5 => Some("4/5".to_string()),
+                    CR_LORA_4_6 => Some("4/6".to_string()),
+                    CR_LORA_4_7 => Some("4/7".to_string()),
+                    CR_LORA_4_8 => Some("4/8".to_string()),
+                    /* implicit header without coding rate information */
+                    _ => Some("OFF".to_string()),
+                };
+                ("LORA", Datr::Lora(format!("SF{}BW{}", pkt.datarate, bw)), codr, Some(pkt.rssis.round() as i32), Some((pkt.snr * 10.0).round() / 10.0))
+            }
+            Modulation::FSK => ("FSK", Datr::Fsk(pkt.datarate), None, None, None),
+            _ => return None,
+        };
+
+        let size = (pkt.size as usize).min(pkt.payload.len());
+        Some(Self {
+            tmst: pkt.count_us,
+            chan: pkt.if_chain,
+            rfch: pkt.rf_chain,
+            freq: pkt.freq_hz as f64 / 1e6,
+            stat,
+            modu: modu.to_string(),
+            datr,
+            codr,
+            rssi: pkt.rssic.round() as i32,
+            rssis,
+            lsnr,
+            foff: pkt.freq_offset,
+            size: size as u16,
+            data: BASE64.encode(&pkt.payload[.. size]),
+        })
+    }
+}
+
+/**
+@struct Stat
+@brief Gateway status report, sent every stat_interval
+*/
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stat {
+    pub time: String,       //*!> UTC system time, e.g. "2014-01-12 08:59:28 GMT" */
+    pub rxnb: u32,          //*!> number of radio packets received */
+    pub rxok: u32,          //*!> number of radio packets received with a valid CRC */
+    pub rxfw: u32,          //*!> number of radio packets forwarded */
+    pub ackr: f32,          //*!> percentage of upstream datagrams that were acknowledged */
+    pub dwnb: u32,          //*!> number of downlink datagrams received */
+    pub txnb: u32,          //*!> number of packets emitted */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temp: Option<f32>,  //*!> concentrator temperature (in °C) */
+}
+
+/**
+@struct Txpk
+@brief Downlink packet, as received from the network server
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Txpk {
+    #[serde(default)]
+    pub imme: bool,
+    pub tmst: Option<u32>,
+    pub tmms: Option<u64>,
+    pub freq: f64,
+    pub rfch: u8,
+    pub powe: Option<i8>,
+    pub modu: String,
+    pub datr: Datr,
+    pub codr: Option<String>,
+    pub fdev: Option<u32>,
+    #[serde(default)]
+    pub ipol: bool,
+    pub prea: Option<u16>,
+    pub size: u16,
+    pub data: String,
+    #[serde(default)]
+    pub ncrc: bool,
+}
+
+/* Error codes of TX_ACK */
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum TxAckError {
+    NONE,
+    TOO_LATE,
+    TOO_EARLY,
+    COLLISION_PACKET,
+    COLLISION_BEACON,
+    TX_FREQ,
+    TX_POWER,
+    GPS_UNLOCKED,
+}
+
+impl fmt::Display for TxAckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            TxAckError::NONE => "NONE",
+            TxAckError::TOO_LATE => "TOO_LATE",
+            TxAckError::TOO_EARLY => "TOO_EARLY",
+            TxAckError::COLLISION_PACKET => "COLLISION_PACKET",
+            TxAckError::COLLISION_BEACON => "COLLISION_BEACON",
+            TxAckError::TX_FREQ => "TX_FREQ",
+            TxAckError::TX_POWER => "TX_POWER",
+            TxAckError::GPS_UNLOCKED => "GPS_UNLOCKED",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl Txpk {
+    /* Decode a PULL_RESP payload */
+    pub fn parse(json: &[u8]) -> Result<Self> {
+        let root: Value = serde_json::from_slice(json).map_err(|e| anyhow!("ERROR: invalid JSON: {}", e))?;
+        let txpk = root.get("txpk").ok_or(anyhow!("ERROR: no \"txpk\" object in JSON"))?;
+        serde_json::from_value(txpk.clone()).map_err(|e| anyhow!("ERROR: invalid txpk: {}", e))
+    }
+
+    /* Build the HAL packet, timing is not checked here */
+    pub fn to_pkt(&self) -> Result<LgwPktTx> {
+        let mut pkt = LgwPktTx {
+            freq_hz: (self.freq * 1e6).round() as u32,
+            rf_chain: self.rfch,
+            invert_pol: self.ipol,
+            no_crc: self.ncrc,
+            ..Default::default()
+        };
+
+        if self.imme {
+            pkt.tx_mode = TxMode::IMMEDIATE;
+        } else if let Some(tmst) = self.tmst {
+            pkt.tx_mode = TxMode::TIMESTAMPED;
+            pkt.count_us = tmst;
+        } else if self.tmms.is_some() {
+            pkt.tx_mode = TxMode::ON_GPS;
+        } else {
+            return Err(anyhow!("ERROR: no mandatory \"txpk.tmst\", \"txpk.tmms\" or \"txpk.imme\""));
+        }
+
+        if let Some(powe) = self.powe {
+            pkt.rf_power = powe;
+        }
+
+        match (self.modu.as_str(), &self.datr) {
+            ("LORA", Datr::Lora(datr)) => {
+                pkt.modulation = Modulation::LORA;
+                (pkt.datarate, pkt.bandwidth) = parse_lora_datr(datr)?;
+                pkt.coderate = match self.codr.as_deref() {
+                    Some("4/5") => CR_LORA_4_5,
+                    Some("4/6") | Some("2/3") => CR_LORA_4_6,
+                    Some("4/7") => CR_LORA_4_7,
+                    Some("4/8") | Some("1/2") => CR_LORA_4_8,
+                    Some(codr) => return Err(anyhow!("ERROR: format error in \"txpk.codr\": {}", codr)),
+                    None => return Err(anyhow!("ERROR: no mandatory \"txpk.codr\"")),
+                };
+                pkt.preamble = self.prea.unwrap_or(STD_LORA_PREAMBLE).max(6);
+            }
+            ("FSK", Datr::Fsk(datr)) => {
+                pkt.modulation = Modulation::FSK;
+                pkt.datarate = *datr;
+                let fdev = self.fdev.ok_or(anyhow!("ERROR: no mandatory \"txpk.fdev\""))?;
+                pkt.f_dev = (fdev / 1000) as u8;
+                pkt.preamble = self.prea.unwrap_or(STD_FSK_PREAMBLE).max(3);
+            }
+            ("LORA", _) | ("FSK", _) => return Err(anyhow!("ERROR: \"txpk.datr\" does not match modulation {}", self.modu)),
+            (modu, _) => return Err(anyhow!("ERROR: invalid modulation in \"txpk.modu\": {}", modu)),
+        }
+
+        let payload = BASE64.decode(self.data.as_bytes()).map_err(|e| anyhow!("ERROR: failed to decode base64 \"txpk.data\": {}", e))?;
+        if payload.len() != self.size as usize {
+            return Err(anyhow!("ERROR: mismatch between \"txpk.size\" ({}) and decoded payload ({} bytes)", self.size, payload.len()));
+        }
+        if payload.len() > pkt.payload.len() {
+            return Err(anyhow!("ERROR: payload too big ({} bytes)", payload.len()));
+        }
+        pkt.size = payload.len() as u16;
+        pkt.payload[.. payload.len()].copy_from_slice(&payload);
+
+        Ok(pkt)
+    }
+}
+
+fn parse_lora_datr(datr: &str) -> Result<(u32, u8)> {
+    let invalid = || anyhow!("ERROR: format error in \"txpk.datr\": {}", datr);
+    let (sf, bw) = datr.strip_prefix("SF").and_then(|s| s.split_once("BW")).ok_or_else(invalid)?;
+    let sf: u32 = sf.parse().map_err(|_| invalid())?;
+    if !(DR_LORA_SF5 ..= DR_LORA_SF12).contains(&sf) {
+        return Err(invalid());
+    }
+    let bw = match bw {
+        "125" => BW_125KHZ,
+        "250" => BW_250KHZ,
+        "500" => BW_500KHZ,
+        _ => return Err(invalid()),
+    };
+    Ok((sf, bw))
+}
+
+pub fn push_data(token: u16, gateway_id: u64, rxpk: &[Rxpk], stat: Option<&Stat>) -> Vec<u8> {
+    let mut root = serde_json::Map::new();
+    if !rxpk.is_empty() {
+        root.insert("rxpk".to_string(), serde_json::to_value(rxpk).unwrap_or_default());
+    }
+    if let Some(stat) = stat {
+        root.insert("stat".to_string(), serde_json::to_value(stat).unwrap_or_default());
+    }
+    let mut buf = encode_header(token, PKT_PUSH_DATA, Some(gateway_id));
+    buf.extend_from_slice(Value::Object(root).to_string().as_bytes());
+    buf
+}
+
+pub fn tx_ack(token: u16, gateway_id: u64, error: TxAckError) -> Vec<u8> {
+    let mut buf = encode_header(token, PKT_TX_ACK, Some(gateway_id));
+    buf.extend_from_slice(format!("{{\"txpk_ack\":{{\"error\":\"{}\"}}}}", error).as_bytes());
+    buf
+}
+
+/* UTC time formatted as in the "stat" object, e.g. "2014-01-12 08:59:28 GMT" */
+pub fn utc_time(now: SystemTime) -> String {
+    let secs = now.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, rem) = (secs / 86400, secs % 86400);
+
+    /* civil date from days since 1970-01-01 (H. Hinnant's algorithm) */
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} GMT", year, month, day, rem / 3600, (rem % 3600) / 60, rem % 60)
+}
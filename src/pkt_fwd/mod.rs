@@ -0,0 +1,641 @@
+/*
+    Packet forwarder: bridges the concentrator and a network server speaking the Semtech UDP
+    protocol (see gwmp).
+
+    Like the reference lora_pkt_fwd it runs two threads:
+    - upstream: packets received by the LgwRunner are sent as PUSH_DATA "rxpk", along with a
+      periodic "stat" report,
+    - downstream: PULL_DATA keepalives open the route for PULL_RESP, whose "txpk" is checked and
+      queued in the runner JIT queue, the outcome being reported with TX_ACK.
+*/
+
+pub mod gwmp;
+
+use std::io;
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use tracing::{debug, error, info, warn};
+
+use crate::hal::loragw_conf::LgwGlobalConf;
+use crate::hal::error::Error;
+use crate::hal::loragw_jit::{JitError, JitPktType};
+use crate::hal::loragw_runner::{LgwRunner, LgwRunnerHandle};
+use crate::hal::{Hal, LgwPktRx, LgwPktTx, LGW_RF_CHAIN_NB, STAT_CRC_BAD, STAT_CRC_OK, STAT_NO_CRC};
+use gwmp::{GwmpHeader, Rxpk, Stat, Txpk, TxAckError};
+
+const FETCH_SLEEP: Duration = Duration::from_millis(10);   /* time waited for RX packets before checking the stop flag */
+const PULL_TIMEOUT: Duration = Duration::from_millis(200); /* read timeout of the downstream socket */
+const NB_PKT_MAX: usize = 255;                              /* max number of packets per PUSH_DATA */
+
+/**
+@struct PktFwdConf
+@brief Forwarder settings, read from the "gateway_conf" object of global_conf.json
+*/
+#[derive(Debug, Clone)]
+pub struct PktFwdConf {
+    pub gateway_id: u64,
+    pub server_address: String,
+    pub serv_port_up: u16,
+    pub serv_port_down: u16,
+    pub keepalive_interval: Duration,   //*!> period of PULL_DATA */
+    pub stat_interval: Duration,        //*!> period of the "stat" report */
+    pub push_timeout: Duration,         //*!> time waited for PUSH_ACK */
+    pub forward_crc_valid: bool,
+    pub forward_crc_error: bool,
+    pub forward_crc_disabled: bool,
+    pub tx_freq_min: [u32; LGW_RF_CHAIN_NB as usize],  //*!> lowest downlink frequency on each RF chain, 0 for no limit */
+    pub tx_freq_max: [u32; LGW_RF_CHAIN_NB as usize],  //*!> highest downlink frequency on each RF chain, 0 for no limit */
+}
+
+#[derive(Debug, Deserialize)]
+struct GatewayConfJson {
+    #[serde(rename = "gateway_ID")]
+    gateway_id: String,
+    #[serde(default = "default_server_address")]
+    server_address: String,
+    #[serde(default = "default_port")]
+    serv_port_up: u16,
+    #[serde(default = "default_port")]
+    serv_port_down: u16,
+    #[serde(default = "default_keepalive")]
+    keepalive_interval: u64,
+    #[serde(default = "default_stat_interval")]
+    stat_interval: u64,
+    #[serde(default = "default_push_timeout")]
+    push_timeout_ms: u64,
+    #[serde(default = "default_true")]
+    forward_crc_valid: bool,
+    #[serde(default)]
+    forward_crc_error: bool,
+    #[serde(default)]
+    forward_crc_disabled: bool,
+}
+
+fn default_server_address() -> String { "127.0.0.1".to_string() }
+fn default_port() -> u16 { 1700 }
+fn default_keepalive() -> u64 { 5 }
+fn default_stat_interval() -> u64 { 30 }
+fn default_push_timeout() -> u64 { 100 }
+fn default_true() -> bool { true }
+
+impl PktFwdConf {
+    pub fn from_global_conf(conf: &LgwGlobalConf) -> Result<Self> {
+        let gw = conf.gateway_conf.as_ref().ok_or(anyhow!("ERROR: missing gateway_conf object"))?;
+        let gw: GatewayConfJson = serde_json::from_value(gw.clone())
+            .map_err(|e| anyhow!("ERROR: invalid gateway_conf: {}", e))?;
+
+        let gateway_id = u64::from_str_radix(&gw.gateway_id, 16)
+            .map_err(|_| anyhow!("ERROR: invalid value for gateway_conf.gateway_ID: {} (expected 16 hex digits)", gw.gateway_id))?;
+
+        Ok(Self {
+            gateway_id,
+            server_address: gw.server_address,
+            serv_port_up: gw.serv_port_up,
+            serv_port_down: gw.serv_port_down,
+            keepalive_interval: Duration::from_secs(gw.keepalive_interval),
+            stat_interval: Duration::from_secs(gw.stat_interval),
+            push_timeout: Duration::from_millis(gw.push_timeout_ms),
+            forward_crc_valid: gw.forward_crc_valid,
+            forward_crc_error: gw.forward_crc_error,
+            forward_crc_disabled: gw.forward_crc_disabled,
+            tx_freq_min: conf.tx_freq_min,
+            tx_freq_max: conf.tx_freq_max,
+        })
+    }
+}
+
+/**
+@struct PktFwdStats
+@brief Counters of the forwarder since start
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PktFwdStats {
+    pub rx_rcv: u32,            //*!> packets received by the concentrator */
+    pub rx_ok: u32,             //*!> packets received with a valid CRC */
+    pub rx_bad: u32,            //*!> packets received with a bad CRC */
+    pub rx_nocrc: u32,          //*!> packets received without CRC */
+    pub up_pkt_fwd: u32,        //*!> packets forwarded to the server */
+    pub up_dgram_sent: u32,     //*!> PUSH_DATA datagrams sent */
+    pub up_ack_rcv: u32,        //*!> PUSH_ACK received */
+    pub dw_pull_sent: u32,      //*!> PULL_DATA sent */
+    pub dw_ack_rcv: u32,        //*!> PULL_ACK received */
+    pub dw_dgram_rcv: u32,      //*!> PULL_RESP received */
+    pub tx_ok: u32,             //*!> packets given to the concentrator */
+    pub tx_rejected: u32,       //*!> packets refused with a TX_ACK error */
+    pub tx_fail: u32,           //*!> packets not queued because of a concentrator error */
+}
+
+/**
+@struct PacketForwarder
+@brief Running forwarder, owning the concentrator runner
+*/
+#[derive(Debug)]
+pub struct PacketForwarder {
+    stop: Arc<AtomicBool>,
+    stats: Arc<Mutex<PktFwdStats>>,
+    up: Option<JoinHandle<LgwRunner>>,
+    down: Option<JoinHandle<()>>,
+}
+
+impl PacketForwarder {
+    /* The runner must have been spawned from a configured Hal */
+    pub fn spawn(conf: PktFwdConf, runner: LgwRunner) -> Result<Self> {
+        let sock_up = connect(&conf.server_address, conf.serv_port_up)?;
+        let sock_down = connect(&conf.server_address, conf.serv_port_down)?;
+        sock_down.set_read_timeout(Some(PULL_TIMEOUT))?;
+        sock_up.set_read_timeout(Some(conf.push_timeout.max(Duration::from_millis(1))))?;
+        info!("INFO: forwarding to {} (up:{} down:{}), gateway ID {:016X}", conf.server_address, conf.serv_port_up, conf.serv_port_down, conf.gateway_id);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stats = Arc::new(Mutex::new(PktFwdStats::default()));
+
+        /* the runner itself is moved to the upstream thread, only its command handle is shared */
+        let handle = runner.handle();
+        let up = {
+            let (conf, stop, stats) = (conf.clone(), stop.clone(), stats.clone());
+            thread::Builder::new()
+                .name("pkt-fwd-up".to_string())
+                .spawn(move || thread_up(conf, runner, sock_up, stop, stats))?
+        };
+        let down = {
+            let (stop, stats) = (stop.clone(), stats.clone());
+            thread::Builder::new()
+                .name("pkt-fwd-down".to_string())
+                .spawn(move || thread_down(conf, handle, sock_down, stop, stats))?
+        };
+
+        Ok(Self {
+            stop,
+            stats,
+            up: Some(up),
+            down: Some(down),
+        })
+    }
+
+    pub fn stats(&self) -> PktFwdStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /* False once one of the threads exited, e.g. because the concentrator runner stopped */
+    pub fn is_running(&self) -> bool {
+        self.up.as_ref().is_some_and(|t| !t.is_finished()) && self.down.as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    /* Stop forwarding and the concentrator, giving the Hal back */
+    pub fn stop(mut self) -> Result<Hal> {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(down) = self.down.take() {
+            let _ = down.join();
+        }
+        let up = self.up.take().ok_or(anyhow!("ERROR: forwarder already stopped"))?;
+        let runner = up.join().map_err(|_| anyhow!("ERROR: upstream thread panicked"))?;
+        runner.stop()
+    }
+}
+
+impl Drop for PacketForwarder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(down) = self.down.take() {
+            let _ = down.join();
+        }
+        if let Some(up) = self.up.take() {
+            let _ = up.join();
+        }
+    }
+}
+
+fn connect(address: &str, port: u16) -> Result<UdpSocket> {
+    let sock = UdpSocket::bind(("0.0.0.0", 0)).map_err(|e| anyhow!("ERROR: failed to open socket: {}", e))?;
+    sock.connect((address, port)).map_err(|e| anyhow!("ERROR: failed to connect to {}:{}: {}", address, port, e))?;
+    Ok(sock)
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+fn forward(conf: &PktFwdConf, pkt: &LgwPktRx, stats: &mut PktFwdStats) -> bool {
+    stats.rx_rcv += 1;
+    match pkt.status {
+        STAT_CRC_OK => {
+            stats.rx_ok += 1;
+            conf.forward_crc_valid
+        }
+        STAT_CRC_BAD => {
+            stats.rx_bad += 1;
+            conf.forward_crc_error
+        }
+        STAT_NO_CRC => {
+            stats.rx_nocrc += 1;
+            conf.forward_crc_disabled
+        }
+        _ => false,
+    }
+}
+
+fn thread_up(conf: PktFwdConf, runner: LgwRunner, sock: UdpSocket, stop: Arc<AtomicBool>, stats: Arc<Mutex<PktFwdStats>>) -> LgwRunner {
+    let mut last_stat = Instant::now();
+    let mut prev = PktFwdStats::default();
+    let mut buf = [0u8; 64];
+
+    while !stop.load(Ordering::Relaxed) {
+        let mut pkts = Vec::new();
+        match runner.packets().recv_timeout(FETCH_SLEEP) {
+            Ok(pkt) => {
+                pkts.push(pkt);
+                pkts.extend(runner.packets().try_iter().take(NB_PKT_MAX - 1));
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                error!("ERROR: concentrator runner stopped, exiting upstream thread");
+                break;
+            }
+        }
+
+        let mut rxpk = Vec::new();
+        {
+            let mut stats = stats.lock().unwrap();
+            for pkt in &pkts {
+                if !forward(&conf, pkt, &mut stats) {
+                    continue;
+                }
+                match Rxpk::from_pkt(pkt) {
+                    Some(r) => rxpk.push(r),
+                    None => warn!("WARNING: [up] dropping packet with unknown modulation/status: {}", pkt),
+                }
+            }
+        }
+
+        let stat = if last_stat.elapsed() >= conf.stat_interval {
+            last_stat = Instant::now();
+            let temp = runner.status().ok().map(|s| s.temperature);
+            let cur = *stats.lock().unwrap();
+            let up_dgram = cur.up_dgram_sent - prev.up_dgram_sent;
+            let stat = Stat {
+                time: gwmp::utc_time(SystemTime::now()),
+                rxnb: cur.rx_rcv - prev.rx_rcv,
+                rxok: cur.rx_ok - prev.rx_ok,
+                rxfw: cur.up_pkt_fwd - prev.up_pkt_fwd,
+                ackr: if up_dgram > 0 { ((cur.up_ack_rcv - prev.up_ack_rcv) as f32 * 1000.0 / up_dgram as f32).round() / 10.0 } else { 0.0 },
+                dwnb: cur.dw_dgram_rcv - prev.dw_dgram_rcv,
+                txnb: cur.tx_ok - prev.tx_ok,
+                temp,
+            };
+            prev = cur;
+            Some(stat)
+        } else {
+            None
+        };
+
+        if rxpk.is_empty() && stat.is_none() {
+            continue;
+        }
+
+        let token: u16 = rand::random();
+        let datagram = gwmp::push_data(token, conf.gateway_id, &rxpk, stat.as_ref());
+        if let Err(e) = sock.send(&datagram) {
+            warn!("WARNING: [up] failed to send PUSH_DATA: {}", e);
+            continue;
+        }
+        {
+            let mut stats = stats.lock().unwrap();
+            stats.up_dgram_sent += 1;
+            stats.up_pkt_fwd += rxpk.len() as u32;
+        }
+        debug!("[up] PUSH_DATA sent, token:{:04X} rxpk:{} stat:{}", token, rxpk.len(), stat.is_some());
+
+        /* wait for the matching PUSH_ACK, late or foreign datagrams are ignored */
+        let deadline = Instant::now() + conf.push_timeout;
+        while Instant::now() < deadline {
+            match sock.recv(&mut buf) {
+                Ok(n) => match GwmpHeader::parse(&buf[.. n]) {
+                    Ok(h) if h.version == gwmp::PROTOCOL_VERSION && h.ident == gwmp::PKT_PUSH_ACK && h.token == token => {
+                        stats.lock().unwrap().up_ack_rcv += 1;
+                        break;
+                    }
+                    _ => debug!("[up] ignored datagram ({} bytes)", n),
+                },
+                Err(e) if is_timeout(&e) => break,
+                Err(e) => {
+                    warn!("WARNING: [up] failed to receive PUSH_ACK: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    info!("INFO: end of upstream thread");
+    runner
+}
+
+/* Check a downlink against the gateway settings, None if it can be queued */
+fn check_tx(conf: &PktFwdConf, txpk: &Txpk, pkt: &LgwPktTx) -> Option<TxAckError> {
+    if txpk.tmms.is_some() && !txpk.imme && txpk.tmst.is_none() {
+        /* class B downlinks need a GPS time reference */
+        return Some(TxAckError::GPS_UNLOCKED);
+    }
+
+    let rf = pkt.rf_chain as usize;
+    if rf >= LGW_RF_CHAIN_NB as usize {
+        return Some(TxAckError::TX_FREQ);
+    }
+    let (freq_min, freq_max) = (conf.tx_freq_min[rf], conf.tx_freq_max[rf]);
+    if (freq_min != 0 && pkt.freq_hz < freq_min) || (freq_max != 0 && pkt.freq_hz > freq_max) {
+        return Some(TxAckError::TX_FREQ);
+    }
+
+    None
+}
+
+/* Queue a downlink, timing and collisions being checked by the JIT queue */
+fn enqueue_tx(runner: &LgwRunnerHandle, txpk: &Txpk, pkt: &LgwPktTx) -> Result<Option<TxAckError>> {
+    let pkt_type = if txpk.imme {
+        JitPktType::DOWNLINK_CLASS_C
+    } else if txpk.tmms.is_some() {
+        JitPktType::DOWNLINK_CLASS_B
+    } else {
+        JitPktType::DOWNLINK_CLASS_A
+    };
+
+    match runner.enqueue(pkt, pkt_type) {
+        Ok(()) => Ok(None),
+        Err(e) => match Error::cause(&e) {
+            Some(Error::Jit(JitError::TOO_LATE)) => Ok(Some(TxAckError::TOO_LATE)),
+            Some(Error::Jit(JitError::TOO_EARLY)) => Ok(Some(TxAckError::TOO_EARLY)),
+            Some(Error::Jit(JitError::COLLISION_BEACON)) => Ok(Some(TxAckError::COLLISION_BEACON)),
+            /* no dedicated TX_ACK code for a full queue, report it as a collision like lora_pkt_fwd */
+            Some(Error::Jit(_)) => Ok(Some(TxAckError::COLLISION_PACKET)),
+            _ => Err(e),
+        },
+    }
+}
+
+fn thread_down(conf: PktFwdConf, runner: LgwRunnerHandle, sock: UdpSocket, stop: Arc<AtomicBool>, stats: Arc<Mutex<PktFwdStats>>) {
+    let mut buf = [0u8; 1000];
+    let mut last_pull: Option<Instant> = None;
+    let mut pull_token: u16 = 0;
+
+    while !stop.load(Ordering::Relaxed) {
+        if last_pull.is_none_or(|t| t.elapsed() >= conf.keepalive_interval) {
+            pull_token = rand::random();
+            match sock.send(&gwmp::encode_header(pull_token, gwmp::PKT_PULL_DATA, Some(conf.gateway_id))) {
+                Ok(_) => stats.lock().unwrap().dw_pull_sent += 1,
+                Err(e) => warn!("WARNING: [down] failed to send PULL_DATA: {}", e),
+            }
+            last_pull = Some(Instant::now());
+        }
+
+        let n = match sock.recv(&mut buf) {
+            Ok(n) => n,
+            Err(e) if is_timeout(&e) => continue,
+            Err(e) => {
+                /* e.g. ICMP port unreachable while the server is down */
+                debug!("[down] recv failed: {}", e);
+                thread::sleep(PULL_TIMEOUT);
+                continue;
+            }
+        };
+
+        let header = match GwmpHeader::parse(&buf[.. n]) {
+            Ok(h) if h.version == gwmp::PROTOCOL_VERSION => h,
+            _ => {
+                warn!("WARNING: [down] ignoring invalid datagram ({} bytes)", n);
+                continue;
+            }
+        };
+
+        match header.ident {
+            gwmp::PKT_PULL_ACK => {
+                if header.token == pull_token {
+                    stats.lock().unwrap().dw_ack_rcv += 1;
+                    debug!("[down] PULL_ACK received");
+                }
+            }
+            gwmp::PKT_PULL_RESP => {
+                stats.lock().unwrap().dw_dgram_rcv += 1;
+
+                let txpk = match Txpk::parse(&buf[gwmp::HEADER_SIZE .. n]) {
+                    Ok(txpk) => txpk,
+                    Err(e) => {
+                        warn!("WARNING: [down] PULL_RESP ignored: {}", e);
+                        continue;
+                    }
+                };
+                let pkt = match txpk.to_pkt() {
+                    Ok(pkt) => pkt,
+                    Err(e) => {
+                        warn!("WARNING: [down] PULL_RESP ignored: {}", e);
+                        continue;
+                    }
+                };
+
+                let res = match check_tx(&conf, &txpk, &pkt) {
+                    Some(err) => Ok(Some(err)),
+                    None => enqueue_tx(&runner, &txpk, &pkt),
+                };
+                let ack = match res {
+                    Ok(Some(err)) => {
+                        warn!("WARNING: [down] downlink rejected: {}", err);
+                        stats.lock().unwrap().tx_rejected += 1;
+                        err
+                    }
+                    Ok(None) => {
+                        stats.lock().unwrap().tx_ok += 1;
+                        TxAckError::NONE
+                    }
+                    Err(e) => {
+                        /* no TX_ACK code for a concentrator failure, the packet could not be
+                           scheduled: report it as a collision like a full queue */
+                        error!("ERROR: [down] failed to queue downlink: {:#}", e);
+                        stats.lock().unwrap().tx_fail += 1;
+                        TxAckError::COLLISION_PACKET
+                    }
+                };
+
+                if let Err(e) = sock.send(&gwmp::tx_ack(header.token, conf.gateway_id, ack)) {
+                    warn!("WARNING: [down] failed to send TX_ACK: {}", e);
+                }
+            }
+            ident => debug!("[down] ignoring datagram with identifier {}", ident),
+        }
+    }
+
+    info!("INFO: end of downstream thread");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    use serde_json::Value;
+
+    use crate::hal::mcu::sim::{SimConcentrator, SimRxPacket};
+
+    const GLOBAL_CONF: &str = r#"{
+        "SX130x_conf": {
+            "com_type": "USB", "com_path": "/dev/ttyACM0", "lorawan_public": true, "clksrc": 0, "full_duplex": false,
+            "radio_0": {
+                "enable": true, "type": "SX1250", "freq": 867500000, "rssi_offset": -215.4,
+                "tx_enable": true, "tx_freq_min": 863000000, "tx_freq_max": 870000000,
+                "tx_gain_lut": [{"rf_power": 14, "pa_gain": 0, "pwr_idx": 17}]
+            },
+            "radio_1": {"enable": true, "type": "SX1250", "freq": 868500000, "rssi_offset": -215.4, "tx_enable": false},
+            "chan_multiSF_0": {"enable": true, "radio": 1, "if": -400000},
+            "chan_multiSF_1": {"enable": true, "radio": 1, "if": -200000},
+            "chan_multiSF_2": {"enable": true, "radio": 1, "if": 0},
+            "chan_multiSF_3": {"enable": true, "radio": 0, "if": -400000}
+        },
+        "gateway_conf": {"gateway_ID": "AA555A0000000000", "server_address": "127.0.0.1", "serv_port_up": 0, "serv_port_down": 0}
+    }"#;
+
+    fn txpk_json(freq: f64) -> Vec<u8> {
+        format!(r#"{{"txpk":{{"imme":true,"freq":{},"rfch":0,"powe":14,"modu":"LORA","datr":"SF9BW125","codr":"4/5","ipol":true,"size":4,"data":"AQIDBA=="}}}}"#, freq).into_bytes()
+    }
+
+    fn recv(sock: &UdpSocket) -> (GwmpHeader, Value, SocketAddr) {
+        let mut buf = [0u8; 2048];
+        let (n, from) = sock.recv_from(&mut buf).expect("no datagram from the forwarder");
+        let header = GwmpHeader::parse(&buf[.. n]).unwrap();
+        let json = if n > gwmp::HEADER_EUI_SIZE { serde_json::from_slice(&buf[gwmp::HEADER_EUI_SIZE .. n]).unwrap() } else { Value::Null };
+        (header, json, from)
+    }
+
+    /* next TX_ACK error, PULL_DATA keepalives are acknowledged on the way */
+    fn recv_tx_ack(sock: &UdpSocket) -> String {
+        loop {
+            let (header, json, from) = recv(sock);
+            match header.ident {
+                gwmp::PKT_TX_ACK => return json["txpk_ack"]["error"].as_str().unwrap().to_string(),
+                gwmp::PKT_PULL_DATA => { sock.send_to(&gwmp::encode_header(header.token, gwmp::PKT_PULL_ACK, None), from).unwrap(); }
+                ident => panic!("unexpected datagram {}", ident),
+            }
+        }
+    }
+
+    #[test]
+    fn check_tx_zero_is_no_limit() {
+        let conf = LgwGlobalConf::parse(GLOBAL_CONF).unwrap();
+        let mut fwd_conf = PktFwdConf::from_global_conf(&conf).unwrap();
+        let txpk = Txpk::parse(&txpk_json(915.0)).unwrap();
+        let pkt = txpk.to_pkt().unwrap();
+        assert_eq!(check_tx(&fwd_conf, &txpk, &pkt), Some(TxAckError::TX_FREQ));
+
+        fwd_conf.tx_freq_min = [0; LGW_RF_CHAIN_NB as usize];
+        fwd_conf.tx_freq_max = [0; LGW_RF_CHAIN_NB as usize];
+        assert_eq!(check_tx(&fwd_conf, &txpk, &pkt), None);
+
+        fwd_conf.tx_freq_max[0] = 870_000_000;
+        assert_eq!(check_tx(&fwd_conf, &txpk, &pkt), Some(TxAckError::TX_FREQ));
+    }
+
+    #[test]
+    fn udp_loopback() {
+        let server_up = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_down = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server_up.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        server_down.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        let conf = LgwGlobalConf::parse(GLOBAL_CONF).unwrap();
+        conf.apply(&mut hal).unwrap();
+        let mut fwd_conf = PktFwdConf::from_global_conf(&conf).unwrap();
+        fwd_conf.serv_port_up = server_up.local_addr().unwrap().port();
+        fwd_conf.serv_port_down = server_down.local_addr().unwrap().port();
+
+        let runner = LgwRunner::spawn(hal, Duration::from_millis(5)).unwrap();
+        let fwd = PacketForwarder::spawn(fwd_conf, runner).unwrap();
+
+        /* downstream route opened by PULL_DATA */
+        let (header, _, gw_down) = recv(&server_down);
+        assert_eq!(header.ident, gwmp::PKT_PULL_DATA);
+        server_down.send_to(&gwmp::encode_header(header.token, gwmp::PKT_PULL_ACK, None), gw_down).unwrap();
+
+        /* uplink forwarded as PUSH_DATA */
+        let payload: Vec<u8> = (0 .. 20).collect();
+        sim.inject_rx(&SimRxPacket { if_chain: 3, datarate: 9, payload: payload.clone(), ..Default::default() }).unwrap();
+        let (header, json, gw_up) = recv(&server_up);
+        assert_eq!(header.ident, gwmp::PKT_PUSH_DATA);
+        let rxpk = &json["rxpk"][0];
+        assert_eq!(rxpk["size"], 20);
+        assert_eq!(rxpk["datr"], "SF9BW125");
+        assert_eq!(rxpk["stat"], 1);
+        server_up.send_to(&gwmp::encode_header(header.token, gwmp::PKT_PUSH_ACK, None), gw_up).unwrap();
+
+        /* downlink accepted, then emitted */
+        let mut pull_resp = gwmp::encode_header(0x1234, gwmp::PKT_PULL_RESP, None);
+        pull_resp.extend_from_slice(&txpk_json(869.525));
+        server_down.send_to(&pull_resp, gw_down).unwrap();
+        assert_eq!(recv_tx_ack(&server_down), "NONE");
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut tx = Vec::new();
+        while tx.is_empty() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+            tx = sim.take_tx();
+        }
+        assert_eq!(tx.len(), 1);
+        assert!(tx[0].freq_hz.abs_diff(869_525_000) < 100, "TX at {} Hz", tx[0].freq_hz);   /* PLL step */
+        assert_eq!(tx[0].payload, [1, 2, 3, 4]);
+
+        /* downlink out of the TX frequency range of radio_0 */
+        let mut pull_resp = gwmp::encode_header(0x1235, gwmp::PKT_PULL_RESP, None);
+        pull_resp.extend_from_slice(&txpk_json(915.0));
+        server_down.send_to(&pull_resp, gw_down).unwrap();
+        assert_eq!(recv_tx_ack(&server_down), "TX_FREQ");
+
+        let stats = fwd.stats();
+        assert_eq!((stats.up_pkt_fwd, stats.dw_dgram_rcv, stats.tx_ok, stats.tx_rejected), (1, 2, 1, 1));
+        assert!(stats.up_ack_rcv >= 1 && stats.dw_ack_rcv >= 1);
+        fwd.stop().unwrap();
+    }
+
+    #[test]
+    fn tx_ack_on_concentrator_failure() {
+        let server_up = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_down = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server_down.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let sim = SimConcentrator::new();
+        let mut hal = Hal::with_transport(Box::new(sim.stream()));
+        let conf = LgwGlobalConf::parse(GLOBAL_CONF).unwrap();
+        conf.apply(&mut hal).unwrap();
+        let mut fwd_conf = PktFwdConf::from_global_conf(&conf).unwrap();
+        fwd_conf.serv_port_up = server_up.local_addr().unwrap().port();
+        fwd_conf.serv_port_down = server_down.local_addr().unwrap().port();
+
+        let runner = LgwRunner::spawn(hal, Duration::from_millis(5)).unwrap();
+        let handle = runner.handle();
+        let fwd = PacketForwarder::spawn(fwd_conf, runner).unwrap();
+
+        let (header, _, gw_down) = recv(&server_down);
+        assert_eq!(header.ident, gwmp::PKT_PULL_DATA);
+        server_down.send_to(&gwmp::encode_header(header.token, gwmp::PKT_PULL_ACK, None), gw_down).unwrap();
+
+        /* concentrator gone: the downlink can not be queued but is still answered */
+        handle.stop();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while fwd.is_running() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!fwd.is_running());
+
+        let mut pull_resp = gwmp::encode_header(0x1236, gwmp::PKT_PULL_RESP, None);
+        pull_resp.extend_from_slice(&txpk_json(869.525));
+        server_down.send_to(&pull_resp, gw_down).unwrap();
+        assert_eq!(recv_tx_ack(&server_down), "COLLISION_PACKET");
+
+        let stats = fwd.stats();
+        assert_eq!((stats.dw_dgram_rcv, stats.tx_ok, stats.tx_rejected, stats.tx_fail), (1, 0, 0, 1));
+        assert!(sim.take_tx().is_empty());
+        fwd.stop().unwrap();
+    }
+}